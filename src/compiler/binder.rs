@@ -0,0 +1,2442 @@
+use std::fmt;
+
+use crate::compiler::lexer::Position;
+use crate::compiler::parser::{
+    CommonTableExpression, CreateIndexQuery, CreateQuery, DataType, DeleteQuery, FrameBound,
+    InsertQuery, JoinConstraint, LockingClause, NamedWindow, OrderByItem, Query, SQLExpression,
+    SQLQuery, SelectQuantifier, SelectQuery, SetExpression, SetOperator, ShowQuery, TableReference,
+    UpdateQuery, WindowFrame, WindowSpecification, WithQuery,
+};
+use crate::compiler::walk::{
+    case_parts, collect_aggregates, collect_window_functions, column_name, contains_subquery,
+    uses_sequences, window_expressions,
+};
+use crate::storage::catalog::{sequence_schema, table_schema, Catalog, TableSchema};
+use crate::storage::information_schema::view_columns;
+use crate::types::cast::{cast, integer_range};
+use crate::types::function::{
+    AggregateFunction, ParameterType, ReturnType, ScalarFunction, WindowFunction,
+};
+use crate::types::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindErrorKind {
+    UnknownDatabase(String),
+    UnknownTable(String),
+    UnknownColumn(String),
+    UnknownIndex(String),
+    UnknownSequence(String),
+    AmbiguousColumn(String),
+    UnknownFunction(String),
+    UnknownWindow(String),
+    DuplicateDatabase(String),
+    DuplicateTable(String),
+    DuplicateColumn(String),
+    DuplicateIndex(String),
+    DuplicateSequence(String),
+    InvalidLiteral(String),
+    /// A constant that cannot be converted to the type it is used as.
+    InvalidValue(String),
+    TypeMismatch(String),
+    AggregateNotAllowed(String),
+    InvalidQuery(String),
+}
+
+impl fmt::Display for BindErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindErrorKind::UnknownDatabase(name) => write!(f, "unknown database \"{}\"", name),
+            BindErrorKind::UnknownTable(name) => write!(f, "unknown table \"{}\"", name),
+            BindErrorKind::UnknownColumn(name) => write!(f, "unknown column \"{}\"", name),
+            BindErrorKind::UnknownIndex(name) => write!(f, "unknown index \"{}\"", name),
+            BindErrorKind::UnknownSequence(name) => write!(f, "unknown sequence \"{}\"", name),
+            BindErrorKind::AmbiguousColumn(name) => {
+                write!(f, "column reference \"{}\" is ambiguous", name)
+            }
+            BindErrorKind::UnknownFunction(name) => write!(f, "unknown function \"{}\"", name),
+            BindErrorKind::UnknownWindow(name) => write!(f, "unknown window \"{}\"", name),
+            BindErrorKind::DuplicateDatabase(name) => {
+                write!(f, "database \"{}\" already exists", name)
+            }
+            BindErrorKind::DuplicateTable(name) => write!(f, "table \"{}\" already exists", name),
+            BindErrorKind::DuplicateIndex(name) => write!(f, "index \"{}\" already exists", name),
+            BindErrorKind::DuplicateSequence(name) => {
+                write!(f, "sequence \"{}\" already exists", name)
+            }
+            BindErrorKind::DuplicateColumn(name) => {
+                write!(f, "column \"{}\" is defined more than once", name)
+            }
+            BindErrorKind::InvalidLiteral(literal) => write!(f, "invalid literal {}", literal),
+            BindErrorKind::InvalidValue(message) => write!(f, "{}", message),
+            BindErrorKind::TypeMismatch(message) => write!(f, "type mismatch: {}", message),
+            BindErrorKind::AggregateNotAllowed(message) => {
+                write!(f, "aggregate function {}", message)
+            }
+            BindErrorKind::InvalidQuery(message) => write!(f, "invalid query: {}", message),
+        }
+    }
+}
+
+/// A statement that does not make sense against the catalog. `near` is the
+/// token the problem is closest to; `locate` turns it into a position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindError {
+    pub kind: BindErrorKind,
+    pub near: Option<String>,
+    pub position: Option<Position>,
+}
+
+impl BindError {
+    pub fn new(kind: BindErrorKind, near: Option<String>) -> Self {
+        BindError {
+            kind,
+            near,
+            position: None,
+        }
+    }
+
+    /// Points the error at the first of the statement's `tokens` that
+    /// matches `near`. Keywords and function names match in any case; a
+    /// column named without its table is not one that follows a `.`.
+    pub fn locate(mut self, tokens: &[String], positions: &[Position]) -> Self {
+        let unqualified = match &self.kind {
+            BindErrorKind::AmbiguousColumn(_) => true,
+            BindErrorKind::UnknownColumn(name) => !name.contains('.'),
+            _ => false,
+        };
+        if let Some(near) = &self.near {
+            self.position = (0..tokens.len())
+                .find(|&index| {
+                    tokens[index].eq_ignore_ascii_case(near)
+                        && !(unqualified && index > 0 && tokens[index - 1] == ".")
+                })
+                .and_then(|index| positions.get(index).copied());
+        }
+        self
+    }
+}
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some(position) => write!(f, "{}: {}", position, self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl std::error::Error for BindError {}
+
+/// A column of a bound relation. `data_type` is `None` for a NULL whose
+/// type nothing determines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundColumn {
+    pub name: String,
+    pub data_type: Option<DataType>,
+}
+
+/// A statement checked against the catalog, with implicit casts made
+/// explicit, and the columns it returns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundStatement {
+    pub statement: SQLQuery,
+    pub columns: Vec<BoundColumn>,
+}
+
+type Typed = (SQLExpression, Option<DataType>);
+
+/// Where an expression appears, which decides whether it may contain
+/// aggregate and window functions.
+#[derive(Clone, Copy)]
+struct Clause {
+    name: &'static str,
+    aggregates: bool,
+    windows: bool,
+}
+
+const WHERE: Clause = Clause {
+    name: "WHERE",
+    aggregates: false,
+    windows: false,
+};
+const JOIN_CONDITION: Clause = Clause {
+    name: "JOIN conditions",
+    aggregates: false,
+    windows: false,
+};
+const GROUP_BY: Clause = Clause {
+    name: "GROUP BY",
+    aggregates: false,
+    windows: false,
+};
+const HAVING: Clause = Clause {
+    name: "HAVING",
+    aggregates: true,
+    windows: false,
+};
+const SELECT_LIST: Clause = Clause {
+    name: "the select list",
+    aggregates: true,
+    windows: true,
+};
+const ORDER_BY: Clause = Clause {
+    name: "ORDER BY",
+    aggregates: true,
+    windows: true,
+};
+const INDEX_DEFINITION: Clause = Clause {
+    name: "index definitions",
+    aggregates: false,
+    windows: false,
+};
+const VALUES: Clause = Clause {
+    name: "VALUES",
+    aggregates: false,
+    windows: false,
+};
+const SET: Clause = Clause {
+    name: "UPDATE SET",
+    aggregates: false,
+    windows: false,
+};
+const AGGREGATE_ARGUMENTS: Clause = Clause {
+    name: "aggregate function arguments",
+    aggregates: false,
+    windows: false,
+};
+
+struct ScopeTable {
+    qualifier: String,
+    columns: Vec<BoundColumn>,
+}
+
+/// The columns an expression can read: the FROM items of its query, then
+/// those of each enclosing query for correlated references.
+#[derive(Default)]
+struct Scope<'s> {
+    tables: Vec<ScopeTable>,
+    /// Columns merged by USING or NATURAL joins, read without a qualifier.
+    merged: Vec<BoundColumn>,
+    outer: Option<&'s Scope<'s>>,
+}
+
+impl Scope<'_> {
+    fn column(&self, name: &str) -> Result<Option<Option<DataType>>, BindError> {
+        if let Some(column) = self.merged.iter().find(|column| column.name == name) {
+            return Ok(Some(column.data_type.clone()));
+        }
+        let mut found = None;
+        for table in &self.tables {
+            if let Some(column) = table.columns.iter().find(|column| column.name == name) {
+                if found.is_some() {
+                    return Err(BindError::new(
+                        BindErrorKind::AmbiguousColumn(name.to_string()),
+                        Some(name.to_string()),
+                    ));
+                }
+                found = Some(column.data_type.clone());
+            }
+        }
+        Ok(found)
+    }
+
+    fn qualified(&self, table: &str, column: &str) -> Result<Option<Option<DataType>>, BindError> {
+        let table = match self.tables.iter().find(|item| item.qualifier == table) {
+            Some(table) => table,
+            None => return Ok(None),
+        };
+        match table.columns.iter().find(|item| item.name == column) {
+            Some(found) => Ok(Some(found.data_type.clone())),
+            None => Err(BindError::new(
+                BindErrorKind::UnknownColumn(format!("{}.{}", table.qualifier, column)),
+                Some(column.to_string()),
+            )),
+        }
+    }
+
+    /// The type of a column reference, searching outwards from this query.
+    fn lookup(&self, expression: &SQLExpression) -> Result<Option<DataType>, BindError> {
+        let mut scope = Some(self);
+        while let Some(current) = scope {
+            let found = match expression {
+                SQLExpression::QualifiedIdentifier { table, column } => {
+                    current.qualified(table, column)?
+                }
+                SQLExpression::Identifier(name) => current.column(name)?,
+                _ => None,
+            };
+            if let Some(data_type) = found {
+                return Ok(data_type);
+            }
+            scope = current.outer;
+        }
+        Err(match expression {
+            SQLExpression::QualifiedIdentifier { table, .. } => BindError::new(
+                BindErrorKind::UnknownTable(table.clone()),
+                Some(table.clone()),
+            ),
+            _ => BindError::new(
+                BindErrorKind::UnknownColumn(column_name(expression)),
+                Some(column_name(expression)),
+            ),
+        })
+    }
+}
+
+/// The columns of one row of `table`, read by its name.
+fn table_scope(table: &TableSchema) -> Scope<'static> {
+    Scope {
+        tables: vec![ScopeTable {
+            qualifier: table.name.clone(),
+            columns: table
+                .columns
+                .iter()
+                .map(|column| BoundColumn {
+                    name: column.name.clone(),
+                    data_type: Some(column.data_type.clone()),
+                })
+                .collect(),
+        }],
+        ..Scope::default()
+    }
+}
+
+/// Resolves the names of a statement against a catalog and types every
+/// expression, between parsing and execution.
+///
+/// The bound statement is the parsed one with an explicit CAST wherever
+/// an operand has to change representation to meet the other, such as an
+/// integer compared with a DOUBLE or a text literal with a DATETIME.
+/// Casts between types that share a representation, like INT and BIGINT,
+/// would change nothing and are left out.
+pub struct Binder<'a> {
+    catalog: &'a Catalog,
+    /// CTEs visible to the query being bound, innermost last.
+    ctes: Vec<(String, Vec<BoundColumn>)>,
+    /// Names of the WINDOW clause of the SELECT being bound.
+    windows: Vec<String>,
+}
+
+impl<'a> Binder<'a> {
+    pub fn new(catalog: &'a Catalog) -> Self {
+        Binder {
+            catalog,
+            ctes: Vec::new(),
+            windows: Vec::new(),
+        }
+    }
+
+    pub fn bind(&mut self, statement: &SQLQuery) -> Result<BoundStatement, BindError> {
+        match statement {
+            SQLQuery::Select(query) => {
+                let (query, columns) = self.bind_query(query, None)?;
+                Ok(BoundStatement {
+                    statement: SQLQuery::Select(query),
+                    columns,
+                })
+            }
+            SQLQuery::With(with) => {
+                let depth = self.ctes.len();
+                let bound = self.bind_with(with);
+                self.ctes.truncate(depth);
+                let (with, columns) = bound?;
+                Ok(BoundStatement {
+                    statement: SQLQuery::With(with),
+                    columns,
+                })
+            }
+            SQLQuery::Insert(insert) => Ok(BoundStatement {
+                statement: SQLQuery::Insert(self.bind_insert(insert)?),
+                columns: Vec::new(),
+            }),
+            SQLQuery::Update(update) => Ok(BoundStatement {
+                statement: SQLQuery::Update(self.bind_update(update)?),
+                columns: Vec::new(),
+            }),
+            SQLQuery::Delete(delete) => Ok(BoundStatement {
+                statement: SQLQuery::Delete(self.bind_delete(delete)?),
+                columns: Vec::new(),
+            }),
+            SQLQuery::Create(create) => {
+                self.bind_create(create)?;
+                Ok(BoundStatement {
+                    statement: statement.clone(),
+                    columns: Vec::new(),
+                })
+            }
+            SQLQuery::CreateIndex(create) => {
+                self.bind_create_index(create)?;
+                Ok(BoundStatement {
+                    statement: statement.clone(),
+                    columns: Vec::new(),
+                })
+            }
+            SQLQuery::CreateSequence(create) => {
+                let sequence = sequence_schema(create, self.catalog.current_database())?;
+                if self.catalog.sequence(&sequence.name).is_some() {
+                    return Err(BindError::new(
+                        BindErrorKind::DuplicateSequence(sequence.name.clone()),
+                        Some(sequence.name),
+                    ));
+                }
+                Ok(BoundStatement {
+                    statement: statement.clone(),
+                    columns: Vec::new(),
+                })
+            }
+            SQLQuery::Show(show) => {
+                if let ShowQuery::Describe(name) = show {
+                    if self.catalog.table(name).is_none() {
+                        return Err(BindError::new(
+                            BindErrorKind::UnknownTable(name.clone()),
+                            Some(name.clone()),
+                        ));
+                    }
+                }
+                Ok(BoundStatement {
+                    statement: statement.clone(),
+                    columns: bound_columns(show_columns(show)),
+                })
+            }
+            SQLQuery::Use(database) => {
+                if !self.catalog.databases().contains(database) {
+                    return Err(BindError::new(
+                        BindErrorKind::UnknownDatabase(database.clone()),
+                        Some(database.clone()),
+                    ));
+                }
+                Ok(BoundStatement {
+                    statement: statement.clone(),
+                    columns: Vec::new(),
+                })
+            }
+            SQLQuery::Transaction(_) => Ok(BoundStatement {
+                statement: statement.clone(),
+                columns: Vec::new(),
+            }),
+            SQLQuery::Lock(lock) => {
+                for name in &lock.tables {
+                    if self.catalog.table(name).is_none() {
+                        return Err(BindError::new(
+                            BindErrorKind::UnknownTable(name.clone()),
+                            Some(name.clone()),
+                        ));
+                    }
+                }
+                Ok(BoundStatement {
+                    statement: statement.clone(),
+                    columns: Vec::new(),
+                })
+            }
+            SQLQuery::AlterTable(alter) => {
+                let table = self.catalog.table(&alter.table).ok_or_else(|| {
+                    BindError::new(
+                        BindErrorKind::UnknownTable(alter.table.clone()),
+                        Some(alter.table.clone()),
+                    )
+                })?;
+                let mut settings = table.vacuum;
+                for (name, value) in &alter.options {
+                    settings.set(name, value.as_deref())?;
+                }
+                Ok(BoundStatement {
+                    statement: statement.clone(),
+                    columns: Vec::new(),
+                })
+            }
+            SQLQuery::Vacuum(vacuum) => {
+                if let Some(name) = &vacuum.table {
+                    if self.catalog.table(name).is_none() {
+                        return Err(BindError::new(
+                            BindErrorKind::UnknownTable(name.clone()),
+                            Some(name.clone()),
+                        ));
+                    }
+                }
+                Ok(BoundStatement {
+                    statement: statement.clone(),
+                    columns: Vec::new(),
+                })
+            }
+        }
+    }
+
+    fn bind_create(&self, create: &CreateQuery) -> Result<(), BindError> {
+        if create.created == "DATABASE" && self.catalog.databases().contains(&create.name) {
+            return Err(BindError::new(
+                BindErrorKind::DuplicateDatabase(create.name.clone()),
+                Some(create.name.clone()),
+            ));
+        }
+        if let Some(table) = table_schema(create, self.catalog.current_database())? {
+            if self.catalog.table(&table.name).is_some() {
+                return Err(BindError::new(
+                    BindErrorKind::DuplicateTable(table.name.clone()),
+                    Some(table.name),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn bind_create_index(&mut self, create: &CreateIndexQuery) -> Result<(), BindError> {
+        let table = self.catalog.table(&create.table).ok_or_else(|| {
+            BindError::new(
+                BindErrorKind::UnknownTable(create.table.clone()),
+                Some(create.table.clone()),
+            )
+        })?;
+        let keys: Vec<SQLExpression> = create.keys.iter().map(|(key, _)| key.clone()).collect();
+        self.bind_index(
+            table,
+            &keys,
+            create.predicate.as_ref().map(|(predicate, _)| predicate),
+        )?;
+        for (index, (_, text)) in create.keys.iter().enumerate() {
+            if create.keys[..index].iter().any(|(_, other)| other == text) {
+                return Err(BindError::new(
+                    BindErrorKind::DuplicateColumn(format!("{}.{}", table.name, text)),
+                    Some(text.clone()),
+                ));
+            }
+        }
+        if self.catalog.index(&create.name).is_some() {
+            return Err(BindError::new(
+                BindErrorKind::DuplicateIndex(create.name.clone()),
+                Some(create.name.clone()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Binds the key and predicate of an index on `table`, which read the
+    /// columns of one row: they may not hold subqueries, aggregates or
+    /// window functions.
+    pub fn bind_index(
+        &mut self,
+        table: &TableSchema,
+        keys: &[SQLExpression],
+        predicate: Option<&SQLExpression>,
+    ) -> Result<(Vec<Typed>, Option<SQLExpression>), BindError> {
+        if let Some(expression) = keys.iter().chain(predicate).find(|e| contains_subquery(e)) {
+            return Err(BindError::new(
+                BindErrorKind::InvalidQuery(format!(
+                    "subqueries are not allowed in {}",
+                    INDEX_DEFINITION.name
+                )),
+                anchor(expression),
+            ));
+        }
+        if let Some(expression) = keys.iter().chain(predicate).find(|e| uses_sequences(e)) {
+            return Err(BindError::new(
+                BindErrorKind::InvalidQuery(format!(
+                    "sequence functions are not allowed in {}",
+                    INDEX_DEFINITION.name
+                )),
+                anchor(expression),
+            ));
+        }
+        let scope = table_scope(table);
+        let keys = keys
+            .iter()
+            .map(|key| self.bind_expression(key, &scope, INDEX_DEFINITION))
+            .collect::<Result<Vec<Typed>, BindError>>()?;
+        let predicate = predicate
+            .map(|predicate| self.bind_condition(predicate, &scope, INDEX_DEFINITION))
+            .transpose()?;
+        Ok((keys, predicate))
+    }
+
+    fn bind_insert(&mut self, insert: &InsertQuery) -> Result<InsertQuery, BindError> {
+        let name = column_name(&insert.table);
+        let table = self.catalog.table(&name).ok_or_else(|| {
+            BindError::new(
+                BindErrorKind::UnknownTable(name.clone()),
+                Some(name.clone()),
+            )
+        })?;
+        if insert.columns.len() != insert.values.len() {
+            let near = insert.values.first().and_then(anchor);
+            return Err(BindError::new(
+                BindErrorKind::InvalidQuery(format!(
+                    "INSERT has {} target columns but {} expressions",
+                    insert.columns.len(),
+                    insert.values.len()
+                )),
+                near,
+            ));
+        }
+        let scope = Scope::default();
+        let mut values = Vec::with_capacity(insert.values.len());
+        for (index, (target, value)) in insert.columns.iter().zip(&insert.values).enumerate() {
+            let target = column_name(target);
+            let column = table.column(&target).ok_or_else(|| {
+                BindError::new(
+                    BindErrorKind::UnknownColumn(format!("{}.{}", name, target)),
+                    Some(target.clone()),
+                )
+            })?;
+            if insert.columns[..index]
+                .iter()
+                .any(|earlier| column_name(earlier) == target)
+            {
+                return Err(BindError::new(
+                    BindErrorKind::DuplicateColumn(format!("{}.{}", name, target)),
+                    Some(target),
+                ));
+            }
+            let (value, data_type) = self.bind_expression(value, &scope, VALUES)?;
+            values.push(assign(value, &data_type, &column.data_type, &target)?);
+        }
+        Ok(InsertQuery {
+            table: insert.table.clone(),
+            columns: insert.columns.clone(),
+            values,
+        })
+    }
+
+    fn bind_update(&mut self, update: &UpdateQuery) -> Result<UpdateQuery, BindError> {
+        let table = self.catalog.table(&update.table).ok_or_else(|| {
+            BindError::new(
+                BindErrorKind::UnknownTable(update.table.clone()),
+                Some(update.table.clone()),
+            )
+        })?;
+        let scope = table_scope(table);
+        let mut assignments = Vec::with_capacity(update.assignments.len());
+        for (index, (target, value)) in update.assignments.iter().enumerate() {
+            let column = table.column(target).ok_or_else(|| {
+                BindError::new(
+                    BindErrorKind::UnknownColumn(format!("{}.{}", table.name, target)),
+                    Some(target.clone()),
+                )
+            })?;
+            if update.assignments[..index]
+                .iter()
+                .any(|(earlier, _)| earlier == target)
+            {
+                return Err(BindError::new(
+                    BindErrorKind::DuplicateColumn(format!("{}.{}", table.name, target)),
+                    Some(target.clone()),
+                ));
+            }
+            let (value, data_type) = self.bind_expression(value, &scope, SET)?;
+            let value = assign(value, &data_type, &column.data_type, target)?;
+            assignments.push((target.clone(), value));
+        }
+        let conditions = match &update.conditions {
+            Some(condition) => Some(self.bind_condition(condition, &scope, WHERE)?),
+            None => None,
+        };
+        Ok(UpdateQuery {
+            table: update.table.clone(),
+            assignments,
+            conditions,
+        })
+    }
+
+    fn bind_delete(&mut self, delete: &DeleteQuery) -> Result<DeleteQuery, BindError> {
+        let table = self.catalog.table(&delete.table).ok_or_else(|| {
+            BindError::new(
+                BindErrorKind::UnknownTable(delete.table.clone()),
+                Some(delete.table.clone()),
+            )
+        })?;
+        let scope = table_scope(table);
+        let conditions = match &delete.conditions {
+            Some(condition) => Some(self.bind_condition(condition, &scope, WHERE)?),
+            None => None,
+        };
+        Ok(DeleteQuery {
+            table: delete.table.clone(),
+            conditions,
+        })
+    }
+
+    fn bind_with(&mut self, with: &WithQuery) -> Result<(WithQuery, Vec<BoundColumn>), BindError> {
+        let mut ctes = Vec::with_capacity(with.ctes.len());
+        for (index, cte) in with.ctes.iter().enumerate() {
+            if with.ctes[..index]
+                .iter()
+                .any(|earlier| earlier.name == cte.name)
+            {
+                return Err(BindError::new(
+                    BindErrorKind::InvalidQuery(format!(
+                        "WITH query name \"{}\" specified more than once",
+                        cte.name
+                    )),
+                    Some(cte.name.clone()),
+                ));
+            }
+            // The anchor of a recursive CTE fixes its columns before the
+            // recursive term, which reads them, is bound.
+            let anchor = match &cte.query.body {
+                SetExpression::Operation {
+                    operator: SetOperator::Union,
+                    left,
+                    right,
+                    ..
+                } if with.recursive => {
+                    check_recursive_term(&cte.name, right)?;
+                    Some(self.bind_set_expression(left, None)?.1)
+                }
+                _ => None,
+            };
+            let (query, columns) = match anchor {
+                Some(anchor) => {
+                    self.ctes
+                        .push((cte.name.clone(), rename_columns(cte, anchor)?));
+                    let bound = self.bind_query(&cte.query, None);
+                    self.ctes.pop();
+                    bound?
+                }
+                None => self.bind_query(&cte.query, None)?,
+            };
+            self.ctes
+                .push((cte.name.clone(), rename_columns(cte, columns)?));
+            ctes.push(CommonTableExpression {
+                name: cte.name.clone(),
+                columns: cte.columns.clone(),
+                query,
+            });
+        }
+        let (body, columns) = self.bind_query(&with.body, None)?;
+        Ok((
+            WithQuery {
+                recursive: with.recursive,
+                ctes,
+                body,
+            },
+            columns,
+        ))
+    }
+
+    fn bind_query(
+        &mut self,
+        query: &Query,
+        outer: Option<&Scope>,
+    ) -> Result<(Query, Vec<BoundColumn>), BindError> {
+        let (body, order_by, columns) = match &query.body {
+            SetExpression::Select(select) => {
+                let (select, order_by, columns) =
+                    self.bind_select(select, &query.order_by, outer)?;
+                (SetExpression::Select(Box::new(select)), order_by, columns)
+            }
+            // ORDER BY of a set operation can only see its result columns.
+            body => {
+                let (body, columns) = self.bind_set_expression(body, outer)?;
+                let scope = Scope {
+                    tables: Vec::new(),
+                    merged: columns.clone(),
+                    outer,
+                };
+                let order_by = self.bind_order_by(&query.order_by, &scope, &[], &columns)?;
+                (body, order_by, columns)
+            }
+        };
+        Ok((
+            Query {
+                body,
+                order_by,
+                limit: query.limit,
+                offset: query.offset,
+            },
+            columns,
+        ))
+    }
+
+    fn bind_set_expression(
+        &mut self,
+        expression: &SetExpression,
+        outer: Option<&Scope>,
+    ) -> Result<(SetExpression, Vec<BoundColumn>), BindError> {
+        match expression {
+            SetExpression::Select(select) => {
+                let (select, _, columns) = self.bind_select(select, &[], outer)?;
+                Ok((SetExpression::Select(Box::new(select)), columns))
+            }
+            SetExpression::Query(query) => {
+                let (query, columns) = self.bind_query(query, outer)?;
+                Ok((SetExpression::Query(Box::new(query)), columns))
+            }
+            SetExpression::Operation {
+                operator,
+                all,
+                left,
+                right,
+            } => {
+                let (left, left_columns) = self.bind_set_expression(left, outer)?;
+                let (right, right_columns) = self.bind_set_expression(right, outer)?;
+                let keyword = match operator {
+                    SetOperator::Union => "UNION",
+                    SetOperator::Intersect => "INTERSECT",
+                    SetOperator::Except => "EXCEPT",
+                };
+                if left_columns.len() != right_columns.len() {
+                    return Err(BindError::new(
+                        BindErrorKind::InvalidQuery(format!(
+                            "each {} query must have the same number of columns",
+                            keyword
+                        )),
+                        Some(keyword.to_string()),
+                    ));
+                }
+                // Values of every numeric type are held alike at run time,
+                // so matching columns only need compatible types.
+                let mut columns = Vec::with_capacity(left_columns.len());
+                for (left, right) in left_columns.into_iter().zip(right_columns) {
+                    let data_type =
+                        common_type(&left.data_type, &right.data_type).ok_or_else(|| {
+                            BindError::new(
+                                BindErrorKind::TypeMismatch(format!(
+                                    "{} types {} and {} cannot be matched",
+                                    keyword,
+                                    type_name(&left.data_type),
+                                    type_name(&right.data_type)
+                                )),
+                                Some(keyword.to_string()),
+                            )
+                        })?;
+                    columns.push(BoundColumn {
+                        name: left.name,
+                        data_type,
+                    });
+                }
+                Ok((
+                    SetExpression::Operation {
+                        operator: *operator,
+                        all: *all,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    },
+                    columns,
+                ))
+            }
+        }
+    }
+
+    fn bind_select(
+        &mut self,
+        select: &SelectQuery,
+        order_by: &[OrderByItem],
+        outer: Option<&Scope>,
+    ) -> Result<(SelectQuery, Vec<OrderByItem>, Vec<BoundColumn>), BindError> {
+        let mut scope = Scope {
+            tables: Vec::new(),
+            merged: Vec::new(),
+            outer,
+        };
+        let mut from = Vec::with_capacity(select.from.len());
+        for reference in &select.from {
+            let (reference, tables, merged) = self.bind_table_reference(reference, outer)?;
+            add_tables(&mut scope, tables)?;
+            scope.merged.extend(merged);
+            from.push(reference);
+        }
+        let conditions = match &select.conditions {
+            Some(condition) => Some(self.bind_condition(condition, &scope, WHERE)?),
+            None => None,
+        };
+        let mut group_by = Vec::with_capacity(select.group_by.len());
+        for expression in &select.group_by {
+            group_by.push(self.bind_expression(expression, &scope, GROUP_BY)?.0);
+        }
+
+        let enclosing = std::mem::take(&mut self.windows);
+        let bound = self.bind_select_outputs(select, order_by, &scope);
+        self.windows = enclosing;
+        let (columns, outputs, having, quantifier, windows, order_by) = bound?;
+        check_grouping(&columns, &having, &quantifier, &order_by, &group_by, &scope)?;
+        if let Some(locking) = &select.locking {
+            check_locking(locking, select, &columns, &order_by, &scope)?;
+        }
+        Ok((
+            SelectQuery {
+                quantifier,
+                columns,
+                from,
+                conditions,
+                group_by,
+                having,
+                windows,
+                locking: select.locking.clone(),
+            },
+            order_by,
+            outputs,
+        ))
+    }
+
+    /// Binds the parts of a SELECT evaluated after grouping, which may use
+    /// its WINDOW clause.
+    #[allow(clippy::type_complexity)]
+    fn bind_select_outputs(
+        &mut self,
+        select: &SelectQuery,
+        order_by: &[OrderByItem],
+        scope: &Scope,
+    ) -> Result<
+        (
+            Vec<SQLExpression>,
+            Vec<BoundColumn>,
+            Option<SQLExpression>,
+            SelectQuantifier,
+            Vec<NamedWindow>,
+            Vec<OrderByItem>,
+        ),
+        BindError,
+    > {
+        let mut windows = Vec::with_capacity(select.windows.len());
+        for window in &select.windows {
+            if self.windows.contains(&window.name) {
+                return Err(BindError::new(
+                    BindErrorKind::InvalidQuery(format!(
+                        "window \"{}\" is already defined",
+                        window.name
+                    )),
+                    Some(window.name.clone()),
+                ));
+            }
+            let specification = self.bind_window(&window.specification, scope, SELECT_LIST)?;
+            windows.push(NamedWindow {
+                name: window.name.clone(),
+                specification,
+            });
+            self.windows.push(window.name.clone());
+        }
+
+        let mut columns = Vec::with_capacity(select.columns.len());
+        let mut outputs = Vec::with_capacity(select.columns.len());
+        for column in &select.columns {
+            if *column == SQLExpression::Wildcard {
+                // Columns merged by a join are listed once, ahead of the rest.
+                outputs.extend(scope.merged.iter().cloned());
+                for table in &scope.tables {
+                    outputs.extend(
+                        table
+                            .columns
+                            .iter()
+                            .filter(|column| {
+                                !scope.merged.iter().any(|merged| merged.name == column.name)
+                            })
+                            .cloned(),
+                    );
+                }
+                columns.push(SQLExpression::Wildcard);
+                continue;
+            }
+            let (expression, data_type) = self.bind_expression(column, scope, SELECT_LIST)?;
+            outputs.push(BoundColumn {
+                name: column_name(&expression),
+                data_type,
+            });
+            columns.push(expression);
+        }
+        let having = match &select.having {
+            Some(having) => Some(self.bind_condition(having, scope, HAVING)?),
+            None => None,
+        };
+        let quantifier = match &select.quantifier {
+            SelectQuantifier::DistinctOn(keys) => {
+                let mut bound = Vec::with_capacity(keys.len());
+                for key in keys {
+                    bound.push(self.bind_sort_key(key, scope, &columns, &outputs)?);
+                }
+                SelectQuantifier::DistinctOn(bound)
+            }
+            quantifier => quantifier.clone(),
+        };
+        let order_by = self.bind_order_by(order_by, scope, &columns, &outputs)?;
+        Ok((columns, outputs, having, quantifier, windows, order_by))
+    }
+
+    fn bind_order_by(
+        &mut self,
+        items: &[OrderByItem],
+        scope: &Scope,
+        select_list: &[SQLExpression],
+        outputs: &[BoundColumn],
+    ) -> Result<Vec<OrderByItem>, BindError> {
+        let mut bound = Vec::with_capacity(items.len());
+        for item in items {
+            bound.push(OrderByItem {
+                expression: self.bind_sort_key(&item.expression, scope, select_list, outputs)?,
+                ascending: item.ascending,
+            });
+        }
+        Ok(bound)
+    }
+
+    /// An ORDER BY or DISTINCT ON key: a select list position or alias, or
+    /// an expression over the FROM items.
+    fn bind_sort_key(
+        &mut self,
+        expression: &SQLExpression,
+        scope: &Scope,
+        select_list: &[SQLExpression],
+        outputs: &[BoundColumn],
+    ) -> Result<SQLExpression, BindError> {
+        match expression {
+            SQLExpression::Literal(literal) => {
+                if let Ok(Value::Integer(position)) = Value::from_literal(literal) {
+                    if position < 1 || position as usize > outputs.len() {
+                        return Err(BindError::new(
+                            BindErrorKind::InvalidQuery(format!(
+                                "ORDER BY position {} is not in select list",
+                                position
+                            )),
+                            Some(literal.clone()),
+                        ));
+                    }
+                    return Ok(expression.clone());
+                }
+            }
+            SQLExpression::Identifier(name)
+                if select_list.iter().any(
+                    |item| matches!(item, SQLExpression::Alias { alias, .. } if alias == name),
+                ) =>
+            {
+                return Ok(expression.clone());
+            }
+            _ => {}
+        }
+        Ok(self.bind_expression(expression, scope, ORDER_BY)?.0)
+    }
+
+    /// Binds a FROM item, returning it with the tables it brings into scope
+    /// and the columns its joins merge.
+    fn bind_table_reference(
+        &mut self,
+        reference: &TableReference,
+        outer: Option<&Scope>,
+    ) -> Result<(TableReference, Vec<ScopeTable>, Vec<BoundColumn>), BindError> {
+        match reference {
+            TableReference::Table { name, alias } => {
+                let columns = match self.ctes.iter().rev().find(|(cte, _)| cte == name) {
+                    Some((_, columns)) => columns.clone(),
+                    None => match (self.catalog.table(name), view_columns(name)) {
+                        (Some(table), _) => table
+                            .columns
+                            .iter()
+                            .map(|column| BoundColumn {
+                                name: column.name.clone(),
+                                data_type: Some(column.data_type.clone()),
+                            })
+                            .collect(),
+                        (None, Some(columns)) => bound_columns(columns),
+                        (None, None) => {
+                            return Err(BindError::new(
+                                BindErrorKind::UnknownTable(name.clone()),
+                                Some(name.clone()),
+                            ))
+                        }
+                    },
+                };
+                let table = ScopeTable {
+                    qualifier: alias.clone().unwrap_or_else(|| name.clone()),
+                    columns,
+                };
+                Ok((reference.clone(), vec![table], Vec::new()))
+            }
+            // Derived tables cannot see the enclosing query.
+            TableReference::Subquery { query, alias } => {
+                let (query, columns) = self.bind_query(query, None)?;
+                let table = ScopeTable {
+                    qualifier: alias.clone(),
+                    columns,
+                };
+                Ok((
+                    TableReference::Subquery {
+                        query: Box::new(query),
+                        alias: alias.clone(),
+                    },
+                    vec![table],
+                    Vec::new(),
+                ))
+            }
+            TableReference::Join {
+                join_type,
+                left,
+                right,
+                constraint,
+            } => {
+                let (left, left_tables, left_merged) = self.bind_table_reference(left, outer)?;
+                let (right, right_tables, right_merged) =
+                    self.bind_table_reference(right, outer)?;
+                let left_scope = Scope {
+                    tables: left_tables,
+                    merged: left_merged,
+                    outer: None,
+                };
+                let right_scope = Scope {
+                    tables: right_tables,
+                    merged: right_merged,
+                    outer: None,
+                };
+                let using = match constraint {
+                    JoinConstraint::Using(columns) => columns.clone(),
+                    JoinConstraint::Natural => {
+                        let mut names = Vec::new();
+                        for name in visible_names(&left_scope) {
+                            if !names.contains(&name) && right_scope.column(&name)?.is_some() {
+                                names.push(name);
+                            }
+                        }
+                        names
+                    }
+                    _ => Vec::new(),
+                };
+                let mut merged = Vec::with_capacity(using.len());
+                for name in &using {
+                    let unknown = || {
+                        BindError::new(
+                            BindErrorKind::UnknownColumn(name.clone()),
+                            Some(name.clone()),
+                        )
+                    };
+                    let left_type = left_scope.column(name)?.ok_or_else(unknown)?;
+                    let right_type = right_scope.column(name)?.ok_or_else(unknown)?;
+                    let data_type = common_type(&left_type, &right_type)
+                        .ok_or_else(|| mismatch("compare", &left_type, &right_type, name))?;
+                    merged.push(BoundColumn {
+                        name: name.clone(),
+                        data_type,
+                    });
+                }
+                let mut scope = Scope {
+                    tables: Vec::new(),
+                    merged: Vec::new(),
+                    outer,
+                };
+                add_tables(&mut scope, left_scope.tables)?;
+                add_tables(&mut scope, right_scope.tables)?;
+                scope.merged = merged;
+                for column in left_scope.merged.into_iter().chain(right_scope.merged) {
+                    if !scope.merged.iter().any(|merged| merged.name == column.name) {
+                        scope.merged.push(column);
+                    }
+                }
+                let constraint = match constraint {
+                    JoinConstraint::On(condition) => JoinConstraint::On(self.bind_condition(
+                        condition,
+                        &scope,
+                        JOIN_CONDITION,
+                    )?),
+                    constraint => constraint.clone(),
+                };
+                Ok((
+                    TableReference::Join {
+                        join_type: *join_type,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        constraint,
+                    },
+                    scope.tables,
+                    scope.merged,
+                ))
+            }
+        }
+    }
+
+    fn bind_window(
+        &mut self,
+        window: &WindowSpecification,
+        scope: &Scope,
+        clause: Clause,
+    ) -> Result<WindowSpecification, BindError> {
+        if let Some(name) = &window.name {
+            if !self.windows.contains(name) {
+                return Err(BindError::new(
+                    BindErrorKind::UnknownWindow(name.clone()),
+                    Some(name.clone()),
+                ));
+            }
+        }
+        let inner = Clause {
+            name: "window definitions",
+            aggregates: clause.aggregates,
+            windows: false,
+        };
+        let mut partition_by = Vec::with_capacity(window.partition_by.len());
+        for expression in &window.partition_by {
+            partition_by.push(self.bind_expression(expression, scope, inner)?.0);
+        }
+        let mut order_by = Vec::with_capacity(window.order_by.len());
+        for item in &window.order_by {
+            order_by.push(OrderByItem {
+                expression: self.bind_expression(&item.expression, scope, inner)?.0,
+                ascending: item.ascending,
+            });
+        }
+        let frame = match &window.frame {
+            Some(frame) => Some(WindowFrame {
+                units: frame.units,
+                start: self.bind_frame_bound(&frame.start, scope)?,
+                end: self.bind_frame_bound(&frame.end, scope)?,
+            }),
+            None => None,
+        };
+        Ok(WindowSpecification {
+            name: window.name.clone(),
+            partition_by,
+            order_by,
+            frame,
+        })
+    }
+
+    fn bind_frame_bound(
+        &mut self,
+        bound: &FrameBound,
+        scope: &Scope,
+    ) -> Result<FrameBound, BindError> {
+        let offset = match bound {
+            FrameBound::Preceding(offset) | FrameBound::Following(offset) => offset,
+            bound => return Ok(bound.clone()),
+        };
+        let clause = Clause {
+            name: "window frames",
+            aggregates: false,
+            windows: false,
+        };
+        let (offset, data_type) = self.bind_expression(offset, scope, clause)?;
+        if !data_type.as_ref().is_none_or(is_numeric) {
+            return Err(BindError::new(
+                BindErrorKind::TypeMismatch(format!(
+                    "frame offset must be numeric, not {}",
+                    type_name(&data_type)
+                )),
+                anchor(&offset),
+            ));
+        }
+        Ok(match bound {
+            FrameBound::Preceding(_) => FrameBound::Preceding(Box::new(offset)),
+            _ => FrameBound::Following(Box::new(offset)),
+        })
+    }
+
+    /// Binds a WHERE, HAVING or ON condition, which has to be BOOL.
+    fn bind_condition(
+        &mut self,
+        condition: &SQLExpression,
+        scope: &Scope,
+        clause: Clause,
+    ) -> Result<SQLExpression, BindError> {
+        let (condition, data_type) = self.bind_expression(condition, scope, clause)?;
+        expect_bool(&condition, &data_type, clause.name)?;
+        Ok(condition)
+    }
+
+    fn bind_expression(
+        &mut self,
+        expression: &SQLExpression,
+        scope: &Scope,
+        clause: Clause,
+    ) -> Result<Typed, BindError> {
+        match expression {
+            SQLExpression::Identifier(_) | SQLExpression::QualifiedIdentifier { .. } => {
+                Ok((expression.clone(), scope.lookup(expression)?))
+            }
+            SQLExpression::Literal(literal) => Ok((expression.clone(), literal_type(literal)?)),
+            SQLExpression::BinaryExpression {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.bind_expression(left, scope, clause)?;
+                let right = self.bind_expression(right, scope, clause)?;
+                let (left, right) = match operator.as_str() {
+                    "AND" | "OR" => {
+                        expect_bool(&left.0, &left.1, operator)?;
+                        expect_bool(&right.0, &right.1, operator)?;
+                        (left.0, right.0)
+                    }
+                    "==" | "!=" | "<" | ">" | "<=" | ">=" => {
+                        let (left, right, _) = unify(left, right)?;
+                        (left, right)
+                    }
+                    _ => {
+                        return Err(BindError::new(
+                            BindErrorKind::InvalidQuery(format!("unknown operator {}", operator)),
+                            Some(operator.clone()),
+                        ))
+                    }
+                };
+                Ok((
+                    SQLExpression::BinaryExpression {
+                        operator: operator.clone(),
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    },
+                    Some(DataType::BOOL),
+                ))
+            }
+            SQLExpression::FunctionCall {
+                name,
+                distinct,
+                arguments,
+            } => {
+                if AggregateFunction::from_name(name).is_some() {
+                    if !clause.aggregates {
+                        return Err(BindError::new(
+                            BindErrorKind::AggregateNotAllowed(format!(
+                                "{} is not allowed in {}",
+                                name, clause.name
+                            )),
+                            Some(name.clone()),
+                        ));
+                    }
+                    return self.bind_aggregate(expression, scope, AGGREGATE_ARGUMENTS);
+                }
+                if WindowFunction::from_name(name).is_some() {
+                    return Err(BindError::new(
+                        BindErrorKind::InvalidQuery(format!(
+                            "window function {} requires an OVER clause",
+                            name
+                        )),
+                        Some(name.clone()),
+                    ));
+                }
+                let function = ScalarFunction::from_name(name).ok_or_else(|| {
+                    BindError::new(
+                        BindErrorKind::UnknownFunction(name.clone()),
+                        Some(name.clone()),
+                    )
+                })?;
+                let mut bound = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    bound.push(self.bind_expression(argument, scope, clause)?);
+                }
+                let (arguments, data_type) = bind_scalar_call(function, name, bound)?;
+                Ok((
+                    SQLExpression::FunctionCall {
+                        name: name.clone(),
+                        distinct: *distinct,
+                        arguments,
+                    },
+                    data_type,
+                ))
+            }
+            SQLExpression::Alias { expression, alias } => {
+                let (expression, data_type) = self.bind_expression(expression, scope, clause)?;
+                Ok((
+                    SQLExpression::Alias {
+                        expression: Box::new(expression),
+                        alias: alias.clone(),
+                    },
+                    data_type,
+                ))
+            }
+            SQLExpression::Subquery(query) => {
+                let (query, columns) = self.bind_query(query, Some(scope))?;
+                let data_type = single_column(&columns)?;
+                Ok((SQLExpression::Subquery(Box::new(query)), data_type))
+            }
+            SQLExpression::InSubquery {
+                expression,
+                subquery,
+                negated,
+            } => {
+                let (expression, data_type) = self.bind_expression(expression, scope, clause)?;
+                let (subquery, columns) = self.bind_query(subquery, Some(scope))?;
+                let candidate = single_column(&columns)?;
+                let common = common_type(&data_type, &candidate)
+                    .ok_or_else(|| mismatch("compare", &data_type, &candidate, "IN"))?;
+                let expression = match &common {
+                    Some(common) => coerce(expression, &data_type, common)?,
+                    None => expression,
+                };
+                Ok((
+                    SQLExpression::InSubquery {
+                        expression: Box::new(expression),
+                        subquery: Box::new(subquery),
+                        negated: *negated,
+                    },
+                    Some(DataType::BOOL),
+                ))
+            }
+            SQLExpression::Exists { subquery, negated } => {
+                let (subquery, _) = self.bind_query(subquery, Some(scope))?;
+                Ok((
+                    SQLExpression::Exists {
+                        subquery: Box::new(subquery),
+                        negated: *negated,
+                    },
+                    Some(DataType::BOOL),
+                ))
+            }
+            SQLExpression::Not(expression) => {
+                let (expression, data_type) = self.bind_expression(expression, scope, clause)?;
+                expect_bool(&expression, &data_type, "NOT")?;
+                Ok((
+                    SQLExpression::Not(Box::new(expression)),
+                    Some(DataType::BOOL),
+                ))
+            }
+            SQLExpression::IsNull {
+                expression,
+                negated,
+            } => {
+                let (expression, _) = self.bind_expression(expression, scope, clause)?;
+                Ok((
+                    SQLExpression::IsNull {
+                        expression: Box::new(expression),
+                        negated: *negated,
+                    },
+                    Some(DataType::BOOL),
+                ))
+            }
+            SQLExpression::Case {
+                operand,
+                branches,
+                else_result,
+            } => self.bind_case(operand, branches, else_result, scope, clause),
+            SQLExpression::Cast {
+                expression,
+                data_type,
+            } => {
+                let (expression, from) = self.bind_expression(expression, scope, clause)?;
+                if let Some(from) = &from {
+                    if !castable(from, data_type) {
+                        return Err(BindError::new(
+                            BindErrorKind::TypeMismatch(format!(
+                                "cannot cast {} to {}",
+                                from, data_type
+                            )),
+                            anchor(&expression),
+                        ));
+                    }
+                }
+                check_literal(&expression, data_type)?;
+                Ok((
+                    SQLExpression::Cast {
+                        expression: Box::new(expression),
+                        data_type: data_type.clone(),
+                    },
+                    Some(data_type.clone()),
+                ))
+            }
+            SQLExpression::WindowFunction { function, window } => {
+                let name = column_name(function).to_uppercase();
+                if !clause.windows {
+                    return Err(BindError::new(
+                        BindErrorKind::InvalidQuery(format!(
+                            "window function {} is not allowed in {}",
+                            name, clause.name
+                        )),
+                        Some(name),
+                    ));
+                }
+                let arguments = Clause {
+                    name: "window function arguments",
+                    aggregates: clause.aggregates,
+                    windows: false,
+                };
+                let (function, data_type) = self.bind_window_call(function, scope, arguments)?;
+                let window = self.bind_window(window, scope, clause)?;
+                Ok((
+                    SQLExpression::WindowFunction {
+                        function: Box::new(function),
+                        window,
+                    },
+                    data_type,
+                ))
+            }
+            SQLExpression::Wildcard => Err(BindError::new(
+                BindErrorKind::InvalidQuery(
+                    "* is only allowed in a select list or COUNT(*)".to_string(),
+                ),
+                Some("*".to_string()),
+            )),
+            SQLExpression::Operator(_) | SQLExpression::CreateColumn { .. } => Err(BindError::new(
+                BindErrorKind::InvalidQuery(format!("{:?} is not a value expression", expression)),
+                None,
+            )),
+        }
+    }
+
+    /// `COUNT`, `SUM`, `AVG`, `MIN` or `MAX`, whose argument is bound in
+    /// `clause`.
+    fn bind_aggregate(
+        &mut self,
+        call: &SQLExpression,
+        scope: &Scope,
+        clause: Clause,
+    ) -> Result<Typed, BindError> {
+        let (name, distinct, arguments) = match call {
+            SQLExpression::FunctionCall {
+                name,
+                distinct,
+                arguments,
+            } => (name, *distinct, arguments),
+            _ => return self.bind_expression(call, scope, clause),
+        };
+        let function = AggregateFunction::from_name(name).ok_or_else(|| {
+            BindError::new(
+                BindErrorKind::UnknownFunction(name.clone()),
+                Some(name.clone()),
+            )
+        })?;
+        let (argument, data_type) = match arguments.as_slice() {
+            [SQLExpression::Wildcard] if function == AggregateFunction::Count && !distinct => {
+                (SQLExpression::Wildcard, None)
+            }
+            [argument] => self.bind_expression(argument, scope, clause)?,
+            _ => {
+                return Err(BindError::new(
+                    BindErrorKind::TypeMismatch(format!("{} takes exactly one argument", name)),
+                    Some(name.clone()),
+                ))
+            }
+        };
+        let result = match function {
+            AggregateFunction::Count => Some(DataType::LONG { unsigned: false }),
+            AggregateFunction::Min | AggregateFunction::Max => data_type,
+            AggregateFunction::Sum | AggregateFunction::Avg => {
+                if !data_type.as_ref().is_none_or(is_numeric) {
+                    return Err(BindError::new(
+                        BindErrorKind::TypeMismatch(format!(
+                            "{} requires a numeric argument, not {}",
+                            name,
+                            type_name(&data_type)
+                        )),
+                        Some(name.clone()),
+                    ));
+                }
+                match (function, &data_type) {
+                    (AggregateFunction::Sum, Some(data_type))
+                        if integer_range(data_type).is_some() =>
+                    {
+                        Some(DataType::BIGINT { unsigned: false })
+                    }
+                    _ => Some(DataType::DOUBLE { unsigned: false }),
+                }
+            }
+        };
+        Ok((
+            SQLExpression::FunctionCall {
+                name: name.clone(),
+                distinct,
+                arguments: vec![argument],
+            },
+            result,
+        ))
+    }
+
+    /// The function of a window function call: an aggregate, or one of the
+    /// ranking and offset functions.
+    fn bind_window_call(
+        &mut self,
+        call: &SQLExpression,
+        scope: &Scope,
+        clause: Clause,
+    ) -> Result<Typed, BindError> {
+        let (name, distinct, arguments) = match call {
+            SQLExpression::FunctionCall {
+                name,
+                distinct,
+                arguments,
+            } => (name, *distinct, arguments),
+            _ => return self.bind_expression(call, scope, clause),
+        };
+        if AggregateFunction::from_name(name).is_some() {
+            return self.bind_aggregate(call, scope, clause);
+        }
+        let function = WindowFunction::from_name(name).ok_or_else(|| {
+            BindError::new(
+                BindErrorKind::UnknownFunction(name.clone()),
+                Some(name.clone()),
+            )
+        })?;
+        let (min, max) = function.arity();
+        if arguments.len() < min || arguments.len() > max {
+            return Err(BindError::new(
+                BindErrorKind::TypeMismatch(format!(
+                    "{} takes {} to {} arguments, got {}",
+                    function.name(),
+                    min,
+                    max,
+                    arguments.len()
+                )),
+                Some(name.clone()),
+            ));
+        }
+        let mut bound = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            bound.push(self.bind_expression(argument, scope, clause)?);
+        }
+        let data_type = match function {
+            WindowFunction::RowNumber | WindowFunction::Rank | WindowFunction::DenseRank => {
+                Some(DataType::LONG { unsigned: false })
+            }
+            _ => bound[0].1.clone(),
+        };
+        let mut arguments = Vec::with_capacity(bound.len());
+        for (position, (argument, argument_type)) in bound.into_iter().enumerate() {
+            arguments.push(match position {
+                1 if argument_type
+                    .as_ref()
+                    .is_some_and(|data_type| integer_range(data_type).is_none()) =>
+                {
+                    return Err(mismatch_argument(
+                        name,
+                        position,
+                        ParameterType::Integer,
+                        &argument_type,
+                        &argument,
+                    ))
+                }
+                // The default of LAG and LEAD stands in for the value.
+                2 => {
+                    if common_type(&argument_type, &data_type).is_none() {
+                        return Err(mismatch(
+                            "combine",
+                            &data_type,
+                            &argument_type,
+                            &anchor(&argument).unwrap_or_default(),
+                        ));
+                    }
+                    match &data_type {
+                        Some(target) => coerce(argument, &argument_type, target)?,
+                        None => argument,
+                    }
+                }
+                _ => argument,
+            });
+        }
+        Ok((
+            SQLExpression::FunctionCall {
+                name: name.clone(),
+                distinct,
+                arguments,
+            },
+            data_type,
+        ))
+    }
+
+    fn bind_case(
+        &mut self,
+        operand: &Option<Box<SQLExpression>>,
+        branches: &[(SQLExpression, SQLExpression)],
+        else_result: &Option<Box<SQLExpression>>,
+        scope: &Scope,
+        clause: Clause,
+    ) -> Result<Typed, BindError> {
+        let operand = match operand {
+            Some(operand) => Some(self.bind_expression(operand, scope, clause)?),
+            None => None,
+        };
+        let mut conditions = Vec::with_capacity(branches.len());
+        let mut results = Vec::with_capacity(branches.len() + 1);
+        for (condition, result) in branches {
+            let condition = self.bind_expression(condition, scope, clause)?;
+            conditions.push(match &operand {
+                // The operand is compared with each WHEN value; only the
+                // value side can take a cast here.
+                Some((operand, operand_type)) => {
+                    let (_, value, _) = unify((operand.clone(), operand_type.clone()), condition)?;
+                    value
+                }
+                None => {
+                    expect_bool(&condition.0, &condition.1, "CASE WHEN")?;
+                    condition.0
+                }
+            });
+            results.push(self.bind_expression(result, scope, clause)?);
+        }
+        if let Some(result) = else_result {
+            results.push(self.bind_expression(result, scope, clause)?);
+        }
+        let mut data_type = None;
+        for (result, result_type) in &results {
+            data_type = common_type(&data_type, result_type).ok_or_else(|| {
+                mismatch(
+                    "combine",
+                    &data_type,
+                    result_type,
+                    &anchor(result).unwrap_or_default(),
+                )
+            })?;
+        }
+        let mut coerced = Vec::with_capacity(results.len());
+        for (result, result_type) in results {
+            coerced.push(match &data_type {
+                Some(target) => coerce(result, &result_type, target)?,
+                None => result,
+            });
+        }
+        let else_result = if else_result.is_some() {
+            coerced.pop().map(Box::new)
+        } else {
+            None
+        };
+        Ok((
+            SQLExpression::Case {
+                operand: operand.map(|(operand, _)| Box::new(operand)),
+                branches: conditions.into_iter().zip(coerced).collect(),
+                else_result,
+            },
+            data_type,
+        ))
+    }
+}
+
+/// A query that groups its rows, or aggregates them into one, returns one
+/// row per group, so outside aggregate calls its select list, HAVING and
+/// ORDER BY can only read the expressions it groups by.
+fn check_grouping(
+    columns: &[SQLExpression],
+    having: &Option<SQLExpression>,
+    quantifier: &SelectQuantifier,
+    order_by: &[OrderByItem],
+    group_by: &[SQLExpression],
+    scope: &Scope,
+) -> Result<(), BindError> {
+    let aliases: Vec<&str> = columns
+        .iter()
+        .filter_map(|column| match column {
+            SQLExpression::Alias { alias, .. } => Some(alias.as_str()),
+            _ => None,
+        })
+        .collect();
+    // ORDER BY may name a select list alias, which is checked where it is
+    // defined.
+    let order_keys = order_by.iter().map(|item| &item.expression).filter(
+        |key| !matches!(key, SQLExpression::Identifier(name) if aliases.contains(&name.as_str())),
+    );
+    let distinct_keys = match quantifier {
+        SelectQuantifier::DistinctOn(keys) => keys.as_slice(),
+        _ => &[],
+    };
+    let expressions: Vec<&SQLExpression> = columns
+        .iter()
+        .chain(having)
+        .chain(distinct_keys)
+        .chain(order_keys)
+        .collect();
+    let mut aggregates = Vec::new();
+    for expression in &expressions {
+        collect_aggregates(expression, &mut aggregates);
+    }
+    if group_by.is_empty() && aggregates.is_empty() && having.is_none() {
+        return Ok(());
+    }
+    for expression in expressions {
+        if *expression == SQLExpression::Wildcard {
+            let columns = scope.tables.iter().flat_map(|table| {
+                table
+                    .columns
+                    .iter()
+                    .map(|column| SQLExpression::QualifiedIdentifier {
+                        table: table.qualifier.clone(),
+                        column: column.name.clone(),
+                    })
+            });
+            for column in columns {
+                if !grouped(&column, group_by, scope) {
+                    return Err(not_grouped(&column, anchor(expression)));
+                }
+            }
+        } else if let Some(column) = ungrouped(expression, group_by, scope) {
+            return Err(not_grouped(column, anchor(column)));
+        }
+    }
+    Ok(())
+}
+
+/// The first column reference of this query in `expression` that is
+/// neither inside an aggregate call nor part of an expression in
+/// `group_by`.
+fn ungrouped<'e>(
+    expression: &'e SQLExpression,
+    group_by: &[SQLExpression],
+    scope: &Scope,
+) -> Option<&'e SQLExpression> {
+    if grouped(expression, group_by, scope) {
+        return None;
+    }
+    match expression {
+        SQLExpression::Identifier(name) => {
+            matches!(scope.column(name), Ok(Some(_))).then_some(expression)
+        }
+        SQLExpression::QualifiedIdentifier { table, .. } => scope
+            .tables
+            .iter()
+            .any(|item| item.qualifier == *table)
+            .then_some(expression),
+        SQLExpression::FunctionCall {
+            name, arguments, ..
+        } => {
+            if AggregateFunction::from_name(name).is_some() {
+                return None;
+            }
+            arguments
+                .iter()
+                .find_map(|argument| ungrouped(argument, group_by, scope))
+        }
+        // The window function runs over the groups, so its arguments read
+        // them as the select list does, aggregate or not.
+        SQLExpression::WindowFunction { function, window } => match function.as_ref() {
+            SQLExpression::FunctionCall { arguments, .. } => arguments
+                .iter()
+                .chain(window_expressions(window))
+                .find_map(|part| ungrouped(part, group_by, scope)),
+            _ => None,
+        },
+        SQLExpression::BinaryExpression { left, right, .. } => {
+            ungrouped(left, group_by, scope).or_else(|| ungrouped(right, group_by, scope))
+        }
+        SQLExpression::Alias { expression, .. }
+        | SQLExpression::Cast { expression, .. }
+        | SQLExpression::InSubquery { expression, .. }
+        | SQLExpression::Not(expression)
+        | SQLExpression::IsNull { expression, .. } => ungrouped(expression, group_by, scope),
+        SQLExpression::Case { .. } => case_parts(expression)
+            .into_iter()
+            .find_map(|part| ungrouped(part, group_by, scope)),
+        _ => None,
+    }
+}
+
+/// Whether `expression` is one of `group_by`, or names the same column
+/// as one of them with or without its table.
+fn grouped(expression: &SQLExpression, group_by: &[SQLExpression], scope: &Scope) -> bool {
+    group_by.iter().any(|key| {
+        key == expression
+            || match (key, expression) {
+                (
+                    SQLExpression::QualifiedIdentifier { table, column },
+                    SQLExpression::Identifier(name),
+                )
+                | (
+                    SQLExpression::Identifier(name),
+                    SQLExpression::QualifiedIdentifier { table, column },
+                ) => {
+                    column == name
+                        && scope
+                            .tables
+                            .iter()
+                            .find(|item| item.columns.iter().any(|column| column.name == *name))
+                            .is_some_and(|item| item.qualifier == *table)
+                }
+                _ => false,
+            }
+    })
+}
+
+fn not_grouped(column: &SQLExpression, near: Option<String>) -> BindError {
+    BindError::new(
+        BindErrorKind::InvalidQuery(format!(
+            "column \"{}\" must appear in the GROUP BY clause or be used in an aggregate function",
+            match column {
+                SQLExpression::QualifiedIdentifier { table, column } => {
+                    format!("{}.{}", table, column)
+                }
+                column => column_name(column),
+            }
+        )),
+        near,
+    )
+}
+
+/// The recursive term of a CTE runs once per round on the rows of the
+/// round before, so an aggregate in it would never see the whole
+/// relation.
+fn check_recursive_term(name: &str, term: &SetExpression) -> Result<(), BindError> {
+    match term {
+        SetExpression::Select(select) => {
+            let mut aggregates = Vec::new();
+            for expression in select.columns.iter().chain(&select.having) {
+                collect_aggregates(expression, &mut aggregates);
+            }
+            match aggregates.first() {
+                Some(aggregate) => Err(BindError::new(
+                    BindErrorKind::AggregateNotAllowed(format!(
+                        "{} is not allowed in the recursive term of \"{}\"",
+                        anchor(aggregate).unwrap_or_default(),
+                        name
+                    )),
+                    anchor(aggregate),
+                )),
+                None => Ok(()),
+            }
+        }
+        SetExpression::Query(query) => check_recursive_term(name, &query.body),
+        SetExpression::Operation { left, right, .. } => {
+            check_recursive_term(name, left)?;
+            check_recursive_term(name, right)
+        }
+    }
+}
+
+/// A locking SELECT has to return rows of its tables one for one, and can
+/// only lock tables of its own FROM clause.
+fn check_locking(
+    locking: &LockingClause,
+    select: &SelectQuery,
+    columns: &[SQLExpression],
+    order_by: &[OrderByItem],
+    scope: &Scope,
+) -> Result<(), BindError> {
+    let mut aggregates = Vec::new();
+    let mut windows = Vec::new();
+    for expression in columns
+        .iter()
+        .chain(order_by.iter().map(|item| &item.expression))
+    {
+        collect_aggregates(expression, &mut aggregates);
+        collect_window_functions(expression, &mut windows);
+    }
+    let clause = if !select.group_by.is_empty() {
+        Some("GROUP BY clause")
+    } else if select.having.is_some() {
+        Some("HAVING clause")
+    } else if select.quantifier != SelectQuantifier::All {
+        Some("DISTINCT clause")
+    } else if !aggregates.is_empty() {
+        Some("aggregate functions")
+    } else if !windows.is_empty() || !select.windows.is_empty() {
+        Some("window functions")
+    } else {
+        None
+    };
+    if let Some(clause) = clause {
+        return Err(BindError::new(
+            BindErrorKind::InvalidQuery(format!("{} is not allowed with {}", locking, clause)),
+            Some(locking.to_string()),
+        ));
+    }
+    for name in &locking.tables {
+        if !scope.tables.iter().any(|table| table.qualifier == *name) {
+            return Err(BindError::new(
+                BindErrorKind::InvalidQuery(format!(
+                    "table \"{}\" in {} clause not found in FROM clause",
+                    name, locking
+                )),
+                Some(name.clone()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the arguments of a scalar function against its signature and
+/// works out what it returns.
+fn bind_scalar_call(
+    function: ScalarFunction,
+    name: &str,
+    arguments: Vec<Typed>,
+) -> Result<(Vec<SQLExpression>, Option<DataType>), BindError> {
+    let signature = function.signature();
+    if !signature.accepts_count(arguments.len()) {
+        return Err(BindError::new(
+            BindErrorKind::TypeMismatch(format!(
+                "{} does not take {} arguments",
+                name,
+                arguments.len()
+            )),
+            Some(name.to_string()),
+        ));
+    }
+    let mut common = None;
+    let mut checked = Vec::with_capacity(arguments.len());
+    for (position, (argument, data_type)) in arguments.into_iter().enumerate() {
+        let parameter = signature.parameter(position);
+        let accepted = match (&data_type, parameter) {
+            (None, _) | (_, ParameterType::Any) => true,
+            (Some(data_type), ParameterType::Text) => {
+                representation(data_type) == Representation::Text
+            }
+            (Some(data_type), ParameterType::Integer) => integer_range(data_type).is_some(),
+            (Some(data_type), ParameterType::Numeric) => is_numeric(data_type),
+            (Some(data_type), ParameterType::DateTime) => matches!(
+                representation(data_type),
+                Representation::DateTime | Representation::Text
+            ),
+            (Some(data_type), ParameterType::Bool) => *data_type == DataType::BOOL,
+        };
+        if !accepted {
+            return Err(mismatch_argument(
+                name, position, parameter, &data_type, &argument,
+            ));
+        }
+        // Text given for a DATETIME parameter is converted once, up front.
+        let argument = match parameter {
+            ParameterType::DateTime => coerce(argument, &data_type, &DataType::DATETIME)?,
+            _ => argument,
+        };
+        if signature.returns == ReturnType::Common {
+            common = common_type(&common, &data_type).ok_or_else(|| {
+                mismatch(
+                    "combine",
+                    &common,
+                    &data_type,
+                    &anchor(&argument).unwrap_or_default(),
+                )
+            })?;
+        }
+        checked.push((argument, data_type));
+    }
+    let data_type = match signature.returns {
+        ReturnType::Text => match checked.first() {
+            Some((_, Some(text @ DataType::TEXT { .. }))) => Some(text.clone()),
+            _ => Some(DataType::TEXT { length: 256 }),
+        },
+        ReturnType::Integer => Some(DataType::LONG { unsigned: false }),
+        ReturnType::Float => Some(DataType::DOUBLE { unsigned: false }),
+        ReturnType::DateTime => Some(DataType::DATETIME),
+        ReturnType::FirstArgument => checked.first().and_then(|(_, data_type)| data_type.clone()),
+        ReturnType::Common => common.clone(),
+    };
+    let mut arguments = Vec::with_capacity(checked.len());
+    for (argument, argument_type) in checked {
+        arguments.push(match &common {
+            Some(common) => coerce(argument, &argument_type, common)?,
+            None => argument,
+        });
+    }
+    Ok((arguments, data_type))
+}
+
+/// How values of a type are held at run time. Converting between types
+/// of the same representation changes no value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Representation {
+    Integer,
+    Float,
+    Text,
+    Bool,
+    DateTime,
+}
+
+fn representation(data_type: &DataType) -> Representation {
+    match data_type {
+        DataType::TEXT { .. } | DataType::UUID { .. } => Representation::Text,
+        DataType::BOOL => Representation::Bool,
+        DataType::DATETIME => Representation::DateTime,
+        DataType::FLOAT { .. } | DataType::DOUBLE { .. } => Representation::Float,
+        _ => Representation::Integer,
+    }
+}
+
+fn is_numeric(data_type: &DataType) -> bool {
+    matches!(
+        representation(data_type),
+        Representation::Integer | Representation::Float
+    )
+}
+
+/// Integer types from narrowest to widest.
+const INTEGER_TYPES: [DataType; 10] = [
+    DataType::BYTE { unsigned: false },
+    DataType::BYTE { unsigned: true },
+    DataType::SHORT { unsigned: false },
+    DataType::SHORT { unsigned: true },
+    DataType::INT { unsigned: false },
+    DataType::INT { unsigned: true },
+    DataType::LONG { unsigned: false },
+    DataType::LONG { unsigned: true },
+    DataType::BIGINT { unsigned: false },
+    DataType::BIGINT { unsigned: true },
+];
+
+/// The narrowest numeric type that holds values of both types. Integers
+/// meet in the first integer type covering both ranges; any float makes
+/// the result a DOUBLE, unless both are the same float type.
+fn common_numeric(left: &DataType, right: &DataType) -> DataType {
+    match (integer_range(left), integer_range(right)) {
+        (Some((left_min, left_max)), Some((right_min, right_max))) => {
+            let (min, max) = (left_min.min(right_min), left_max.max(right_max));
+            INTEGER_TYPES
+                .iter()
+                .find(|candidate| {
+                    integer_range(candidate).is_some_and(|(low, high)| low <= min && max <= high)
+                })
+                .cloned()
+                .unwrap_or(DataType::BIGINT { unsigned: false })
+        }
+        _ if left == right => left.clone(),
+        _ => DataType::DOUBLE { unsigned: false },
+    }
+}
+
+/// The type two values meet in when compared or combined, `None` when they
+/// cannot meet. An untyped NULL takes the other type.
+fn common_type(left: &Option<DataType>, right: &Option<DataType>) -> Option<Option<DataType>> {
+    let (left, right) = match (left, right) {
+        (None, other) | (other, None) => return Some(other.clone()),
+        (Some(left), Some(right)) => (left, right),
+    };
+    if left == right {
+        return Some(Some(left.clone()));
+    }
+    match (representation(left), representation(right)) {
+        (
+            Representation::Integer | Representation::Float,
+            Representation::Integer | Representation::Float,
+        ) => Some(Some(common_numeric(left, right))),
+        (Representation::Text, Representation::Text) => Some(Some(match (left, right) {
+            (DataType::TEXT { length: left }, DataType::TEXT { length: right }) => DataType::TEXT {
+                length: (*left).max(*right),
+            },
+            (DataType::TEXT { .. }, _) => left.clone(),
+            _ => right.clone(),
+        })),
+        (left_representation, right_representation)
+            if left_representation == right_representation =>
+        {
+            Some(Some(left.clone()))
+        }
+        _ => None,
+    }
+}
+
+/// Brings the two sides of a comparison to a common type. A text literal
+/// compared with a DATETIME, BOOL or UUID is read as that type.
+fn unify(
+    left: Typed,
+    right: Typed,
+) -> Result<(SQLExpression, SQLExpression, Option<DataType>), BindError> {
+    let (left, left_type) = left;
+    let (right, right_type) = right;
+    let common = common_type(
+        &literal_as(&left, &left_type, &right_type),
+        &literal_as(&right, &right_type, &left_type),
+    )
+    .ok_or_else(|| {
+        mismatch(
+            "compare",
+            &left_type,
+            &right_type,
+            &anchor(&right).unwrap_or_default(),
+        )
+    })?;
+    match &common {
+        Some(target) => Ok((
+            coerce(left, &left_type, target)?,
+            coerce(right, &right_type, target)?,
+            common,
+        )),
+        None => Ok((left, right, common)),
+    }
+}
+
+/// The type a text literal is read as next to a value of type `other`,
+/// for finding the common type; `coerce` then casts the literal to it.
+fn literal_as(
+    expression: &SQLExpression,
+    data_type: &Option<DataType>,
+    other: &Option<DataType>,
+) -> Option<DataType> {
+    match (expression, data_type, other) {
+        (
+            SQLExpression::Literal(_),
+            Some(DataType::TEXT { .. }),
+            Some(other @ (DataType::DATETIME | DataType::BOOL)),
+        ) => Some(other.clone()),
+        _ => data_type.clone(),
+    }
+}
+
+/// Wraps `expression` in a CAST to `target` when its representation
+/// differs. Literals are converted right away, so a bad one is reported
+/// before anything runs.
+fn coerce(
+    expression: SQLExpression,
+    data_type: &Option<DataType>,
+    target: &DataType,
+) -> Result<SQLExpression, BindError> {
+    let needs_cast = data_type
+        .as_ref()
+        .is_some_and(|data_type| representation(data_type) != representation(target));
+    if !needs_cast {
+        return Ok(expression);
+    }
+    check_literal(&expression, target)?;
+    Ok(SQLExpression::Cast {
+        expression: Box::new(expression),
+        data_type: target.clone(),
+    })
+}
+
+/// Checks that a value can be stored in a column of type `target`. The
+/// value keeps its own type: the executor converts it when the row is
+/// written, so that a number the column cannot hold is reported as a
+/// violation of that column.
+fn assign(
+    expression: SQLExpression,
+    data_type: &Option<DataType>,
+    target: &DataType,
+    column: &str,
+) -> Result<SQLExpression, BindError> {
+    let from = match data_type {
+        Some(from) if from != target => from,
+        _ => return Ok(expression),
+    };
+    let text_literal = matches!(expression, SQLExpression::Literal(_))
+        && representation(from) == Representation::Text;
+    let compatible = common_type(data_type, &Some(target.clone())).is_some()
+        || (text_literal && castable(from, target));
+    if !compatible {
+        return Err(BindError::new(
+            BindErrorKind::TypeMismatch(format!(
+                "column {} is {} but the expression is {}",
+                column, target, from
+            )),
+            anchor(&expression),
+        ));
+    }
+    Ok(expression)
+}
+
+/// Whether an explicit CAST from one type to another can succeed, following
+/// the rules of `cast`.
+fn castable(from: &DataType, to: &DataType) -> bool {
+    let from = representation(from);
+    if let DataType::UUID { .. } = to {
+        return matches!(from, Representation::Text | Representation::Integer);
+    }
+    match representation(to) {
+        Representation::Integer | Representation::Float => matches!(
+            from,
+            Representation::Integer
+                | Representation::Float
+                | Representation::Bool
+                | Representation::Text
+        ),
+        Representation::Bool => matches!(
+            from,
+            Representation::Bool | Representation::Integer | Representation::Text
+        ),
+        Representation::Text => true,
+        Representation::DateTime => matches!(
+            from,
+            Representation::DateTime | Representation::Text | Representation::Integer
+        ),
+    }
+}
+
+/// Converts a literal to `target` to find out whether it fits.
+fn check_literal(expression: &SQLExpression, target: &DataType) -> Result<(), BindError> {
+    if let SQLExpression::Literal(literal) = expression {
+        let value = Value::from_literal(literal).map_err(|_| {
+            BindError::new(
+                BindErrorKind::InvalidLiteral(literal.clone()),
+                Some(literal.clone()),
+            )
+        })?;
+        cast(value, target).map_err(|error| {
+            BindError::new(
+                BindErrorKind::InvalidValue(error.to_string()),
+                Some(literal.clone()),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Integer literals get the narrowest of INT, LONG and BIGINT that holds
+/// them, other numbers DOUBLE and quoted text a TEXT of its own length.
+fn literal_type(literal: &str) -> Result<Option<DataType>, BindError> {
+    let value = Value::from_literal(literal).map_err(|_| {
+        BindError::new(
+            BindErrorKind::InvalidLiteral(literal.to_string()),
+            Some(literal.to_string()),
+        )
+    })?;
+    Ok(match value {
+        Value::Null => None,
+        Value::Bool(_) => Some(DataType::BOOL),
+        Value::Integer(integer) if i32::try_from(integer).is_ok() => {
+            Some(DataType::INT { unsigned: false })
+        }
+        Value::Integer(integer) if i64::try_from(integer).is_ok() => {
+            Some(DataType::LONG { unsigned: false })
+        }
+        Value::Integer(_) => Some(DataType::BIGINT { unsigned: false }),
+        Value::Float(_) => Some(DataType::DOUBLE { unsigned: false }),
+        Value::Text(text) => Some(DataType::TEXT {
+            length: text.chars().count() as i32,
+        }),
+        Value::DateTime(_) => Some(DataType::DATETIME),
+    })
+}
+
+fn expect_bool(
+    expression: &SQLExpression,
+    data_type: &Option<DataType>,
+    context: &str,
+) -> Result<(), BindError> {
+    match data_type {
+        None | Some(DataType::BOOL) => Ok(()),
+        Some(data_type) => Err(BindError::new(
+            BindErrorKind::TypeMismatch(format!(
+                "argument of {} must be BOOL, not {}",
+                context, data_type
+            )),
+            anchor(expression),
+        )),
+    }
+}
+
+/// The type of the one column a subquery used as a value returns.
+fn single_column(columns: &[BoundColumn]) -> Result<Option<DataType>, BindError> {
+    match columns {
+        [column] => Ok(column.data_type.clone()),
+        _ => Err(BindError::new(
+            BindErrorKind::TypeMismatch("subquery must return only one column".to_string()),
+            Some("SELECT".to_string()),
+        )),
+    }
+}
+
+const NAME: DataType = DataType::TEXT { length: 256 };
+
+/// The columns a SHOW or DESCRIBE statement returns.
+pub fn show_columns(show: &ShowQuery) -> &'static [(&'static str, DataType)] {
+    match show {
+        ShowQuery::Databases => &[("database", NAME)],
+        ShowQuery::Tables => &[("table", NAME)],
+        ShowQuery::Describe(_) => &[
+            ("column", NAME),
+            ("type", NAME),
+            ("nullable", NAME),
+            ("key", NAME),
+            ("extra", NAME),
+        ],
+    }
+}
+
+/// Columns of a relation whose shape is fixed, such as a catalog view.
+fn bound_columns(columns: &[(&str, DataType)]) -> Vec<BoundColumn> {
+    columns
+        .iter()
+        .map(|(name, data_type)| BoundColumn {
+            name: name.to_string(),
+            data_type: Some(data_type.clone()),
+        })
+        .collect()
+}
+
+/// Applies the column list of `name (columns) AS (...)` to the CTE's result.
+fn rename_columns(
+    cte: &CommonTableExpression,
+    columns: Vec<BoundColumn>,
+) -> Result<Vec<BoundColumn>, BindError> {
+    if cte.columns.is_empty() {
+        return Ok(columns);
+    }
+    if cte.columns.len() != columns.len() {
+        return Err(BindError::new(
+            BindErrorKind::InvalidQuery(format!(
+                "WITH query \"{}\" has {} columns available but {} columns specified",
+                cte.name,
+                columns.len(),
+                cte.columns.len()
+            )),
+            Some(cte.name.clone()),
+        ));
+    }
+    Ok(cte
+        .columns
+        .iter()
+        .zip(columns)
+        .map(|(name, column)| BoundColumn {
+            name: name.clone(),
+            data_type: column.data_type,
+        })
+        .collect())
+}
+
+/// Adds FROM items to a scope; two of them may not share a name.
+fn add_tables(scope: &mut Scope, tables: Vec<ScopeTable>) -> Result<(), BindError> {
+    for table in tables {
+        if scope
+            .tables
+            .iter()
+            .any(|existing| existing.qualifier == table.qualifier)
+        {
+            return Err(BindError::new(
+                BindErrorKind::InvalidQuery(format!(
+                    "table name \"{}\" specified more than once",
+                    table.qualifier
+                )),
+                Some(table.qualifier),
+            ));
+        }
+        scope.tables.push(table);
+    }
+    Ok(())
+}
+
+/// Column names of a scope in the order a `*` lists them.
+fn visible_names(scope: &Scope) -> Vec<String> {
+    scope
+        .merged
+        .iter()
+        .chain(scope.tables.iter().flat_map(|table| &table.columns))
+        .map(|column| column.name.clone())
+        .collect()
+}
+
+/// The token an expression starts with, for locating errors in it.
+fn anchor(expression: &SQLExpression) -> Option<String> {
+    match expression {
+        SQLExpression::Identifier(name) | SQLExpression::Literal(name) => Some(name.clone()),
+        SQLExpression::QualifiedIdentifier { table, .. } => Some(table.clone()),
+        SQLExpression::FunctionCall { name, .. } => Some(name.clone()),
+        SQLExpression::BinaryExpression { left, .. } => anchor(left),
+        SQLExpression::Alias { expression, .. }
+        | SQLExpression::Cast { expression, .. }
+        | SQLExpression::InSubquery { expression, .. }
+        | SQLExpression::IsNull { expression, .. } => anchor(expression),
+        SQLExpression::Not(_) => Some("NOT".to_string()),
+        SQLExpression::WindowFunction { function, .. } => anchor(function),
+        SQLExpression::Case { .. } => Some("CASE".to_string()),
+        SQLExpression::Subquery(_) => Some("SELECT".to_string()),
+        SQLExpression::Exists { .. } => Some("EXISTS".to_string()),
+        SQLExpression::Wildcard => Some("*".to_string()),
+        _ => None,
+    }
+}
+
+fn type_name(data_type: &Option<DataType>) -> String {
+    match data_type {
+        Some(data_type) => data_type.to_string(),
+        None => "NULL".to_string(),
+    }
+}
+
+fn mismatch(
+    action: &str,
+    left: &Option<DataType>,
+    right: &Option<DataType>,
+    near: &str,
+) -> BindError {
+    BindError::new(
+        BindErrorKind::TypeMismatch(format!(
+            "cannot {} {} with {}",
+            action,
+            type_name(left),
+            type_name(right)
+        )),
+        Some(near.to_string()).filter(|near| !near.is_empty()),
+    )
+}
+
+fn mismatch_argument(
+    name: &str,
+    position: usize,
+    parameter: ParameterType,
+    data_type: &Option<DataType>,
+    argument: &SQLExpression,
+) -> BindError {
+    BindError::new(
+        BindErrorKind::TypeMismatch(format!(
+            "argument {} of {} must be {}, found {}",
+            position + 1,
+            name,
+            parameter.name(),
+            type_name(data_type)
+        )),
+        anchor(argument).or_else(|| Some(name.to_string())),
+    )
+}
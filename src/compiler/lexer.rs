@@ -19,31 +19,56 @@ pub struct Token {
     pub value: String,
 }*/
 
+use std::fmt;
 use std::iter::Peekable;
 use std::str::Chars;
 
+/// Where a token starts in the source, both counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 #[derive(Debug)]
 pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
+    position: Position,
+    positions: Vec<Position>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Lexer {
             input: input.chars().peekable(),
+            position: Position { line: 1, column: 1 },
+            positions: Vec::new(),
         }
     }
 
+    /// The start of every token returned by `tokenize`, by token index.
+    pub fn positions(&self) -> &[Position] {
+        &self.positions
+    }
+
     pub fn tokenize(&mut self) -> Vec<String> {
         let mut tokens = Vec::new();
         while let Some(&ch) = self.input.peek() {
+            self.positions.truncate(tokens.len());
+            self.positions.push(self.position);
             match ch {
                 ' ' | '\t' | '\n' | '\r' => {
-                    self.input.next();
+                    self.advance();
                 }
-                '(' | ')' | ',' | ';' => {
+                '(' | ')' | ',' | ';' | '*' | '.' | '-' => {
                     tokens.push(ch.to_string());
-                    self.input.next();
+                    self.advance();
                 }
                 '\'' | '"' => {
                     tokens.push(self.collect_string_literal(ch));
@@ -51,24 +76,45 @@ impl<'a> Lexer<'a> {
                 '!' | '=' | '<' | '>' => {
                     tokens.push(self.collect_operator());
                 }
+                ':' => {
+                    self.advance();
+                    if self.input.peek() == Some(&':') {
+                        self.advance();
+                        tokens.push("::".to_string());
+                    }
+                }
                 _ => {
-                    if ch.is_alphanumeric() || ch == '_' {
+                    if ch.is_ascii_digit() {
+                        tokens.push(self.collect_number());
+                    } else if ch.is_alphanumeric() || ch == '_' {
                         tokens.push(self.collect_identifier());
                     } else {
-                        self.input.next();
+                        self.advance();
                     }
                 }
             }
         }
+        self.positions.truncate(tokens.len());
         tokens
     }
 
+    fn advance(&mut self) -> Option<char> {
+        let ch = self.input.next()?;
+        if ch == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(ch)
+    }
+
     fn collect_string_literal(&mut self, quote: char) -> String {
         let mut literal = String::new();
-        literal.push(self.input.next().unwrap());
+        literal.push(self.advance().unwrap());
         while let Some(&ch) = self.input.peek() {
             literal.push(ch);
-            self.input.next();
+            self.advance();
             if ch == quote {
                 break;
             }
@@ -81,7 +127,7 @@ impl<'a> Lexer<'a> {
         while let Some(&ch) = self.input.peek() {
             if ch == '!' || ch == '=' || ch == '<' || ch == '>' {
                 operator.push(ch);
-                self.input.next();
+                self.advance();
             } else {
                 break;
             }
@@ -89,12 +135,25 @@ impl<'a> Lexer<'a> {
         operator
     }
 
+    /// Numbers may carry a fractional part, so `3.14` stays one token
+    /// instead of being split at the `.` that separates qualified names.
+    fn collect_number(&mut self) -> String {
+        let mut number = self.collect_identifier();
+        let mut lookahead = self.input.clone();
+        if lookahead.next() == Some('.') && lookahead.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+            self.advance();
+            number.push('.');
+            number.push_str(&self.collect_identifier());
+        }
+        number
+    }
+
     fn collect_identifier(&mut self) -> String {
         let mut identifier = String::new();
         while let Some(&ch) = self.input.peek() {
             if ch.is_alphanumeric() || ch == '_' {
                 identifier.push(ch);
-                self.input.next();
+                self.advance();
             } else {
                 break;
             }
@@ -102,4 +161,3 @@ impl<'a> Lexer<'a> {
         identifier
     }
 }
-
@@ -1,16 +1,73 @@
+use std::fmt;
 use std::iter::Peekable;
+use std::ops::Range;
 use std::slice::Iter;
 
-#[derive(Debug)]
-enum SQLExpression {
+use crate::compiler::lexer::{Lexer, Position};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SQLExpression {
     Identifier(String),
+    /// A column qualified by its table name or alias, as in `owners.name`.
+    QualifiedIdentifier {
+        table: String,
+        column: String,
+    },
     Literal(String),
     Operator(String),
+    /// `*` in a select list or as the argument of `COUNT(*)`.
+    Wildcard,
     BinaryExpression {
         operator: String,
         left: Box<SQLExpression>,
         right: Box<SQLExpression>,
     },
+    FunctionCall {
+        name: String,
+        distinct: bool,
+        arguments: Vec<SQLExpression>,
+    },
+    /// A select list item renamed with `AS`.
+    Alias {
+        expression: Box<SQLExpression>,
+        alias: String,
+    },
+    /// A parenthesized SELECT used as a scalar value.
+    Subquery(Box<Query>),
+    InSubquery {
+        expression: Box<SQLExpression>,
+        subquery: Box<Query>,
+        negated: bool,
+    },
+    Exists {
+        subquery: Box<Query>,
+        negated: bool,
+    },
+    /// `NOT expression`, the negation of a condition.
+    Not(Box<SQLExpression>),
+    /// `expression IS [NOT] NULL`.
+    IsNull {
+        expression: Box<SQLExpression>,
+        negated: bool,
+    },
+    /// `CASE [operand] WHEN ... THEN ... [ELSE ...] END`. With an operand,
+    /// each WHEN holds a value compared to it; without, a condition.
+    Case {
+        operand: Option<Box<SQLExpression>>,
+        branches: Vec<(SQLExpression, SQLExpression)>,
+        else_result: Option<Box<SQLExpression>>,
+    },
+    /// `CAST(expression AS type)` or `expression::type`.
+    Cast {
+        expression: Box<SQLExpression>,
+        data_type: DataType,
+    },
+    /// A function evaluated over a window of rows, `name(...) OVER window`.
+    /// `function` is always a `FunctionCall`.
+    WindowFunction {
+        function: Box<SQLExpression>,
+        window: WindowSpecification,
+    },
     CreateColumn {
         name: String,
         data_type: DataType,
@@ -20,8 +77,109 @@ enum SQLExpression {
     },
 }
 
-#[derive(Debug)]
-enum DataType {
+/// Prints the expression the way a query spells it. Subqueries and the
+/// windows of window functions are shown only as `(...)`.
+impl fmt::Display for SQLExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let not = |negated: bool| if negated { "NOT " } else { "" };
+        match self {
+            SQLExpression::Identifier(name) => write!(f, "{}", name),
+            SQLExpression::QualifiedIdentifier { table, column } => {
+                write!(f, "{}.{}", table, column)
+            }
+            SQLExpression::Literal(text) | SQLExpression::Operator(text) => write!(f, "{}", text),
+            SQLExpression::Wildcard => write!(f, "*"),
+            SQLExpression::BinaryExpression {
+                operator,
+                left,
+                right,
+            } => write!(f, "{} {} {}", Operand(left), operator, Operand(right)),
+            SQLExpression::FunctionCall {
+                name,
+                distinct,
+                arguments,
+            } => {
+                write!(f, "{}({}", name, if *distinct { "DISTINCT " } else { "" })?;
+                for (index, argument) in arguments.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, ")")
+            }
+            SQLExpression::Alias { expression, alias } => write!(f, "{} AS {}", expression, alias),
+            SQLExpression::Subquery(_) => write!(f, "(...)"),
+            SQLExpression::InSubquery {
+                expression,
+                negated,
+                ..
+            } => write!(f, "{} {}IN (...)", Operand(expression), not(*negated)),
+            SQLExpression::Exists { negated, .. } => write!(f, "{}EXISTS (...)", not(*negated)),
+            SQLExpression::Not(expression) => write!(f, "NOT {}", Operand(expression)),
+            SQLExpression::IsNull {
+                expression,
+                negated,
+            } => write!(f, "{} IS {}NULL", Operand(expression), not(*negated)),
+            SQLExpression::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                write!(f, "CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {}", operand)?;
+                }
+                for (condition, result) in branches {
+                    write!(f, " WHEN {} THEN {}", condition, result)?;
+                }
+                if let Some(result) = else_result {
+                    write!(f, " ELSE {}", result)?;
+                }
+                write!(f, " END")
+            }
+            SQLExpression::Cast {
+                expression,
+                data_type,
+            } => write!(f, "CAST({} AS {})", expression, data_type),
+            SQLExpression::WindowFunction { function, window } => match &window.name {
+                Some(name)
+                    if window.partition_by.is_empty()
+                        && window.order_by.is_empty()
+                        && window.frame.is_none() =>
+                {
+                    write!(f, "{} OVER {}", function, name)
+                }
+                _ => write!(f, "{} OVER (...)", function),
+            },
+            SQLExpression::CreateColumn {
+                name, data_type, ..
+            } => write!(f, "{} {}", name, data_type),
+        }
+    }
+}
+
+/// An operand of an operator, parenthesized when it is itself an operation.
+struct Operand<'a>(&'a SQLExpression);
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            SQLExpression::BinaryExpression { .. }
+            | SQLExpression::Not(_)
+            | SQLExpression::IsNull { .. }
+            | SQLExpression::InSubquery { .. } => write!(f, "({})", self.0),
+            expression => write!(f, "{}", expression),
+        }
+    }
+}
+
+/// Column types. The integers are `BYTE` (8 bits), `SHORT` (16), `INT`
+/// (32), `LONG` (64) and `BIGINT` (128); see `storage::tuple` for how each
+/// is stored.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataType {
     TEXT { length: i32 },
     BOOL,
     DATETIME,
@@ -37,69 +195,1074 @@ enum DataType {
     UUID { unsigned: bool },
 }
 
-#[derive(Debug)]
-struct SelectQuery {
-    columns: Vec<SQLExpression>,
-    table: SQLExpression,
-    conditions: Option<SQLExpression>,
+/// Prints the type the way CREATE TABLE spells it.
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, unsigned) = match self {
+            DataType::TEXT { .. } => ("TEXT", false),
+            DataType::BOOL => ("BOOL", false),
+            DataType::DATETIME => ("DATETIME", false),
+            DataType::FLOAT { unsigned } => ("FLOAT", *unsigned),
+            DataType::DOUBLE { unsigned } => ("DOUBLE", *unsigned),
+            DataType::BYTE { unsigned } => ("BYTE", *unsigned),
+            DataType::SHORT { unsigned } => ("SHORT", *unsigned),
+            DataType::INT { unsigned } => ("INT", *unsigned),
+            DataType::LONG { unsigned } => ("LONG", *unsigned),
+            DataType::BIGINT { unsigned } => ("BIGINT", *unsigned),
+            DataType::UUID { unsigned } => ("UUID", *unsigned),
+        };
+        if unsigned {
+            write!(f, "U_{}", name)
+        } else {
+            write!(f, "{}", name)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableReference {
+    Table {
+        name: String,
+        alias: Option<String>,
+    },
+    /// A derived table, `(SELECT ...) alias`.
+    Subquery {
+        query: Box<Query>,
+        alias: String,
+    },
+    Join {
+        join_type: JoinType,
+        left: Box<TableReference>,
+        right: Box<TableReference>,
+        constraint: JoinConstraint,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinType {
+    Inner,
+    Left,
+    Right,
+    Full,
+    Cross,
+    /// Semi and anti joins are never written in SQL; the planner produces
+    /// them when it decorrelates `EXISTS` and `IN` subqueries.
+    Semi,
+    Anti,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinConstraint {
+    On(SQLExpression),
+    Using(Vec<String>),
+    Natural,
+    None,
 }
 
-#[derive(Debug)]
-struct InsertQuery {
-    table: SQLExpression,
-    columns: Vec<SQLExpression>,
-    values: Vec<SQLExpression>,
+/// `SELECT [ALL | DISTINCT | DISTINCT ON (expressions)]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectQuantifier {
+    All,
+    Distinct,
+    /// Keeps the first row, in ORDER BY order, of each group of rows with
+    /// equal values of the expressions.
+    DistinctOn(Vec<SQLExpression>),
 }
 
-#[derive(Debug)]
-struct CreateQuery {
-    created: String,
-    name: String,
-    columns: Option<Vec<SQLExpression>>,
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectQuery {
+    pub quantifier: SelectQuantifier,
+    pub columns: Vec<SQLExpression>,
+    /// Comma separated FROM items; more than one means a cross join.
+    pub from: Vec<TableReference>,
+    pub conditions: Option<SQLExpression>,
+    pub group_by: Vec<SQLExpression>,
+    pub having: Option<SQLExpression>,
+    pub windows: Vec<NamedWindow>,
+    pub locking: Option<LockingClause>,
 }
 
-#[derive(Debug)]
+/// The `(...)` after OVER or in a WINDOW clause. `name` refers to a window
+/// of the WINDOW clause whose partitioning and ordering this one builds on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WindowSpecification {
+    pub name: Option<String>,
+    pub partition_by: Vec<SQLExpression>,
+    pub order_by: Vec<OrderByItem>,
+    pub frame: Option<WindowFrame>,
+}
+
+/// `name AS (...)` in the WINDOW clause.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedWindow {
+    pub name: String,
+    pub specification: WindowSpecification,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameUnits {
+    Rows,
+    Range,
+}
+
+/// `n PRECEDING` and `n FOLLOWING` count rows under ROWS and are a distance
+/// from the current row's ORDER BY value under RANGE.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(Box<SQLExpression>),
+    CurrentRow,
+    Following(Box<SQLExpression>),
+    UnboundedFollowing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowFrame {
+    pub units: FrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+/// SELECT blocks combined by set operators. INTERSECT binds tighter than
+/// UNION and EXCEPT, and operators of equal precedence associate to the left.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetExpression {
+    Select(Box<SelectQuery>),
+    /// A parenthesized query used as an operand, which may carry its own
+    /// ORDER BY and LIMIT.
+    Query(Box<Query>),
+    Operation {
+        operator: SetOperator,
+        all: bool,
+        left: Box<SetExpression>,
+        right: Box<SetExpression>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderByItem {
+    pub expression: SQLExpression,
+    pub ascending: bool,
+}
+
+/// A complete query: ORDER BY, LIMIT and OFFSET apply to the result of the
+/// whole set expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub body: SetExpression,
+    pub order_by: Vec<OrderByItem>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+/// One `name [(columns)] AS (...)` entry of a WITH clause. Under
+/// `WITH RECURSIVE` a query of the form `anchor UNION [ALL] term` may read
+/// the CTE itself from `term`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommonTableExpression {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub query: Query,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithQuery {
+    pub recursive: bool,
+    pub ctes: Vec<CommonTableExpression>,
+    pub body: Query,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertQuery {
+    pub table: SQLExpression,
+    pub columns: Vec<SQLExpression>,
+    pub values: Vec<SQLExpression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateQuery {
+    pub created: String,
+    pub name: String,
+    pub columns: Option<Vec<SQLExpression>>,
+}
+
+/// How an index organizes its entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexMethod {
+    /// Ordered, for equality, ranges and prefixes of composite keys.
+    BTree,
+    /// Hashed, for equality on the whole key only.
+    Hash,
+}
+
+impl fmt::Display for IndexMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexMethod::BTree => write!(f, "BTREE"),
+            IndexMethod::Hash => write!(f, "HASH"),
+        }
+    }
+}
+
+/// `CREATE [UNIQUE] INDEX name ON table [USING method] (key, ...)
+/// [WHERE predicate]`, where the method may also follow the key list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateIndexQuery {
+    pub name: String,
+    pub table: String,
+    /// Columns or expressions over them, each with the text it was
+    /// written as, which is what the catalog keeps.
+    pub keys: Vec<(SQLExpression, String)>,
+    /// The condition of a partial index, which only has entries for the
+    /// rows that satisfy it, with its text.
+    pub predicate: Option<(SQLExpression, String)>,
+    pub unique: bool,
+    pub method: IndexMethod,
+}
+
+/// `CREATE SEQUENCE name [INCREMENT [BY] n] [MINVALUE n | NO MINVALUE]
+/// [MAXVALUE n | NO MAXVALUE] [START [WITH] n] [CACHE n] [[NO] CYCLE]`,
+/// the options in any order. Options left out take their defaults.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CreateSequenceQuery {
+    pub name: String,
+    pub increment: Option<i128>,
+    pub min: Option<i128>,
+    pub max: Option<i128>,
+    pub start: Option<i128>,
+    /// How many values to allocate at a time.
+    pub cache: Option<i128>,
+    /// Whether to wrap around past the limit instead of failing.
+    pub cycle: bool,
+}
+
+/// `SHOW DATABASES`, `SHOW TABLES` and `DESCRIBE table`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShowQuery {
+    Databases,
+    Tables,
+    Describe(String),
+}
+
+/// How much of the work of transactions running at the same time a
+/// transaction sees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IsolationLevel {
+    ReadUncommitted,
+    #[default]
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl fmt::Display for IsolationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IsolationLevel::ReadUncommitted => write!(f, "READ UNCOMMITTED"),
+            IsolationLevel::ReadCommitted => write!(f, "READ COMMITTED"),
+            IsolationLevel::RepeatableRead => write!(f, "REPEATABLE READ"),
+            IsolationLevel::Serializable => write!(f, "SERIALIZABLE"),
+        }
+    }
+}
+
+/// How a lock lets other transactions lock the same table or row. Tables
+/// take every mode; rows only `Share` and `Exclusive`. The intention modes
+/// go on a table that rows of it are locked in, so that a lock of the
+/// whole table sees them. `LOCK TABLE` spells the modes `ROW SHARE`, `ROW
+/// EXCLUSIVE`, `SHARE`, `SHARE ROW EXCLUSIVE` and `EXCLUSIVE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LockMode {
+    IntentionShare,
+    IntentionExclusive,
+    Share,
+    /// `Share` and `IntentionExclusive` at once.
+    ShareIntentionExclusive,
+    Exclusive,
+}
+
+impl fmt::Display for LockMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockMode::IntentionShare => write!(f, "ROW SHARE"),
+            LockMode::IntentionExclusive => write!(f, "ROW EXCLUSIVE"),
+            LockMode::Share => write!(f, "SHARE"),
+            LockMode::ShareIntentionExclusive => write!(f, "SHARE ROW EXCLUSIVE"),
+            LockMode::Exclusive => write!(f, "EXCLUSIVE"),
+        }
+    }
+}
+
+/// What to do about a lock another transaction holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockWait {
+    /// Wait for it to be released.
+    #[default]
+    Wait,
+    /// Fail at once.
+    NoWait,
+    /// Leave out the row, for rows only.
+    SkipLocked,
+}
+
+/// `FOR UPDATE` or `FOR SHARE`, `[OF table, ...]`, `[NOWAIT | SKIP LOCKED]`
+/// at the end of a SELECT. It locks the rows the SELECT returns, of the
+/// tables named after `OF`, by alias where they have one, or else of every
+/// table of its FROM clause.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockingClause {
+    /// `Exclusive` for `FOR UPDATE`, `Share` for `FOR SHARE`.
+    pub mode: LockMode,
+    pub tables: Vec<String>,
+    pub wait: LockWait,
+}
+
+impl fmt::Display for LockingClause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            LockMode::Share => write!(f, "FOR SHARE"),
+            _ => write!(f, "FOR UPDATE"),
+        }
+    }
+}
+
+/// `LOCK [TABLE] name, ... [IN mode MODE] [NOWAIT]`, which without a mode
+/// locks in `EXCLUSIVE` mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockTableQuery {
+    pub tables: Vec<String>,
+    pub mode: LockMode,
+    pub wait: LockWait,
+}
+
+/// `ALTER TABLE name SET (option = value, ...)` or `ALTER TABLE name
+/// RESET (option, ...)`, which changes the storage options of a table.
+/// A reset option has no value, and goes back to its default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterTableQuery {
+    pub table: String,
+    pub options: Vec<(String, Option<String>)>,
+}
+
+/// `DELETE FROM table [WHERE condition]`, of every row without a
+/// condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteQuery {
+    pub table: String,
+    pub conditions: Option<SQLExpression>,
+}
+
+/// `UPDATE table SET column = value, ... [WHERE condition]`, of every
+/// row without a condition. Each value reads the row as it was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateQuery {
+    pub table: String,
+    pub assignments: Vec<(String, SQLExpression)>,
+    pub conditions: Option<SQLExpression>,
+}
+
+/// `VACUUM [FULL] [table]`, of every table of the current database
+/// without one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VacuumQuery {
+    pub full: bool,
+    pub table: Option<String>,
+}
+
+/// `BEGIN [ISOLATION LEVEL level]`, `COMMIT`, `ROLLBACK`, `SAVEPOINT name`,
+/// `ROLLBACK TO [SAVEPOINT] name` and `RELEASE [SAVEPOINT] name`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransactionQuery {
+    Begin(Option<IsolationLevel>),
+    Commit,
+    Rollback,
+    Savepoint(String),
+    RollbackTo(String),
+    Release(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum SQLQuery {
-    Select(SelectQuery),
+    Select(Query),
+    With(WithQuery),
     Insert(InsertQuery),
+    Update(UpdateQuery),
+    Delete(DeleteQuery),
     Create(CreateQuery),
+    CreateIndex(CreateIndexQuery),
+    CreateSequence(CreateSequenceQuery),
+    Show(ShowQuery),
+    /// `USE database`.
+    Use(String),
+    Transaction(TransactionQuery),
+    Lock(LockTableQuery),
+    AlterTable(AlterTableQuery),
+    Vacuum(VacuumQuery),
+}
+
+/// A statement of a script that failed to parse. `token` is the index of
+/// the token where parsing stopped and `near` is that token, if any;
+/// `locate` turns the index into a position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub statement: usize,
+    pub token: usize,
+    pub near: Option<String>,
+    pub position: Option<Position>,
+}
+
+impl ParseError {
+    /// Points the error at its token, given the `positions` the lexer
+    /// recorded for the tokens of the whole script.
+    pub fn locate(mut self, positions: &[Position]) -> Self {
+        self.position = positions.get(self.token).copied();
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.near, self.position) {
+            (Some(near), Some(position)) => write!(f, "{}: syntax error near '{}'", position, near),
+            (Some(near), None) => write!(
+                f,
+                "statement {}: syntax error near '{}'",
+                self.statement, near
+            ),
+            (None, _) => write!(f, "statement {}: unexpected end of input", self.statement),
+        }
+    }
 }
 
 pub struct Parser<'a> {
     tokens: Peekable<Iter<'a, String>>,
+    length: usize,
+    spans: Vec<Range<usize>>,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a Vec<String>) -> Self {
+    pub fn new(tokens: &'a [String]) -> Self {
         Parser {
             tokens: tokens.iter().peekable(),
+            length: tokens.len(),
+            spans: Vec::new(),
         }
     }
 
+    /// The tokens of each statement returned by `parse_script`, in order.
+    pub fn spans(&self) -> &[Range<usize>] {
+        &self.spans
+    }
+
+    /// Parses one statement and the `;` that may end it.
     pub fn parse(&mut self) -> Option<SQLQuery> {
+        let query = self.parse_statement()?;
+        if self.peek_token(";") {
+            self.next_token();
+        }
+        Some(query)
+    }
+
+    /// Parses every statement of a script. Statements are separated by `;`,
+    /// which is optional after the last one. A statement that fails to parse
+    /// is reported and skipped up to the first `;` after the error, so the
+    /// statements after it are still parsed.
+    pub fn parse_script(&mut self) -> (Vec<SQLQuery>, Vec<ParseError>) {
+        let mut queries = Vec::new();
+        let mut errors = Vec::new();
+        let mut statement = 0;
+        loop {
+            while self.peek_token(";") {
+                self.next_token();
+            }
+            if self.tokens.peek().is_none() {
+                break;
+            }
+            statement += 1;
+            let start = self.tokens.clone();
+            let first = self.position();
+            let query = self.parse_statement();
+            let ended = self.tokens.peek().is_none() || self.peek_token(";");
+            match query {
+                Some(query) if ended => {
+                    self.spans.push(first..self.position());
+                    self.next_token();
+                    queries.push(query);
+                }
+                _ => {
+                    let error = self.position();
+                    errors.push(ParseError {
+                        statement,
+                        token: error,
+                        near: self.tokens.peek().map(|token| token.to_string()),
+                        position: None,
+                    });
+                    // A statement that parsed up to the start of another
+                    // only lacks its `;`, and the other is parsed next.
+                    if query.is_none() || !self.starts_statement() {
+                        self.tokens = start;
+                        self.skip_statement(error.saturating_sub(1).max(first));
+                    }
+                }
+            }
+        }
+        (queries, errors)
+    }
+
+    fn parse_statement(&mut self) -> Option<SQLQuery> {
         match self.tokens.peek()?.as_str() {
-            "SELECT" => self.parse_select().map(SQLQuery::Select),
+            "SELECT" | "(" => self.parse_query().map(SQLQuery::Select),
+            "WITH" => self.parse_with().map(SQLQuery::With),
             "INSERT" => self.parse_insert().map(SQLQuery::Insert),
+            "UPDATE" => self.parse_update().map(SQLQuery::Update),
+            "DELETE" => self.parse_delete().map(SQLQuery::Delete),
+            "CREATE" if self.peek_second("INDEX") || self.peek_second("UNIQUE") => {
+                self.parse_create_index().map(SQLQuery::CreateIndex)
+            }
+            "CREATE" if self.peek_second("SEQUENCE") => {
+                self.parse_create_sequence().map(SQLQuery::CreateSequence)
+            }
             "CREATE" => self.parse_create().map(SQLQuery::Create),
+            "SHOW" | "DESCRIBE" => self.parse_show().map(SQLQuery::Show),
+            "USE" => {
+                self.next_token();
+                self.next_token().cloned().map(SQLQuery::Use)
+            }
+            "BEGIN" | "START" | "COMMIT" | "ROLLBACK" | "SAVEPOINT" | "RELEASE" => {
+                self.parse_transaction().map(SQLQuery::Transaction)
+            }
+            "LOCK" => self.parse_lock_table().map(SQLQuery::Lock),
+            "ALTER" => self.parse_alter_table().map(SQLQuery::AlterTable),
+            "VACUUM" => self.parse_vacuum().map(SQLQuery::Vacuum),
             _ => None,
         }
     }
 
+    /// A transaction statement. `TRANSACTION` or `WORK` may follow
+    /// `BEGIN`, `COMMIT` and `ROLLBACK`; `START` needs `TRANSACTION`.
+    fn parse_transaction(&mut self) -> Option<TransactionQuery> {
+        let keyword = self.next_token()?.clone();
+        if keyword == "START" {
+            self.expect_keyword("TRANSACTION")?;
+        } else if matches!(keyword.as_str(), "BEGIN" | "COMMIT" | "ROLLBACK")
+            && (self.peek_keyword("TRANSACTION") || self.peek_keyword("WORK"))
+        {
+            self.next_token();
+        }
+        match keyword.as_str() {
+            "BEGIN" | "START" => {
+                if !self.peek_keyword("ISOLATION") {
+                    return Some(TransactionQuery::Begin(None));
+                }
+                self.next_token();
+                self.expect_keyword("LEVEL")?;
+                let level = match self.next_token()?.as_str() {
+                    "READ" => match self.next_token()?.as_str() {
+                        "UNCOMMITTED" => IsolationLevel::ReadUncommitted,
+                        "COMMITTED" => IsolationLevel::ReadCommitted,
+                        _ => return None,
+                    },
+                    "REPEATABLE" => {
+                        self.expect_keyword("READ")?;
+                        IsolationLevel::RepeatableRead
+                    }
+                    "SERIALIZABLE" => IsolationLevel::Serializable,
+                    _ => return None,
+                };
+                Some(TransactionQuery::Begin(Some(level)))
+            }
+            "COMMIT" => Some(TransactionQuery::Commit),
+            "ROLLBACK" if self.peek_keyword("TO") => {
+                self.next_token();
+                if self.peek_keyword("SAVEPOINT") {
+                    self.next_token();
+                }
+                self.next_token().cloned().map(TransactionQuery::RollbackTo)
+            }
+            "ROLLBACK" => Some(TransactionQuery::Rollback),
+            "SAVEPOINT" => self.next_token().cloned().map(TransactionQuery::Savepoint),
+            _ => {
+                if self.peek_keyword("SAVEPOINT") {
+                    self.next_token();
+                }
+                self.next_token().cloned().map(TransactionQuery::Release)
+            }
+        }
+    }
+
+    fn parse_lock_table(&mut self) -> Option<LockTableQuery> {
+        self.expect_keyword("LOCK")?;
+        if self.peek_keyword("TABLE") {
+            self.next_token();
+        }
+        let mut tables = vec![self.next_token()?.clone()];
+        while self.peek_token(",") {
+            self.next_token();
+            tables.push(self.next_token()?.clone());
+        }
+        let mut mode = LockMode::Exclusive;
+        if self.peek_keyword("IN") {
+            self.next_token();
+            mode = match self.next_token()?.clone().as_str() {
+                "ROW" => match self.next_token()?.as_str() {
+                    "SHARE" => LockMode::IntentionShare,
+                    "EXCLUSIVE" => LockMode::IntentionExclusive,
+                    _ => return None,
+                },
+                "SHARE" if self.peek_keyword("ROW") => {
+                    self.next_token();
+                    self.expect_keyword("EXCLUSIVE")?;
+                    LockMode::ShareIntentionExclusive
+                }
+                "SHARE" => LockMode::Share,
+                "EXCLUSIVE" => LockMode::Exclusive,
+                _ => return None,
+            };
+            self.expect_keyword("MODE")?;
+        }
+        let wait = if self.peek_keyword("NOWAIT") {
+            self.next_token();
+            LockWait::NoWait
+        } else {
+            LockWait::Wait
+        };
+        Some(LockTableQuery { tables, mode, wait })
+    }
+
+    fn parse_alter_table(&mut self) -> Option<AlterTableQuery> {
+        self.expect_keyword("ALTER")?;
+        self.expect_keyword("TABLE")?;
+        let table = self.next_token()?.clone();
+        let set = match self.next_token()?.as_str() {
+            "SET" => true,
+            "RESET" => false,
+            _ => return None,
+        };
+        self.expect_token("(")?;
+        let mut options = Vec::new();
+        loop {
+            let name = self.next_token()?.clone();
+            let value = if set {
+                self.expect_token("=")?;
+                // The lexer reads the sign of a negative value apart.
+                let sign = if self.peek_token("-") {
+                    self.next_token();
+                    "-"
+                } else {
+                    ""
+                };
+                Some(format!("{}{}", sign, self.next_token()?))
+            } else {
+                None
+            };
+            options.push((name, value));
+            match self.next_token()?.as_str() {
+                "," => continue,
+                ")" => break,
+                _ => return None,
+            }
+        }
+        Some(AlterTableQuery { table, options })
+    }
+
+    fn parse_vacuum(&mut self) -> Option<VacuumQuery> {
+        self.expect_keyword("VACUUM")?;
+        let full = self.peek_keyword("FULL");
+        if full {
+            self.next_token();
+        }
+        // Anything but the end of the statement is the name of a table.
+        let table = if self.tokens.peek().is_none() || self.peek_token(";") {
+            None
+        } else {
+            Some(self.parse_name()?)
+        };
+        Some(VacuumQuery { full, table })
+    }
+
+    fn parse_show(&mut self) -> Option<ShowQuery> {
+        if self.peek_keyword("DESCRIBE") {
+            self.next_token();
+            return self.next_token().cloned().map(ShowQuery::Describe);
+        }
+        self.expect_keyword("SHOW")?;
+        match self.next_token()?.as_str() {
+            "DATABASES" => Some(ShowQuery::Databases),
+            "TABLES" => Some(ShowQuery::Tables),
+            _ => None,
+        }
+    }
+
+    /// Skips past the first `;` from the token at `error`, where parsing
+    /// failed, or the one before it, which the parser may have read to
+    /// fail. Parentheses left open by the error are not matched, so that
+    /// one missing `)` does not swallow the statements after it.
+    fn skip_statement(&mut self, error: usize) {
+        while self.position() < error && self.next_token().is_some() {}
+        while let Some(token) = self.next_token() {
+            if token == ";" {
+                break;
+            }
+        }
+    }
+
+    /// Whether the next token is the keyword a statement starts with.
+    fn starts_statement(&mut self) -> bool {
+        self.tokens
+            .peek()
+            .is_some_and(|token| is_statement_keyword(token))
+    }
+
+    /// The index of the next token.
+    fn position(&self) -> usize {
+        self.length - self.tokens.len()
+    }
+
+    /// A query that is a single SELECT may end in a locking clause, before
+    /// or after LIMIT and OFFSET.
+    fn parse_query(&mut self) -> Option<Query> {
+        let mut body = self.parse_set_expression()?;
+        let order_by = self.parse_order_by()?;
+        self.parse_locking(&mut body)?;
+        let limit = if self.peek_keyword("LIMIT") {
+            self.next_token();
+            Some(self.next_token()?.parse().ok()?)
+        } else {
+            None
+        };
+        let offset = if self.peek_keyword("OFFSET") {
+            self.next_token();
+            Some(self.next_token()?.parse().ok()?)
+        } else {
+            None
+        };
+        self.parse_locking(&mut body)?;
+        Some(Query {
+            body,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    /// Parses an optional `ORDER BY item [ASC|DESC], ...`.
+    fn parse_order_by(&mut self) -> Option<Vec<OrderByItem>> {
+        let mut order_by = Vec::new();
+        if !self.peek_keyword("ORDER") {
+            return Some(order_by);
+        }
+        self.next_token();
+        self.expect_keyword("BY")?;
+        loop {
+            let expression = self.parse_logical_expression()?;
+            let ascending = if self.peek_keyword("DESC") {
+                self.next_token();
+                false
+            } else {
+                if self.peek_keyword("ASC") {
+                    self.next_token();
+                }
+                true
+            };
+            order_by.push(OrderByItem {
+                expression,
+                ascending,
+            });
+            if !self.peek_token(",") {
+                break;
+            }
+            self.next_token();
+        }
+        Some(order_by)
+    }
+
+    fn parse_set_expression(&mut self) -> Option<SetExpression> {
+        let mut left = self.parse_set_term()?;
+        while self.peek_keyword("UNION") || self.peek_keyword("EXCEPT") {
+            let operator = if self.next_token()? == "UNION" {
+                SetOperator::Union
+            } else {
+                SetOperator::Except
+            };
+            let all = self.parse_set_quantifier();
+            let right = self.parse_set_term()?;
+            left = SetExpression::Operation {
+                operator,
+                all,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Some(left)
+    }
+
+    fn parse_set_term(&mut self) -> Option<SetExpression> {
+        let mut left = self.parse_set_primary()?;
+        while self.peek_keyword("INTERSECT") {
+            self.next_token();
+            let all = self.parse_set_quantifier();
+            let right = self.parse_set_primary()?;
+            left = SetExpression::Operation {
+                operator: SetOperator::Intersect,
+                all,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Some(left)
+    }
+
+    fn parse_set_primary(&mut self) -> Option<SetExpression> {
+        if self.peek_token("(") {
+            self.next_token();
+            let query = self.parse_query()?;
+            self.expect_token(")")?;
+            Some(SetExpression::Query(Box::new(query)))
+        } else {
+            self.parse_select()
+                .map(|select| SetExpression::Select(Box::new(select)))
+        }
+    }
+
+    /// Consumes an optional `ALL` or `DISTINCT` after a set operator.
+    fn parse_set_quantifier(&mut self) -> bool {
+        if self.peek_keyword("ALL") {
+            self.next_token();
+            true
+        } else {
+            if self.peek_keyword("DISTINCT") {
+                self.next_token();
+            }
+            false
+        }
+    }
+
     fn parse_select(&mut self) -> Option<SelectQuery> {
         self.expect_keyword("SELECT")?;
-        let columns = self.parse_columns()?;
+        let quantifier = if self.peek_keyword("DISTINCT") {
+            self.next_token();
+            if self.peek_keyword("ON") {
+                self.next_token();
+                self.expect_token("(")?;
+                let expressions = self.parse_expression_list()?;
+                self.expect_token(")")?;
+                SelectQuantifier::DistinctOn(expressions)
+            } else {
+                SelectQuantifier::Distinct
+            }
+        } else {
+            if self.peek_keyword("ALL") {
+                self.next_token();
+            }
+            SelectQuantifier::All
+        };
+        let columns = self.parse_select_list()?;
         self.expect_keyword("FROM")?;
-        let table = self.parse_identifier()?;
+        let from = self.parse_from()?;
         let conditions = if self.peek_keyword("WHERE") {
             self.next_token();
-            self.parse_conditions()
+            Some(self.parse_conditions()?)
+        } else {
+            None
+        };
+        let group_by = if self.peek_keyword("GROUP") {
+            self.next_token();
+            self.expect_keyword("BY")?;
+            self.parse_expression_list()?
+        } else {
+            Vec::new()
+        };
+        let having = if self.peek_keyword("HAVING") {
+            self.next_token();
+            Some(self.parse_conditions()?)
         } else {
             None
         };
+        let mut windows = Vec::new();
+        if self.peek_keyword("WINDOW") {
+            self.next_token();
+            loop {
+                let name = self.next_token()?.clone();
+                self.expect_keyword("AS")?;
+                let specification = self.parse_window_specification()?;
+                windows.push(NamedWindow {
+                    name,
+                    specification,
+                });
+                if !self.peek_token(",") {
+                    break;
+                }
+                self.next_token();
+            }
+        }
         Some(SelectQuery {
+            quantifier,
             columns,
-            table,
+            from,
             conditions,
+            group_by,
+            having,
+            windows,
+            locking: None,
+        })
+    }
+
+    /// Puts an optional locking clause on `body`, which has to be a
+    /// SELECT without one.
+    fn parse_locking(&mut self, body: &mut SetExpression) -> Option<()> {
+        if !self.peek_keyword("FOR") {
+            return Some(());
+        }
+        match body {
+            SetExpression::Select(select) if select.locking.is_none() => {
+                select.locking = Some(self.parse_locking_clause()?);
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_locking_clause(&mut self) -> Option<LockingClause> {
+        self.expect_keyword("FOR")?;
+        let mode = match self.next_token()?.as_str() {
+            "UPDATE" => LockMode::Exclusive,
+            "SHARE" => LockMode::Share,
+            _ => return None,
+        };
+        let mut tables = Vec::new();
+        if self.peek_keyword("OF") {
+            self.next_token();
+            tables.push(self.next_token()?.clone());
+            while self.peek_token(",") {
+                self.next_token();
+                tables.push(self.next_token()?.clone());
+            }
+        }
+        let wait = if self.peek_keyword("NOWAIT") {
+            self.next_token();
+            LockWait::NoWait
+        } else if self.peek_keyword("SKIP") {
+            self.next_token();
+            self.expect_keyword("LOCKED")?;
+            LockWait::SkipLocked
+        } else {
+            LockWait::Wait
+        };
+        Some(LockingClause { mode, tables, wait })
+    }
+
+    /// Parses `( [name] [PARTITION BY ...] [ORDER BY ...] [frame] )`.
+    fn parse_window_specification(&mut self) -> Option<WindowSpecification> {
+        self.expect_token("(")?;
+        let mut specification = WindowSpecification::default();
+        if let Some(token) = self.tokens.peek() {
+            if !matches!(
+                token.as_str(),
+                "PARTITION" | "ORDER" | "ROWS" | "RANGE" | ")"
+            ) {
+                specification.name = Some(self.next_token()?.clone());
+            }
+        }
+        if self.peek_keyword("PARTITION") {
+            self.next_token();
+            self.expect_keyword("BY")?;
+            specification.partition_by = self.parse_expression_list()?;
+        }
+        specification.order_by = self.parse_order_by()?;
+        if self.peek_keyword("ROWS") || self.peek_keyword("RANGE") {
+            let units = if self.next_token()? == "ROWS" {
+                FrameUnits::Rows
+            } else {
+                FrameUnits::Range
+            };
+            let (start, end) = if self.peek_keyword("BETWEEN") {
+                self.next_token();
+                let start = self.parse_frame_bound()?;
+                self.expect_keyword("AND")?;
+                (start, self.parse_frame_bound()?)
+            } else {
+                (self.parse_frame_bound()?, FrameBound::CurrentRow)
+            };
+            if start == FrameBound::UnboundedFollowing || end == FrameBound::UnboundedPreceding {
+                return None;
+            }
+            specification.frame = Some(WindowFrame { units, start, end });
+        }
+        self.expect_token(")")?;
+        Some(specification)
+    }
+
+    fn parse_frame_bound(&mut self) -> Option<FrameBound> {
+        if self.peek_keyword("UNBOUNDED") {
+            self.next_token();
+            return match self.next_token()?.as_str() {
+                "PRECEDING" => Some(FrameBound::UnboundedPreceding),
+                "FOLLOWING" => Some(FrameBound::UnboundedFollowing),
+                _ => None,
+            };
+        }
+        if self.peek_keyword("CURRENT") {
+            self.next_token();
+            self.expect_keyword("ROW")?;
+            return Some(FrameBound::CurrentRow);
+        }
+        let offset = Box::new(self.parse_literal()?);
+        match self.next_token()?.as_str() {
+            "PRECEDING" => Some(FrameBound::Preceding(offset)),
+            "FOLLOWING" => Some(FrameBound::Following(offset)),
+            _ => None,
+        }
+    }
+
+    fn parse_with(&mut self) -> Option<WithQuery> {
+        self.expect_keyword("WITH")?;
+        let recursive = if self.peek_keyword("RECURSIVE") {
+            self.next_token();
+            true
+        } else {
+            false
+        };
+        let mut ctes = Vec::new();
+        loop {
+            let name = self.next_token()?.clone();
+            let mut columns = Vec::new();
+            if self.peek_token("(") {
+                self.next_token();
+                loop {
+                    columns.push(self.next_token()?.clone());
+                    if !self.peek_token(",") {
+                        break;
+                    }
+                    self.next_token();
+                }
+                self.expect_token(")")?;
+            }
+            self.expect_keyword("AS")?;
+            self.expect_token("(")?;
+            let query = self.parse_query()?;
+            self.expect_token(")")?;
+            ctes.push(CommonTableExpression {
+                name,
+                columns,
+                query,
+            });
+            if !self.peek_token(",") {
+                break;
+            }
+            self.next_token();
+        }
+        let body = self.parse_query()?;
+        Some(WithQuery {
+            recursive,
+            ctes,
+            body,
         })
     }
 
@@ -116,10 +1279,51 @@ impl<'a> Parser<'a> {
         self.expect_token(")")?;
         Some(InsertQuery {
             table,
-            columns,
-            values,
+            columns,
+            values,
+        })
+    }
+
+    fn parse_update(&mut self) -> Option<UpdateQuery> {
+        self.expect_keyword("UPDATE")?;
+        let table = self.parse_name()?;
+        self.expect_keyword("SET")?;
+        let mut assignments = Vec::new();
+        loop {
+            let column = self.parse_name()?;
+            self.expect_token("=")?;
+            assignments.push((column, self.parse_logical_expression()?));
+            if !self.peek_token(",") {
+                break;
+            }
+            self.next_token();
+        }
+        let conditions = if self.peek_keyword("WHERE") {
+            self.next_token();
+            Some(self.parse_conditions()?)
+        } else {
+            None
+        };
+        Some(UpdateQuery {
+            table,
+            assignments,
+            conditions,
         })
     }
+
+    fn parse_delete(&mut self) -> Option<DeleteQuery> {
+        self.expect_keyword("DELETE")?;
+        self.expect_keyword("FROM")?;
+        let table = self.parse_name()?;
+        let conditions = if self.peek_keyword("WHERE") {
+            self.next_token();
+            Some(self.parse_conditions()?)
+        } else {
+            None
+        };
+        Some(DeleteQuery { table, conditions })
+    }
+
     fn parse_create(&mut self) -> Option<CreateQuery> {
         self.expect_keyword("CREATE")?;
         if let Some(token) = self.tokens.next() {
@@ -135,7 +1339,7 @@ impl<'a> Parser<'a> {
                 loop {
                     let name;
                     let data_type;
-                    let mut data_type_case;
+                    let data_type_case;
                     let mut is_primary = false;
                     let mut not_null = false;
                     let mut identity = false;
@@ -145,39 +1349,16 @@ impl<'a> Parser<'a> {
                         if let Some(column) = self.tokens.next() {
                             data_type = column.to_string().to_uppercase();
 
-                            match data_type.as_str() {
-                                "TEXT" => data_type_case = DataType::TEXT { length: 256 },
-                                "BOOL" => data_type_case = DataType::BOOL,
-                                "DATETIME" => data_type_case = DataType::DATETIME,
-
-                                "FLOAT" => data_type_case = DataType::FLOAT { unsigned: false },
-                                "DOUBLE" => data_type_case = DataType::DOUBLE { unsigned: false },
-                                "BYTE" => data_type_case = DataType::BYTE { unsigned: false },
-                                "SHORT" => data_type_case = DataType::SHORT { unsigned: false },
-                                "INT" => data_type_case = DataType::INT { unsigned: false },
-                                "LONG" => data_type_case = DataType::LONG { unsigned: false },
-                                "BIGINT" => data_type_case = DataType::BIGINT { unsigned: false },
-                                "UUID" => data_type_case = DataType::UUID { unsigned: false },
-
-                                "U_FLOAT" => data_type_case = DataType::FLOAT { unsigned: true },
-                                "U_DOUBLE" => data_type_case = DataType::DOUBLE { unsigned: true },
-                                "U_BYTE" => data_type_case = DataType::BYTE { unsigned: true },
-                                "U_SHORT" => data_type_case = DataType::SHORT { unsigned: true },
-                                "U_INT" => data_type_case = DataType::INT { unsigned: true },
-                                "U_LONG" => data_type_case = DataType::LONG { unsigned: true },
-                                "U_BIGINT" => data_type_case = DataType::BIGINT { unsigned: true },
-                                "U_UUID" => data_type_case = DataType::UUID { unsigned: true },
-                                _ => return None,
-                            }
+                            data_type_case = parse_data_type(&data_type)?;
 
                             if let Some(column) = self.tokens.next() {
-                                if (column.to_string() == "PRIMARY") {
+                                if column == "PRIMARY" {
                                     is_primary = true;
-                                } else if (column.to_string() == "NOTNULL") {
+                                } else if column == "NOTNULL" {
                                     not_null = true;
-                                } else if (column.to_string() == "IDENTITY") {
+                                } else if column == "IDENTITY" {
                                     identity = true;
-                                } else if (column.to_string() == ",") {
+                                } else if column == "," {
                                     cols.push(SQLExpression::CreateColumn {
                                         name,
                                         data_type: data_type_case,
@@ -186,7 +1367,7 @@ impl<'a> Parser<'a> {
                                         identity,
                                     });
                                     continue;
-                                } else if (column.to_string() == ")") {
+                                } else if column == ")" {
                                     cols.push(SQLExpression::CreateColumn {
                                         name,
                                         data_type: data_type_case,
@@ -199,11 +1380,11 @@ impl<'a> Parser<'a> {
                                     return None;
                                 }
                                 if let Some(column) = self.tokens.next() {
-                                    if (column.to_string() == "NOTNULL") {
+                                    if column == "NOTNULL" {
                                         not_null = true;
-                                    } else if (column.to_string() == "IDENTITY") {
+                                    } else if column == "IDENTITY" {
                                         identity = true;
-                                    } else if (column.to_string() == ",") {
+                                    } else if column == "," {
                                         cols.push(SQLExpression::CreateColumn {
                                             name,
                                             data_type: data_type_case,
@@ -212,7 +1393,7 @@ impl<'a> Parser<'a> {
                                             identity,
                                         });
                                         continue;
-                                    } else if (column.to_string() == ")") {
+                                    } else if column == ")" {
                                         cols.push(SQLExpression::CreateColumn {
                                             name,
                                             data_type: data_type_case,
@@ -225,9 +1406,9 @@ impl<'a> Parser<'a> {
                                         return None;
                                     }
                                     if let Some(column) = self.tokens.next() {
-                                        if (column.to_string() == "IDENTITY") {
+                                        if column == "IDENTITY" {
                                             identity = true;
-                                        } else if (column.to_string() == ",") {
+                                        } else if column == "," {
                                             cols.push(SQLExpression::CreateColumn {
                                                 name,
                                                 data_type: data_type_case,
@@ -236,7 +1417,7 @@ impl<'a> Parser<'a> {
                                                 identity,
                                             });
                                             continue;
-                                        } else if (column.to_string() == ")") {
+                                        } else if column == ")" {
                                             cols.push(SQLExpression::CreateColumn {
                                                 name,
                                                 data_type: data_type_case,
@@ -249,7 +1430,7 @@ impl<'a> Parser<'a> {
                                             return None;
                                         }
                                         if let Some(column) = self.tokens.next() {
-                                            if (column.to_string() == ",") {
+                                            if column == "," {
                                                 cols.push(SQLExpression::CreateColumn {
                                                     name,
                                                     data_type: data_type_case,
@@ -258,7 +1439,7 @@ impl<'a> Parser<'a> {
                                                     identity,
                                                 });
                                                 continue;
-                                            } else if (column.to_string() == ")") {
+                                            } else if column == ")" {
                                                 cols.push(SQLExpression::CreateColumn {
                                                     name,
                                                     data_type: data_type_case,
@@ -281,15 +1462,11 @@ impl<'a> Parser<'a> {
                         return None;
                     }
                 }
-                self.expect_token(";")?;
 
                 columns = Some(cols);
             } else if token_str == "DATABASE" {
                 created = token_str.to_string();
                 name = self.tokens.next()?.to_string();
-                if self.tokens.next()?.to_string() != ";" {
-                    return None;
-                };
                 columns = None;
             } else {
                 return None;
@@ -304,6 +1481,268 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn parse_create_index(&mut self) -> Option<CreateIndexQuery> {
+        self.expect_keyword("CREATE")?;
+        let unique = self.peek_keyword("UNIQUE");
+        if unique {
+            self.next_token();
+        }
+        self.expect_keyword("INDEX")?;
+        let name = self.next_token()?.clone();
+        self.expect_keyword("ON")?;
+        let table = self.next_token()?.clone();
+        let mut method = self.parse_index_method()?;
+        self.expect_token("(")?;
+        let mut keys = vec![self.parse_with_text(Parser::parse_logical_expression)?];
+        while self.peek_token(",") {
+            self.next_token();
+            keys.push(self.parse_with_text(Parser::parse_logical_expression)?);
+        }
+        self.expect_token(")")?;
+        if method.is_none() {
+            method = self.parse_index_method()?;
+        }
+        let predicate = if self.peek_keyword("WHERE") {
+            self.next_token();
+            Some(self.parse_with_text(Parser::parse_conditions)?)
+        } else {
+            None
+        };
+        Some(CreateIndexQuery {
+            name,
+            table,
+            keys,
+            predicate,
+            unique,
+            method: method.unwrap_or(IndexMethod::BTree),
+        })
+    }
+
+    fn parse_create_sequence(&mut self) -> Option<CreateSequenceQuery> {
+        self.expect_keyword("CREATE")?;
+        self.expect_keyword("SEQUENCE")?;
+        let mut sequence = CreateSequenceQuery {
+            name: self.next_token()?.clone(),
+            ..CreateSequenceQuery::default()
+        };
+        while let Some(option) = self.tokens.peek() {
+            match option.as_str() {
+                "INCREMENT" => {
+                    self.next_token();
+                    if self.peek_keyword("BY") {
+                        self.next_token();
+                    }
+                    sequence.increment = Some(self.parse_signed_integer()?);
+                }
+                "START" => {
+                    self.next_token();
+                    if self.peek_keyword("WITH") {
+                        self.next_token();
+                    }
+                    sequence.start = Some(self.parse_signed_integer()?);
+                }
+                "MINVALUE" => {
+                    self.next_token();
+                    sequence.min = Some(self.parse_signed_integer()?);
+                }
+                "MAXVALUE" => {
+                    self.next_token();
+                    sequence.max = Some(self.parse_signed_integer()?);
+                }
+                "CACHE" => {
+                    self.next_token();
+                    sequence.cache = Some(self.parse_signed_integer()?);
+                }
+                "CYCLE" => {
+                    self.next_token();
+                    sequence.cycle = true;
+                }
+                "NO" => {
+                    self.next_token();
+                    match self.next_token()?.as_str() {
+                        "MINVALUE" => sequence.min = None,
+                        "MAXVALUE" => sequence.max = None,
+                        "CYCLE" => sequence.cycle = false,
+                        _ => return None,
+                    }
+                }
+                _ => break,
+            }
+        }
+        Some(sequence)
+    }
+
+    /// An integer constant, which may be negative.
+    fn parse_signed_integer(&mut self) -> Option<i128> {
+        let negative = self.peek_token("-");
+        if negative {
+            self.next_token();
+        }
+        let value = self.next_token()?.parse::<i128>().ok()?;
+        Some(if negative { -value } else { value })
+    }
+
+    /// Runs `parse` and returns what it parsed with the tokens it read,
+    /// spaced so that they read back as the same tokens.
+    fn parse_with_text<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Option<T>,
+    ) -> Option<(T, String)> {
+        let start = self.tokens.clone();
+        let first = self.position();
+        let parsed = parse(self)?;
+        let mut text = String::new();
+        let mut previous: Option<&String> = None;
+        for token in start.take(self.position() - first) {
+            let tight = matches!(token.as_str(), ")" | "," | "::")
+                || previous.is_some_and(|previous| {
+                    previous == "(" || previous == "::" || token == "(" && is_callable(previous)
+                });
+            if previous.is_some() && !tight {
+                text.push(' ');
+            }
+            text.push_str(token);
+            previous = Some(token);
+        }
+        Some((parsed, text))
+    }
+
+    /// `USING BTREE` or `USING HASH`, if present.
+    fn parse_index_method(&mut self) -> Option<Option<IndexMethod>> {
+        if !self.peek_keyword("USING") {
+            return Some(None);
+        }
+        self.next_token();
+        match self.next_token()?.as_str() {
+            "BTREE" => Some(Some(IndexMethod::BTree)),
+            "HASH" => Some(Some(IndexMethod::Hash)),
+            _ => None,
+        }
+    }
+
+    fn parse_from(&mut self) -> Option<Vec<TableReference>> {
+        let mut from = Vec::new();
+        loop {
+            from.push(self.parse_joined_table()?);
+            if !self.peek_token(",") {
+                break;
+            }
+            self.next_token();
+        }
+        Some(from)
+    }
+
+    fn parse_joined_table(&mut self) -> Option<TableReference> {
+        let mut left = self.parse_table_primary()?;
+        while let Some((join_type, natural)) = self.parse_join_operator()? {
+            let right = self.parse_table_primary()?;
+            let constraint = if natural {
+                JoinConstraint::Natural
+            } else if join_type == JoinType::Cross {
+                JoinConstraint::None
+            } else if self.peek_keyword("ON") {
+                self.next_token();
+                JoinConstraint::On(self.parse_conditions()?)
+            } else if self.peek_keyword("USING") {
+                self.next_token();
+                self.expect_token("(")?;
+                let mut columns = Vec::new();
+                loop {
+                    columns.push(self.next_token()?.clone());
+                    if !self.peek_token(",") {
+                        break;
+                    }
+                    self.next_token();
+                }
+                self.expect_token(")")?;
+                JoinConstraint::Using(columns)
+            } else {
+                return None;
+            };
+            left = TableReference::Join {
+                join_type,
+                left: Box::new(left),
+                right: Box::new(right),
+                constraint,
+            };
+        }
+        Some(left)
+    }
+
+    /// Consumes `[NATURAL] [INNER | LEFT | RIGHT | FULL [OUTER] | CROSS] JOIN`.
+    /// The outer `None` is a syntax error, the inner one means no join follows.
+    fn parse_join_operator(&mut self) -> Option<Option<(JoinType, bool)>> {
+        let natural = if self.peek_keyword("NATURAL") {
+            self.next_token();
+            true
+        } else {
+            false
+        };
+        let join_type = match self.tokens.peek().map(|token| token.as_str()) {
+            Some("JOIN") => JoinType::Inner,
+            Some("INNER") => {
+                self.next_token();
+                JoinType::Inner
+            }
+            Some("LEFT") | Some("RIGHT") | Some("FULL") => {
+                let join_type = match self.next_token()?.as_str() {
+                    "LEFT" => JoinType::Left,
+                    "RIGHT" => JoinType::Right,
+                    _ => JoinType::Full,
+                };
+                if self.peek_keyword("OUTER") {
+                    self.next_token();
+                }
+                join_type
+            }
+            Some("CROSS") if !natural => {
+                self.next_token();
+                JoinType::Cross
+            }
+            _ if natural => return None,
+            _ => return Some(None),
+        };
+        self.expect_keyword("JOIN")?;
+        Some(Some((join_type, natural)))
+    }
+
+    fn parse_table_primary(&mut self) -> Option<TableReference> {
+        if self.peek_token("(") {
+            self.next_token();
+            let query = self.parse_query()?;
+            self.expect_token(")")?;
+            let alias = self.parse_alias()??;
+            return Some(TableReference::Subquery {
+                query: Box::new(query),
+                alias,
+            });
+        }
+        let mut name = self.next_token()?.clone();
+        if self.peek_token(".") {
+            self.next_token();
+            name = format!("{}.{}", name, self.next_token()?);
+        }
+        let alias = self.parse_alias()?;
+        Some(TableReference::Table { name, alias })
+    }
+
+    /// Parses an optional `[AS] alias`.
+    fn parse_alias(&mut self) -> Option<Option<String>> {
+        if self.peek_keyword("AS") {
+            self.next_token();
+            return Some(Some(self.next_token()?.clone()));
+        }
+        match self.tokens.peek() {
+            Some(token)
+                if !is_reserved_keyword(token)
+                    && token.starts_with(|ch: char| ch.is_alphabetic() || ch == '_') =>
+            {
+                Some(Some(self.next_token()?.clone()))
+            }
+            _ => Some(None),
+        }
+    }
+
     fn parse_columns(&mut self) -> Option<Vec<SQLExpression>> {
         let mut columns = Vec::new();
         loop {
@@ -320,14 +1759,47 @@ impl<'a> Parser<'a> {
         Some(columns)
     }
 
+    fn parse_select_list(&mut self) -> Option<Vec<SQLExpression>> {
+        if self.peek_token("*") {
+            self.next_token();
+            return Some(vec![SQLExpression::Wildcard]);
+        }
+        let mut columns = Vec::new();
+        loop {
+            let expression = self.parse_logical_expression()?;
+            columns.push(match self.parse_alias()? {
+                Some(alias) => SQLExpression::Alias {
+                    expression: Box::new(expression),
+                    alias,
+                },
+                None => expression,
+            });
+            if !self.peek_token(",") {
+                break;
+            }
+            self.next_token();
+        }
+        Some(columns)
+    }
+
+    fn parse_expression_list(&mut self) -> Option<Vec<SQLExpression>> {
+        let mut expressions = Vec::new();
+        loop {
+            expressions.push(self.parse_logical_expression()?);
+            if !self.peek_token(",") {
+                break;
+            }
+            self.next_token();
+        }
+        Some(expressions)
+    }
+
+    /// The expressions of a VALUES list, such as constants or a call to
+    /// `nextval`.
     fn parse_values(&mut self) -> Option<Vec<SQLExpression>> {
         let mut values = Vec::new();
         loop {
-            if let Some(value) = self.parse_literal() {
-                values.push(value);
-            } else {
-                return None;
-            }
+            values.push(self.parse_logical_expression()?);
             if !self.peek_token(",") {
                 break;
             }
@@ -340,28 +1812,81 @@ impl<'a> Parser<'a> {
         self.parse_logical_expression()
     }
 
+    /// OR binds loosest, then AND, then NOT, then IS [NOT] NULL, then the
+    /// comparisons.
     fn parse_logical_expression(&mut self) -> Option<SQLExpression> {
-        let mut left = self.parse_comparison_expression()?;
+        let mut left = self.parse_and_expression()?;
+        while self.peek_keyword("OR") {
+            self.next_token();
+            let right = self.parse_and_expression()?;
+            left = SQLExpression::BinaryExpression {
+                operator: "OR".to_string(),
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Some(left)
+    }
 
-        while let Some(operator) = self.peek_operator() {
-            if operator == "AND" || operator == "OR" {
+    fn parse_and_expression(&mut self) -> Option<SQLExpression> {
+        let mut left = self.parse_not_expression()?;
+        while self.peek_keyword("AND") {
+            self.next_token();
+            let right = self.parse_not_expression()?;
+            left = SQLExpression::BinaryExpression {
+                operator: "AND".to_string(),
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Some(left)
+    }
+
+    /// `NOT EXISTS` is left to the primary expression, which keeps it a
+    /// single negated test the planner can turn into an anti join.
+    fn parse_not_expression(&mut self) -> Option<SQLExpression> {
+        if self.peek_keyword("NOT") && !self.peek_second("EXISTS") {
+            self.next_token();
+            let expression = self.parse_not_expression()?;
+            return Some(SQLExpression::Not(Box::new(expression)));
+        }
+        self.parse_is_expression()
+    }
+
+    fn parse_is_expression(&mut self) -> Option<SQLExpression> {
+        let mut expression = self.parse_comparison_expression()?;
+        while self.peek_keyword("IS") {
+            self.next_token();
+            let negated = self.peek_keyword("NOT");
+            if negated {
                 self.next_token();
-                let right = self.parse_comparison_expression()?;
-                left = SQLExpression::BinaryExpression {
-                    operator,
-                    left: Box::new(left),
-                    right: Box::new(right),
-                };
-            } else {
-                break;
             }
+            self.expect_keyword("NULL")?;
+            expression = SQLExpression::IsNull {
+                expression: Box::new(expression),
+                negated,
+            };
         }
-
-        Some(left)
+        Some(expression)
     }
 
     fn parse_comparison_expression(&mut self) -> Option<SQLExpression> {
-        let mut left = self.parse_primary_expression()?;
+        let mut left = self.parse_postfix_expression()?;
+
+        if self.peek_keyword("IN") || (self.peek_keyword("NOT") && self.peek_second("IN")) {
+            let negated = self.next_token()? == "NOT";
+            if negated {
+                self.next_token();
+            }
+            self.expect_token("(")?;
+            let subquery = self.parse_query()?;
+            self.expect_token(")")?;
+            left = SQLExpression::InSubquery {
+                expression: Box::new(left),
+                subquery: Box::new(subquery),
+                negated,
+            };
+        }
 
         while let Some(operator) = self.peek_operator() {
             if operator == "=="
@@ -372,7 +1897,7 @@ impl<'a> Parser<'a> {
                 || operator == ">="
             {
                 self.next_token();
-                let right = self.parse_primary_expression()?;
+                let right = self.parse_postfix_expression()?;
                 left = SQLExpression::BinaryExpression {
                     operator,
                     left: Box::new(left),
@@ -386,25 +1911,266 @@ impl<'a> Parser<'a> {
         Some(left)
     }
 
+    /// A primary expression followed by any number of `::type` casts.
+    fn parse_postfix_expression(&mut self) -> Option<SQLExpression> {
+        let mut expression = self.parse_primary_expression()?;
+        while self.peek_token("::") {
+            self.next_token();
+            let data_type = parse_data_type(&self.next_token()?.to_uppercase())?;
+            expression = SQLExpression::Cast {
+                expression: Box::new(expression),
+                data_type,
+            };
+        }
+        Some(expression)
+    }
+
+    fn parse_case(&mut self) -> Option<SQLExpression> {
+        self.expect_keyword("CASE")?;
+        let operand = if self.peek_keyword("WHEN") {
+            None
+        } else {
+            Some(Box::new(self.parse_logical_expression()?))
+        };
+        let mut branches = Vec::new();
+        while self.peek_keyword("WHEN") {
+            self.next_token();
+            let condition = self.parse_logical_expression()?;
+            self.expect_keyword("THEN")?;
+            branches.push((condition, self.parse_logical_expression()?));
+        }
+        if branches.is_empty() {
+            return None;
+        }
+        let else_result = if self.peek_keyword("ELSE") {
+            self.next_token();
+            Some(Box::new(self.parse_logical_expression()?))
+        } else {
+            None
+        };
+        self.expect_keyword("END")?;
+        Some(SQLExpression::Case {
+            operand,
+            branches,
+            else_result,
+        })
+    }
+
     fn parse_primary_expression(&mut self) -> Option<SQLExpression> {
         if self.peek_token("(") {
             self.next_token();
+            if self.peek_keyword("SELECT") {
+                let subquery = self.parse_query()?;
+                self.expect_token(")")?;
+                return Some(SQLExpression::Subquery(Box::new(subquery)));
+            }
             let expr = self.parse_logical_expression();
             self.expect_token(")")?;
             expr
-        } else if let Some(identifier) = self.parse_identifier() {
+        } else if self.peek_keyword("EXISTS")
+            || (self.peek_keyword("NOT") && self.peek_second("EXISTS"))
+        {
+            let negated = self.next_token()? == "NOT";
+            if negated {
+                self.next_token();
+            }
+            self.expect_token("(")?;
+            let subquery = self.parse_query()?;
+            self.expect_token(")")?;
+            Some(SQLExpression::Exists {
+                subquery: Box::new(subquery),
+                negated,
+            })
+        } else if self.peek_keyword("CASE") {
+            self.parse_case()
+        } else if self.peek_keyword("CAST") && self.peek_second("(") {
+            self.next_token();
+            self.next_token();
+            let expression = self.parse_logical_expression()?;
+            self.expect_keyword("AS")?;
+            let data_type = parse_data_type(&self.next_token()?.to_uppercase())?;
+            self.expect_token(")")?;
+            Some(SQLExpression::Cast {
+                expression: Box::new(expression),
+                data_type,
+            })
+        } else if self.peek_token("*") {
+            self.next_token();
+            Some(SQLExpression::Wildcard)
+        } else if self.peek_token("-")
+            && self
+                .tokens
+                .clone()
+                .nth(1)
+                .is_some_and(|second| second.starts_with(|ch: char| ch.is_ascii_digit()))
+        {
+            // A minus sign before a number makes a negative literal; there
+            // is no arithmetic to give it any other meaning.
+            self.next_token();
+            let number = self.next_token()?;
+            Some(SQLExpression::Literal(format!("-{}", number)))
+        } else if self
+            .tokens
+            .peek()
+            .is_some_and(|token| is_literal_token(token))
+        {
+            self.parse_literal()
+        } else {
+            let identifier = self.parse_identifier()?;
+            if self.peek_token(".") {
+                self.next_token();
+                if let SQLExpression::Identifier(table) = identifier {
+                    let column = self.next_token()?.clone();
+                    return Some(SQLExpression::QualifiedIdentifier { table, column });
+                }
+            }
+            if self.peek_token("(") {
+                if let SQLExpression::Identifier(name) = identifier {
+                    let function = self.parse_function_call(name)?;
+                    if !self.peek_keyword("OVER") {
+                        return Some(function);
+                    }
+                    self.next_token();
+                    let window = if self.peek_token("(") {
+                        self.parse_window_specification()?
+                    } else {
+                        WindowSpecification {
+                            name: Some(self.next_token()?.clone()),
+                            ..WindowSpecification::default()
+                        }
+                    };
+                    return Some(SQLExpression::WindowFunction {
+                        function: Box::new(function),
+                        window,
+                    });
+                }
+            }
             Some(identifier)
+        }
+    }
+
+    fn parse_function_call(&mut self, name: String) -> Option<SQLExpression> {
+        let name = name.to_uppercase();
+        if self.uses_keyword_arguments(&name) {
+            return self.parse_keyword_arguments(name);
+        }
+        self.expect_token("(")?;
+        let distinct = if self.peek_keyword("DISTINCT") {
+            self.next_token();
+            true
         } else {
-            self.parse_literal()
+            false
+        };
+        let arguments = if self.peek_token(")") {
+            Vec::new()
+        } else {
+            self.parse_expression_list()?
+        };
+        self.expect_token(")")?;
+        Some(SQLExpression::FunctionCall {
+            name,
+            distinct,
+            arguments,
+        })
+    }
+
+    /// Whether a call uses one of the SQL standard keyword forms
+    /// `SUBSTRING(s FROM start FOR length)`, `POSITION(sub IN s)`,
+    /// `TRIM([LEADING|TRAILING|BOTH] [chars] FROM s)` or
+    /// `EXTRACT(field FROM value)`.
+    fn uses_keyword_arguments(&self, name: &str) -> bool {
+        let keyword = match name {
+            "SUBSTRING" | "TRIM" | "EXTRACT" => "FROM",
+            "POSITION" => "IN",
+            _ => return false,
+        };
+        let mut tokens = self.tokens.clone().skip(1).peekable();
+        if name == "TRIM"
+            && tokens
+                .peek()
+                .is_some_and(|token| matches!(token.as_str(), "LEADING" | "TRAILING" | "BOTH"))
+        {
+            return true;
+        }
+        let mut depth = 0;
+        for token in tokens {
+            match token.as_str() {
+                "(" => depth += 1,
+                ")" if depth == 0 => return false,
+                ")" => depth -= 1,
+                "," if depth == 0 => return false,
+                token if depth == 0 && token == keyword => return true,
+                _ => {}
+            }
         }
+        false
+    }
+
+    /// Parses a keyword form call into an ordinary one: TRIM becomes LTRIM,
+    /// RTRIM or TRIM with the characters second, and the EXTRACT field a
+    /// text literal.
+    fn parse_keyword_arguments(&mut self, name: String) -> Option<SQLExpression> {
+        self.expect_token("(")?;
+        let (name, arguments) = match name.as_str() {
+            "SUBSTRING" => {
+                let value = self.parse_logical_expression()?;
+                self.expect_keyword("FROM")?;
+                let mut arguments = vec![value, self.parse_logical_expression()?];
+                if self.peek_keyword("FOR") {
+                    self.next_token();
+                    arguments.push(self.parse_logical_expression()?);
+                }
+                (name, arguments)
+            }
+            "POSITION" => {
+                let needle = self.parse_postfix_expression()?;
+                self.expect_keyword("IN")?;
+                (name, vec![needle, self.parse_logical_expression()?])
+            }
+            "TRIM" => {
+                let function = match self.tokens.peek().map(|token| token.as_str()) {
+                    Some("LEADING") => "LTRIM",
+                    Some("TRAILING") => "RTRIM",
+                    _ => "TRIM",
+                };
+                if matches!(
+                    self.tokens.peek().map(|token| token.as_str()),
+                    Some("LEADING" | "TRAILING" | "BOTH")
+                ) {
+                    self.next_token();
+                }
+                let characters = if self.peek_keyword("FROM") {
+                    None
+                } else {
+                    Some(self.parse_postfix_expression()?)
+                };
+                self.expect_keyword("FROM")?;
+                let mut arguments = vec![self.parse_logical_expression()?];
+                arguments.extend(characters);
+                (function.to_string(), arguments)
+            }
+            _ => {
+                let field = self.next_token()?.to_uppercase();
+                self.expect_keyword("FROM")?;
+                let value = self.parse_logical_expression()?;
+                (
+                    name,
+                    vec![SQLExpression::Literal(format!("'{}'", field)), value],
+                )
+            }
+        };
+        self.expect_token(")")?;
+        Some(SQLExpression::FunctionCall {
+            name,
+            distinct: false,
+            arguments,
+        })
     }
 
     fn peek_operator(&mut self) -> Option<String> {
         if let Some(token) = self.tokens.peek() {
             let token_str = token.as_str();
-            if token_str == "AND"
-                || token_str == "OR"
-                || token_str == "=="
+            if token_str == "=="
                 || token_str == "!="
                 || token_str == "<"
                 || token_str == ">"
@@ -418,31 +2184,39 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_identifier(&mut self) -> Option<SQLExpression> {
-        if let Some(token) = self.next_token() {
-            Some(SQLExpression::Identifier(token.clone()))
-        } else {
-            None
+        self.next_token()
+            .map(|token| SQLExpression::Identifier(token.clone()))
+    }
+
+    /// The name of a table or column: a word that is not a keyword
+    /// clauses or statements start with.
+    fn parse_name(&mut self) -> Option<String> {
+        let token = self.tokens.peek()?;
+        let word = token
+            .chars()
+            .next()
+            .is_some_and(|first| first.is_alphabetic() || first == '_')
+            && token.chars().all(|ch| ch.is_alphanumeric() || ch == '_');
+        if !word || is_reserved_keyword(token) {
+            return None;
         }
+        self.next_token().cloned()
     }
 
     fn parse_literal(&mut self) -> Option<SQLExpression> {
-        if let Some(token) = self.next_token() {
-            Some(SQLExpression::Literal(token.clone()))
-        } else {
-            None
-        }
+        self.next_token()
+            .map(|token| SQLExpression::Literal(token.clone()))
     }
 
+    /// Consumes `keyword` if it is next; anything else is left in place,
+    /// so that a failed parse never eats the `;` ending its statement.
     fn expect_keyword(&mut self, keyword: &str) -> Option<()> {
-        if self.next_token() == Some(&keyword.to_string()) {
-            Some(())
-        } else {
-            None
-        }
+        self.expect_token(keyword)
     }
 
     fn expect_token(&mut self, token: &str) -> Option<()> {
-        if self.next_token() == Some(&token.to_string()) {
+        if self.peek_token(token) {
+            self.next_token();
             Some(())
         } else {
             None
@@ -457,7 +2231,126 @@ impl<'a> Parser<'a> {
         self.tokens.peek() == Some(&&token.to_string())
     }
 
+    /// Looks one token past the next one.
+    fn peek_second(&self, token: &str) -> bool {
+        self.tokens
+            .clone()
+            .nth(1)
+            .is_some_and(|second| second == token)
+    }
+
     fn next_token(&mut self) -> Option<&String> {
         self.tokens.next()
     }
 }
+
+/// Parses an expression on its own, as the catalog stores those of index
+/// definitions.
+pub fn parse_expression(text: &str) -> Option<SQLExpression> {
+    let tokens = Lexer::new(text).tokenize();
+    let mut parser = Parser::new(&tokens);
+    let expression = parser.parse_logical_expression()?;
+    parser.tokens.peek().is_none().then_some(expression)
+}
+
+/// Whether `(` after `token` may open the arguments of a call. A call
+/// parses the same with or without a space before them, and its text is
+/// written without one, so that it reads the same either way.
+fn is_callable(token: &str) -> bool {
+    token.starts_with(|character: char| character.is_alphabetic())
+        && !matches!(
+            token,
+            "AND" | "OR" | "NOT" | "IS" | "IN" | "WHEN" | "THEN" | "ELSE" | "AS"
+        )
+}
+
+/// The column types of CREATE TABLE, also used by CAST. `U_` prefixes the
+/// unsigned variants.
+pub fn parse_data_type(name: &str) -> Option<DataType> {
+    let (unsigned, base) = match name.strip_prefix("U_") {
+        Some(base) => (true, base),
+        None => (false, name),
+    };
+    match base {
+        "TEXT" if !unsigned => Some(DataType::TEXT { length: 256 }),
+        "BOOL" if !unsigned => Some(DataType::BOOL),
+        "DATETIME" if !unsigned => Some(DataType::DATETIME),
+        "FLOAT" => Some(DataType::FLOAT { unsigned }),
+        "DOUBLE" => Some(DataType::DOUBLE { unsigned }),
+        "BYTE" => Some(DataType::BYTE { unsigned }),
+        "SHORT" => Some(DataType::SHORT { unsigned }),
+        "INT" => Some(DataType::INT { unsigned }),
+        "LONG" => Some(DataType::LONG { unsigned }),
+        "BIGINT" => Some(DataType::BIGINT { unsigned }),
+        "UUID" => Some(DataType::UUID { unsigned }),
+        _ => None,
+    }
+}
+
+/// Quoted strings, numbers and the `NULL`/`TRUE`/`FALSE` keywords are literals,
+/// everything else in primary position is an identifier.
+fn is_literal_token(token: &str) -> bool {
+    token.starts_with('\'')
+        || token.starts_with('"')
+        || token.starts_with(|ch: char| ch.is_ascii_digit())
+        || token == "NULL"
+        || token == "TRUE"
+        || token == "FALSE"
+}
+
+/// Keywords that end a FROM item, or start the next statement, so they are
+/// never taken for a table alias.
+fn is_reserved_keyword(token: &str) -> bool {
+    matches!(
+        token,
+        "FROM"
+            | "WHERE"
+            | "GROUP"
+            | "HAVING"
+            | "WINDOW"
+            | "JOIN"
+            | "INNER"
+            | "LEFT"
+            | "RIGHT"
+            | "FULL"
+            | "OUTER"
+            | "CROSS"
+            | "NATURAL"
+            | "ON"
+            | "USING"
+            | "UNION"
+            | "INTERSECT"
+            | "EXCEPT"
+            | "ORDER"
+            | "LIMIT"
+            | "OFFSET"
+            | "FOR"
+            | "ASC"
+            | "DESC"
+    ) || is_statement_keyword(token)
+}
+
+/// Keywords a statement starts with.
+fn is_statement_keyword(token: &str) -> bool {
+    matches!(
+        token,
+        "SELECT"
+            | "WITH"
+            | "INSERT"
+            | "UPDATE"
+            | "DELETE"
+            | "CREATE"
+            | "SHOW"
+            | "DESCRIBE"
+            | "USE"
+            | "BEGIN"
+            | "START"
+            | "COMMIT"
+            | "ROLLBACK"
+            | "SAVEPOINT"
+            | "RELEASE"
+            | "LOCK"
+            | "ALTER"
+            | "VACUUM"
+    )
+}
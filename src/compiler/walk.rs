@@ -0,0 +1,209 @@
+//! Walks over parsed expressions: the parts of a node, and the column
+//! references, calls and subqueries anywhere inside one.
+
+use crate::compiler::parser::{SQLExpression, WindowSpecification};
+use crate::types::function::{AggregateFunction, ScalarFunction};
+
+/// Appends every column reference inside `expression` to `references`.
+pub fn column_references<'a>(
+    expression: &'a SQLExpression,
+    references: &mut Vec<&'a SQLExpression>,
+) {
+    match expression {
+        SQLExpression::Identifier(_) | SQLExpression::QualifiedIdentifier { .. } => {
+            references.push(expression)
+        }
+        SQLExpression::BinaryExpression { left, right, .. } => {
+            column_references(left, references);
+            column_references(right, references);
+        }
+        SQLExpression::FunctionCall { arguments, .. } => {
+            for argument in arguments {
+                column_references(argument, references);
+            }
+        }
+        SQLExpression::Alias { expression, .. }
+        | SQLExpression::InSubquery { expression, .. }
+        | SQLExpression::Cast { expression, .. }
+        | SQLExpression::Not(expression)
+        | SQLExpression::IsNull { expression, .. } => column_references(expression, references),
+        SQLExpression::Case { .. } => {
+            for part in case_parts(expression) {
+                column_references(part, references);
+            }
+        }
+        SQLExpression::WindowFunction { function, window } => {
+            column_references(function, references);
+            for expression in window_expressions(window) {
+                column_references(expression, references);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The operand, WHEN, THEN and ELSE expressions of a CASE, in order.
+pub fn case_parts(expression: &SQLExpression) -> Vec<&SQLExpression> {
+    let mut parts = Vec::new();
+    if let SQLExpression::Case {
+        operand,
+        branches,
+        else_result,
+    } = expression
+    {
+        parts.extend(operand.as_deref());
+        for (condition, result) in branches {
+            parts.push(condition);
+            parts.push(result);
+        }
+        parts.extend(else_result.as_deref());
+    }
+    parts
+}
+
+/// The PARTITION BY and ORDER BY expressions of a window.
+pub fn window_expressions(window: &WindowSpecification) -> impl Iterator<Item = &SQLExpression> {
+    window
+        .partition_by
+        .iter()
+        .chain(window.order_by.iter().map(|item| &item.expression))
+}
+
+/// The name a projected expression gets in a result set.
+pub fn column_name(expression: &SQLExpression) -> String {
+    match expression {
+        SQLExpression::Identifier(name) => name.clone(),
+        SQLExpression::QualifiedIdentifier { column, .. } => column.clone(),
+        SQLExpression::Alias { alias, .. } => alias.clone(),
+        SQLExpression::FunctionCall { name, .. } => name.to_lowercase(),
+        SQLExpression::WindowFunction { function, .. } => column_name(function),
+        SQLExpression::Cast { expression, .. } => column_name(expression),
+        SQLExpression::Case { .. } => "case".to_string(),
+        SQLExpression::Exists { .. } => "exists".to_string(),
+        _ => "?column?".to_string(),
+    }
+}
+
+pub fn contains_subquery(expression: &SQLExpression) -> bool {
+    match expression {
+        SQLExpression::Subquery(_)
+        | SQLExpression::InSubquery { .. }
+        | SQLExpression::Exists { .. } => true,
+        SQLExpression::BinaryExpression { left, right, .. } => {
+            contains_subquery(left) || contains_subquery(right)
+        }
+        SQLExpression::FunctionCall { arguments, .. } => arguments.iter().any(contains_subquery),
+        SQLExpression::Alias { expression, .. }
+        | SQLExpression::Cast { expression, .. }
+        | SQLExpression::Not(expression)
+        | SQLExpression::IsNull { expression, .. } => contains_subquery(expression),
+        SQLExpression::Case { .. } => case_parts(expression).into_iter().any(contains_subquery),
+        SQLExpression::WindowFunction { function, window } => {
+            contains_subquery(function) || window_expressions(window).any(contains_subquery)
+        }
+        _ => false,
+    }
+}
+
+/// Whether evaluating `expression` calls `nextval`, `currval` or `setval`,
+/// so that it reads or changes a sequence.
+pub fn uses_sequences(expression: &SQLExpression) -> bool {
+    match expression {
+        SQLExpression::FunctionCall {
+            name, arguments, ..
+        } => {
+            ScalarFunction::from_name(name).is_some_and(ScalarFunction::uses_sequences)
+                || arguments.iter().any(uses_sequences)
+        }
+        SQLExpression::BinaryExpression { left, right, .. } => {
+            uses_sequences(left) || uses_sequences(right)
+        }
+        SQLExpression::Alias { expression, .. }
+        | SQLExpression::Cast { expression, .. }
+        | SQLExpression::Not(expression)
+        | SQLExpression::IsNull { expression, .. } => uses_sequences(expression),
+        SQLExpression::Case { .. } => case_parts(expression).into_iter().any(uses_sequences),
+        SQLExpression::WindowFunction { function, window } => {
+            uses_sequences(function) || window_expressions(window).any(uses_sequences)
+        }
+        _ => false,
+    }
+}
+
+/// Collects every aggregate call in `expression` into `calls`, skipping
+/// duplicates, so that the same call in the select list and HAVING is only
+/// computed once.
+pub fn collect_aggregates(expression: &SQLExpression, calls: &mut Vec<SQLExpression>) {
+    match expression {
+        SQLExpression::FunctionCall {
+            name, arguments, ..
+        } => {
+            if AggregateFunction::from_name(name).is_some() {
+                if !calls.contains(expression) {
+                    calls.push(expression.clone());
+                }
+            } else {
+                for argument in arguments {
+                    collect_aggregates(argument, calls);
+                }
+            }
+        }
+        SQLExpression::BinaryExpression { left, right, .. } => {
+            collect_aggregates(left, calls);
+            collect_aggregates(right, calls);
+        }
+        SQLExpression::Alias { expression, .. }
+        | SQLExpression::InSubquery { expression, .. }
+        | SQLExpression::Cast { expression, .. }
+        | SQLExpression::Not(expression)
+        | SQLExpression::IsNull { expression, .. } => collect_aggregates(expression, calls),
+        SQLExpression::Case { .. } => {
+            for part in case_parts(expression) {
+                collect_aggregates(part, calls);
+            }
+        }
+        // `SUM(x) OVER (...)` is computed by the window operator, but its
+        // arguments and window may contain ordinary aggregates.
+        SQLExpression::WindowFunction { function, window } => {
+            if let SQLExpression::FunctionCall { arguments, .. } = function.as_ref() {
+                for argument in arguments {
+                    collect_aggregates(argument, calls);
+                }
+            }
+            for expression in window_expressions(window) {
+                collect_aggregates(expression, calls);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collects every window function call in `expression` into `calls`,
+/// skipping duplicates.
+pub fn collect_window_functions(expression: &SQLExpression, calls: &mut Vec<SQLExpression>) {
+    match expression {
+        SQLExpression::WindowFunction { .. } if !calls.contains(expression) => {
+            calls.push(expression.clone());
+        }
+        SQLExpression::FunctionCall { arguments, .. } => {
+            for argument in arguments {
+                collect_window_functions(argument, calls);
+            }
+        }
+        SQLExpression::BinaryExpression { left, right, .. } => {
+            collect_window_functions(left, calls);
+            collect_window_functions(right, calls);
+        }
+        SQLExpression::Alias { expression, .. }
+        | SQLExpression::InSubquery { expression, .. }
+        | SQLExpression::Cast { expression, .. }
+        | SQLExpression::Not(expression)
+        | SQLExpression::IsNull { expression, .. } => collect_window_functions(expression, calls),
+        SQLExpression::Case { .. } => {
+            for part in case_parts(expression) {
+                collect_window_functions(part, calls);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,496 @@
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+use crate::compiler::binder::Binder;
+use crate::compiler::parser::{DataType, IndexMethod, SQLExpression};
+use crate::compiler::walk::{case_parts, column_references, contains_subquery, uses_sequences};
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::{evaluate, is_satisfied, Context, Schema};
+use crate::execution::planner::conjuncts;
+use crate::storage::catalog::{Catalog, IndexSchema, TableSchema};
+use crate::storage::key::{encode_key, prefix_range, prefix_successor};
+use crate::storage::page::PageId;
+use crate::types::value::Value;
+
+/// How to read the rows of a base table that a WHERE clause restricts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessPath {
+    /// Every row of the heap.
+    SeqScan,
+    /// The rows whose key in an index equals `values`.
+    IndexLookup {
+        index: IndexSchema,
+        values: Vec<Value>,
+    },
+    /// The rows whose keys in a B+tree index fall between two bounds.
+    IndexRange {
+        index: PageId,
+        lower: Bound<Vec<u8>>,
+        upper: Bound<Vec<u8>>,
+    },
+}
+
+/// An index with its key and predicate bound against its table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundIndex {
+    pub index: IndexSchema,
+    /// The parts of the key with their types.
+    pub keys: Vec<(SQLExpression, Option<DataType>)>,
+    pub predicate: Option<SQLExpression>,
+}
+
+impl BoundIndex {
+    pub fn bind(
+        catalog: &Catalog,
+        table: &TableSchema,
+        index: &IndexSchema,
+    ) -> Result<Self, ExecutionError> {
+        let (keys, predicate) =
+            Binder::new(catalog).bind_index(table, &index.keys()?, index.condition()?.as_ref())?;
+        Ok(BoundIndex {
+            index: index.clone(),
+            keys,
+            predicate,
+        })
+    }
+
+    /// The key of `row` in the index, or `None` if the predicate of a
+    /// partial index leaves the row out. NOW() reads `now`.
+    pub fn entry(
+        &self,
+        table: &TableSchema,
+        row: &[Value],
+        now: i64,
+    ) -> Result<Option<Vec<Value>>, ExecutionError> {
+        let names: Vec<&str> = table
+            .columns
+            .iter()
+            .map(|column| column.name.as_str())
+            .collect();
+        let schema = Schema::for_table(&table.name, &names);
+        let context = Context {
+            provider: None,
+            outer: None,
+            now,
+        };
+        if let Some(predicate) = &self.predicate {
+            if !is_satisfied(predicate, &schema, row, &context)? {
+                return Ok(None);
+            }
+        }
+        self.keys
+            .iter()
+            .map(|(key, _)| evaluate(key, &schema, row, &context))
+            .collect::<Result<Vec<Value>, ExecutionError>>()
+            .map(Some)
+    }
+}
+
+/// A conjunct `expression OP constant`, turned around if the constant
+/// came first.
+struct Restriction<'a> {
+    target: &'a SQLExpression,
+    operator: &'a str,
+    value: Value,
+}
+
+/// How well an index serves a WHERE clause, better paths comparing
+/// greater: the key parts it fixes, whether it finds at most one row,
+/// whether the conditions cover its whole key or bound the part after the
+/// fixed ones, and whether it is a hash index, the cheaper of two that
+/// look up the same key.
+type Score = (usize, bool, bool, bool);
+
+/// Picks the index that serves the most conjuncts of a WHERE clause on a
+/// table read under `qualifier`.
+///
+/// A B+tree index is usable if the conditions fix one or more of the
+/// leading parts of its key with `==`, or bound the first part they do
+/// not fix; a hash index only if they fix its whole key. A part is a
+/// column, or an expression the condition repeats as the index wrote it.
+/// A partial index is usable only if the conditions imply its predicate.
+/// The index that fixes the most parts wins, as ranked by [`Score`]. The
+/// chosen path may return rows the WHERE clause rejects, so the clause is
+/// still applied to them, with NOW() reading the same `now`.
+pub fn choose_access_path(
+    indexes: &[BoundIndex],
+    qualifier: &str,
+    conditions: &[&SQLExpression],
+    now: i64,
+) -> Result<AccessPath, ExecutionError> {
+    let restrictions = restrictions(conditions, now)?;
+    let mut best: Option<(Score, AccessPath)> = None;
+    for index in indexes {
+        let root = match index.index.root {
+            Some(root) => root,
+            None => continue,
+        };
+        if let Some(predicate) = &index.predicate {
+            if !implies(conditions, &restrictions, predicate, qualifier, now)? {
+                continue;
+            }
+        }
+        if let Some((score, path)) = index_path(index, root, &restrictions, qualifier) {
+            if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                best = Some((score, path));
+            }
+        }
+    }
+    Ok(best.map_or(AccessPath::SeqScan, |(_, path)| path))
+}
+
+fn index_path(
+    bound: &BoundIndex,
+    root: PageId,
+    restrictions: &[Restriction],
+    qualifier: &str,
+) -> Option<(Score, AccessPath)> {
+    let index = &bound.index;
+    // The restrictions on a part of the key whose constant is stored the
+    // way the part's values are, so that its key sorts among theirs.
+    let on = |(key, data_type): &(SQLExpression, Option<DataType>)| {
+        restrictions
+            .iter()
+            .filter(|restriction| same_expression(restriction.target, key, qualifier))
+            .filter(|restriction| {
+                data_type
+                    .as_ref()
+                    .is_some_and(|data_type| represents(data_type, &restriction.value))
+            })
+            .collect::<Vec<&Restriction>>()
+    };
+    let mut values = Vec::new();
+    for key in &bound.keys {
+        let equal = on(key)
+            .into_iter()
+            .find(|restriction| restriction.operator == "==");
+        match equal {
+            Some(restriction) => values.push(restriction.value.clone()),
+            None => break,
+        }
+    }
+    let fixed = values.len();
+    let hash = index.method == IndexMethod::Hash;
+    if fixed == bound.keys.len() {
+        return Some((
+            (fixed, index.unique, true, hash),
+            AccessPath::IndexLookup {
+                index: index.clone(),
+                values,
+            },
+        ));
+    }
+    if hash {
+        return None;
+    }
+    let prefix = encode_key(&values);
+    // Bounds on the first part not fixed, as the smallest key in range
+    // and the smallest key above it.
+    let mut lower: Option<Vec<u8>> = None;
+    let mut upper: Option<Vec<u8>> = None;
+    if let Some(key) = bound.keys.get(fixed) {
+        for restriction in on(key) {
+            let mut key = prefix.clone();
+            key.extend(encode_key(std::slice::from_ref(&restriction.value)));
+            let (bound, is_lower) = match restriction.operator {
+                ">=" => (Some(key), true),
+                ">" => (prefix_successor(&key), true),
+                "<=" => (prefix_successor(&key), false),
+                "<" => (Some(key), false),
+                _ => continue,
+            };
+            match (bound, is_lower) {
+                (Some(key), true) => lower = lower.max(Some(key)),
+                (Some(key), false) => {
+                    upper = Some(upper.map_or(key.clone(), |upper| upper.min(key)))
+                }
+                (None, _) => {}
+            }
+        }
+    }
+    let bounded = lower.is_some() || upper.is_some();
+    let (prefix_lower, prefix_upper) = prefix_range(&prefix);
+    let lower = match lower {
+        Some(key) => Bound::Included(key),
+        // NULLs, flagged 0, sort first; non-NULL values start with 1.
+        None if bounded => Bound::Included([prefix.as_slice(), &[1]].concat()),
+        None => prefix_lower,
+    };
+    let upper = upper.map_or(prefix_upper, Bound::Excluded);
+    if fixed == 0 && !bounded {
+        return None;
+    }
+    Some((
+        (fixed, false, bounded, false),
+        AccessPath::IndexRange {
+            index: root,
+            lower,
+            upper,
+        },
+    ))
+}
+
+/// The conjuncts that compare an expression over the columns with a
+/// constant.
+fn restrictions<'a>(
+    conditions: &[&'a SQLExpression],
+    now: i64,
+) -> Result<Vec<Restriction<'a>>, ExecutionError> {
+    let mut restrictions = Vec::new();
+    for condition in conditions {
+        let (operator, left, right) = match condition {
+            SQLExpression::BinaryExpression {
+                operator,
+                left,
+                right,
+            } => (operator.as_str(), left.as_ref(), right.as_ref()),
+            _ => continue,
+        };
+        if !matches!(operator, "==" | "!=" | "<" | ">" | "<=" | ">=") {
+            continue;
+        }
+        let (target, constant, operator) = match (is_constant(left), is_constant(right)) {
+            (false, true) => (left, right, operator),
+            (true, false) => (right, left, flip(operator)),
+            _ => continue,
+        };
+        if contains_subquery(target) {
+            continue;
+        }
+        let context = Context {
+            provider: None,
+            outer: None,
+            now,
+        };
+        let value = evaluate(constant, &Schema::new(Vec::new()), &[], &context)?;
+        if value != Value::Null {
+            restrictions.push(Restriction {
+                target,
+                operator,
+                value,
+            });
+        }
+    }
+    Ok(restrictions)
+}
+
+/// Whether rows meeting `conditions` always meet the predicate of a
+/// partial index: each of its conjuncts is one of the conditions, or
+/// follows from a restriction the conditions place on the same
+/// expression, as `age > 30` implies `age >= 18` and `age IS NOT NULL`.
+fn implies(
+    conditions: &[&SQLExpression],
+    restrictions: &[Restriction],
+    predicate: &SQLExpression,
+    qualifier: &str,
+    now: i64,
+) -> Result<bool, ExecutionError> {
+    for conjunct in conjuncts(predicate) {
+        if conditions
+            .iter()
+            .any(|condition| same_expression(condition, conjunct, qualifier))
+        {
+            continue;
+        }
+        // A comparison with a constant is never TRUE for a NULL.
+        if let SQLExpression::IsNull {
+            expression,
+            negated: true,
+        } = conjunct
+        {
+            if restrictions
+                .iter()
+                .any(|given| same_expression(given.target, expression, qualifier))
+            {
+                continue;
+            }
+        }
+        let mut follows = false;
+        for needed in self::restrictions(&[conjunct], now)? {
+            for given in restrictions
+                .iter()
+                .filter(|given| same_expression(given.target, needed.target, qualifier))
+            {
+                follows |= restricts_within(given, &needed)?;
+            }
+        }
+        if !follows {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Whether every value meeting `given` meets `needed`, both restricting
+/// the same expression.
+fn restricts_within(given: &Restriction, needed: &Restriction) -> Result<bool, ExecutionError> {
+    let order = match given.value.compare(&needed.value)? {
+        Some(order) => order,
+        None => return Ok(false),
+    };
+    Ok(match (needed.operator, given.operator) {
+        ("==", "==") => order == Ordering::Equal,
+        ("!=", "==") => order != Ordering::Equal,
+        ("!=", "!=") => order == Ordering::Equal,
+        ("!=", ">") => order != Ordering::Less,
+        ("!=", ">=") => order == Ordering::Greater,
+        ("!=", "<") => order != Ordering::Greater,
+        ("!=", "<=") => order == Ordering::Less,
+        (">", "==" | ">=") => order == Ordering::Greater,
+        (">", ">") | (">=", "==" | ">=" | ">") => order != Ordering::Less,
+        ("<", "==" | "<=") => order == Ordering::Less,
+        ("<", "<") | ("<=", "==" | "<=" | "<") => order != Ordering::Greater,
+        _ => false,
+    })
+}
+
+/// Whether `query`, read under `qualifier`, is the expression `key` of
+/// an index, whose columns are those of the one table it is on.
+fn same_expression(query: &SQLExpression, key: &SQLExpression, qualifier: &str) -> bool {
+    match (query, key) {
+        (
+            SQLExpression::Identifier(_) | SQLExpression::QualifiedIdentifier { .. },
+            SQLExpression::Identifier(name)
+            | SQLExpression::QualifiedIdentifier { column: name, .. },
+        ) => column_of(query, qualifier) == Some(name),
+        (SQLExpression::Literal(left), SQLExpression::Literal(right)) => left == right,
+        (
+            SQLExpression::BinaryExpression {
+                operator,
+                left,
+                right,
+            },
+            SQLExpression::BinaryExpression {
+                operator: key_operator,
+                left: key_left,
+                right: key_right,
+            },
+        ) => {
+            operator == key_operator
+                && same_expression(left, key_left, qualifier)
+                && same_expression(right, key_right, qualifier)
+        }
+        (
+            SQLExpression::FunctionCall {
+                name,
+                distinct,
+                arguments,
+            },
+            SQLExpression::FunctionCall {
+                name: key_name,
+                distinct: key_distinct,
+                arguments: key_arguments,
+            },
+        ) => {
+            name.eq_ignore_ascii_case(key_name)
+                && distinct == key_distinct
+                && same_expressions(arguments.iter(), key_arguments.iter(), qualifier)
+        }
+        (
+            SQLExpression::Cast {
+                expression,
+                data_type,
+            },
+            SQLExpression::Cast {
+                expression: key_expression,
+                data_type: key_type,
+            },
+        ) => data_type == key_type && same_expression(expression, key_expression, qualifier),
+        (SQLExpression::Not(expression), SQLExpression::Not(key_expression)) => {
+            same_expression(expression, key_expression, qualifier)
+        }
+        (
+            SQLExpression::IsNull {
+                expression,
+                negated,
+            },
+            SQLExpression::IsNull {
+                expression: key_expression,
+                negated: key_negated,
+            },
+        ) => negated == key_negated && same_expression(expression, key_expression, qualifier),
+        (
+            SQLExpression::Case {
+                operand,
+                branches,
+                else_result,
+            },
+            SQLExpression::Case {
+                operand: key_operand,
+                branches: key_branches,
+                else_result: key_else,
+            },
+        ) => {
+            operand.is_some() == key_operand.is_some()
+                && branches.len() == key_branches.len()
+                && else_result.is_some() == key_else.is_some()
+                && same_expressions(
+                    case_parts(query).into_iter(),
+                    case_parts(key).into_iter(),
+                    qualifier,
+                )
+        }
+        _ => false,
+    }
+}
+
+fn same_expressions<'a>(
+    query: impl ExactSizeIterator<Item = &'a SQLExpression>,
+    key: impl ExactSizeIterator<Item = &'a SQLExpression>,
+    qualifier: &str,
+) -> bool {
+    query.len() == key.len()
+        && query
+            .zip(key)
+            .all(|(query, key)| same_expression(query, key, qualifier))
+}
+
+fn column_of<'a>(expression: &'a SQLExpression, qualifier: &str) -> Option<&'a String> {
+    match expression {
+        SQLExpression::Identifier(name) => Some(name),
+        SQLExpression::QualifiedIdentifier { table, column } if table == qualifier => Some(column),
+        _ => None,
+    }
+}
+
+fn is_constant(expression: &SQLExpression) -> bool {
+    let mut references = Vec::new();
+    column_references(expression, &mut references);
+    references.is_empty() && !contains_subquery(expression) && !uses_sequences(expression)
+}
+
+/// The operator that compares the same way with its operands swapped.
+fn flip(operator: &str) -> &str {
+    match operator {
+        "<" => ">",
+        ">" => "<",
+        "<=" => ">=",
+        ">=" => "<=",
+        operator => operator,
+    }
+}
+
+/// Whether `value` is stored the way values of `data_type` are, so that
+/// its key sorts among theirs. NULL matches nothing.
+fn represents(data_type: &DataType, value: &Value) -> bool {
+    matches!(
+        (data_type, value),
+        (
+            DataType::TEXT { .. } | DataType::UUID { .. },
+            Value::Text(_)
+        ) | (DataType::BOOL, Value::Bool(_))
+            | (DataType::DATETIME, Value::DateTime(_))
+            | (
+                DataType::FLOAT { .. } | DataType::DOUBLE { .. },
+                Value::Float(_)
+            )
+            | (
+                DataType::BYTE { .. }
+                    | DataType::SHORT { .. }
+                    | DataType::INT { .. }
+                    | DataType::LONG { .. }
+                    | DataType::BIGINT { .. },
+                Value::Integer(_),
+            )
+    )
+}
@@ -0,0 +1,727 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::compiler::parser::SQLExpression;
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::{evaluate, Context, Schema};
+use crate::types::function::AggregateFunction;
+use crate::types::value::Value;
+
+/// Bytes a hash aggregate or an external sort keeps in memory before it
+/// spills, unless the session says otherwise.
+pub const DEFAULT_WORK_MEMORY: usize = 4 << 20;
+const SPILL_PARTITIONS: usize = 16;
+const MAX_SPILL_DEPTH: usize = 8;
+
+static SPILL_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// One aggregate call such as `COUNT(DISTINCT x)`; `argument` is `None` for `COUNT(*)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateCall {
+    pub function: AggregateFunction,
+    pub distinct: bool,
+    pub argument: Option<SQLExpression>,
+}
+
+impl AggregateCall {
+    pub fn from_expression(expression: &SQLExpression) -> Result<Option<Self>, ExecutionError> {
+        let (name, distinct, arguments) = match expression {
+            SQLExpression::FunctionCall {
+                name,
+                distinct,
+                arguments,
+            } => (name, *distinct, arguments),
+            _ => return Ok(None),
+        };
+        let function = match AggregateFunction::from_name(name) {
+            Some(function) => function,
+            None => return Ok(None),
+        };
+        let argument = match arguments.as_slice() {
+            [SQLExpression::Wildcard] if function == AggregateFunction::Count && !distinct => None,
+            [argument] => Some(argument.clone()),
+            _ => {
+                return Err(ExecutionError::TypeMismatch(format!(
+                    "{} takes exactly one argument",
+                    name
+                )))
+            }
+        };
+        Ok(Some(AggregateCall {
+            function,
+            distinct,
+            argument,
+        }))
+    }
+}
+
+/// Hash aggregation operator.
+///
+/// Groups are kept in an in-memory table until its estimated size reaches
+/// `memory` bytes. After that, rows belonging to groups that are not in
+/// the table are partitioned by hash into spill files, and each partition
+/// is aggregated on its own once the input is exhausted. The values the
+/// DISTINCT calls of a group have seen count toward `memory` too: a group
+/// whose seen values would take it past the budget is written to its
+/// partition as it stands and taken out of the table, and the partition
+/// goes on from that state with the rest of the group's rows.
+pub struct HashAggregate {
+    group_by: Vec<SQLExpression>,
+    aggregate_expressions: Vec<SQLExpression>,
+    aggregates: Vec<AggregateCall>,
+    memory: usize,
+    spill_directory: PathBuf,
+}
+
+impl HashAggregate {
+    pub fn new(
+        group_by: Vec<SQLExpression>,
+        aggregate_expressions: Vec<SQLExpression>,
+    ) -> Result<Self, ExecutionError> {
+        let mut aggregates = Vec::new();
+        for expression in &aggregate_expressions {
+            match AggregateCall::from_expression(expression)? {
+                Some(call) => aggregates.push(call),
+                None => {
+                    return Err(ExecutionError::Unsupported(format!(
+                        "{} is not an aggregate call",
+                        expression
+                    )))
+                }
+            }
+        }
+        Ok(HashAggregate {
+            group_by,
+            aggregate_expressions,
+            aggregates,
+            memory: DEFAULT_WORK_MEMORY,
+            spill_directory: std::env::temp_dir(),
+        })
+    }
+
+    /// Spills once the groups take about `memory` bytes. At least one
+    /// group is always kept, so any budget makes progress.
+    pub fn with_memory(mut self, memory: usize) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    pub fn with_spill_directory<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.spill_directory = directory.into();
+        self
+    }
+
+    /// Grouping expressions followed by the aggregate calls. A column
+    /// grouped by without its table keeps the qualified slot it has in
+    /// `input`, so that it can still be read by either name.
+    pub fn output_schema(&self, input: &Schema) -> Schema {
+        let mut columns: Vec<SQLExpression> = self
+            .group_by
+            .iter()
+            .map(|key| match input.resolve(key) {
+                Ok(Some(position)) if matches!(key, SQLExpression::Identifier(_)) => {
+                    input.columns[position].clone()
+                }
+                _ => key.clone(),
+            })
+            .collect();
+        columns.extend(self.aggregate_expressions.iter().cloned());
+        Schema::new(columns)
+    }
+
+    /// Groups `rows` as they come, so that of the input no more than one
+    /// row is held at a time.
+    pub fn execute<I>(
+        &self,
+        input_schema: &Schema,
+        rows: I,
+        context: &Context,
+    ) -> Result<Vec<Vec<Value>>, ExecutionError>
+    where
+        I: IntoIterator<Item = Result<Vec<Value>, ExecutionError>>,
+    {
+        let prepared = rows
+            .into_iter()
+            .map(|row| self.prepare(input_schema, &row?, context));
+        let mut output = Vec::new();
+        self.aggregate_pass(prepared, 0, &mut output)?;
+        if output.is_empty() && self.group_by.is_empty() {
+            let state = GroupState::new(&self.aggregates);
+            output.push(state.finish()?);
+        }
+        Ok(output)
+    }
+
+    /// Evaluates the grouping key and the aggregate arguments of one input row.
+    fn prepare(
+        &self,
+        schema: &Schema,
+        row: &[Value],
+        context: &Context,
+    ) -> Result<PreparedRow, ExecutionError> {
+        let mut key = Vec::with_capacity(self.group_by.len());
+        for expression in &self.group_by {
+            key.push(evaluate(expression, schema, row, context)?);
+        }
+        let mut arguments = Vec::with_capacity(self.aggregates.len());
+        for call in &self.aggregates {
+            arguments.push(match &call.argument {
+                Some(argument) => evaluate(argument, schema, row, context)?,
+                None => Value::Bool(true),
+            });
+        }
+        Ok(PreparedRow {
+            key,
+            input: Input::Arguments(arguments),
+        })
+    }
+
+    fn aggregate_pass<I>(
+        &self,
+        rows: I,
+        depth: usize,
+        output: &mut Vec<Vec<Value>>,
+    ) -> Result<(), ExecutionError>
+    where
+        I: Iterator<Item = Result<PreparedRow, ExecutionError>>,
+    {
+        let mut positions: HashMap<Vec<Value>, usize> = HashMap::new();
+        let mut groups: Vec<(Vec<Value>, GroupState)> = Vec::new();
+        let mut spill: Option<SpillPartitions> = None;
+        let mut used = 0;
+
+        for row in rows {
+            let row = row?;
+            let position = match positions.get(&row.key) {
+                Some(position) => *position,
+                None if used < self.memory || groups.is_empty() || depth >= MAX_SPILL_DEPTH => {
+                    used += self.group_size(&row.key);
+                    positions.insert(row.key.clone(), groups.len());
+                    groups.push((row.key.clone(), GroupState::new(&self.aggregates)));
+                    groups.len() - 1
+                }
+                None => {
+                    self.partitions(&mut spill)?.write(&row, depth)?;
+                    continue;
+                }
+            };
+            let growth = groups[position].1.growth(&row.input);
+            if growth > 0
+                && used + growth > self.memory
+                && groups.len() > 1
+                && depth < MAX_SPILL_DEPTH
+            {
+                let (key, state) = groups.swap_remove(position);
+                positions.remove(&key);
+                if let Some((moved, _)) = groups.get(position) {
+                    positions.insert(moved.clone(), position);
+                }
+                let partitions = self.partitions(&mut spill)?;
+                partitions.write(
+                    &PreparedRow {
+                        key,
+                        input: Input::State(state),
+                    },
+                    depth,
+                )?;
+                partitions.write(&row, depth)?;
+                // No new groups from here on: the rest of the group's rows
+                // must follow its state, which they would not if the group
+                // came back into the table.
+                used = used.max(self.memory);
+                continue;
+            }
+            let state = &mut groups[position].1;
+            match row.input {
+                Input::Arguments(arguments) => state.update(&self.aggregates, &arguments)?,
+                Input::State(other) => state.merge(&self.aggregates, other)?,
+            }
+            used += growth;
+        }
+
+        for (key, state) in groups {
+            let mut result = key;
+            result.extend(state.finish()?);
+            output.push(result);
+        }
+
+        if let Some(partitions) = spill {
+            let mut files = partitions.finish()?;
+            while let Some(path) = files.take() {
+                let reader = SpillReader::open(&path, self.group_by.len(), &self.aggregates);
+                let result =
+                    reader.and_then(|reader| self.aggregate_pass(reader, depth + 1, output));
+                let _ = fs::remove_file(&path);
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The spill files of this pass, created for the first row spilled.
+    fn partitions<'s>(
+        &self,
+        spill: &'s mut Option<SpillPartitions>,
+    ) -> Result<&'s mut SpillPartitions, ExecutionError> {
+        if spill.is_none() {
+            *spill = Some(SpillPartitions::create(&self.spill_directory)?);
+        }
+        Ok(spill.as_mut().expect("spill partitions were just created"))
+    }
+
+    /// Estimated bytes a new group with `key` takes: the key, held by both
+    /// the table and the list of groups, and the state of every call.
+    fn group_size(&self, key: &[Value]) -> usize {
+        let key: usize = key.iter().map(value_size).sum();
+        2 * key
+            + size_of::<(Vec<Value>, GroupState)>()
+            + size_of::<usize>()
+            + self.aggregates.len()
+                * (size_of::<Accumulator>() + size_of::<Option<HashSet<Value>>>())
+    }
+}
+
+/// Estimated bytes `value` takes in memory.
+pub fn value_size(value: &Value) -> usize {
+    size_of::<Value>()
+        + match value {
+            Value::Text(text) => text.len(),
+            _ => 0,
+        }
+}
+
+struct PreparedRow {
+    key: Vec<Value>,
+    input: Input,
+}
+
+/// What a row adds to its group: the arguments of the calls, or, for a
+/// group spilled part way, the state it had reached.
+enum Input {
+    Arguments(Vec<Value>),
+    State(GroupState),
+}
+
+enum Accumulator {
+    Count(i128),
+    Sum(Option<Value>),
+    Avg { sum: f64, count: i128 },
+    Min(Option<Value>),
+    Max(Option<Value>),
+}
+
+/// Running state of a list of aggregate calls. The window operator reuses
+/// it to aggregate over frames.
+pub struct GroupState {
+    accumulators: Vec<Accumulator>,
+    seen: Vec<Option<HashSet<Value>>>,
+}
+
+impl GroupState {
+    pub fn new(calls: &[AggregateCall]) -> Self {
+        GroupState {
+            accumulators: calls
+                .iter()
+                .map(|call| match call.function {
+                    AggregateFunction::Count => Accumulator::Count(0),
+                    AggregateFunction::Sum => Accumulator::Sum(None),
+                    AggregateFunction::Avg => Accumulator::Avg { sum: 0.0, count: 0 },
+                    AggregateFunction::Min => Accumulator::Min(None),
+                    AggregateFunction::Max => Accumulator::Max(None),
+                })
+                .collect(),
+            seen: calls
+                .iter()
+                .map(|call| {
+                    if call.distinct {
+                        Some(HashSet::new())
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        calls: &[AggregateCall],
+        arguments: &[Value],
+    ) -> Result<(), ExecutionError> {
+        for (index, value) in arguments.iter().enumerate() {
+            if value.is_null() {
+                continue;
+            }
+            if let Some(seen) = self.seen[index].as_mut() {
+                if !seen.insert(value.clone()) {
+                    continue;
+                }
+            }
+            accumulate(&mut self.accumulators[index], calls[index].function, value)?;
+        }
+        Ok(())
+    }
+
+    /// Adds what `other` aggregated to this state, as if the rows of both
+    /// had been given to this one.
+    fn merge(&mut self, calls: &[AggregateCall], other: GroupState) -> Result<(), ExecutionError> {
+        let parts = other.accumulators.into_iter().zip(other.seen);
+        for (index, (accumulator, seen)) in parts.enumerate() {
+            let function = calls[index].function;
+            if let (Some(seen), Some(values)) = (self.seen[index].as_mut(), seen) {
+                for value in values {
+                    if !seen.contains(&value) {
+                        accumulate(&mut self.accumulators[index], function, &value)?;
+                        seen.insert(value);
+                    }
+                }
+                continue;
+            }
+            match (&mut self.accumulators[index], accumulator) {
+                (Accumulator::Count(count), Accumulator::Count(other)) => *count += other,
+                (
+                    Accumulator::Avg { sum, count },
+                    Accumulator::Avg {
+                        sum: other_sum,
+                        count: other_count,
+                    },
+                ) => {
+                    *sum += other_sum;
+                    *count += other_count;
+                }
+                (
+                    current,
+                    Accumulator::Sum(Some(value))
+                    | Accumulator::Min(Some(value))
+                    | Accumulator::Max(Some(value)),
+                ) => accumulate(current, function, &value)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Estimated bytes the values `input` adds to the DISTINCT calls take.
+    fn growth(&self, input: &Input) -> usize {
+        let mut bytes = 0;
+        for (index, seen) in self.seen.iter().enumerate() {
+            let Some(seen) = seen else { continue };
+            match input {
+                Input::Arguments(arguments) => {
+                    let value = &arguments[index];
+                    if !value.is_null() && !seen.contains(value) {
+                        bytes += value_size(value);
+                    }
+                }
+                Input::State(other) => {
+                    for value in other.seen[index].iter().flatten() {
+                        if !seen.contains(value) {
+                            bytes += value_size(value);
+                        }
+                    }
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Writes the state to a spill file, to be read back by [`read_from`].
+    ///
+    /// [`read_from`]: GroupState::read_from
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), ExecutionError> {
+        for (accumulator, seen) in self.accumulators.iter().zip(&self.seen) {
+            match accumulator {
+                Accumulator::Count(count) => Value::Integer(*count).write_to(writer)?,
+                Accumulator::Avg { sum, count } => {
+                    Value::Float(*sum).write_to(writer)?;
+                    Value::Integer(*count).write_to(writer)?;
+                }
+                Accumulator::Sum(value) | Accumulator::Min(value) | Accumulator::Max(value) => {
+                    value.as_ref().unwrap_or(&Value::Null).write_to(writer)?
+                }
+            }
+            if let Some(seen) = seen {
+                Value::Integer(seen.len() as i128).write_to(writer)?;
+                for value in seen {
+                    value.write_to(writer)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_from<R: Read>(calls: &[AggregateCall], reader: &mut R) -> Result<Self, ExecutionError> {
+        let mut state = GroupState::new(calls);
+        for (accumulator, seen) in state.accumulators.iter_mut().zip(&mut state.seen) {
+            match accumulator {
+                Accumulator::Count(count) => *count = integer(read_value(reader)?)?,
+                Accumulator::Avg { sum, count } => {
+                    *sum = match read_value(reader)? {
+                        Value::Float(value) => value,
+                        value => return Err(corrupt(&value)),
+                    };
+                    *count = integer(read_value(reader)?)?;
+                }
+                Accumulator::Sum(value) | Accumulator::Min(value) | Accumulator::Max(value) => {
+                    *value = Some(read_value(reader)?).filter(|value| !value.is_null())
+                }
+            }
+            if let Some(seen) = seen {
+                for _ in 0..integer(read_value(reader)?)? {
+                    seen.insert(read_value(reader)?);
+                }
+            }
+        }
+        Ok(state)
+    }
+
+    pub fn finish(&self) -> Result<Vec<Value>, ExecutionError> {
+        Ok(self
+            .accumulators
+            .iter()
+            .map(|accumulator| match accumulator {
+                Accumulator::Count(count) => Value::Integer(*count),
+                Accumulator::Sum(sum) => sum.clone().unwrap_or(Value::Null),
+                Accumulator::Avg { count: 0, .. } => Value::Null,
+                Accumulator::Avg { sum, count } => Value::Float(sum / *count as f64),
+                Accumulator::Min(value) | Accumulator::Max(value) => {
+                    value.clone().unwrap_or(Value::Null)
+                }
+            })
+            .collect())
+    }
+}
+
+/// Adds one value, not NULL, to what `accumulator` holds.
+fn accumulate(
+    accumulator: &mut Accumulator,
+    function: AggregateFunction,
+    value: &Value,
+) -> Result<(), ExecutionError> {
+    match accumulator {
+        Accumulator::Count(count) => *count += 1,
+        Accumulator::Sum(sum) => {
+            *sum = Some(match sum.take() {
+                None => numeric(value, function)?.clone(),
+                Some(current) => add(&current, numeric(value, function)?)?,
+            });
+        }
+        Accumulator::Avg { sum, count } => {
+            *sum += match numeric(value, function)? {
+                Value::Integer(value) => *value as f64,
+                Value::Float(value) => *value,
+                _ => unreachable!(),
+            };
+            *count += 1;
+        }
+        Accumulator::Min(current) => {
+            if current.as_ref().map_or(Ok(true), |current| {
+                value
+                    .compare(current)
+                    .map(|ordering| ordering.is_some_and(|o| o.is_lt()))
+            })? {
+                *current = Some(value.clone());
+            }
+        }
+        Accumulator::Max(current) => {
+            if current.as_ref().map_or(Ok(true), |current| {
+                value
+                    .compare(current)
+                    .map(|ordering| ordering.is_some_and(|o| o.is_gt()))
+            })? {
+                *current = Some(value.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn numeric(value: &Value, function: AggregateFunction) -> Result<&Value, ExecutionError> {
+    match value {
+        Value::Integer(_) | Value::Float(_) => Ok(value),
+        _ => Err(ExecutionError::TypeMismatch(format!(
+            "{:?} expects a numeric argument, found {}",
+            function,
+            value.type_name()
+        ))),
+    }
+}
+
+fn add(left: &Value, right: &Value) -> Result<Value, ExecutionError> {
+    match (left, right) {
+        (Value::Integer(left), Value::Integer(right)) => left
+            .checked_add(*right)
+            .map(Value::Integer)
+            .ok_or_else(|| ExecutionError::Overflow("SUM".to_string())),
+        (Value::Integer(left), Value::Float(right)) => Ok(Value::Float(*left as f64 + right)),
+        (Value::Float(left), Value::Integer(right)) => Ok(Value::Float(left + *right as f64)),
+        (Value::Float(left), Value::Float(right)) => Ok(Value::Float(left + right)),
+        _ => Err(ExecutionError::TypeMismatch(format!(
+            "cannot add {} and {}",
+            left.type_name(),
+            right.type_name()
+        ))),
+    }
+}
+
+/// Spill files not read yet. Dropping them removes them, so that an error
+/// anywhere in the aggregate leaves none behind.
+struct SpillFiles(Vec<PathBuf>);
+
+impl SpillFiles {
+    /// The next file to read, which the caller removes once done.
+    fn take(&mut self) -> Option<PathBuf> {
+        (!self.0.is_empty()).then(|| self.0.remove(0))
+    }
+}
+
+impl Drop for SpillFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+struct SpillPartitions {
+    files: SpillFiles,
+    writers: Vec<BufWriter<File>>,
+}
+
+impl SpillPartitions {
+    fn create(directory: &Path) -> Result<Self, ExecutionError> {
+        let id = SPILL_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let mut files = SpillFiles(Vec::with_capacity(SPILL_PARTITIONS));
+        let mut writers = Vec::with_capacity(SPILL_PARTITIONS);
+        for partition in 0..SPILL_PARTITIONS {
+            let path = directory.join(format!(
+                "omnissiah-aggregate-{}-{}-{}.spill",
+                std::process::id(),
+                id,
+                partition
+            ));
+            let file = File::create(&path)?;
+            files.0.push(path);
+            writers.push(BufWriter::new(file));
+        }
+        Ok(SpillPartitions { files, writers })
+    }
+
+    /// Writes the key of `row`, then whether a state follows, then the
+    /// arguments or the state.
+    fn write(&mut self, row: &PreparedRow, depth: usize) -> Result<(), ExecutionError> {
+        let mut hasher = DefaultHasher::new();
+        depth.hash(&mut hasher);
+        row.key.hash(&mut hasher);
+        let writer = &mut self.writers[hasher.finish() as usize % SPILL_PARTITIONS];
+        for value in &row.key {
+            value.write_to(writer)?;
+        }
+        match &row.input {
+            Input::Arguments(arguments) => {
+                Value::Bool(false).write_to(writer)?;
+                for value in arguments {
+                    value.write_to(writer)?;
+                }
+            }
+            Input::State(state) => {
+                Value::Bool(true).write_to(writer)?;
+                state.write_to(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<SpillFiles, ExecutionError> {
+        let SpillPartitions { files, writers } = self;
+        for mut writer in writers {
+            writer.flush()?;
+        }
+        Ok(files)
+    }
+}
+
+struct SpillReader<'a> {
+    reader: BufReader<File>,
+    key_width: usize,
+    calls: &'a [AggregateCall],
+}
+
+impl<'a> SpillReader<'a> {
+    fn open(
+        path: &Path,
+        key_width: usize,
+        calls: &'a [AggregateCall],
+    ) -> Result<Self, ExecutionError> {
+        Ok(SpillReader {
+            reader: BufReader::new(File::open(path)?),
+            key_width,
+            calls,
+        })
+    }
+
+    /// The rest of a row whose first value is `first`.
+    fn read_row(&mut self, first: Value) -> Result<PreparedRow, ExecutionError> {
+        let mut values = vec![first];
+        for _ in 0..self.key_width {
+            values.push(read_value(&mut self.reader)?);
+        }
+        let tag = values.pop().unwrap_or(Value::Null);
+        let key = values;
+        let input = match tag {
+            Value::Bool(false) => {
+                let mut arguments = Vec::with_capacity(self.calls.len());
+                for _ in 0..self.calls.len() {
+                    arguments.push(read_value(&mut self.reader)?);
+                }
+                Input::Arguments(arguments)
+            }
+            Value::Bool(true) => Input::State(GroupState::read_from(self.calls, &mut self.reader)?),
+            value => return Err(corrupt(&value)),
+        };
+        Ok(PreparedRow { key, input })
+    }
+}
+
+impl Iterator for SpillReader<'_> {
+    type Item = Result<PreparedRow, ExecutionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match Value::read_from(&mut self.reader) {
+            Ok(None) => None,
+            Ok(Some(first)) => Some(self.read_row(first)),
+            Err(error) => Some(Err(error.into())),
+        }
+    }
+}
+
+/// The next value of a spill file, which must be there.
+fn read_value<R: Read>(reader: &mut R) -> Result<Value, ExecutionError> {
+    match Value::read_from(reader)? {
+        Some(value) => Ok(value),
+        None => Err(ExecutionError::Io(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "truncated aggregate spill file",
+        ))),
+    }
+}
+
+fn integer(value: Value) -> Result<i128, ExecutionError> {
+    match value {
+        Value::Integer(value) => Ok(value),
+        value => Err(corrupt(&value)),
+    }
+}
+
+fn corrupt(value: &Value) -> ExecutionError {
+    ExecutionError::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("unexpected {} in aggregate spill file", value.type_name()),
+    ))
+}
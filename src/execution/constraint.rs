@@ -0,0 +1,130 @@
+//! The rules a row keeps to be written to a table: NOT NULL and primary
+//! key columns hold a value, numbers fit the width and sign of their
+//! column's type and text its length, and no two rows share the key of a
+//! unique index.
+
+use crate::compiler::parser::DataType;
+use crate::execution::error::{ConstraintViolation, ExecutionError, ViolationKind};
+use crate::storage::catalog::{ColumnSchema, ConstraintKind, IndexSchema, TableSchema};
+use crate::types::cast::cast;
+use crate::types::value::{Value, ValueError};
+
+/// `row`, in the column order of `table`, with every value converted to
+/// the type of its column, or the first rule it breaks.
+pub fn check_row(table: &TableSchema, row: Vec<Value>) -> Result<Vec<Value>, ExecutionError> {
+    row.into_iter()
+        .zip(&table.columns)
+        .map(|(value, column)| check_value(table, column, value))
+        .collect()
+}
+
+/// The violation of a row whose key `index` already has.
+pub fn duplicate_key(table: &TableSchema, index: &IndexSchema) -> ExecutionError {
+    let primary = table.constraints.iter().any(|constraint| {
+        constraint.kind == ConstraintKind::PrimaryKey && constraint.name == index.name
+    });
+    violation(
+        if primary {
+            ViolationKind::PrimaryKey
+        } else {
+            ViolationKind::Unique
+        },
+        table,
+        &index.columns.join(", "),
+        index.name.clone(),
+    )
+}
+
+fn check_value(
+    table: &TableSchema,
+    column: &ColumnSchema,
+    value: Value,
+) -> Result<Value, ExecutionError> {
+    if value.is_null() {
+        if !column.not_null && !column.is_primary {
+            return Ok(Value::Null);
+        }
+        let constraint = table
+            .constraints
+            .iter()
+            .find(|constraint| {
+                constraint.kind == ConstraintKind::NotNull
+                    && constraint.columns == [column.name.as_str()]
+            })
+            .map(|constraint| constraint.name.clone())
+            .unwrap_or_else(|| format!("{}_{}_not_null", table.name, column.name));
+        return Err(violation(
+            ViolationKind::NotNull,
+            table,
+            &column.name,
+            constraint,
+        ));
+    }
+    // A cast cuts text to the length of its type, which only an explicit
+    // one may do.
+    if let DataType::TEXT { length } = column.data_type {
+        let characters = match &value {
+            Value::Text(text) => text.chars().count(),
+            value => value.to_string().chars().count(),
+        };
+        if characters > length.max(0) as usize {
+            return Err(violation(
+                ViolationKind::Range,
+                table,
+                &column.name,
+                column.data_type.to_string(),
+            ));
+        }
+    }
+    match cast(value.clone(), &column.data_type) {
+        Err(ValueError::Overflow(_)) => {
+            let kind = if is_unsigned(&column.data_type) && is_negative(&value) {
+                ViolationKind::Unsigned
+            } else {
+                ViolationKind::Range
+            };
+            Err(violation(
+                kind,
+                table,
+                &column.name,
+                column.data_type.to_string(),
+            ))
+        }
+        result => Ok(result?),
+    }
+}
+
+fn violation(
+    kind: ViolationKind,
+    table: &TableSchema,
+    column: &str,
+    constraint: String,
+) -> ExecutionError {
+    ExecutionError::ConstraintViolation(ConstraintViolation {
+        kind,
+        table: table.name.clone(),
+        column: column.to_string(),
+        constraint,
+    })
+}
+
+fn is_unsigned(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::FLOAT { unsigned: true }
+            | DataType::DOUBLE { unsigned: true }
+            | DataType::BYTE { unsigned: true }
+            | DataType::SHORT { unsigned: true }
+            | DataType::INT { unsigned: true }
+            | DataType::LONG { unsigned: true }
+            | DataType::BIGINT { unsigned: true }
+    )
+}
+
+/// Whether `value` reads as a number below zero, as text may.
+fn is_negative(value: &Value) -> bool {
+    matches!(
+        cast(value.clone(), &DataType::DOUBLE { unsigned: false }),
+        Ok(Value::Float(float)) if float < 0.0
+    )
+}
@@ -0,0 +1,255 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+use crate::compiler::parser::{
+    CommonTableExpression, LockingClause, SQLExpression, SetExpression, SetOperator, WithQuery,
+};
+use crate::compiler::walk::column_name;
+use crate::execution::error::ExecutionError;
+use crate::execution::planner::TableProvider;
+use crate::execution::query::{execute_query, execute_set_expression};
+use crate::execution::relation::{Relation, Rows};
+use crate::types::function::ScalarFunction;
+use crate::types::value::Value;
+
+/// Rounds a recursive CTE may take, unless the session says otherwise.
+/// A term that gives new rows every round never runs dry, cycle or not.
+pub const DEFAULT_RECURSION_LIMIT: usize = 10_000;
+
+/// Serves materialized CTEs, falling back to the enclosing tables for any
+/// other name. Nesting providers gives each CTE the ones defined before it.
+pub struct CteProvider<'a> {
+    base: &'a dyn TableProvider,
+    tables: HashMap<String, (Vec<String>, Vec<Vec<Value>>)>,
+}
+
+impl<'a> CteProvider<'a> {
+    pub fn new(base: &'a dyn TableProvider) -> Self {
+        CteProvider {
+            base,
+            tables: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, name: String, columns: Vec<String>, rows: Vec<Vec<Value>>) {
+        self.tables.insert(name, (columns, rows));
+    }
+}
+
+impl TableProvider for CteProvider<'_> {
+    fn scan(&self, table: &str) -> Result<(Vec<String>, Rows<'_>), ExecutionError> {
+        match self.tables.get(table) {
+            Some((columns, rows)) => Ok((columns.clone(), Box::new(rows.iter().cloned().map(Ok)))),
+            None => self.base.scan(table),
+        }
+    }
+
+    fn scan_where(
+        &self,
+        table: &str,
+        qualifier: &str,
+        conditions: &[&SQLExpression],
+    ) -> Result<(Vec<String>, Rows<'_>), ExecutionError> {
+        match self.tables.get(table) {
+            Some((columns, rows)) => Ok((columns.clone(), Box::new(rows.iter().cloned().map(Ok)))),
+            None => self.base.scan_where(table, qualifier, conditions),
+        }
+    }
+
+    fn scan_for_lock(
+        &self,
+        table: &str,
+        qualifier: &str,
+        conditions: &[&SQLExpression],
+    ) -> Result<(Vec<String>, Rows<'_>), ExecutionError> {
+        if self.tables.contains_key(table) {
+            return Err(ExecutionError::Unsupported(format!(
+                "the rows of WITH query \"{}\" cannot be locked",
+                table
+            )));
+        }
+        self.base.scan_for_lock(table, qualifier, conditions)
+    }
+
+    fn lock_row(
+        &self,
+        table: &str,
+        row: &Value,
+        locking: &LockingClause,
+    ) -> Result<bool, ExecutionError> {
+        self.base.lock_row(table, row, locking)
+    }
+
+    fn now(&self) -> i64 {
+        self.base.now()
+    }
+
+    fn work_memory(&self) -> usize {
+        self.base.work_memory()
+    }
+
+    fn recursion_limit(&self) -> usize {
+        self.base.recursion_limit()
+    }
+
+    fn call_sequence_function(
+        &self,
+        function: ScalarFunction,
+        arguments: Vec<Value>,
+    ) -> Result<Value, ExecutionError> {
+        self.base.call_sequence_function(function, arguments)
+    }
+}
+
+pub fn execute_with(
+    query: &WithQuery,
+    provider: &dyn TableProvider,
+) -> Result<Relation, ExecutionError> {
+    let mut ctes = CteProvider::new(provider);
+    for cte in &query.ctes {
+        let (columns, rows) = materialize(cte, query.recursive, &ctes)?;
+        ctes.insert(cte.name.clone(), columns, rows);
+    }
+    execute_query(&query.body, &ctes, None)
+}
+
+/// Evaluates one CTE. Under `WITH RECURSIVE`, a query shaped
+/// `anchor UNION [ALL] term` is evaluated by applying `term` to the rows
+/// produced by the previous round only, until a round adds nothing new.
+/// `UNION` never adds a row twice, so it runs dry over cyclic data; under
+/// `UNION ALL` a round that gives the rows an earlier one gave would give
+/// the rounds after that one over and over, so it is refused as a cycle.
+fn materialize(
+    cte: &CommonTableExpression,
+    recursive: bool,
+    provider: &dyn TableProvider,
+) -> Result<(Vec<String>, Vec<Vec<Value>>), ExecutionError> {
+    let query = &cte.query;
+    let (anchor, term, union_all) = match &query.body {
+        SetExpression::Operation {
+            operator: SetOperator::Union,
+            all,
+            left,
+            right,
+        } if recursive
+            && query.order_by.is_empty()
+            && query.limit.is_none()
+            && query.offset.is_none() =>
+        {
+            (left, right, *all)
+        }
+        _ => {
+            let result = execute_query(query, provider, None)?;
+            let columns = output_columns(cte, &result)?;
+            return Ok((columns, result.rows));
+        }
+    };
+
+    let anchor = execute_set_expression(anchor, provider, None)?;
+    let columns = output_columns(cte, &anchor)?;
+    let mut rows = anchor.rows;
+    let mut seen = HashSet::new();
+    if !union_all {
+        rows.retain(|row| seen.insert(row.clone()));
+    }
+    let mut working = rows.clone();
+    // Where in `rows` each round's, the anchor's first, are, by what
+    // `fingerprint` gives them.
+    let mut rounds: HashMap<u64, Vec<(usize, Range<usize>)>> = HashMap::new();
+    if union_all {
+        rounds.insert(fingerprint(&rows), vec![(0, 0..rows.len())]);
+    }
+    let limit = provider.recursion_limit();
+    let mut iterations = 0;
+    while !working.is_empty() {
+        iterations += 1;
+        if iterations > limit {
+            return Err(ExecutionError::RecursionLimit(format!(
+                "recursive query \"{}\" did not finish within {} iterations",
+                cte.name, limit
+            )));
+        }
+        let mut step = CteProvider::new(provider);
+        step.insert(cte.name.clone(), columns.clone(), working);
+        let produced = execute_set_expression(term, &step, None)?;
+        check_width(cte, &produced, columns.len())?;
+        working = produced
+            .rows
+            .into_iter()
+            .filter(|row| union_all || seen.insert(row.clone()))
+            .collect();
+        if union_all && !working.is_empty() {
+            let earlier = rounds.entry(fingerprint(&working)).or_default();
+            if let Some((round, _)) = earlier
+                .iter()
+                .find(|(_, range)| same_rows(&rows[range.clone()], &working))
+            {
+                return Err(ExecutionError::RecursionLimit(format!(
+                    "recursive query \"{}\" cycles: round {} gives the rows of round {} again",
+                    cte.name, iterations, round
+                )));
+            }
+            earlier.push((iterations, rows.len()..rows.len() + working.len()));
+        }
+        rows.extend(working.iter().cloned());
+    }
+    Ok((columns, rows))
+}
+
+/// A hash of `rows` that does not depend on their order.
+fn fingerprint(rows: &[Vec<Value>]) -> u64 {
+    rows.iter().fold(rows.len() as u64, |sum, row| {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        sum.wrapping_add(hasher.finish())
+    })
+}
+
+/// Whether `left` and `right` hold the same rows as often, in any order.
+fn same_rows(left: &[Vec<Value>], right: &[Vec<Value>]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+    let mut counts: HashMap<&Vec<Value>, isize> = HashMap::new();
+    for row in left {
+        *counts.entry(row).or_default() += 1;
+    }
+    for row in right {
+        *counts.entry(row).or_default() -= 1;
+    }
+    counts.values().all(|count| *count == 0)
+}
+
+fn check_width(
+    cte: &CommonTableExpression,
+    produced: &Relation,
+    width: usize,
+) -> Result<(), ExecutionError> {
+    if produced.schema.columns.len() != width {
+        return Err(ExecutionError::TypeMismatch(format!(
+            "each part of \"{}\" must have {} columns",
+            cte.name, width
+        )));
+    }
+    Ok(())
+}
+
+fn output_columns(
+    cte: &CommonTableExpression,
+    anchor: &Relation,
+) -> Result<Vec<String>, ExecutionError> {
+    if cte.columns.is_empty() {
+        return Ok(anchor.schema.columns.iter().map(column_name).collect());
+    }
+    if cte.columns.len() != anchor.schema.columns.len() {
+        return Err(ExecutionError::TypeMismatch(format!(
+            "\"{}\" names {} columns but its query returns {}",
+            cte.name,
+            cte.columns.len(),
+            anchor.schema.columns.len()
+        )));
+    }
+    Ok(cte.columns.clone())
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::io;
+
+use crate::compiler::binder::BindError;
+use crate::storage::catalog::CatalogError;
+use crate::types::value::ValueError;
+
+/// The rule a row breaks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViolationKind {
+    NotNull,
+    PrimaryKey,
+    Unique,
+    /// A negative number for an unsigned type.
+    Unsigned,
+    /// A number, or text, wider than its type holds.
+    Range,
+}
+
+/// Where a written row breaks a rule: the table, the column, or the
+/// columns of a key joined by commas, and the constraint, which for
+/// the rules a type imposes is the type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintViolation {
+    pub kind: ViolationKind,
+    pub table: String,
+    pub column: String,
+    pub constraint: String,
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (table, column, constraint) = (&self.table, &self.column, &self.constraint);
+        match self.kind {
+            ViolationKind::NotNull => write!(
+                f,
+                "null value in column \"{}\" of table \"{}\" violates not-null constraint \"{}\"",
+                column, table, constraint
+            ),
+            ViolationKind::PrimaryKey => write!(
+                f,
+                "duplicate key ({}) of table \"{}\" violates primary key constraint \"{}\"",
+                column, table, constraint
+            ),
+            ViolationKind::Unique => write!(
+                f,
+                "duplicate key ({}) of table \"{}\" violates unique constraint \"{}\"",
+                column, table, constraint
+            ),
+            ViolationKind::Unsigned => write!(
+                f,
+                "negative value in column \"{}\" of table \"{}\" violates unsigned type {}",
+                column, table, constraint
+            ),
+            ViolationKind::Range => write!(
+                f,
+                "value in column \"{}\" of table \"{}\" is out of range for type {}",
+                column, table, constraint
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExecutionError {
+    UnknownColumn(String),
+    AmbiguousColumn(String),
+    UnknownTable(String),
+    UnknownFunction(String),
+    UnknownWindow(String),
+    InvalidLiteral(String),
+    TypeMismatch(String),
+    Overflow(String),
+    DivisionByZero,
+    /// A function was given an argument outside the values it takes.
+    InvalidArgument(String),
+    Cardinality(String),
+    RecursionLimit(String),
+    AggregateNotAllowed(String),
+    InvalidQuery(String),
+    Unsupported(String),
+    /// A row would give a unique index, named here, a key it already has.
+    DuplicateKey(String),
+    UnknownSequence(String),
+    /// A sequence cannot give or take the value asked of it.
+    SequenceValue(String),
+    /// A row written to a table breaks a rule of one of its columns.
+    ConstraintViolation(ConstraintViolation),
+    /// The statement does not fit the transaction the session is in, or
+    /// out of.
+    Transaction(String),
+    /// The transaction cannot go on without breaking its isolation level.
+    /// It is rolled back, and may succeed if run again.
+    SerializationFailure(String),
+    /// A lock another transaction holds was not released in time, or
+    /// the statement was not to wait for it.
+    LockNotAvailable(String),
+    /// Waiting for the lock would have closed a cycle of transactions
+    /// waiting for each other, described here. The transaction that was
+    /// to wait is rolled back.
+    Deadlock(String),
+    /// The statement does not make sense against the catalog.
+    Bind(BindError),
+    Io(io::Error),
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionError::UnknownColumn(name) => write!(f, "unknown column \"{}\"", name),
+            ExecutionError::AmbiguousColumn(name) => {
+                write!(f, "column reference \"{}\" is ambiguous", name)
+            }
+            ExecutionError::UnknownTable(name) => write!(f, "unknown table \"{}\"", name),
+            ExecutionError::UnknownFunction(name) => write!(f, "unknown function \"{}\"", name),
+            ExecutionError::UnknownWindow(name) => write!(f, "unknown window \"{}\"", name),
+            ExecutionError::InvalidLiteral(literal) => write!(f, "invalid literal {}", literal),
+            ExecutionError::TypeMismatch(message) => write!(f, "type mismatch: {}", message),
+            ExecutionError::Overflow(message) => write!(f, "overflow: {}", message),
+            ExecutionError::DivisionByZero => write!(f, "division by zero"),
+            ExecutionError::InvalidArgument(message) => write!(f, "invalid argument: {}", message),
+            ExecutionError::Cardinality(message) => write!(f, "{}", message),
+            ExecutionError::RecursionLimit(message) => write!(f, "{}", message),
+            ExecutionError::AggregateNotAllowed(name) => {
+                write!(f, "aggregate function {} is not allowed here", name)
+            }
+            ExecutionError::InvalidQuery(message) => write!(f, "invalid query: {}", message),
+            ExecutionError::Unsupported(message) => write!(f, "unsupported: {}", message),
+            ExecutionError::DuplicateKey(index) => {
+                write!(f, "duplicate key value violates unique index \"{}\"", index)
+            }
+            ExecutionError::UnknownSequence(name) => write!(f, "unknown sequence \"{}\"", name),
+            ExecutionError::SequenceValue(message) => write!(f, "{}", message),
+            ExecutionError::ConstraintViolation(violation) => write!(f, "{}", violation),
+            ExecutionError::Transaction(message) => write!(f, "{}", message),
+            ExecutionError::SerializationFailure(message) => {
+                write!(f, "could not serialize access due to {}", message)
+            }
+            ExecutionError::LockNotAvailable(message) => write!(f, "{}", message),
+            ExecutionError::Deadlock(message) => write!(f, "deadlock detected: {}", message),
+            ExecutionError::Bind(error) => write!(f, "{}", error),
+            ExecutionError::Io(error) => write!(f, "i/o error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+impl From<io::Error> for ExecutionError {
+    fn from(error: io::Error) -> Self {
+        ExecutionError::Io(error)
+    }
+}
+
+impl From<BindError> for ExecutionError {
+    fn from(error: BindError) -> Self {
+        ExecutionError::Bind(error)
+    }
+}
+
+impl From<CatalogError> for ExecutionError {
+    fn from(error: CatalogError) -> Self {
+        match error {
+            CatalogError::Bind(error) => ExecutionError::Bind(error),
+            CatalogError::Io(error) => ExecutionError::Io(error),
+        }
+    }
+}
+
+impl From<ValueError> for ExecutionError {
+    fn from(error: ValueError) -> Self {
+        match error {
+            ValueError::InvalidLiteral(literal) => ExecutionError::InvalidLiteral(literal),
+            ValueError::TypeMismatch(message) => ExecutionError::TypeMismatch(message),
+            ValueError::Overflow(message) => ExecutionError::Overflow(message),
+        }
+    }
+}
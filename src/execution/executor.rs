@@ -0,0 +1,1437 @@
+use std::cell::RefCell;
+use std::io;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+use std::vec;
+
+use crate::compiler::binder::{Binder, BoundStatement};
+use crate::compiler::parser::{
+    AlterTableQuery, CreateIndexQuery, DeleteQuery, InsertQuery, IsolationLevel, LockMode,
+    LockTableQuery, LockWait, LockingClause, SQLExpression, SQLQuery, TransactionQuery,
+    UpdateQuery, VacuumQuery,
+};
+use crate::compiler::walk::column_name;
+use crate::execution::access::{choose_access_path, AccessPath, BoundIndex};
+use crate::execution::aggregate::DEFAULT_WORK_MEMORY;
+use crate::execution::constraint::{check_row, duplicate_key};
+use crate::execution::cte::{execute_with, DEFAULT_RECURSION_LIMIT};
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::{evaluate, is_satisfied, Context, Schema};
+use crate::execution::function::current_time;
+use crate::execution::planner::{conjuncts, TableProvider, ROW_ID};
+use crate::execution::query::execute_query;
+use crate::execution::relation::{Relation, Rows};
+use crate::execution::sequence::{CurrentValues, Sequences};
+use crate::execution::show::execute_show;
+use crate::execution::vacuum::{Autovacuum, AutovacuumOptions};
+use crate::storage::btree::RangeCursor;
+use crate::storage::catalog::{sequence_schema, Catalog, IndexSchema, TableSchema};
+use crate::storage::engine::{HeapCursor, OpenScan, StorageEngine, StorageOptions};
+use crate::storage::heap::PageRecords;
+use crate::storage::information_schema::view_columns;
+use crate::storage::lock::{LockManager, LockTarget};
+use crate::storage::mvcc::{version_row, Snapshot, VersionState, Xid};
+use crate::storage::page::{PageId, RecordId};
+use crate::storage::tuple::RowLayout;
+use crate::storage::wal::LogSync;
+use crate::types::function::ScalarFunction;
+use crate::types::value::Value;
+
+/// What a statement produced: the rows of a query, or the number of rows a
+/// statement changed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    pub affected: usize,
+}
+
+/// A session of a database: runs statements against its catalog and the
+/// rows of its tables.
+///
+/// Every statement is bound first, so the executor only sees names that
+/// exist and values of types their columns accept. Outside a transaction
+/// block each statement is a transaction of its own; inside one, the
+/// statements up to `COMMIT` are. Sessions made with
+/// [`Executor::session`] share the database, each on a thread of its own
+/// if need be, and see each other's work as their isolation levels say.
+pub struct Executor {
+    database: Arc<Database>,
+    block: Option<TransactionBlock>,
+    /// What the running statement reads through.
+    snapshot: Option<Snapshot>,
+    /// When the running statement started, which NOW() returns.
+    now: i64,
+    /// Scans only get a shared executor, and may call `nextval`.
+    current_values: RefCell<CurrentValues>,
+    /// How long to wait for a lock before failing; without one, for as
+    /// long as it takes.
+    lock_timeout: Option<Duration>,
+    /// Bytes an aggregate or a sort keeps in memory before it spills.
+    work_memory: usize,
+    /// Rounds a recursive CTE may take.
+    recursion_limit: usize,
+}
+
+/// What the sessions of a database share.
+struct Database {
+    catalog: RwLock<Catalog>,
+    /// Locked for a step of a statement at a time, such as reading a
+    /// table, so that other sessions go on between steps.
+    storage: Mutex<StorageEngine>,
+    sequences: Mutex<Sequences>,
+    /// Held by a statement that writes, or a commit, for as long as it
+    /// runs. Its changes are then the only ones of its log transaction,
+    /// which rolls them back if it fails. Nothing waits for a lock while
+    /// holding it, as the holder of the lock may need it to commit.
+    writer: Mutex<()>,
+    /// What a commit waits on, once it lets go of `writer` and `storage`,
+    /// for the log to be synced under group commit.
+    log_sync: Arc<LogSync>,
+    locks: LockManager,
+}
+
+/// The transaction a `BEGIN` opened, until `COMMIT` or `ROLLBACK`.
+#[derive(Debug)]
+struct TransactionBlock {
+    isolation: IsolationLevel,
+    xid: Xid,
+    /// Savepoints by name, oldest first, each with its level in the
+    /// transaction.
+    savepoints: Vec<(String, usize)>,
+    /// Set when a statement fails, after undoing what it did. The block
+    /// then takes nothing but `ROLLBACK` or `ROLLBACK TO`, so that it
+    /// commits all of its statements or none.
+    failed: bool,
+    /// Set when the transaction was rolled back before the block ended,
+    /// as a deadlock rolls back the transaction that would close it. The
+    /// block then takes nothing but `ROLLBACK`.
+    aborted: bool,
+}
+
+impl Executor {
+    /// An executor over `catalog` whose rows are kept in memory.
+    pub fn new(catalog: Catalog) -> Self {
+        Executor::with_storage(catalog, StorageEngine::memory(StorageOptions::default()))
+    }
+
+    /// Opens the catalog and data file in `directory`, creating them if
+    /// the directory is new.
+    pub fn open<P: AsRef<Path>>(
+        directory: P,
+        options: StorageOptions,
+    ) -> Result<Self, ExecutionError> {
+        let directory = directory.as_ref();
+        let catalog = Catalog::open(directory)?;
+        let storage = StorageEngine::open(directory, options)?;
+        Ok(Executor::with_storage(catalog, storage))
+    }
+
+    fn with_storage(catalog: Catalog, mut storage: StorageEngine) -> Self {
+        let log_sync = storage.log_sync();
+        Executor {
+            database: Arc::new(Database {
+                catalog: RwLock::new(catalog),
+                storage: Mutex::new(storage),
+                sequences: Mutex::default(),
+                writer: Mutex::new(()),
+                log_sync,
+                locks: LockManager::default(),
+            }),
+            block: None,
+            snapshot: None,
+            now: 0,
+            current_values: RefCell::default(),
+            lock_timeout: None,
+            work_memory: DEFAULT_WORK_MEMORY,
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+        }
+    }
+
+    /// Another session of the same database, outside any transaction.
+    pub fn session(&self) -> Executor {
+        Executor {
+            database: Arc::clone(&self.database),
+            block: None,
+            snapshot: None,
+            now: 0,
+            current_values: RefCell::default(),
+            lock_timeout: None,
+            work_memory: DEFAULT_WORK_MEMORY,
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+        }
+    }
+
+    /// How long statements of the session wait for a lock before they
+    /// fail; `None`, as at first, waits for as long as it takes.
+    pub fn set_lock_timeout(&mut self, timeout: Option<Duration>) {
+        self.lock_timeout = timeout;
+    }
+
+    /// How many bytes of groups or rows the aggregates and sorts of the
+    /// session's statements keep in memory before they spill the rest to disk.
+    pub fn set_work_memory(&mut self, bytes: usize) {
+        self.work_memory = bytes;
+    }
+
+    /// How many rounds a recursive CTE of the session's statements may
+    /// take before it fails, as one walking a deep hierarchy may need more
+    /// than the default.
+    pub fn set_recursion_limit(&mut self, rounds: usize) {
+        self.recursion_limit = rounds;
+    }
+
+    pub fn catalog(&self) -> RwLockReadGuard<'_, Catalog> {
+        self.database
+            .catalog
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn catalog_mut(&self) -> RwLockWriteGuard<'_, Catalog> {
+        self.database
+            .catalog
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn storage(&self) -> MutexGuard<'_, StorageEngine> {
+        lock(&self.database.storage)
+    }
+
+    /// The isolation level of the open transaction block, if there is one.
+    pub fn isolation_level(&self) -> Option<IsolationLevel> {
+        self.block.as_ref().map(|block| block.isolation)
+    }
+
+    /// Runs `statement` in the open transaction block, or outside one in
+    /// a transaction of its own, committed if it succeeds and rolled back
+    /// if it fails.
+    pub fn execute(&mut self, statement: &SQLQuery) -> Result<ResultSet, ExecutionError> {
+        if let SQLQuery::Transaction(transaction) = statement {
+            return self.execute_transaction(transaction);
+        }
+        let xid = match &self.block {
+            None => {
+                let xid = self.storage().begin_transaction(IsolationLevel::default());
+                let result = self.run(statement, xid, true);
+                if result.is_err() {
+                    self.storage().abort_transaction(xid);
+                }
+                self.database.locks.release_all(xid);
+                return result;
+            }
+            Some(block) if block.failed => return Err(ExecutionError::Transaction(
+                "current transaction is aborted, commands ignored until end of transaction block"
+                    .to_string(),
+            )),
+            Some(block) => block.xid,
+        };
+        let result = self.execute_in_block(statement, xid);
+        if let Err(error) = &result {
+            let aborted = matches!(error, ExecutionError::Deadlock(_));
+            if aborted {
+                self.abort_transaction(xid);
+            }
+            if let Some(block) = self.block.as_mut() {
+                block.failed = true;
+                block.aborted |= aborted;
+            }
+        }
+        result
+    }
+
+    /// Rolls back `xid` and releases its locks.
+    fn abort_transaction(&self, xid: Xid) {
+        self.storage().abort_transaction(xid);
+        self.database.locks.release_all(xid);
+    }
+
+    /// Runs a statement of a transaction block. The catalog is kept
+    /// outside the log, so statements that change it are refused, and so
+    /// is VACUUM, which removes versions for good rather than as part of
+    /// a transaction.
+    fn execute_in_block(
+        &mut self,
+        statement: &SQLQuery,
+        xid: Xid,
+    ) -> Result<ResultSet, ExecutionError> {
+        let refused = match statement {
+            SQLQuery::Create(create) => Some(format!("CREATE {}", create.created)),
+            SQLQuery::CreateIndex(_) => Some("CREATE INDEX".to_string()),
+            SQLQuery::CreateSequence(_) => Some("CREATE SEQUENCE".to_string()),
+            SQLQuery::AlterTable(_) => Some("ALTER TABLE".to_string()),
+            SQLQuery::Vacuum(_) => Some("VACUUM".to_string()),
+            _ => None,
+        };
+        if let Some(refused) = refused {
+            return Err(ExecutionError::Transaction(format!(
+                "{} cannot run inside a transaction block",
+                refused
+            )));
+        }
+        self.run(statement, xid, false)
+    }
+
+    /// Runs `statement` in transaction `xid`, committing the transaction
+    /// after it if `commit`. A statement that writes is a log transaction
+    /// of its own, rolled back if the statement fails.
+    fn run(
+        &mut self,
+        statement: &SQLQuery,
+        xid: Xid,
+        commit: bool,
+    ) -> Result<ResultSet, ExecutionError> {
+        let bound = Binder::new(&self.catalog()).bind(statement)?;
+        let writes = matches!(
+            bound.statement,
+            SQLQuery::Create(_)
+                | SQLQuery::CreateIndex(_)
+                | SQLQuery::CreateSequence(_)
+                | SQLQuery::Insert(_)
+                | SQLQuery::Update(_)
+                | SQLQuery::Delete(_)
+                | SQLQuery::AlterTable(_)
+                | SQLQuery::Vacuum(_)
+        );
+        let snapshot = self.storage().transactions().snapshot(xid);
+        self.snapshot = Some(snapshot);
+        self.now = current_time();
+        let database = Arc::clone(&self.database);
+        let mut pending = None;
+        // The commit still to end once the log is synced up to it.
+        let mut committing = None;
+        let result = if writes {
+            let locks = match &bound.statement {
+                SQLQuery::Insert(insert) => {
+                    vec![(column_name(&insert.table), LockMode::IntentionExclusive)]
+                }
+                SQLQuery::Update(update) => {
+                    vec![(update.table.clone(), LockMode::IntentionExclusive)]
+                }
+                SQLQuery::Delete(delete) => {
+                    vec![(delete.table.clone(), LockMode::IntentionExclusive)]
+                }
+                // VACUUM FULL moves rows, which no one may hold the ids of.
+                SQLQuery::Vacuum(vacuum) if vacuum.full => self
+                    .vacuumed_tables(vacuum)
+                    .into_iter()
+                    .map(|table| (table, LockMode::Exclusive))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            for (table, mode) in locks {
+                let table = self.qualified_name(&table);
+                let locked = self.acquire_lock(xid, LockTarget::Table(table), mode, LockWait::Wait);
+                if let Err(error) = locked {
+                    self.snapshot = None;
+                    return Err(error);
+                }
+            }
+            // The rows a statement changes are locked before the writer is
+            // taken, as no one waits for a lock while holding it.
+            let targets = match &bound.statement {
+                SQLQuery::Update(update) => {
+                    self.lock_targets(xid, &update.table, update.conditions.as_ref())
+                }
+                SQLQuery::Delete(delete) => {
+                    self.lock_targets(xid, &delete.table, delete.conditions.as_ref())
+                }
+                _ => Ok(Vec::new()),
+            };
+            let targets = match targets {
+                Ok(targets) => targets,
+                Err(error) => {
+                    self.snapshot = None;
+                    return Err(error);
+                }
+            };
+            let _writer = lock(&database.writer);
+            self.storage().begin();
+            let result = self.execute_bound(bound, targets).and_then(|result| {
+                let mut storage = self.storage();
+                if commit {
+                    committing = storage.commit_transaction(xid)?;
+                    // A statement that wrote no rows still wrote pages.
+                    pending = committing.max(storage.commit()?);
+                } else {
+                    storage.end_statement();
+                }
+                Ok(result)
+            });
+            if result.is_err() {
+                self.storage().rollback()?;
+            }
+            result
+        } else {
+            self.execute_bound(bound, Vec::new()).and_then(|result| {
+                if commit {
+                    committing = self.storage().commit_transaction(xid)?;
+                    pending = committing;
+                }
+                Ok(result)
+            })
+        };
+        self.snapshot = None;
+        let synced = match (&result, pending) {
+            (Ok(_), Some(lsn)) => database.log_sync.wait(lsn),
+            _ => Ok(()),
+        };
+        if committing.is_some() {
+            self.storage().end_commit(xid);
+        }
+        synced?;
+        result
+    }
+
+    fn execute_transaction(
+        &mut self,
+        transaction: &TransactionQuery,
+    ) -> Result<ResultSet, ExecutionError> {
+        let database = Arc::clone(&self.database);
+        let block = match (transaction, self.block.as_mut()) {
+            (TransactionQuery::Begin(isolation), None) => {
+                let isolation = isolation.unwrap_or_default();
+                self.block = Some(TransactionBlock {
+                    isolation,
+                    xid: lock(&database.storage).begin_transaction(isolation),
+                    savepoints: Vec::new(),
+                    failed: false,
+                    aborted: false,
+                });
+                return Ok(ResultSet::default());
+            }
+            (TransactionQuery::Begin(_), Some(_)) => {
+                return Err(ExecutionError::Transaction(
+                    "there is already a transaction in progress".to_string(),
+                ))
+            }
+            (TransactionQuery::Commit | TransactionQuery::Rollback, None) => {
+                return Err(ExecutionError::Transaction(
+                    "there is no transaction in progress".to_string(),
+                ))
+            }
+            (_, None) => {
+                return Err(ExecutionError::Transaction(
+                    "savepoints can only be used in transaction blocks".to_string(),
+                ))
+            }
+            (_, Some(block)) => block,
+        };
+        let xid = block.xid;
+        match transaction {
+            TransactionQuery::Commit if block.failed => {
+                self.block = None;
+                self.abort_transaction(xid);
+                return Err(ExecutionError::Transaction(
+                    "the transaction failed, so it was rolled back".to_string(),
+                ));
+            }
+            TransactionQuery::Commit => {
+                self.block = None;
+                let committed = {
+                    let _writer = lock(&database.writer);
+                    let mut storage = lock(&database.storage);
+                    storage.commit_transaction(xid).or_else(|error| {
+                        storage.rollback()?;
+                        storage.abort_transaction(xid);
+                        Err(error)
+                    })
+                };
+                let synced = match committed {
+                    Ok(Some(lsn)) => {
+                        let synced = database.log_sync.wait(lsn);
+                        lock(&database.storage).end_commit(xid);
+                        synced
+                    }
+                    Ok(None) => Ok(()),
+                    Err(error) => Err(error),
+                };
+                database.locks.release_all(xid);
+                synced?;
+            }
+            TransactionQuery::Rollback => {
+                self.block = None;
+                self.abort_transaction(xid);
+            }
+            _ if block.aborted => {
+                return Err(ExecutionError::Transaction(
+                    "current transaction is aborted, commands ignored until end of transaction block"
+                        .to_string(),
+                ));
+            }
+            TransactionQuery::Savepoint(_) | TransactionQuery::Release(_) if block.failed => {
+                return Err(ExecutionError::Transaction(
+                    "current transaction is aborted, commands ignored until end of transaction block"
+                        .to_string(),
+                ));
+            }
+            TransactionQuery::Savepoint(name) => {
+                let level = lock(&database.storage).transactions().savepoint(xid);
+                block.savepoints.push((name.clone(), level));
+            }
+            TransactionQuery::RollbackTo(name) | TransactionQuery::Release(name) => {
+                let position = block
+                    .savepoints
+                    .iter()
+                    .rposition(|(savepoint, _)| savepoint == name)
+                    .ok_or_else(|| {
+                        ExecutionError::Transaction(format!(
+                            "savepoint \"{}\" does not exist",
+                            name
+                        ))
+                    })?;
+                let level = block.savepoints[position].1;
+                let mut storage = lock(&database.storage);
+                if let TransactionQuery::RollbackTo(_) = transaction {
+                    storage.transactions().rollback_to(xid, level);
+                    block.savepoints.truncate(position + 1);
+                    block.failed = false;
+                } else {
+                    storage.transactions().release(xid, level);
+                    block.savepoints.truncate(position);
+                }
+            }
+            TransactionQuery::Begin(_) => {}
+        }
+        Ok(ResultSet::default())
+    }
+
+    /// Runs a bound statement; `targets` are the rows an UPDATE or DELETE
+    /// changes, locked by [`Executor::lock_targets`].
+    fn execute_bound(
+        &mut self,
+        bound: BoundStatement,
+        targets: Vec<(RecordId, Vec<Value>)>,
+    ) -> Result<ResultSet, ExecutionError> {
+        let columns = bound
+            .columns
+            .iter()
+            .map(|column| column.name.clone())
+            .collect();
+        let relation = match &bound.statement {
+            SQLQuery::Create(create) => {
+                self.catalog_mut().create(create)?;
+                if create.created == "TABLE" {
+                    self.table_storage(&create.name)?;
+                }
+                return Ok(ResultSet::default());
+            }
+            SQLQuery::CreateIndex(create) => {
+                self.execute_create_index(create)?;
+                return Ok(ResultSet::default());
+            }
+            SQLQuery::CreateSequence(create) => {
+                let mut sequence = sequence_schema(create, self.catalog().current_database())?;
+                {
+                    let mut storage = self.storage();
+                    sequence.page = Some(storage.create_sequence(sequence.start)?);
+                    storage.commit_durable()?;
+                }
+                self.catalog_mut().create_sequence(sequence)?;
+                return Ok(ResultSet::default());
+            }
+            SQLQuery::Use(database) => {
+                self.catalog_mut().use_database(database)?;
+                return Ok(ResultSet::default());
+            }
+            SQLQuery::Insert(insert) => return self.execute_insert(insert),
+            SQLQuery::Update(update) => return self.execute_update(update, &targets),
+            SQLQuery::Delete(delete) => return self.execute_delete(delete, &targets),
+            SQLQuery::Show(show) => execute_show(show, &self.catalog())?,
+            SQLQuery::Select(query) => execute_query(query, self, None)?,
+            SQLQuery::With(with) => execute_with(with, self)?,
+            SQLQuery::Transaction(transaction) => return self.execute_transaction(transaction),
+            SQLQuery::Lock(lock) => {
+                self.execute_lock_table(lock)?;
+                return Ok(ResultSet::default());
+            }
+            SQLQuery::AlterTable(alter) => {
+                self.execute_alter_table(alter)?;
+                return Ok(ResultSet::default());
+            }
+            SQLQuery::Vacuum(vacuum) => return self.execute_vacuum(vacuum),
+        };
+        Ok(result_set(columns, relation))
+    }
+
+    /// Locks tables until the transaction ends, which outside a block
+    /// would be at once.
+    fn execute_lock_table(&self, lock: &LockTableQuery) -> Result<(), ExecutionError> {
+        if self.block.is_none() {
+            return Err(ExecutionError::Transaction(
+                "LOCK TABLE can only be used in transaction blocks".to_string(),
+            ));
+        }
+        let xid = self.statement_snapshot()?.transaction();
+        for table in &lock.tables {
+            let target = LockTarget::Table(self.qualified_name(table));
+            self.acquire_lock(xid, target, lock.mode, lock.wait)?;
+        }
+        Ok(())
+    }
+
+    /// Changes the autovacuum settings of a table.
+    fn execute_alter_table(&self, alter: &AlterTableQuery) -> Result<(), ExecutionError> {
+        let mut settings = self.table_schema(&alter.table)?.vacuum;
+        for (name, value) in &alter.options {
+            settings.set(name, value.as_deref())?;
+        }
+        self.catalog_mut()
+            .set_vacuum_settings(&alter.table, settings)?;
+        Ok(())
+    }
+
+    /// Removes the versions of the tables that no transaction can see
+    /// again, counting them as the rows affected.
+    fn execute_vacuum(&mut self, vacuum: &VacuumQuery) -> Result<ResultSet, ExecutionError> {
+        let mut removed = 0;
+        for name in self.vacuumed_tables(vacuum) {
+            let table = self.table_storage(&name)?;
+            removed += self.vacuum_table(&table, vacuum.full)?;
+        }
+        Ok(ResultSet {
+            affected: removed,
+            ..ResultSet::default()
+        })
+    }
+
+    /// The table a VACUUM names, or every table of the current database.
+    fn vacuumed_tables(&self, vacuum: &VacuumQuery) -> Vec<String> {
+        if let Some(table) = &vacuum.table {
+            return vec![table.clone()];
+        }
+        let catalog = self.catalog();
+        catalog
+            .tables()
+            .iter()
+            .filter(|table| table.database == catalog.current_database())
+            .map(|table| table.name.clone())
+            .collect()
+    }
+
+    /// Removes the versions of `table` no transaction can see again, with
+    /// their index entries, compacts the pages they were on and frees the
+    /// empty pages at the end of the heap, returning how many versions
+    /// went. `full` first moves rows from the last pages to room on
+    /// earlier ones, for as long as there is room and no scan of the table
+    /// is open, so that more pages empty; their ids change, so the table
+    /// must be locked exclusively.
+    fn vacuum_table(&self, table: &TableSchema, full: bool) -> Result<usize, ExecutionError> {
+        let heap = match table.heap {
+            Some(heap) => heap,
+            None => return Ok(0),
+        };
+        let indexes = self.bound_indexes(table)?;
+        let layout = RowLayout::new(&table.columns);
+        let mut storage = self.storage();
+        // An entry is removed before its version, whose slot a new row
+        // may take.
+        let dead = storage.dead_versions(heap)?;
+        for (id, record) in &dead {
+            let row = layout.decode(record)?;
+            for (index, bound) in table.indexes.iter().zip(&indexes) {
+                if let Some(values) = bound.entry(table, &row, self.now)? {
+                    storage.index_delete(index, &values, *id)?;
+                }
+            }
+        }
+        let ids: Vec<RecordId> = dead.iter().map(|(id, _)| *id).collect();
+        storage.remove_versions(heap, &ids)?;
+        if full {
+            'pages: for page in storage.heap_pages(heap)?.into_iter().rev() {
+                for (id, record) in storage.read_page(heap, page)? {
+                    let moved = match storage.relocate(heap, id)? {
+                        Some(moved) => moved,
+                        None => break 'pages,
+                    };
+                    let row = layout.decode(version_row(&record))?;
+                    for (index, bound) in table.indexes.iter().zip(&indexes) {
+                        if let Some(values) = bound.entry(table, &row, self.now)? {
+                            storage.index_delete(index, &values, id)?;
+                            storage.index_insert(index, &values, moved)?;
+                        }
+                    }
+                }
+            }
+        }
+        storage.truncate_heap(heap)?;
+        Ok(dead.len())
+    }
+
+    /// Starts a worker that vacuums the tables of every database as
+    /// they become due, until the handle is dropped.
+    pub fn start_autovacuum(&self, options: AutovacuumOptions) -> Autovacuum {
+        Autovacuum::start(self.session(), options)
+    }
+
+    /// Vacuums the tables of every database that are due, each in a log
+    /// transaction of its own, returning how many versions went.
+    pub fn autovacuum(&self, options: &AutovacuumOptions) -> Result<usize, ExecutionError> {
+        let tables: Vec<TableSchema> = self.catalog().tables().to_vec();
+        let mut removed = 0;
+        for table in tables {
+            let due = table.heap.is_some_and(|heap| {
+                options.is_due(&table.vacuum, self.storage().table_statistics(heap))
+            });
+            if !due {
+                continue;
+            }
+            let _writer = lock(&self.database.writer);
+            // Read again now that no CREATE INDEX runs, so that every
+            // index of the table loses its entries.
+            let table = match self
+                .catalog()
+                .tables()
+                .iter()
+                .find(|schema| schema.database == table.database && schema.name == table.name)
+                .cloned()
+            {
+                Some(table) => table,
+                None => continue,
+            };
+            self.storage().begin();
+            let result = self.vacuum_table(&table, false).and_then(|count| {
+                // Nothing waits for the commit to be synced: it only removed
+                // versions no one can see.
+                self.storage().commit()?;
+                Ok(count)
+            });
+            match result {
+                Ok(count) => removed += count,
+                Err(error) => {
+                    self.storage().rollback()?;
+                    return Err(error);
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Inserts one row. Identity columns the statement leaves out take the
+    /// next value of their sequence; other columns left out are NULL.
+    fn execute_insert(&mut self, insert: &InsertQuery) -> Result<ResultSet, ExecutionError> {
+        let table = self.table_storage(&column_name(&insert.table))?;
+        let xid = self.statement_snapshot()?.transaction();
+        let context = Context {
+            provider: Some(&*self),
+            outer: None,
+            now: self.now,
+        };
+        let empty = Schema::new(Vec::new());
+        let mut row = vec![Value::Null; table.columns.len()];
+        for (target, value) in insert.columns.iter().zip(&insert.values) {
+            let target = column_name(target);
+            let position = table
+                .columns
+                .iter()
+                .position(|column| column.name == target)
+                .ok_or_else(|| {
+                    ExecutionError::UnknownColumn(format!("{}.{}", table.name, target))
+                })?;
+            row[position] = evaluate(value, &empty, &[], &context)?;
+        }
+        for (position, column) in table.columns.iter().enumerate() {
+            let given = insert
+                .columns
+                .iter()
+                .any(|target| column_name(target) == column.name);
+            if column.identity && !given {
+                let sequence = self
+                    .catalog()
+                    .identity_sequence(&table.name, &column.name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        ExecutionError::UnknownSequence(format!(
+                            "{}_{}_seq",
+                            table.name, column.name
+                        ))
+                    })?;
+                let value =
+                    lock(&self.database.sequences).next_value(&mut self.storage(), &sequence)?;
+                self.current_values.borrow_mut().set(&sequence, value);
+                row[position] = Value::Integer(value);
+            }
+        }
+        // Stored values always have the representation their type implies.
+        let row = check_row(&table, row)?;
+        self.store_row(&table, xid, &row)?;
+        Ok(ResultSet {
+            affected: 1,
+            ..ResultSet::default()
+        })
+    }
+
+    /// Replaces each row of `targets` the statement still sees with the
+    /// one its assignments give, checked as an inserted row is. A row a
+    /// transaction that committed since changed is skipped under READ
+    /// COMMITTED.
+    fn execute_update(
+        &mut self,
+        update: &UpdateQuery,
+        targets: &[(RecordId, Vec<Value>)],
+    ) -> Result<ResultSet, ExecutionError> {
+        let table = self.table_schema(&update.table)?;
+        let heap = match table.heap {
+            Some(heap) => heap,
+            None => return Ok(ResultSet::default()),
+        };
+        let snapshot = self.statement_snapshot()?.clone();
+        let schema = Schema::for_table(&table.name, &column_names(&table));
+        let context = Context {
+            provider: Some(&*self),
+            outer: None,
+            now: self.now,
+        };
+        let mut rows = Vec::with_capacity(targets.len());
+        for (id, old) in targets {
+            let mut row = old.clone();
+            for (target, value) in &update.assignments {
+                let position = table
+                    .columns
+                    .iter()
+                    .position(|column| &column.name == target)
+                    .ok_or_else(|| {
+                        ExecutionError::UnknownColumn(format!("{}.{}", table.name, target))
+                    })?;
+                row[position] = evaluate(value, &schema, old, &context)?;
+            }
+            rows.push((*id, check_row(&table, row)?));
+        }
+        let mut affected = 0;
+        for (id, row) in rows {
+            if self.storage().delete_version(heap, id, &snapshot)? {
+                self.store_row(&table, snapshot.transaction(), &row)?;
+                affected += 1;
+            }
+        }
+        Ok(ResultSet {
+            affected,
+            ..ResultSet::default()
+        })
+    }
+
+    /// Deletes the rows of `targets` the statement still sees. Their index
+    /// entries stay until VACUUM removes the versions.
+    fn execute_delete(
+        &mut self,
+        delete: &DeleteQuery,
+        targets: &[(RecordId, Vec<Value>)],
+    ) -> Result<ResultSet, ExecutionError> {
+        let table = self.table_schema(&delete.table)?;
+        let heap = match table.heap {
+            Some(heap) => heap,
+            None => return Ok(ResultSet::default()),
+        };
+        let snapshot = self.statement_snapshot()?.clone();
+        let mut storage = self.storage();
+        let mut affected = 0;
+        for (id, _) in targets {
+            if storage.delete_version(heap, *id, &snapshot)? {
+                affected += 1;
+            }
+        }
+        Ok(ResultSet {
+            affected,
+            ..ResultSet::default()
+        })
+    }
+
+    /// Stores `row` as a new version written by `xid`, with its index
+    /// entries. Fails if a unique index already has a live row with its
+    /// key.
+    fn store_row(
+        &self,
+        table: &TableSchema,
+        xid: Xid,
+        row: &[Value],
+    ) -> Result<(), ExecutionError> {
+        let indexes = self.bound_indexes(table)?;
+        let entries = indexes
+            .iter()
+            .map(|index| index.entry(table, row, self.now))
+            .collect::<Result<Vec<Option<Vec<Value>>>, ExecutionError>>()?;
+        let record = RowLayout::new(&table.columns).encode(row)?;
+        let heap = table.heap.expect("table_storage gives the table a heap");
+        let mut storage = self.storage();
+        storage.transactions().write(xid, heap)?;
+        // Rows that share the key of a unique index may all be there as
+        // versions; only one of them may be live. NULL equals nothing, so
+        // a key with one is never a duplicate.
+        for (index, entry) in table.indexes.iter().zip(&entries) {
+            let values = match entry {
+                Some(values) if index.unique && !values.iter().any(Value::is_null) => values,
+                _ => continue,
+            };
+            for id in storage.index_lookup(index, values)? {
+                match storage.version_state(heap, id, xid)? {
+                    Some(VersionState::Live) => return Err(duplicate_key(table, index)),
+                    Some(VersionState::InProgress(_)) => {
+                        return Err(ExecutionError::SerializationFailure(format!(
+                            "a concurrent write of the same key of index \"{}\"",
+                            index.name
+                        )))
+                    }
+                    Some(VersionState::Dead) | None => {}
+                }
+            }
+        }
+        let id = storage.insert_version(heap, xid, &record)?;
+        for (index, entry) in table.indexes.iter().zip(&entries) {
+            if let Some(values) = entry {
+                storage.index_insert(index, values, id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the B+tree of a new index from the rows the table has, then
+    /// records the index with its key and predicate as written. A unique
+    /// index over rows with duplicate keys is not created.
+    fn execute_create_index(&mut self, create: &CreateIndexQuery) -> Result<(), ExecutionError> {
+        let table = self.table_storage(&create.table)?;
+        let mut index = IndexSchema {
+            name: create.name.clone(),
+            columns: create.keys.iter().map(|(_, text)| text.clone()).collect(),
+            predicate: create.predicate.as_ref().map(|(_, text)| text.clone()),
+            unique: create.unique,
+            method: create.method,
+            root: None,
+        };
+        index.root = Some(self.build_index(&table, &index)?);
+        self.catalog_mut().create_index(&table.name, index)?;
+        Ok(())
+    }
+
+    /// The schema of a table of the current database, after giving it a
+    /// heap, index pages and pages for the sequences of its identity
+    /// columns if it lacks them, as tables created before the data file
+    /// had them do.
+    fn table_storage(&mut self, name: &str) -> Result<TableSchema, ExecutionError> {
+        let table = self
+            .catalog()
+            .table(name)
+            .cloned()
+            .ok_or_else(|| ExecutionError::UnknownTable(name.to_string()))?;
+        let (database, sequences) = {
+            let catalog = self.catalog();
+            let sequences: Vec<(String, i128)> = table
+                .columns
+                .iter()
+                .filter_map(|column| catalog.identity_sequence(name, &column.name))
+                .filter(|sequence| sequence.page.is_none())
+                .map(|sequence| (sequence.name.clone(), sequence.start))
+                .collect();
+            (catalog.current_database().to_string(), sequences)
+        };
+        let prepared = table.heap.is_some()
+            && table.indexes.iter().all(|index| index.root.is_some())
+            && sequences.is_empty();
+        if !prepared && self.block.is_some() {
+            return Err(ExecutionError::Transaction(format!(
+                "table \"{}\" was created before its storage and needs a statement outside a transaction block to prepare it",
+                name
+            )));
+        }
+        if table.heap.is_none() {
+            let heap = {
+                let mut storage = self.storage();
+                let heap = storage.create_heap()?;
+                storage.commit_durable()?;
+                heap
+            };
+            self.catalog_mut().set_heap(&database, name, heap)?;
+        }
+        for index in table.indexes.iter().filter(|index| index.root.is_none()) {
+            let root = self.build_index(&table, index)?;
+            self.catalog_mut()
+                .set_index_root(&database, &index.name, root)?;
+        }
+        for (sequence, start) in sequences {
+            let page = {
+                let mut storage = self.storage();
+                let page = storage.create_sequence(start)?;
+                storage.commit_durable()?;
+                page
+            };
+            self.catalog_mut()
+                .set_sequence_page(&database, &sequence, page)?;
+        }
+        self.catalog()
+            .table(name)
+            .cloned()
+            .ok_or_else(|| ExecutionError::UnknownTable(name.to_string()))
+    }
+
+    /// Creates the pages of `index` holding every version `table` has
+    /// now, returning its root. Of the versions that may count, no two may
+    /// share a key of a unique index.
+    fn build_index(
+        &mut self,
+        table: &TableSchema,
+        index: &IndexSchema,
+    ) -> Result<PageId, ExecutionError> {
+        let bound = BoundIndex::bind(&self.catalog(), table, index)?;
+        let xid = self.statement_snapshot()?.transaction();
+        let layout = RowLayout::new(&table.columns);
+        let mut storage = self.storage();
+        let index = IndexSchema {
+            root: Some(storage.create_index(index.method)?),
+            ..index.clone()
+        };
+        if let Some(heap) = table.heap {
+            let mut cursor = storage.scan(heap)?;
+            while let Some(versions) = storage.next_versions(&mut cursor, xid)? {
+                for (id, state, record) in versions {
+                    let values = match bound.entry(table, &layout.decode(&record)?, self.now)? {
+                        Some(values) => values,
+                        None => continue,
+                    };
+                    if index.unique
+                        && state != VersionState::Dead
+                        && !values.iter().any(Value::is_null)
+                    {
+                        // The entries already in with the same key are
+                        // those of the versions read before.
+                        for other in storage.index_lookup(&index, &values)? {
+                            if storage.version_state(heap, other, xid)? != Some(VersionState::Dead)
+                            {
+                                return Err(ExecutionError::DuplicateKey(index.name.clone()));
+                            }
+                        }
+                    }
+                    storage.index_insert(&index, &values, id)?;
+                }
+            }
+        }
+        storage.commit_durable()?;
+        Ok(index.root.expect("the index was given a root above"))
+    }
+
+    /// The indexes of `table`, bound to read its rows.
+    fn bound_indexes(&self, table: &TableSchema) -> Result<Vec<BoundIndex>, ExecutionError> {
+        let catalog = self.catalog();
+        table
+            .indexes
+            .iter()
+            .map(|index| BoundIndex::bind(&catalog, table, index))
+            .collect()
+    }
+
+    fn table_schema(&self, name: &str) -> Result<TableSchema, ExecutionError> {
+        self.catalog()
+            .table(name)
+            .cloned()
+            .ok_or_else(|| ExecutionError::UnknownTable(name.to_string()))
+    }
+
+    /// The name of a table of the current database as locks know it.
+    fn qualified_name(&self, table: &str) -> String {
+        format!("{}.{}", self.catalog().current_database(), table)
+    }
+
+    /// Locks `target` for `xid`, waiting at most the session's lock
+    /// timeout.
+    fn acquire_lock(
+        &self,
+        xid: Xid,
+        target: LockTarget,
+        mode: LockMode,
+        wait: LockWait,
+    ) -> Result<bool, ExecutionError> {
+        self.database
+            .locks
+            .acquire(xid, &target, mode, wait, self.lock_timeout)
+    }
+
+    /// Locks the rows of `table` that satisfy `condition` for an UPDATE or
+    /// DELETE of transaction `xid`, returning them with their ids.
+    fn lock_targets(
+        &self,
+        xid: Xid,
+        table: &str,
+        condition: Option<&SQLExpression>,
+    ) -> Result<Vec<(RecordId, Vec<Value>)>, ExecutionError> {
+        let schema = self.table_schema(table)?;
+        let conditions = condition.map(conjuncts).unwrap_or_default();
+        let path = choose_access_path(
+            &self.bound_indexes(&schema)?,
+            &schema.name,
+            &conditions,
+            self.now,
+        )?;
+        let relation = Schema::for_table(&schema.name, &column_names(&schema));
+        let context = Context {
+            provider: Some(self),
+            outer: None,
+            now: self.now,
+        };
+        let table = self.qualified_name(&schema.name);
+        let mut targets = Vec::new();
+        for row in self.read_path(&schema, path)? {
+            let (id, row) = row?;
+            if let Some(condition) = condition {
+                if !is_satisfied(condition, &relation, &row, &context)? {
+                    continue;
+                }
+            }
+            let target = LockTarget::Row {
+                table: table.clone(),
+                row: id,
+            };
+            self.acquire_lock(xid, target, LockMode::Exclusive, LockWait::Wait)?;
+            targets.push((id, row));
+        }
+        Ok(targets)
+    }
+
+    fn statement_snapshot(&self) -> Result<&Snapshot, ExecutionError> {
+        self.snapshot.as_ref().ok_or_else(|| {
+            ExecutionError::Transaction("tables are only read by a statement".to_string())
+        })
+    }
+
+    /// The rows of a table an access path leads to that the running
+    /// statement sees, with their ids, read as they are used.
+    fn read_path(
+        &self,
+        table: &TableSchema,
+        path: AccessPath,
+    ) -> Result<TableRows<'_>, ExecutionError> {
+        let snapshot = self.statement_snapshot()?;
+        let source = match table.heap {
+            Some(heap) => {
+                let mut storage = self.storage();
+                storage.transactions().read(snapshot.transaction(), heap)?;
+                match path {
+                    AccessPath::SeqScan => RowSource::Heap(storage.scan(heap)?),
+                    AccessPath::IndexLookup { index, values } => RowSource::Ids {
+                        heap,
+                        ids: storage.index_lookup(&index, &values)?.into_iter(),
+                        _open: storage.open_scan(heap),
+                    },
+                    AccessPath::IndexRange {
+                        index,
+                        lower,
+                        upper,
+                    } => RowSource::Range {
+                        heap,
+                        index,
+                        cursor: RangeCursor::new(as_slice(&lower), as_slice(&upper)),
+                        _open: storage.open_scan(heap),
+                    },
+                }
+            }
+            None => RowSource::Empty,
+        };
+        Ok(TableRows {
+            executor: self,
+            snapshot,
+            layout: RowLayout::new(&table.columns),
+            source,
+            records: Vec::new().into_iter(),
+        })
+    }
+}
+
+/// Where [`TableRows`] finds the rows of its table.
+enum RowSource {
+    Heap(HeapCursor),
+    /// The ids an index lookup found.
+    Ids {
+        heap: PageId,
+        ids: vec::IntoIter<RecordId>,
+        _open: OpenScan,
+    },
+    /// A range of a B+tree index.
+    Range {
+        heap: PageId,
+        index: PageId,
+        cursor: RangeCursor,
+        _open: OpenScan,
+    },
+    Empty,
+}
+
+/// The rows of a table the running statement sees, with their ids. The
+/// engine is locked for a page of the heap, a leaf of the index or a row
+/// at a time, so that other sessions go on while the rows are used.
+struct TableRows<'a> {
+    executor: &'a Executor,
+    snapshot: &'a Snapshot,
+    layout: RowLayout,
+    source: RowSource,
+    records: vec::IntoIter<(RecordId, Vec<u8>)>,
+}
+
+impl TableRows<'_> {
+    /// The visible records of the next page, leaf or id, or `None` once
+    /// there are no more.
+    fn next_records(&mut self) -> io::Result<Option<PageRecords>> {
+        let mut storage = self.executor.storage();
+        let (heap, ids) = match &mut self.source {
+            RowSource::Heap(cursor) => return storage.next_visible(cursor, self.snapshot),
+            RowSource::Ids { heap, ids, .. } => match ids.next() {
+                Some(id) => (*heap, vec![id]),
+                None => return Ok(None),
+            },
+            RowSource::Range {
+                heap,
+                index,
+                cursor,
+                ..
+            } => match storage.next_index_entries(*index, cursor)? {
+                Some(entries) => (*heap, entries.into_iter().map(|(_, id)| id).collect()),
+                None => return Ok(None),
+            },
+            RowSource::Empty => return Ok(None),
+        };
+        let mut records = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(record) = storage.get_visible(heap, id, self.snapshot)? {
+                records.push((id, record));
+            }
+        }
+        Ok(Some(records))
+    }
+}
+
+impl Iterator for TableRows<'_> {
+    type Item = Result<(RecordId, Vec<Value>), ExecutionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((id, record)) = self.records.next() {
+                return Some(match self.layout.decode(&record) {
+                    Ok(row) => Ok((id, row)),
+                    Err(error) => Err(error.into()),
+                });
+            }
+            match self.next_records() {
+                Ok(Some(records)) => self.records = records.into_iter(),
+                Ok(None) => return None,
+                Err(error) => return Some(Err(error.into())),
+            }
+        }
+    }
+}
+
+/// A session left in a transaction block rolls it back, releasing its
+/// locks.
+impl Drop for Executor {
+    fn drop(&mut self) {
+        if let Some(block) = self.block.take() {
+            self.abort_transaction(block.xid);
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn as_slice(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+    bound.as_ref().map(Vec::as_slice)
+}
+
+/// Base tables come from storage, `information_schema` views from the
+/// catalog.
+impl TableProvider for Executor {
+    fn scan(&self, table: &str) -> Result<(Vec<String>, Rows<'_>), ExecutionError> {
+        if view_columns(table).is_some() {
+            let catalog = self.catalog();
+            let (columns, rows) = catalog.scan(table)?;
+            let rows: Vec<Vec<Value>> = rows.collect::<Result<_, _>>()?;
+            return Ok((columns, Box::new(rows.into_iter().map(Ok))));
+        }
+        let schema = self.table_schema(table)?;
+        let rows = self.read_path(&schema, AccessPath::SeqScan)?;
+        Ok((
+            column_names(&schema),
+            Box::new(rows.map(|row| row.map(|(_, row)| row))),
+        ))
+    }
+
+    /// Reads a base table through the index that best serves
+    /// `conditions`.
+    fn scan_where(
+        &self,
+        table: &str,
+        qualifier: &str,
+        conditions: &[&SQLExpression],
+    ) -> Result<(Vec<String>, Rows<'_>), ExecutionError> {
+        if view_columns(table).is_some() {
+            return self.scan(table);
+        }
+        let schema = self.table_schema(table)?;
+        let path = choose_access_path(
+            &self.bound_indexes(&schema)?,
+            qualifier,
+            conditions,
+            self.now,
+        )?;
+        let rows = self.read_path(&schema, path)?;
+        Ok((
+            column_names(&schema),
+            Box::new(rows.map(|row| row.map(|(_, row)| row))),
+        ))
+    }
+
+    fn scan_for_lock(
+        &self,
+        table: &str,
+        qualifier: &str,
+        conditions: &[&SQLExpression],
+    ) -> Result<(Vec<String>, Rows<'_>), ExecutionError> {
+        if view_columns(table).is_some() {
+            return Err(ExecutionError::Unsupported(format!(
+                "the rows of \"{}\" cannot be locked",
+                table
+            )));
+        }
+        // The table is locked before the ids of its rows are read, so that
+        // VACUUM FULL, which holds it exclusively to move rows, moves none
+        // before they are locked.
+        let xid = self.statement_snapshot()?.transaction();
+        let target = LockTarget::Table(self.qualified_name(table));
+        self.acquire_lock(xid, target, LockMode::IntentionShare, LockWait::Wait)?;
+        let schema = self.table_schema(table)?;
+        let path = choose_access_path(
+            &self.bound_indexes(&schema)?,
+            qualifier,
+            conditions,
+            self.now,
+        )?;
+        let rows = self.read_path(&schema, path)?;
+        let mut columns = column_names(&schema);
+        columns.push(ROW_ID.to_string());
+        Ok((
+            columns,
+            Box::new(rows.map(|row| {
+                row.map(|(id, mut row)| {
+                    row.push(row_id(id));
+                    row
+                })
+            })),
+        ))
+    }
+
+    /// Takes the intention lock of the table first, which under NOWAIT
+    /// does not wait either.
+    fn lock_row(
+        &self,
+        table: &str,
+        row: &Value,
+        locking: &LockingClause,
+    ) -> Result<bool, ExecutionError> {
+        let row = record_id(row)?;
+        let xid = self.statement_snapshot()?.transaction();
+        let intention = match locking.mode {
+            LockMode::Share => LockMode::IntentionShare,
+            _ => LockMode::IntentionExclusive,
+        };
+        let table_wait = match locking.wait {
+            LockWait::NoWait => LockWait::NoWait,
+            _ => LockWait::Wait,
+        };
+        let table = self.qualified_name(table);
+        self.acquire_lock(xid, LockTarget::Table(table.clone()), intention, table_wait)?;
+        self.acquire_lock(
+            xid,
+            LockTarget::Row { table, row },
+            locking.mode,
+            locking.wait,
+        )
+    }
+
+    fn now(&self) -> i64 {
+        self.now
+    }
+
+    fn work_memory(&self) -> usize {
+        self.work_memory
+    }
+
+    fn recursion_limit(&self) -> usize {
+        self.recursion_limit
+    }
+
+    /// `nextval` and `setval` change the sequence for every session at
+    /// once, and stay changed if the transaction rolls back; `currval` is
+    /// the last value `nextval` gave this session.
+    fn call_sequence_function(
+        &self,
+        function: ScalarFunction,
+        arguments: Vec<Value>,
+    ) -> Result<Value, ExecutionError> {
+        // The signature has been checked, so the first argument is text
+        // and a second one an integer.
+        let (name, value, is_called) = match arguments.as_slice() {
+            [Value::Text(name)] => (name, 0, true),
+            [Value::Text(name), Value::Integer(value)] => (name, *value, true),
+            [Value::Text(name), Value::Integer(value), Value::Bool(is_called)] => {
+                (name, *value, *is_called)
+            }
+            _ => {
+                return Err(ExecutionError::TypeMismatch(
+                    "sequence functions take the name of a sequence first".to_string(),
+                ))
+            }
+        };
+        let sequence = self
+            .catalog()
+            .sequence(name)
+            .cloned()
+            .ok_or_else(|| ExecutionError::UnknownSequence(name.clone()))?;
+        if let ScalarFunction::CurrVal = function {
+            return Ok(Value::Integer(self.current_values.borrow().get(&sequence)?));
+        }
+        let mut sequences = lock(&self.database.sequences);
+        let mut storage = self.storage();
+        Ok(Value::Integer(match function {
+            ScalarFunction::SetVal => {
+                sequences.set_value(&mut storage, &sequence, value, is_called)?
+            }
+            _ => {
+                let value = sequences.next_value(&mut storage, &sequence)?;
+                self.current_values.borrow_mut().set(&sequence, value);
+                value
+            }
+        }))
+    }
+}
+
+fn column_names(table: &TableSchema) -> Vec<String> {
+    table
+        .columns
+        .iter()
+        .map(|column| column.name.clone())
+        .collect()
+}
+
+/// A record id as the value of a [`ROW_ID`] column.
+fn row_id(id: RecordId) -> Value {
+    Value::Integer(i128::from(id.page) << 16 | i128::from(id.slot))
+}
+
+fn record_id(value: &Value) -> Result<RecordId, ExecutionError> {
+    match value {
+        Value::Integer(id) => Ok(RecordId {
+            page: (id >> 16) as PageId,
+            slot: (id & 0xffff) as u16,
+        }),
+        _ => Err(ExecutionError::TypeMismatch(
+            "rows are locked by the ids of their records".to_string(),
+        )),
+    }
+}
+
+fn result_set(columns: Vec<String>, relation: Relation) -> ResultSet {
+    ResultSet {
+        columns,
+        affected: relation.rows.len(),
+        rows: relation.rows,
+    }
+}
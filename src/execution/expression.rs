@@ -0,0 +1,358 @@
+use std::cmp::Ordering;
+
+use crate::compiler::parser::{Query, SQLExpression};
+use crate::compiler::walk::column_name;
+use crate::execution::error::ExecutionError;
+use crate::execution::planner::TableProvider;
+use crate::execution::query::execute_query;
+use crate::execution::relation::Relation;
+use crate::types::cast::cast;
+use crate::types::function::{AggregateFunction, ScalarFunction};
+use crate::types::value::Value;
+
+/// Describes the columns of the rows flowing between operators. Each slot
+/// records the expression that produced it: base table columns are plain
+/// identifiers, the output of an aggregation also holds the grouping
+/// expressions and aggregate calls, so later stages can refer to them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    pub columns: Vec<SQLExpression>,
+}
+
+impl Schema {
+    pub fn new(columns: Vec<SQLExpression>) -> Self {
+        Schema { columns }
+    }
+
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Self {
+        Schema {
+            columns: names
+                .iter()
+                .map(|name| SQLExpression::Identifier(name.as_ref().to_string()))
+                .collect(),
+        }
+    }
+
+    /// Columns of a base table, qualified by the table name or its alias.
+    pub fn for_table<S: AsRef<str>>(table: &str, names: &[S]) -> Self {
+        Schema {
+            columns: names
+                .iter()
+                .map(|name| SQLExpression::QualifiedIdentifier {
+                    table: table.to_string(),
+                    column: name.as_ref().to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn concat(&self, other: &Schema) -> Schema {
+        let mut columns = self.columns.clone();
+        columns.extend(other.columns.iter().cloned());
+        Schema { columns }
+    }
+
+    pub fn position(&self, expression: &SQLExpression) -> Option<usize> {
+        self.columns.iter().position(|column| column == expression)
+    }
+
+    /// Finds the slot an expression reads from. An unqualified identifier
+    /// that has no slot of its own matches a qualified column of the same
+    /// name, as long as only one table provides it.
+    pub fn resolve(&self, expression: &SQLExpression) -> Result<Option<usize>, ExecutionError> {
+        if let Some(position) = self.position(expression) {
+            return Ok(Some(position));
+        }
+        let name = match expression {
+            SQLExpression::Identifier(name) => name,
+            _ => return Ok(None),
+        };
+        let mut found = None;
+        for (position, column) in self.columns.iter().enumerate() {
+            if let SQLExpression::QualifiedIdentifier { column, .. } = column {
+                if column == name {
+                    if found.is_some() {
+                        return Err(ExecutionError::AmbiguousColumn(name.clone()));
+                    }
+                    found = Some(position);
+                }
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// What an expression can reach besides the current row: tables, for
+/// evaluating subqueries, the rows of enclosing queries, for correlated
+/// column references, and the time the statement started, for NOW().
+#[derive(Clone, Copy, Default)]
+pub struct Context<'a> {
+    pub provider: Option<&'a dyn TableProvider>,
+    pub outer: Option<&'a Scope<'a>>,
+    pub now: i64,
+}
+
+impl<'a> Context<'a> {
+    pub fn provider(&self) -> Result<&'a dyn TableProvider, ExecutionError> {
+        self.provider
+            .ok_or_else(|| ExecutionError::Unsupported("no tables are accessible here".to_string()))
+    }
+}
+
+/// The current row of an enclosing query.
+pub struct Scope<'a> {
+    pub schema: &'a Schema,
+    pub row: &'a [Value],
+    pub outer: Option<&'a Scope<'a>>,
+}
+
+pub fn evaluate(
+    expression: &SQLExpression,
+    schema: &Schema,
+    row: &[Value],
+    context: &Context,
+) -> Result<Value, ExecutionError> {
+    if let Some(position) = schema.resolve(expression)? {
+        return Ok(row[position].clone());
+    }
+    match expression {
+        SQLExpression::Identifier(_) | SQLExpression::QualifiedIdentifier { .. } => {
+            let mut scope = context.outer;
+            while let Some(current) = scope {
+                if let Some(position) = current.schema.resolve(expression)? {
+                    return Ok(current.row[position].clone());
+                }
+                scope = current.outer;
+            }
+            Err(ExecutionError::UnknownColumn(match expression {
+                SQLExpression::QualifiedIdentifier { table, column } => {
+                    format!("{}.{}", table, column)
+                }
+                _ => column_name(expression),
+            }))
+        }
+        SQLExpression::Literal(literal) => Ok(Value::from_literal(literal)?),
+        SQLExpression::BinaryExpression {
+            operator,
+            left,
+            right,
+        } => {
+            let left = evaluate(left, schema, row, context)?;
+            if operator == "AND" && matches!(left, Value::Bool(false)) {
+                return Ok(Value::Bool(false));
+            }
+            if operator == "OR" && matches!(left, Value::Bool(true)) {
+                return Ok(Value::Bool(true));
+            }
+            let right = evaluate(right, schema, row, context)?;
+            apply_binary_operator(operator, &left, &right)
+        }
+        SQLExpression::FunctionCall {
+            name, arguments, ..
+        } => {
+            if AggregateFunction::from_name(name).is_some() {
+                return Err(ExecutionError::AggregateNotAllowed(name.clone()));
+            }
+            let function = match ScalarFunction::from_name(name) {
+                Some(function) => function,
+                None => return Err(ExecutionError::UnknownFunction(name.clone())),
+            };
+            let mut values = Vec::with_capacity(arguments.len());
+            for argument in arguments {
+                values.push(evaluate(argument, schema, row, context)?);
+            }
+            if function.uses_sequences() {
+                function.check(name, &values)?;
+                if values.iter().any(Value::is_null) {
+                    return Ok(Value::Null);
+                }
+                return context.provider()?.call_sequence_function(function, values);
+            }
+            function.call(name, values, context.now)
+        }
+        SQLExpression::Alias { expression, .. } => evaluate(expression, schema, row, context),
+        SQLExpression::Case {
+            operand,
+            branches,
+            else_result,
+        } => {
+            let operand = match operand {
+                Some(operand) => Some(evaluate(operand, schema, row, context)?),
+                None => None,
+            };
+            for (condition, result) in branches {
+                let matched = match &operand {
+                    Some(operand) => {
+                        let value = evaluate(condition, schema, row, context)?;
+                        operand.compare(&value)? == Some(Ordering::Equal)
+                    }
+                    None => is_satisfied(condition, schema, row, context)?,
+                };
+                if matched {
+                    return evaluate(result, schema, row, context);
+                }
+            }
+            match else_result {
+                Some(result) => evaluate(result, schema, row, context),
+                None => Ok(Value::Null),
+            }
+        }
+        SQLExpression::Cast {
+            expression,
+            data_type,
+        } => Ok(cast(
+            evaluate(expression, schema, row, context)?,
+            data_type,
+        )?),
+        SQLExpression::Subquery(query) => {
+            let result = run_subquery(query, schema, row, context)?;
+            if result.schema.columns.len() != 1 {
+                return Err(ExecutionError::TypeMismatch(
+                    "subquery must return only one column".to_string(),
+                ));
+            }
+            match result.rows.len() {
+                0 => Ok(Value::Null),
+                1 => Ok(result.rows[0][0].clone()),
+                _ => Err(ExecutionError::Cardinality(
+                    "more than one row returned by a subquery used as an expression".to_string(),
+                )),
+            }
+        }
+        SQLExpression::InSubquery {
+            expression,
+            subquery,
+            negated,
+        } => {
+            let value = evaluate(expression, schema, row, context)?;
+            let result = run_subquery(subquery, schema, row, context)?;
+            if result.schema.columns.len() != 1 {
+                return Err(ExecutionError::TypeMismatch(
+                    "subquery has too many columns".to_string(),
+                ));
+            }
+            // x IN (...) is TRUE on a match, otherwise UNKNOWN if x or any
+            // candidate is NULL, and FALSE only when nothing could match.
+            let mut found = Some(false);
+            for candidate in &result.rows {
+                match value.compare(&candidate[0])? {
+                    Some(Ordering::Equal) => {
+                        found = Some(true);
+                        break;
+                    }
+                    Some(_) => {}
+                    None => found = None,
+                }
+            }
+            Ok(match found {
+                Some(found) => Value::Bool(found != *negated),
+                None => Value::Null,
+            })
+        }
+        SQLExpression::Exists { subquery, negated } => {
+            let result = run_subquery(subquery, schema, row, context)?;
+            Ok(Value::Bool(result.rows.is_empty() == *negated))
+        }
+        SQLExpression::Not(expression) => {
+            let value = evaluate(expression, schema, row, context)?;
+            Ok(match as_truth(&value)? {
+                Some(truth) => Value::Bool(!truth),
+                None => Value::Null,
+            })
+        }
+        SQLExpression::IsNull {
+            expression,
+            negated,
+        } => {
+            let value = evaluate(expression, schema, row, context)?;
+            Ok(Value::Bool(value.is_null() != *negated))
+        }
+        SQLExpression::WindowFunction { function, .. } => {
+            Err(ExecutionError::Unsupported(format!(
+                "window function {} is only allowed in the select list and ORDER BY",
+                column_name(function).to_uppercase()
+            )))
+        }
+        SQLExpression::Wildcard => Err(ExecutionError::Unsupported(
+            "* is only allowed in a select list or COUNT(*)".to_string(),
+        )),
+        SQLExpression::Operator(_) | SQLExpression::CreateColumn { .. } => Err(
+            ExecutionError::Unsupported(format!("{} is not a value expression", expression)),
+        ),
+    }
+}
+
+/// Runs a nested query with the current row visible to it as its outer scope.
+fn run_subquery(
+    query: &Query,
+    schema: &Schema,
+    row: &[Value],
+    context: &Context,
+) -> Result<Relation, ExecutionError> {
+    let provider = context.provider()?;
+    let scope = Scope {
+        schema,
+        row,
+        outer: context.outer,
+    };
+    execute_query(query, provider, Some(&scope))
+}
+
+pub fn apply_binary_operator(
+    operator: &str,
+    left: &Value,
+    right: &Value,
+) -> Result<Value, ExecutionError> {
+    match operator {
+        "AND" => Ok(match (as_truth(left)?, as_truth(right)?) {
+            (Some(false), _) | (_, Some(false)) => Value::Bool(false),
+            (Some(true), Some(true)) => Value::Bool(true),
+            _ => Value::Null,
+        }),
+        "OR" => Ok(match (as_truth(left)?, as_truth(right)?) {
+            (Some(true), _) | (_, Some(true)) => Value::Bool(true),
+            (Some(false), Some(false)) => Value::Bool(false),
+            _ => Value::Null,
+        }),
+        "==" | "!=" | "<" | ">" | "<=" | ">=" => {
+            let ordering = match left.compare(right)? {
+                Some(ordering) => ordering,
+                None => return Ok(Value::Null),
+            };
+            Ok(Value::Bool(match operator {
+                "==" => ordering == Ordering::Equal,
+                "!=" => ordering != Ordering::Equal,
+                "<" => ordering == Ordering::Less,
+                ">" => ordering == Ordering::Greater,
+                "<=" => ordering != Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            }))
+        }
+        _ => Err(ExecutionError::Unsupported(format!(
+            "operator {}",
+            operator
+        ))),
+    }
+}
+
+/// Three-valued truth of a condition: `None` stands for UNKNOWN.
+pub fn as_truth(value: &Value) -> Result<Option<bool>, ExecutionError> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Bool(value) => Ok(Some(*value)),
+        _ => Err(ExecutionError::TypeMismatch(format!(
+            "expected BOOL condition, found {}",
+            value.type_name()
+        ))),
+    }
+}
+
+/// A row passes a WHERE or HAVING filter only when the condition is TRUE.
+pub fn is_satisfied(
+    condition: &SQLExpression,
+    schema: &Schema,
+    row: &[Value],
+    context: &Context,
+) -> Result<bool, ExecutionError> {
+    Ok(as_truth(&evaluate(condition, schema, row, context)?)? == Some(true))
+}
@@ -0,0 +1,394 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::compiler::parser::SQLExpression;
+use crate::compiler::walk::{case_parts, window_expressions};
+use crate::execution::error::ExecutionError;
+use crate::types::function::{
+    AggregateFunction, ParameterType, ScalarFunction, Signature, WindowFunction,
+};
+use crate::types::value::{
+    civil_from_days, days_from_civil, parse_datetime, Value, MICROS_PER_DAY,
+};
+
+/// Evaluating calls; the names and signatures are in `types::function`.
+impl ScalarFunction {
+    /// Functions other than these return NULL as soon as any argument is NULL.
+    fn handles_null(self) -> bool {
+        matches!(self, ScalarFunction::Coalesce | ScalarFunction::NullIf)
+    }
+
+    /// Checks the number and kinds of `arguments`.
+    pub fn check(self, name: &str, arguments: &[Value]) -> Result<(), ExecutionError> {
+        let signature = self.signature();
+        if !signature.accepts_count(arguments.len()) {
+            return Err(arity_error(name, &signature, arguments.len()));
+        }
+        for (position, argument) in arguments.iter().enumerate() {
+            check_argument(name, position, signature.parameter(position), argument)?;
+        }
+        Ok(())
+    }
+
+    /// Calls the function; `now` is what NOW() returns, the same for every
+    /// row of a statement.
+    pub fn call(
+        self,
+        name: &str,
+        arguments: Vec<Value>,
+        now: i64,
+    ) -> Result<Value, ExecutionError> {
+        self.check(name, &arguments)?;
+        if !self.handles_null() && arguments.iter().any(Value::is_null) {
+            return Ok(Value::Null);
+        }
+        let count = arguments.len();
+        let mut arguments = arguments.into_iter();
+        let mut next = || arguments.next().unwrap_or(Value::Null);
+        match self {
+            ScalarFunction::Upper => Ok(Value::Text(text(next()).to_uppercase())),
+            ScalarFunction::Lower => Ok(Value::Text(text(next()).to_lowercase())),
+            ScalarFunction::Length => Ok(Value::Integer(text(next()).chars().count() as i128)),
+            ScalarFunction::Substring => {
+                let value = text(next());
+                let start = integer(next());
+                let end = match next() {
+                    Value::Null => None,
+                    Value::Integer(length) if length < 0 => {
+                        return Err(ExecutionError::TypeMismatch(
+                            "negative substring length not allowed".to_string(),
+                        ))
+                    }
+                    length => Some(start.saturating_add(integer(length))),
+                };
+                // Positions are one-based and may start before the string;
+                // only the part that overlaps it is returned.
+                Ok(Value::Text(
+                    value
+                        .chars()
+                        .enumerate()
+                        .filter(|(index, _)| {
+                            let position = *index as i128 + 1;
+                            position >= start && end.is_none_or(|end| position < end)
+                        })
+                        .map(|(_, ch)| ch)
+                        .collect(),
+                ))
+            }
+            ScalarFunction::Trim | ScalarFunction::LeftTrim | ScalarFunction::RightTrim => {
+                let value = text(next());
+                let characters: Vec<char> = match next() {
+                    Value::Null => vec![' '],
+                    characters => text(characters).chars().collect(),
+                };
+                let trimmed = match self {
+                    ScalarFunction::LeftTrim => value.trim_start_matches(characters.as_slice()),
+                    ScalarFunction::RightTrim => value.trim_end_matches(characters.as_slice()),
+                    _ => value.trim_matches(characters.as_slice()),
+                };
+                Ok(Value::Text(trimmed.to_string()))
+            }
+            ScalarFunction::Replace => {
+                let value = text(next());
+                let from = text(next());
+                let to = text(next());
+                if from.is_empty() {
+                    return Ok(Value::Text(value));
+                }
+                Ok(Value::Text(value.replace(&from, &to)))
+            }
+            ScalarFunction::Position => {
+                let needle = text(next());
+                let haystack = text(next());
+                Ok(Value::Integer(match haystack.find(&needle) {
+                    Some(offset) => haystack[..offset].chars().count() as i128 + 1,
+                    None => 0,
+                }))
+            }
+            ScalarFunction::Abs => match next() {
+                Value::Integer(value) => value
+                    .checked_abs()
+                    .map(Value::Integer)
+                    .ok_or_else(|| ExecutionError::Overflow(format!("ABS({})", value))),
+                value => Ok(Value::Float(float(value).abs())),
+            },
+            ScalarFunction::Round => {
+                let value = next();
+                let digits = match next() {
+                    Value::Null => 0,
+                    digits => integer(digits),
+                };
+                match value {
+                    Value::Integer(value) => Ok(Value::Integer(value)),
+                    value => {
+                        let scale = 10f64.powi(digits.clamp(-308, 308) as i32);
+                        Ok(Value::Float((float(value) * scale).round() / scale))
+                    }
+                }
+            }
+            ScalarFunction::Floor => Ok(match next() {
+                Value::Integer(value) => Value::Integer(value),
+                value => Value::Float(float(value).floor()),
+            }),
+            ScalarFunction::Ceil => Ok(match next() {
+                Value::Integer(value) => Value::Integer(value),
+                value => Value::Float(float(value).ceil()),
+            }),
+            ScalarFunction::Power => {
+                let base = float(next());
+                let exponent = float(next());
+                // The result would not be a real number.
+                if base < 0.0 && exponent.fract() != 0.0 {
+                    return Err(ExecutionError::InvalidArgument(format!(
+                        "POWER({}, {}) of a negative base needs an integer exponent",
+                        base, exponent
+                    )));
+                }
+                let power = base.powf(exponent);
+                if !power.is_finite() {
+                    return Err(ExecutionError::Overflow(format!(
+                        "POWER({}, {})",
+                        base, exponent
+                    )));
+                }
+                Ok(Value::Float(power))
+            }
+            ScalarFunction::Mod => match (next(), next()) {
+                (_, Value::Integer(0)) => Err(ExecutionError::DivisionByZero),
+                (Value::Integer(left), Value::Integer(right)) => {
+                    Ok(Value::Integer(left.checked_rem(right).unwrap_or(0)))
+                }
+                (_, Value::Float(0.0)) => Err(ExecutionError::DivisionByZero),
+                (left, right) => Ok(Value::Float(float(left) % float(right))),
+            },
+            ScalarFunction::Coalesce => Ok(std::iter::repeat_with(next)
+                .take(count)
+                .find(|value| !value.is_null())
+                .unwrap_or(Value::Null)),
+            ScalarFunction::NullIf => {
+                let value = next();
+                let other = next();
+                Ok(match value.compare(&other)? {
+                    Some(std::cmp::Ordering::Equal) => Value::Null,
+                    _ => value,
+                })
+            }
+            ScalarFunction::Now => Ok(Value::DateTime(now)),
+            ScalarFunction::ToDateTime => datetime(name, next()).map(Value::DateTime),
+            ScalarFunction::Extract => {
+                let field = text(next()).to_uppercase();
+                let value = datetime(name, next())?;
+                extract(&field, value).map(Value::Integer)
+            }
+            ScalarFunction::DateTrunc => {
+                let field = text(next()).to_uppercase();
+                let value = datetime(name, next())?;
+                truncate(&field, value).map(Value::DateTime)
+            }
+            ScalarFunction::NextVal | ScalarFunction::CurrVal | ScalarFunction::SetVal => Err(
+                ExecutionError::Unsupported(format!("{} outside of a database", name)),
+            ),
+        }
+    }
+}
+
+fn arity_error(name: &str, signature: &Signature, count: usize) -> ExecutionError {
+    let expected = if signature.variadic {
+        format!("at least {}", signature.required)
+    } else if signature.required == signature.parameters.len() {
+        signature.required.to_string()
+    } else {
+        format!("{} to {}", signature.required, signature.parameters.len())
+    };
+    ExecutionError::TypeMismatch(format!(
+        "{} takes {} arguments, got {}",
+        name, expected, count
+    ))
+}
+
+fn check_argument(
+    name: &str,
+    position: usize,
+    parameter: ParameterType,
+    argument: &Value,
+) -> Result<(), ExecutionError> {
+    let accepted = matches!(
+        (parameter, argument),
+        (_, Value::Null)
+            | (ParameterType::Any, _)
+            | (ParameterType::Text, Value::Text(_))
+            | (ParameterType::Integer, Value::Integer(_))
+            | (ParameterType::Numeric, Value::Integer(_) | Value::Float(_))
+            | (ParameterType::DateTime, Value::DateTime(_) | Value::Text(_))
+            | (ParameterType::Bool, Value::Bool(_))
+    );
+    if accepted {
+        return Ok(());
+    }
+    Err(ExecutionError::TypeMismatch(format!(
+        "argument {} of {} must be {}, found {}",
+        position + 1,
+        name,
+        parameter.name(),
+        argument.type_name()
+    )))
+}
+
+// The accessors below run after `check_argument`, so the variant is known.
+
+fn text(value: Value) -> String {
+    match value {
+        Value::Text(text) => text,
+        value => value.to_string(),
+    }
+}
+
+fn integer(value: Value) -> i128 {
+    match value {
+        Value::Integer(value) => value,
+        _ => 0,
+    }
+}
+
+fn float(value: Value) -> f64 {
+    match value {
+        Value::Integer(value) => value as f64,
+        Value::Float(value) => value,
+        _ => f64::NAN,
+    }
+}
+
+fn datetime(name: &str, value: Value) -> Result<i64, ExecutionError> {
+    match value {
+        Value::DateTime(value) => Ok(value),
+        Value::Text(text) => parse_datetime(&text).ok_or_else(|| {
+            ExecutionError::InvalidLiteral(format!("'{}' is not a valid {} argument", text, name))
+        }),
+        value => Err(ExecutionError::TypeMismatch(format!(
+            "{} expects DATETIME, found {}",
+            name,
+            value.type_name()
+        ))),
+    }
+}
+
+/// EXTRACT fields; DOW counts from Sunday as 0 and EPOCH is in whole seconds.
+fn extract(field: &str, value: i64) -> Result<i128, ExecutionError> {
+    let days = value.div_euclid(MICROS_PER_DAY);
+    let micros = value.rem_euclid(MICROS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    Ok(match field {
+        "YEAR" => year as i128,
+        "MONTH" => month as i128,
+        "DAY" => day as i128,
+        "HOUR" => (micros / 3_600_000_000) as i128,
+        "MINUTE" => (micros / 60_000_000 % 60) as i128,
+        "SECOND" => (micros / 1_000_000 % 60) as i128,
+        "MICROSECOND" => (micros % 60_000_000) as i128,
+        "DOW" => (days + 4).rem_euclid(7) as i128,
+        "DOY" => (days - days_from_civil(year, 1, 1) + 1) as i128,
+        "EPOCH" => value.div_euclid(1_000_000) as i128,
+        _ => return Err(unknown_field(field)),
+    })
+}
+
+fn truncate(field: &str, value: i64) -> Result<i64, ExecutionError> {
+    let days = value.div_euclid(MICROS_PER_DAY);
+    let (year, month, _) = civil_from_days(days);
+    let unit = match field {
+        "YEAR" => return Ok(days_from_civil(year, 1, 1) * MICROS_PER_DAY),
+        "MONTH" => return Ok(days_from_civil(year, month, 1) * MICROS_PER_DAY),
+        "DAY" => MICROS_PER_DAY,
+        "HOUR" => 3_600_000_000,
+        "MINUTE" => 60_000_000,
+        "SECOND" => 1_000_000,
+        _ => return Err(unknown_field(field)),
+    };
+    Ok(value.div_euclid(unit) * unit)
+}
+
+fn unknown_field(field: &str) -> ExecutionError {
+    ExecutionError::Unsupported(format!("datetime field {}", field))
+}
+
+/// The time of the clock, in microseconds since the epoch.
+pub fn current_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_micros() as i64)
+        .unwrap_or(0)
+}
+
+/// Checks every function call in `expression` before any row is read:
+/// the function must exist, be used where its kind is allowed and get a
+/// number of arguments its signature accepts. Literal arguments are also
+/// checked against the parameter types.
+pub fn check_functions(expression: &SQLExpression) -> Result<(), ExecutionError> {
+    match expression {
+        SQLExpression::FunctionCall {
+            name, arguments, ..
+        } => {
+            if let Some(function) = ScalarFunction::from_name(name) {
+                let signature = function.signature();
+                if !signature.accepts_count(arguments.len()) {
+                    return Err(arity_error(name, &signature, arguments.len()));
+                }
+                for (position, argument) in arguments.iter().enumerate() {
+                    if let SQLExpression::Literal(literal) = argument {
+                        let value = Value::from_literal(literal)?;
+                        check_argument(name, position, signature.parameter(position), &value)?;
+                    }
+                }
+            } else if WindowFunction::from_name(name).is_some() {
+                return Err(ExecutionError::Unsupported(format!(
+                    "window function {} requires an OVER clause",
+                    name
+                )));
+            } else if AggregateFunction::from_name(name).is_none() {
+                return Err(ExecutionError::UnknownFunction(name.clone()));
+            }
+            for argument in arguments {
+                if *argument != SQLExpression::Wildcard {
+                    check_functions(argument)?;
+                }
+            }
+            Ok(())
+        }
+        SQLExpression::WindowFunction { function, window } => {
+            if let SQLExpression::FunctionCall {
+                name, arguments, ..
+            } = function.as_ref()
+            {
+                if WindowFunction::from_name(name).is_none()
+                    && AggregateFunction::from_name(name).is_none()
+                {
+                    return Err(ExecutionError::UnknownFunction(name.clone()));
+                }
+                for argument in arguments {
+                    if *argument != SQLExpression::Wildcard {
+                        check_functions(argument)?;
+                    }
+                }
+            }
+            for expression in window_expressions(window) {
+                check_functions(expression)?;
+            }
+            Ok(())
+        }
+        SQLExpression::BinaryExpression { left, right, .. } => {
+            check_functions(left)?;
+            check_functions(right)
+        }
+        SQLExpression::Alias { expression, .. }
+        | SQLExpression::InSubquery { expression, .. }
+        | SQLExpression::Cast { expression, .. }
+        | SQLExpression::Not(expression)
+        | SQLExpression::IsNull { expression, .. } => check_functions(expression),
+        SQLExpression::Case { .. } => {
+            for part in case_parts(expression) {
+                check_functions(part)?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,346 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::compiler::parser::{JoinType, SQLExpression};
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::{evaluate, is_satisfied, Context, Schema};
+use crate::execution::relation::Relation;
+use crate::types::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinStrategy {
+    NestedLoop,
+    Hash,
+    SortMerge,
+}
+
+/// A planned join between two relations.
+///
+/// `left_keys[i] == right_keys[i]` are the equality conditions usable by the
+/// hash and sort-merge strategies; `residual` is whatever else the join
+/// condition requires. Columns named in `using` appear once in the output,
+/// ahead of the columns of both inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub join_type: JoinType,
+    pub left_keys: Vec<SQLExpression>,
+    pub right_keys: Vec<SQLExpression>,
+    pub residual: Option<SQLExpression>,
+    pub using: Vec<String>,
+}
+
+struct Output<'a> {
+    join: &'a Join,
+    context: &'a Context<'a>,
+    schema: Schema,
+    left_width: usize,
+    right_width: usize,
+    using_positions: Vec<(usize, usize)>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl<'a> Output<'a> {
+    fn new(
+        join: &'a Join,
+        left: &Schema,
+        right: &Schema,
+        context: &'a Context<'a>,
+    ) -> Result<Self, ExecutionError> {
+        let mut using_positions = Vec::with_capacity(join.using.len());
+        for name in &join.using {
+            let column = SQLExpression::Identifier(name.clone());
+            match (left.resolve(&column)?, right.resolve(&column)?) {
+                (Some(left), Some(right)) => using_positions.push((left, right)),
+                _ => return Err(ExecutionError::UnknownColumn(name.clone())),
+            }
+        }
+        Ok(Output {
+            join,
+            context,
+            schema: join.combined_schema(left, right),
+            left_width: left.columns.len(),
+            right_width: right.columns.len(),
+            using_positions,
+            rows: Vec::new(),
+        })
+    }
+
+    fn build(&self, left: Option<&[Value]>, right: Option<&[Value]>) -> Vec<Value> {
+        let mut row = Vec::with_capacity(self.schema.columns.len());
+        for (left_position, right_position) in &self.using_positions {
+            let left_value = left.map(|row| &row[*left_position]);
+            let right_value = right.map(|row| &row[*right_position]);
+            row.push(match (left_value, right_value) {
+                (Some(value), _) if !value.is_null() => value.clone(),
+                (_, Some(value)) => value.clone(),
+                _ => Value::Null,
+            });
+        }
+        match left {
+            Some(values) => row.extend(values.iter().cloned()),
+            None => row.extend(std::iter::repeat_n(Value::Null, self.left_width)),
+        }
+        match right {
+            Some(values) => row.extend(values.iter().cloned()),
+            None => row.extend(std::iter::repeat_n(Value::Null, self.right_width)),
+        }
+        row
+    }
+
+    /// Emits the pair when the residual condition holds; returns whether it
+    /// did. Semi and anti joins only record the match.
+    fn try_match(&mut self, left: &[Value], right: &[Value]) -> Result<bool, ExecutionError> {
+        let row = self.build(Some(left), Some(right));
+        if let Some(residual) = &self.join.residual {
+            if !is_satisfied(residual, &self.schema, &row, self.context)? {
+                return Ok(false);
+            }
+        }
+        if !matches!(self.join.join_type, JoinType::Semi | JoinType::Anti) {
+            self.rows.push(row);
+        }
+        Ok(true)
+    }
+
+    fn finish(
+        mut self,
+        left: &Relation,
+        left_matched: &[bool],
+        right: &Relation,
+        right_matched: &[bool],
+    ) -> Relation {
+        if matches!(self.join.join_type, JoinType::Semi | JoinType::Anti) {
+            let keep = self.join.join_type == JoinType::Semi;
+            let rows = left
+                .rows
+                .iter()
+                .zip(left_matched)
+                .filter(|(_, matched)| **matched == keep)
+                .map(|(row, _)| row.clone())
+                .collect();
+            return Relation::new(left.schema.clone(), rows);
+        }
+        if matches!(self.join.join_type, JoinType::Left | JoinType::Full) {
+            for (row, _) in left.rows.iter().zip(left_matched).filter(|(_, m)| !**m) {
+                let padded = self.build(Some(row), None);
+                self.rows.push(padded);
+            }
+        }
+        if matches!(self.join.join_type, JoinType::Right | JoinType::Full) {
+            for (row, _) in right.rows.iter().zip(right_matched).filter(|(_, m)| !**m) {
+                let padded = self.build(None, Some(row));
+                self.rows.push(padded);
+            }
+        }
+        Relation::new(self.schema, self.rows)
+    }
+}
+
+impl Join {
+    /// Semi and anti joins return the left input's columns only.
+    pub fn output_schema(&self, left: &Schema, right: &Schema) -> Schema {
+        match self.join_type {
+            JoinType::Semi | JoinType::Anti => left.clone(),
+            _ => self.combined_schema(left, right),
+        }
+    }
+
+    /// The schema of a matched pair, which the residual condition is evaluated against.
+    fn combined_schema(&self, left: &Schema, right: &Schema) -> Schema {
+        let mut columns: Vec<SQLExpression> = self
+            .using
+            .iter()
+            .map(|name| SQLExpression::Identifier(name.clone()))
+            .collect();
+        columns.extend(left.columns.iter().cloned());
+        columns.extend(right.columns.iter().cloned());
+        Schema::new(columns)
+    }
+
+    pub fn execute(
+        &self,
+        strategy: JoinStrategy,
+        left: Relation,
+        right: Relation,
+        context: &Context,
+    ) -> Result<Relation, ExecutionError> {
+        if strategy != JoinStrategy::NestedLoop && self.left_keys.is_empty() {
+            return Err(ExecutionError::Unsupported(format!(
+                "{:?} join needs at least one equality condition",
+                strategy
+            )));
+        }
+        match strategy {
+            JoinStrategy::NestedLoop => self.nested_loop(left, right, context),
+            JoinStrategy::Hash => self.hash(left, right, context),
+            JoinStrategy::SortMerge => self.sort_merge(left, right, context),
+        }
+    }
+
+    fn nested_loop(
+        &self,
+        left: Relation,
+        right: Relation,
+        context: &Context,
+    ) -> Result<Relation, ExecutionError> {
+        let mut output = Output::new(self, &left.schema, &right.schema, context)?;
+        let left_keys = keys_of(&self.left_keys, &left, context)?;
+        let right_keys = keys_of(&self.right_keys, &right, context)?;
+        let mut left_matched = vec![false; left.rows.len()];
+        let mut right_matched = vec![false; right.rows.len()];
+        for (i, left_row) in left.rows.iter().enumerate() {
+            for (j, right_row) in right.rows.iter().enumerate() {
+                if keys_equal(&left_keys[i], &right_keys[j])
+                    && output.try_match(left_row, right_row)?
+                {
+                    left_matched[i] = true;
+                    right_matched[j] = true;
+                }
+            }
+        }
+        Ok(output.finish(&left, &left_matched, &right, &right_matched))
+    }
+
+    /// Builds a hash table over the right input and probes it with the left.
+    fn hash(
+        &self,
+        left: Relation,
+        right: Relation,
+        context: &Context,
+    ) -> Result<Relation, ExecutionError> {
+        let mut output = Output::new(self, &left.schema, &right.schema, context)?;
+        let left_keys = keys_of(&self.left_keys, &left, context)?;
+        let right_keys = keys_of(&self.right_keys, &right, context)?;
+        let mut table: HashMap<&[Value], Vec<usize>> = HashMap::new();
+        for (j, key) in right_keys.iter().enumerate() {
+            if let Some(key) = key {
+                table.entry(key.as_slice()).or_default().push(j);
+            }
+        }
+        let mut left_matched = vec![false; left.rows.len()];
+        let mut right_matched = vec![false; right.rows.len()];
+        for (i, key) in left_keys.iter().enumerate() {
+            let candidates = match key.as_ref().and_then(|key| table.get(key.as_slice())) {
+                Some(candidates) => candidates,
+                None => continue,
+            };
+            for &j in candidates {
+                if output.try_match(&left.rows[i], &right.rows[j])? {
+                    left_matched[i] = true;
+                    right_matched[j] = true;
+                }
+            }
+        }
+        Ok(output.finish(&left, &left_matched, &right, &right_matched))
+    }
+
+    /// Sorts both inputs on the join keys and merges runs of equal keys.
+    fn sort_merge(
+        &self,
+        left: Relation,
+        right: Relation,
+        context: &Context,
+    ) -> Result<Relation, ExecutionError> {
+        let mut output = Output::new(self, &left.schema, &right.schema, context)?;
+        let left_keys = keys_of(&self.left_keys, &left, context)?;
+        let right_keys = keys_of(&self.right_keys, &right, context)?;
+        let left_order = sorted_positions(&left_keys);
+        let right_order = sorted_positions(&right_keys);
+        let mut left_matched = vec![false; left.rows.len()];
+        let mut right_matched = vec![false; right.rows.len()];
+
+        let (mut i, mut j) = (0, 0);
+        while i < left_order.len() && j < right_order.len() {
+            let left_key = left_keys[left_order[i]].as_ref().unwrap();
+            let right_key = right_keys[right_order[j]].as_ref().unwrap();
+            match compare_keys(left_key, right_key) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    let left_end = run_end(&left_keys, &left_order, i);
+                    let right_end = run_end(&right_keys, &right_order, j);
+                    for &l in &left_order[i..left_end] {
+                        for &r in &right_order[j..right_end] {
+                            if output.try_match(&left.rows[l], &right.rows[r])? {
+                                left_matched[l] = true;
+                                right_matched[r] = true;
+                            }
+                        }
+                    }
+                    i = left_end;
+                    j = right_end;
+                }
+            }
+        }
+        Ok(output.finish(&left, &left_matched, &right, &right_matched))
+    }
+}
+
+/// Evaluates the join keys of every row; `None` when a key contains NULL,
+/// since such a row can never satisfy an equality.
+fn keys_of(
+    expressions: &[SQLExpression],
+    relation: &Relation,
+    context: &Context,
+) -> Result<Vec<Option<Vec<Value>>>, ExecutionError> {
+    let mut keys = Vec::with_capacity(relation.rows.len());
+    for row in &relation.rows {
+        let mut key = Vec::with_capacity(expressions.len());
+        for expression in expressions {
+            key.push(normalize_key(evaluate(
+                expression,
+                &relation.schema,
+                row,
+                context,
+            )?));
+        }
+        keys.push(if key.iter().any(Value::is_null) {
+            None
+        } else {
+            Some(key)
+        });
+    }
+    Ok(keys)
+}
+
+/// Integral floats hash like the equal integer so `1 == 1.0` keys meet.
+fn normalize_key(value: Value) -> Value {
+    match value {
+        Value::Float(float) if float.fract() == 0.0 && float.abs() < 1e38 => {
+            Value::Integer(float as i128)
+        }
+        value => value,
+    }
+}
+
+fn keys_equal(left: &Option<Vec<Value>>, right: &Option<Vec<Value>>) -> bool {
+    match (left, right) {
+        (Some(left), Some(right)) => compare_keys(left, right) == Ordering::Equal,
+        _ => false,
+    }
+}
+
+fn compare_keys(left: &[Value], right: &[Value]) -> Ordering {
+    left.iter()
+        .zip(right)
+        .map(|(left, right)| left.sort_cmp(right))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+fn sorted_positions(keys: &[Option<Vec<Value>>]) -> Vec<usize> {
+    let mut positions: Vec<usize> = (0..keys.len()).filter(|i| keys[*i].is_some()).collect();
+    positions.sort_by(|a, b| compare_keys(keys[*a].as_ref().unwrap(), keys[*b].as_ref().unwrap()));
+    positions
+}
+
+fn run_end(keys: &[Option<Vec<Value>>], order: &[usize], start: usize) -> usize {
+    let first = keys[order[start]].as_ref().unwrap();
+    let mut end = start + 1;
+    while end < order.len()
+        && compare_keys(first, keys[order[end]].as_ref().unwrap()) == Ordering::Equal
+    {
+        end += 1;
+    }
+    end
+}
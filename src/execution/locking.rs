@@ -0,0 +1,79 @@
+//! The row locks of `SELECT ... FOR UPDATE` and `FOR SHARE`.
+//!
+//! A locking SELECT reads its locked tables with the id of each row, and
+//! returns the ids after its own columns. Rows are then locked in the
+//! order the query returns them, only as many as LIMIT and OFFSET keep,
+//! so that under SKIP LOCKED the rows others hold make way for the next
+//! ones rather than for fewer rows.
+
+use crate::compiler::parser::{LockingClause, SelectQuery, TableReference};
+use crate::execution::error::ExecutionError;
+use crate::execution::planner::TableProvider;
+use crate::execution::relation::Relation;
+
+/// The base tables of the FROM clause of `select` whose rows `locking`
+/// locks, each as its qualifier and name, in the order they appear.
+pub fn locked_tables(select: &SelectQuery, locking: &LockingClause) -> Vec<(String, String)> {
+    let mut tables = Vec::new();
+    for reference in &select.from {
+        collect_tables(reference, locking, &mut tables);
+    }
+    tables
+}
+
+fn collect_tables(
+    reference: &TableReference,
+    locking: &LockingClause,
+    tables: &mut Vec<(String, String)>,
+) {
+    match reference {
+        TableReference::Table { name, alias } => {
+            let qualifier = alias.as_deref().unwrap_or(name);
+            if locking.tables.is_empty() || locking.tables.iter().any(|table| table == qualifier) {
+                tables.push((qualifier.to_string(), name.clone()));
+            }
+        }
+        TableReference::Join { left, right, .. } => {
+            collect_tables(left, locking, tables);
+            collect_tables(right, locking, tables);
+        }
+        TableReference::Subquery { .. } => {}
+    }
+}
+
+/// Locks the rows of `relation`, the result of `select` with the ids of
+/// its locked tables at the end of each row, in order until `wanted` rows
+/// are locked. Rows SKIP LOCKED leaves out are dropped, and so are the
+/// ids from the rows kept.
+pub fn lock_rows(
+    relation: Relation,
+    select: &SelectQuery,
+    locking: &LockingClause,
+    wanted: Option<usize>,
+    provider: &dyn TableProvider,
+) -> Result<Relation, ExecutionError> {
+    let tables = locked_tables(select, locking);
+    let width = relation.schema.columns.len() - tables.len();
+    let mut rows = Vec::new();
+    for mut row in relation.rows {
+        if wanted.is_some_and(|wanted| rows.len() >= wanted) {
+            break;
+        }
+        let mut locked = true;
+        for ((_, table), id) in tables.iter().zip(&row[width..]) {
+            // The side of an outer join that found no row has nothing
+            // to lock.
+            if !id.is_null() && !provider.lock_row(table, id, locking)? {
+                locked = false;
+                break;
+            }
+        }
+        if locked {
+            row.truncate(width);
+            rows.push(row);
+        }
+    }
+    let mut schema = relation.schema;
+    schema.columns.truncate(width);
+    Ok(Relation::new(schema, rows))
+}
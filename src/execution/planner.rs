@@ -0,0 +1,317 @@
+use crate::compiler::parser::{
+    JoinConstraint, JoinType, LockingClause, SQLExpression, TableReference,
+};
+use crate::compiler::walk::{column_name, column_references};
+use crate::execution::aggregate::DEFAULT_WORK_MEMORY;
+use crate::execution::cte::DEFAULT_RECURSION_LIMIT;
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::{Context, Schema};
+use crate::execution::function::current_time;
+use crate::execution::join::{Join, JoinStrategy};
+use crate::execution::query::execute_query;
+use crate::execution::relation::{Relation, RowStream, Rows};
+use crate::types::function::ScalarFunction;
+use crate::types::value::Value;
+
+/// Below this many row pairs a nested loop is cheaper than building anything.
+const NESTED_LOOP_MAX_PAIRS: usize = 1024;
+/// Largest build side the hash join is allowed to keep in memory.
+const HASH_JOIN_MAX_BUILD_ROWS: usize = 1 << 20;
+
+/// The column a table read for a locking SELECT ends with, identifying
+/// each row to [`TableProvider::lock_row`]. No name written in a query
+/// can refer to it.
+pub const ROW_ID: &str = "#row";
+
+/// Source of base table rows for the planner.
+pub trait TableProvider {
+    /// Returns the column names of `table` and all of its rows, read as
+    /// they are used.
+    fn scan(&self, table: &str) -> Result<(Vec<String>, Rows<'_>), ExecutionError>;
+
+    /// Returns the column names of `table`, read as `qualifier`, and at
+    /// least the rows that satisfy every one of `conditions`. The caller
+    /// still filters them, so a provider without indexes returns them all.
+    fn scan_where(
+        &self,
+        table: &str,
+        _qualifier: &str,
+        _conditions: &[&SQLExpression],
+    ) -> Result<(Vec<String>, Rows<'_>), ExecutionError> {
+        self.scan(table)
+    }
+
+    /// Like `scan_where`, for a SELECT that locks the rows it returns: the
+    /// columns end with [`ROW_ID`].
+    fn scan_for_lock(
+        &self,
+        table: &str,
+        _qualifier: &str,
+        _conditions: &[&SQLExpression],
+    ) -> Result<(Vec<String>, Rows<'_>), ExecutionError> {
+        Err(ExecutionError::Unsupported(format!(
+            "the rows of \"{}\" cannot be locked",
+            table
+        )))
+    }
+
+    /// Locks the row of `table` that `row`, its [`ROW_ID`] value, names,
+    /// as `locking` says. Returns whether it did, which is only false
+    /// under SKIP LOCKED.
+    fn lock_row(
+        &self,
+        table: &str,
+        _row: &Value,
+        _locking: &LockingClause,
+    ) -> Result<bool, ExecutionError> {
+        Err(ExecutionError::Unsupported(format!(
+            "the rows of \"{}\" cannot be locked",
+            table
+        )))
+    }
+
+    /// The time the running statement started, which NOW() returns.
+    fn now(&self) -> i64 {
+        current_time()
+    }
+
+    /// Bytes a hash aggregate or an external sort may keep in memory
+    /// before it spills.
+    fn work_memory(&self) -> usize {
+        DEFAULT_WORK_MEMORY
+    }
+
+    /// Rounds a recursive CTE may take before it fails.
+    fn recursion_limit(&self) -> usize {
+        DEFAULT_RECURSION_LIMIT
+    }
+
+    /// Runs `nextval`, `currval` or `setval` with arguments already
+    /// checked against its signature, none of them NULL.
+    fn call_sequence_function(
+        &self,
+        _function: ScalarFunction,
+        _arguments: Vec<Value>,
+    ) -> Result<Value, ExecutionError> {
+        Err(ExecutionError::Unsupported(
+            "sequences are not available here".to_string(),
+        ))
+    }
+}
+
+/// Evaluates a FROM clause into a single relation. Comma separated items are
+/// cross joined left to right. A lone base table is read through
+/// `conditions`, the conjuncts of the WHERE clause, so that an index can
+/// serve them, and its rows come as they are read. The base tables read
+/// as one of `locked` come with the ids of their rows, to be locked.
+pub fn evaluate_from<'a>(
+    from: &[TableReference],
+    conditions: &[&SQLExpression],
+    locked: &[String],
+    context: &Context<'a>,
+) -> Result<RowStream<'a>, ExecutionError> {
+    if let [TableReference::Table { name, alias }] = from {
+        let qualifier = alias.as_deref().unwrap_or(name);
+        let provider = context.provider()?;
+        let (columns, rows) = if locked.iter().any(|locked| locked == qualifier) {
+            provider.scan_for_lock(name, qualifier, conditions)?
+        } else if !conditions.is_empty() {
+            provider.scan_where(name, qualifier, conditions)?
+        } else {
+            provider.scan(name)?
+        };
+        return Ok(RowStream::new(Schema::for_table(qualifier, &columns), rows));
+    }
+    let mut items = from.iter();
+    let mut relation = match items.next() {
+        Some(item) => evaluate_table_reference(item, locked, context)?,
+        None => return Err(ExecutionError::Unsupported("empty FROM clause".to_string())),
+    };
+    for item in items {
+        let right = evaluate_table_reference(item, locked, context)?;
+        let join = plan_join(
+            JoinType::Cross,
+            &JoinConstraint::None,
+            &relation.schema,
+            &right.schema,
+        )?;
+        relation = join.execute(JoinStrategy::NestedLoop, relation, right, context)?;
+    }
+    Ok(relation.into())
+}
+
+fn evaluate_table_reference(
+    reference: &TableReference,
+    locked: &[String],
+    context: &Context,
+) -> Result<Relation, ExecutionError> {
+    match reference {
+        TableReference::Table { name, alias } => {
+            let qualifier = alias.as_deref().unwrap_or(name);
+            let (columns, rows) = if locked.iter().any(|locked| locked == qualifier) {
+                context.provider()?.scan_for_lock(name, qualifier, &[])?
+            } else {
+                context.provider()?.scan(name)?
+            };
+            RowStream::new(Schema::for_table(qualifier, &columns), rows).materialize()
+        }
+        TableReference::Subquery { query, alias } => {
+            let result = execute_query(query, context.provider()?, None)?;
+            let names: Vec<String> = result.schema.columns.iter().map(column_name).collect();
+            Ok(Relation::new(Schema::for_table(alias, &names), result.rows))
+        }
+        TableReference::Join {
+            join_type,
+            left,
+            right,
+            constraint,
+        } => {
+            let left = evaluate_table_reference(left, locked, context)?;
+            let right = evaluate_table_reference(right, locked, context)?;
+            let join = plan_join(*join_type, constraint, &left.schema, &right.schema)?;
+            let strategy = choose_join_strategy(&join, left.rows.len(), right.rows.len());
+            join.execute(strategy, left, right, context)
+        }
+    }
+}
+
+/// Splits a join constraint into equi-join keys and a residual condition.
+pub fn plan_join(
+    join_type: JoinType,
+    constraint: &JoinConstraint,
+    left: &Schema,
+    right: &Schema,
+) -> Result<Join, ExecutionError> {
+    let mut join = Join {
+        join_type,
+        left_keys: Vec::new(),
+        right_keys: Vec::new(),
+        residual: None,
+        using: Vec::new(),
+    };
+    match constraint {
+        JoinConstraint::None => {}
+        JoinConstraint::On(condition) => {
+            let mut residual = Vec::new();
+            for conjunct in conjuncts(condition) {
+                match equi_join_key(conjunct, left, right)? {
+                    Some((left_key, right_key)) => {
+                        join.left_keys.push(left_key);
+                        join.right_keys.push(right_key);
+                    }
+                    None => residual.push(conjunct.clone()),
+                }
+            }
+            join.residual =
+                residual
+                    .into_iter()
+                    .reduce(|left, right| SQLExpression::BinaryExpression {
+                        operator: "AND".to_string(),
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    });
+        }
+        JoinConstraint::Using(columns) => join.using = columns.clone(),
+        JoinConstraint::Natural => {
+            let right_names = column_names(right);
+            join.using = column_names(left)
+                .into_iter()
+                .filter(|name| right_names.contains(name))
+                .collect();
+        }
+    }
+    for name in join.using.clone() {
+        let column = SQLExpression::Identifier(name.clone());
+        let left_key = left
+            .resolve(&column)?
+            .map(|position| left.columns[position].clone());
+        let right_key = right
+            .resolve(&column)?
+            .map(|position| right.columns[position].clone());
+        match (left_key, right_key) {
+            (Some(left_key), Some(right_key)) => {
+                join.left_keys.push(left_key);
+                join.right_keys.push(right_key);
+            }
+            _ => return Err(ExecutionError::UnknownColumn(name)),
+        }
+    }
+    Ok(join)
+}
+
+pub fn choose_join_strategy(join: &Join, left_rows: usize, right_rows: usize) -> JoinStrategy {
+    if join.left_keys.is_empty() || left_rows.saturating_mul(right_rows) <= NESTED_LOOP_MAX_PAIRS {
+        JoinStrategy::NestedLoop
+    } else if right_rows <= HASH_JOIN_MAX_BUILD_ROWS {
+        JoinStrategy::Hash
+    } else {
+        JoinStrategy::SortMerge
+    }
+}
+
+pub fn conjuncts(condition: &SQLExpression) -> Vec<&SQLExpression> {
+    match condition {
+        SQLExpression::BinaryExpression {
+            operator,
+            left,
+            right,
+        } if operator == "AND" => {
+            let mut result = conjuncts(left);
+            result.extend(conjuncts(right));
+            result
+        }
+        _ => vec![condition],
+    }
+}
+
+/// Recognizes `l == r` where one side only reads the left input and the
+/// other only the right input.
+fn equi_join_key(
+    conjunct: &SQLExpression,
+    left: &Schema,
+    right: &Schema,
+) -> Result<Option<(SQLExpression, SQLExpression)>, ExecutionError> {
+    if let SQLExpression::BinaryExpression {
+        operator,
+        left: first,
+        right: second,
+    } = conjunct
+    {
+        if operator == "==" {
+            if reads_only(first, left)? && reads_only(second, right)? {
+                return Ok(Some((*first.clone(), *second.clone())));
+            }
+            if reads_only(second, left)? && reads_only(first, right)? {
+                return Ok(Some((*second.clone(), *first.clone())));
+            }
+        }
+    }
+    Ok(None)
+}
+
+pub fn reads_only(expression: &SQLExpression, schema: &Schema) -> Result<bool, ExecutionError> {
+    let mut references = Vec::new();
+    column_references(expression, &mut references);
+    if references.is_empty() {
+        return Ok(false);
+    }
+    for reference in references {
+        if schema.resolve(reference)?.is_none() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn column_names(schema: &Schema) -> Vec<String> {
+    schema
+        .columns
+        .iter()
+        .filter_map(|column| match column {
+            SQLExpression::Identifier(name) => Some(name.clone()),
+            SQLExpression::QualifiedIdentifier { column, .. } => Some(column.clone()),
+            _ => None,
+        })
+        .filter(|name| name != ROW_ID)
+        .collect()
+}
@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::compiler::parser::{Query, SQLExpression, SetExpression, SetOperator};
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::{Context, Scope};
+use crate::execution::locking::lock_rows;
+use crate::execution::planner::TableProvider;
+use crate::execution::relation::Relation;
+use crate::execution::select::execute_select;
+use crate::execution::sort::{compare_keys, sort_key};
+use crate::types::value::Value;
+
+/// Runs a complete query: the set expression, then ORDER BY, OFFSET and LIMIT.
+pub fn execute_query(
+    query: &Query,
+    provider: &dyn TableProvider,
+    outer: Option<&Scope>,
+) -> Result<Relation, ExecutionError> {
+    let offset = query.offset.unwrap_or(0) as usize;
+    let mut relation = match &query.body {
+        // A single SELECT sorts before projecting, so it can order by
+        // columns it does not return.
+        SetExpression::Select(select) => {
+            let relation = execute_select(select, &query.order_by, provider, outer)?;
+            match &select.locking {
+                Some(locking) => {
+                    let wanted = query.limit.map(|limit| offset + limit as usize);
+                    lock_rows(relation, select, locking, wanted, provider)?
+                }
+                None => relation,
+            }
+        }
+        body => {
+            let relation = execute_set_expression(body, provider, outer)?;
+            let context = Context {
+                provider: Some(provider),
+                outer,
+                now: provider.now(),
+            };
+            let mut keys = Vec::with_capacity(query.order_by.len());
+            for item in &query.order_by {
+                let key = match ordinal(&item.expression, relation.schema.columns.len())? {
+                    Some(position) => SortKey::Position(position),
+                    None => SortKey::Expression(item.expression.clone()),
+                };
+                keys.push((key, item.ascending));
+            }
+            sort_relation(relation, &keys, &context)?
+        }
+    };
+    if offset > 0 {
+        relation.rows.drain(..offset.min(relation.rows.len()));
+    }
+    if let Some(limit) = query.limit {
+        relation.rows.truncate(limit as usize);
+    }
+    Ok(relation)
+}
+
+pub fn execute_set_expression(
+    expression: &SetExpression,
+    provider: &dyn TableProvider,
+    outer: Option<&Scope>,
+) -> Result<Relation, ExecutionError> {
+    match expression {
+        SetExpression::Select(select) => execute_select(select, &[], provider, outer),
+        SetExpression::Query(query) => execute_query(query, provider, outer),
+        SetExpression::Operation {
+            operator,
+            all,
+            left,
+            right,
+        } => {
+            let left = execute_set_expression(left, provider, outer)?;
+            let right = execute_set_expression(right, provider, outer)?;
+            Ok(set_operation(*operator, *all, left, right))
+        }
+    }
+}
+
+/// Combines two results. Rows are compared as a whole with NULLs equal to
+/// each other; the ALL forms keep duplicates by multiplicity. The binder has
+/// already matched the column counts and types of both sides.
+pub fn set_operation(
+    operator: SetOperator,
+    all: bool,
+    left: Relation,
+    right: Relation,
+) -> Relation {
+    let schema = left.schema;
+    let rows = match operator {
+        SetOperator::Union => {
+            let mut rows = left.rows;
+            rows.extend(right.rows);
+            if !all {
+                rows = distinct(rows);
+            }
+            rows
+        }
+        SetOperator::Intersect | SetOperator::Except => {
+            let mut counts: HashMap<Vec<Value>, usize> = HashMap::new();
+            for row in right.rows {
+                *counts.entry(row).or_default() += 1;
+            }
+            let rows = if all { left.rows } else { distinct(left.rows) };
+            rows.into_iter()
+                .filter(|row| {
+                    let available = counts.get_mut(row);
+                    let matched = match available {
+                        Some(count) if *count > 0 => {
+                            if all {
+                                *count -= 1;
+                            }
+                            true
+                        }
+                        _ => false,
+                    };
+                    matched == (operator == SetOperator::Intersect)
+                })
+                .collect()
+        }
+    };
+    Relation::new(schema, rows)
+}
+
+fn distinct(rows: Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+    let mut seen = HashSet::new();
+    rows.into_iter()
+        .filter(|row| seen.insert(row.clone()))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortKey {
+    Position(usize),
+    Expression(SQLExpression),
+}
+
+/// Sorts rows by `(key, ascending)` pairs; the sort is stable.
+pub fn sort_relation(
+    relation: Relation,
+    keys: &[(SortKey, bool)],
+    context: &Context,
+) -> Result<Relation, ExecutionError> {
+    if keys.is_empty() {
+        return Ok(relation);
+    }
+    let mut keyed = Vec::with_capacity(relation.rows.len());
+    for row in relation.rows {
+        keyed.push((sort_key(keys, &relation.schema, &row, context)?, row));
+    }
+    keyed.sort_by(|(left, _), (right, _)| compare_keys(keys, left, right));
+    Ok(Relation::new(
+        relation.schema,
+        keyed.into_iter().map(|(_, row)| row).collect(),
+    ))
+}
+
+/// `ORDER BY 1`: the one-based position of an output column, turned into
+/// a zero-based index.
+pub fn ordinal(expression: &SQLExpression, width: usize) -> Result<Option<usize>, ExecutionError> {
+    match expression {
+        SQLExpression::Literal(literal) => match Value::from_literal(literal)? {
+            Value::Integer(position) if position >= 1 && position as usize <= width => {
+                Ok(Some(position as usize - 1))
+            }
+            Value::Integer(position) => Err(ExecutionError::Unsupported(format!(
+                "ORDER BY position {} is not in select list",
+                position
+            ))),
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,46 @@
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::Schema;
+use crate::types::value::Value;
+
+/// A materialized intermediate result: rows together with the schema that
+/// describes them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relation {
+    pub schema: Schema,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Relation {
+    pub fn new(schema: Schema, rows: Vec<Vec<Value>>) -> Self {
+        Relation { schema, rows }
+    }
+}
+
+/// Rows produced one at a time, such as those of a table read a page at
+/// a time.
+pub type Rows<'a> = Box<dyn Iterator<Item = Result<Vec<Value>, ExecutionError>> + 'a>;
+
+/// An intermediate result whose rows are produced as they are used, so
+/// that an operator that needs one row at a time holds no more.
+pub struct RowStream<'a> {
+    pub schema: Schema,
+    pub rows: Rows<'a>,
+}
+
+impl<'a> RowStream<'a> {
+    pub fn new(schema: Schema, rows: Rows<'a>) -> Self {
+        RowStream { schema, rows }
+    }
+
+    /// Reads every row, for an operator that needs them all at once.
+    pub fn materialize(self) -> Result<Relation, ExecutionError> {
+        let rows = self.rows.collect::<Result<_, _>>()?;
+        Ok(Relation::new(self.schema, rows))
+    }
+}
+
+impl From<Relation> for RowStream<'_> {
+    fn from(relation: Relation) -> Self {
+        RowStream::new(relation.schema, Box::new(relation.rows.into_iter().map(Ok)))
+    }
+}
@@ -0,0 +1,473 @@
+use crate::compiler::parser::{
+    JoinConstraint, JoinType, OrderByItem, Query, SQLExpression, SelectQuantifier, SelectQuery,
+    SetExpression,
+};
+use crate::compiler::walk::{
+    collect_aggregates, collect_window_functions, column_name, column_references,
+    contains_subquery, window_expressions,
+};
+use crate::execution::aggregate::HashAggregate;
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::{evaluate, is_satisfied, Context, Schema, Scope};
+use crate::execution::function::check_functions;
+use crate::execution::join::Join;
+use crate::execution::locking::locked_tables;
+use crate::execution::planner::{
+    choose_join_strategy, conjuncts, evaluate_from, plan_join, reads_only, TableProvider, ROW_ID,
+};
+use crate::execution::query::{ordinal, SortKey};
+use crate::execution::relation::{Relation, RowStream};
+use crate::execution::sort::ExternalSort;
+use crate::execution::window::WindowOperator;
+use crate::types::value::Value;
+
+/// Runs a SELECT: FROM, WHERE, grouping and aggregation, HAVING, window
+/// functions, DISTINCT, ORDER BY and the final projection. `outer` is the
+/// current row of the enclosing query when this is a subquery. A locking
+/// SELECT returns the ids of the rows to lock after its columns.
+pub fn execute_select(
+    query: &SelectQuery,
+    order_by: &[OrderByItem],
+    provider: &dyn TableProvider,
+    outer: Option<&Scope>,
+) -> Result<Relation, ExecutionError> {
+    check_select(query, order_by)?;
+    let context = Context {
+        provider: Some(provider),
+        outer,
+        now: provider.now(),
+    };
+    let restrictions = query.conditions.as_ref().map(conjuncts).unwrap_or_default();
+    let locked: Vec<String> = match &query.locking {
+        Some(locking) => locked_tables(query, locking)
+            .into_iter()
+            .map(|(qualifier, _)| qualifier)
+            .collect(),
+        None => Vec::new(),
+    };
+    let mut relation = evaluate_from(&query.from, &restrictions, &locked, &context)?;
+    if let Some(conditions) = &query.conditions {
+        relation = apply_where(relation, conditions, &context)?;
+    }
+
+    let mut expressions = Vec::new();
+    for column in &query.columns {
+        if *column == SQLExpression::Wildcard {
+            expressions.extend(visible_columns(&relation.schema));
+        } else {
+            expressions.push(column.clone());
+        }
+    }
+    let mut order_keys = Vec::with_capacity(order_by.len());
+    for item in order_by {
+        order_keys.push(order_expression(&item.expression, &expressions)?);
+    }
+    let mut distinct_keys = Vec::new();
+    if let SelectQuantifier::DistinctOn(keys) = &query.quantifier {
+        for key in keys {
+            distinct_keys.push(order_expression(key, &expressions)?);
+        }
+    }
+    expressions.extend(
+        locked
+            .into_iter()
+            .map(|table| SQLExpression::QualifiedIdentifier {
+                table,
+                column: ROW_ID.to_string(),
+            }),
+    );
+
+    let mut aggregates = Vec::new();
+    for expression in expressions.iter().chain(&order_keys).chain(&distinct_keys) {
+        collect_aggregates(expression, &mut aggregates);
+    }
+    if let Some(having) = &query.having {
+        collect_aggregates(having, &mut aggregates);
+    }
+    for window in &query.windows {
+        for expression in window_expressions(&window.specification) {
+            collect_aggregates(expression, &mut aggregates);
+        }
+    }
+    if !query.group_by.is_empty() || !aggregates.is_empty() {
+        let aggregate = HashAggregate::new(query.group_by.clone(), aggregates)?
+            .with_memory(provider.work_memory());
+        let schema = aggregate.output_schema(&relation.schema);
+        let rows = aggregate.execute(&relation.schema, relation.rows, &context)?;
+        relation = Relation::new(schema, rows).into();
+    }
+    if let Some(having) = &query.having {
+        relation = filter(relation, vec![having], &context);
+    }
+
+    let mut windows = Vec::new();
+    for expression in expressions.iter().chain(&order_keys).chain(&distinct_keys) {
+        collect_window_functions(expression, &mut windows);
+    }
+    if !windows.is_empty() {
+        let window = WindowOperator::new(windows, &query.windows)?;
+        let schema = window.output_schema(&relation.schema);
+        let input = relation.materialize()?;
+        let rows = window.execute(&input.schema, input.rows, &context)?;
+        relation = Relation::new(schema, rows).into();
+    }
+
+    let mut sort_keys: Vec<(SortKey, bool)> = order_keys
+        .into_iter()
+        .zip(order_by)
+        .map(|(key, item)| (SortKey::Expression(key), item.ascending))
+        .collect();
+    match &query.quantifier {
+        SelectQuantifier::All => {}
+        // Grouping by the whole select list leaves one row per distinct
+        // result, and the hash aggregate spills when there are many.
+        SelectQuantifier::Distinct => {
+            let outputs: Vec<SQLExpression> = expressions.iter().map(strip_alias).collect();
+            for (key, _) in &sort_keys {
+                if let SortKey::Expression(key) = key {
+                    if !outputs.contains(key) {
+                        return Err(ExecutionError::InvalidQuery(
+                            "for SELECT DISTINCT, ORDER BY expressions must appear in select list"
+                                .to_string(),
+                        ));
+                    }
+                }
+            }
+            let aggregate =
+                HashAggregate::new(outputs, Vec::new())?.with_memory(provider.work_memory());
+            let schema = aggregate.output_schema(&relation.schema);
+            let rows = aggregate.execute(&relation.schema, relation.rows, &context)?;
+            relation = Relation::new(schema, rows).into();
+        }
+        // Once rows are sorted with the DISTINCT ON keys leading, each group
+        // is a run and its first row is the one to keep; the sort spills
+        // like the hash aggregate does.
+        SelectQuantifier::DistinctOn(_) => {
+            if sort_keys.is_empty() {
+                sort_keys = distinct_keys
+                    .iter()
+                    .map(|key| (SortKey::Expression(key.clone()), true))
+                    .collect();
+            }
+            let leading_match = sort_keys.len() >= distinct_keys.len()
+                && sort_keys[..distinct_keys.len()].iter().all(|(key, _)| {
+                    matches!(key, SortKey::Expression(key) if distinct_keys.contains(key))
+                });
+            if !leading_match {
+                return Err(ExecutionError::InvalidQuery(
+                    "SELECT DISTINCT ON expressions must match initial ORDER BY expressions"
+                        .to_string(),
+                ));
+            }
+            let sort = ExternalSort::new(sort_keys).with_memory(provider.work_memory());
+            let sorted = sort.execute(relation, &context)?;
+            let firsts = first_of_runs(sorted, &distinct_keys, &context);
+            return project(&expressions, firsts, &context);
+        }
+    }
+    if !sort_keys.is_empty() {
+        let sort = ExternalSort::new(sort_keys).with_memory(provider.work_memory());
+        relation = sort.execute(relation, &context)?;
+    }
+    project(&expressions, relation, &context)
+}
+
+fn strip_alias(expression: &SQLExpression) -> SQLExpression {
+    match expression {
+        SQLExpression::Alias { expression, .. } => *expression.clone(),
+        expression => expression.clone(),
+    }
+}
+
+/// Keeps the first row of each run of rows with equal `keys`.
+fn first_of_runs<'a>(
+    relation: RowStream<'a>,
+    keys: &'a [SQLExpression],
+    context: &'a Context,
+) -> RowStream<'a> {
+    let schema = relation.schema.clone();
+    let mut previous: Option<Vec<Value>> = None;
+    let rows = relation.rows.filter_map(move |row| {
+        let row = match row {
+            Ok(row) => row,
+            Err(error) => return Some(Err(error)),
+        };
+        let mut key = Vec::with_capacity(keys.len());
+        for expression in keys {
+            match evaluate(expression, &schema, &row, context) {
+                Ok(value) => key.push(value),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+        if previous.as_ref() == Some(&key) {
+            return None;
+        }
+        previous = Some(key);
+        Some(Ok(row))
+    });
+    RowStream::new(relation.schema, Box::new(rows))
+}
+
+fn check_select(query: &SelectQuery, order_by: &[OrderByItem]) -> Result<(), ExecutionError> {
+    let clauses = query
+        .columns
+        .iter()
+        .chain(match &query.quantifier {
+            SelectQuantifier::DistinctOn(keys) => keys.as_slice(),
+            _ => &[],
+        })
+        .chain(&query.conditions)
+        .chain(&query.group_by)
+        .chain(&query.having)
+        .chain(order_by.iter().map(|item| &item.expression))
+        .chain(
+            query
+                .windows
+                .iter()
+                .flat_map(|window| window_expressions(&window.specification)),
+        );
+    for expression in clauses {
+        check_functions(expression)?;
+    }
+    Ok(())
+}
+
+/// ORDER BY may name a select list alias or position; both stand for the
+/// expression behind them.
+fn order_expression(
+    expression: &SQLExpression,
+    select_list: &[SQLExpression],
+) -> Result<SQLExpression, ExecutionError> {
+    let target = match ordinal(expression, select_list.len())? {
+        Some(position) => &select_list[position],
+        None => match expression {
+            SQLExpression::Identifier(name) => select_list
+                .iter()
+                .find(|item| matches!(item, SQLExpression::Alias { alias, .. } if alias == name))
+                .unwrap_or(expression),
+            _ => expression,
+        },
+    };
+    Ok(strip_alias(target))
+}
+
+/// Keeps the rows for which every condition is TRUE, as they come.
+pub fn filter<'a>(
+    relation: RowStream<'a>,
+    conditions: Vec<&'a SQLExpression>,
+    context: &'a Context,
+) -> RowStream<'a> {
+    if conditions.is_empty() {
+        return relation;
+    }
+    let schema = relation.schema.clone();
+    let rows = relation.rows.filter_map(move |row| {
+        let row = match row {
+            Ok(row) => row,
+            Err(error) => return Some(Err(error)),
+        };
+        for condition in &conditions {
+            match is_satisfied(condition, &schema, &row, context) {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+        Some(Ok(row))
+    });
+    RowStream::new(relation.schema, Box::new(rows))
+}
+
+/// Evaluates the select list, with any `*` already expanded.
+pub fn project(
+    expressions: &[SQLExpression],
+    relation: RowStream,
+    context: &Context,
+) -> Result<Relation, ExecutionError> {
+    let mut rows = Vec::new();
+    for row in relation.rows {
+        let row = row?;
+        let mut projected = Vec::with_capacity(expressions.len());
+        for expression in expressions {
+            projected.push(evaluate(expression, &relation.schema, &row, context)?);
+        }
+        rows.push(projected);
+    }
+    let names: Vec<String> = expressions.iter().map(column_name).collect();
+    Ok(Relation::new(Schema::from_names(&names), rows))
+}
+
+/// Columns joined with USING appear once, unqualified, so their qualified
+/// copies are left out of `*`, as are the ids of rows to lock.
+fn visible_columns(schema: &Schema) -> Vec<SQLExpression> {
+    schema
+        .columns
+        .iter()
+        .filter(|column| match column {
+            SQLExpression::QualifiedIdentifier { column, .. } => {
+                column != ROW_ID
+                    && !schema
+                        .columns
+                        .contains(&SQLExpression::Identifier(column.clone()))
+            }
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Filters by the WHERE clause, turning `EXISTS`, `NOT EXISTS` and `IN`
+/// subqueries at the top level into semi and anti joins where possible so
+/// the subquery runs once instead of once per row.
+fn apply_where<'a>(
+    mut relation: RowStream<'a>,
+    conditions: &'a SQLExpression,
+    context: &'a Context,
+) -> Result<RowStream<'a>, ExecutionError> {
+    let mut remaining = Vec::new();
+    for conjunct in conjuncts(conditions) {
+        match decorrelate(conjunct, &relation.schema, context)? {
+            Some((join, inner)) => {
+                let outer = relation.materialize()?;
+                let strategy = choose_join_strategy(&join, outer.rows.len(), inner.rows.len());
+                relation = join.execute(strategy, outer, inner, context)?.into();
+            }
+            None => remaining.push(conjunct),
+        }
+    }
+    Ok(filter(relation, remaining, context))
+}
+
+fn decorrelate(
+    conjunct: &SQLExpression,
+    outer: &Schema,
+    context: &Context,
+) -> Result<Option<(Join, Relation)>, ExecutionError> {
+    let (subquery, join_type, mut correlation) = match conjunct {
+        SQLExpression::Exists { subquery, negated } => (
+            subquery,
+            if *negated {
+                JoinType::Anti
+            } else {
+                JoinType::Semi
+            },
+            Vec::new(),
+        ),
+        // NOT IN is left alone: a NULL among the candidates makes it
+        // UNKNOWN for every row, which an anti join cannot express.
+        SQLExpression::InSubquery {
+            expression,
+            subquery,
+            negated: false,
+        } => {
+            let select = match simple_select(subquery) {
+                Some(select) => select,
+                None => return Ok(None),
+            };
+            let item = match select.columns.as_slice() {
+                [SQLExpression::Alias { expression, .. }] => expression.as_ref(),
+                [SQLExpression::Wildcard] => return Ok(None),
+                [item] => item,
+                _ => return Ok(None),
+            };
+            if contains_subquery(expression) || contains_subquery(item) {
+                return Ok(None);
+            }
+            let equality = SQLExpression::BinaryExpression {
+                operator: "==".to_string(),
+                left: expression.clone(),
+                right: Box::new(item.clone()),
+            };
+            (subquery, JoinType::Semi, vec![equality])
+        }
+        _ => return Ok(None),
+    };
+
+    let subquery = match simple_select(subquery) {
+        Some(select) => select,
+        None => return Ok(None),
+    };
+    let mut aggregates = Vec::new();
+    for column in &subquery.columns {
+        collect_aggregates(column, &mut aggregates);
+    }
+    if !subquery.group_by.is_empty() || subquery.having.is_some() || !aggregates.is_empty() {
+        return Ok(None);
+    }
+
+    let inner = evaluate_from(&subquery.from, &[], &[], context)?;
+    let mut filters = Vec::new();
+    if let Some(conditions) = &subquery.conditions {
+        for condition in conjuncts(conditions) {
+            if contains_subquery(condition) {
+                return Ok(None);
+            }
+            let mut references = Vec::new();
+            column_references(condition, &mut references);
+            if references.is_empty() || reads_only(condition, &inner.schema)? {
+                filters.push(condition);
+            } else {
+                correlation.push(condition.clone());
+            }
+        }
+    }
+    let combined = outer.concat(&inner.schema);
+    for condition in &correlation {
+        if !scoped_consistently(condition, outer, &inner.schema, &combined)? {
+            return Ok(None);
+        }
+    }
+
+    let inner = filter(inner, filters, context).materialize()?;
+    let constraint =
+        match correlation
+            .into_iter()
+            .reduce(|left, right| SQLExpression::BinaryExpression {
+                operator: "AND".to_string(),
+                left: Box::new(left),
+                right: Box::new(right),
+            }) {
+            Some(condition) => JoinConstraint::On(condition),
+            None => JoinConstraint::None,
+        };
+    let join = plan_join(join_type, &constraint, outer, &inner.schema)?;
+    Ok(Some((join, inner)))
+}
+
+/// The single SELECT block of a query without LIMIT or OFFSET; ORDER BY
+/// does not matter to EXISTS and IN.
+fn simple_select(query: &Query) -> Option<&SelectQuery> {
+    match &query.body {
+        SetExpression::Select(select) if query.limit.is_none() && query.offset.is_none() => {
+            Some(select)
+        }
+        _ => None,
+    }
+}
+
+/// A correlated condition can move into a join only if every column it
+/// reads resolves, in the joined schema, to the same side SQL scoping would
+/// pick: the subquery's own tables first, then the outer query.
+fn scoped_consistently(
+    condition: &SQLExpression,
+    outer: &Schema,
+    inner: &Schema,
+    combined: &Schema,
+) -> Result<bool, ExecutionError> {
+    let mut references = Vec::new();
+    column_references(condition, &mut references);
+    for reference in references {
+        let expect_inner = if inner.resolve(reference)?.is_some() {
+            true
+        } else if outer.resolve(reference)?.is_some() {
+            false
+        } else {
+            return Ok(false);
+        };
+        match combined.resolve(reference) {
+            Ok(Some(position)) if (position >= outer.columns.len()) == expect_inner => {}
+            _ => return Ok(false),
+        }
+    }
+    Ok(true)
+}
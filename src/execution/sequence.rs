@@ -0,0 +1,172 @@
+//! `nextval`, `currval` and `setval`.
+
+use std::collections::HashMap;
+
+use crate::execution::error::ExecutionError;
+use crate::storage::catalog::SequenceSchema;
+use crate::storage::engine::StorageEngine;
+use crate::storage::page::{corrupt, PageId};
+use crate::storage::sequence::SequenceState;
+
+/// Where a sequence is, and how many values after that its page already
+/// accounts for, so that they are handed out without writing it.
+struct Cached {
+    state: SequenceState,
+    remaining: i128,
+}
+
+/// The sequences used since the data file was opened, by database and
+/// name, shared by every session.
+#[derive(Default)]
+pub struct Sequences {
+    cached: HashMap<(String, String), Cached>,
+}
+
+/// The value `nextval` last returned for each sequence in one session.
+#[derive(Default)]
+pub struct CurrentValues {
+    values: HashMap<(String, String), i128>,
+}
+
+impl CurrentValues {
+    pub fn set(&mut self, sequence: &SequenceSchema, value: i128) {
+        self.values.insert(key(sequence), value);
+    }
+
+    /// The value `nextval` last returned for `sequence` in this session.
+    pub fn get(&self, sequence: &SequenceSchema) -> Result<i128, ExecutionError> {
+        self.values.get(&key(sequence)).copied().ok_or_else(|| {
+            ExecutionError::SequenceValue(format!(
+                "currval of sequence \"{}\" is not yet defined in this session",
+                sequence.name
+            ))
+        })
+    }
+}
+
+impl Sequences {
+    /// The next value of `sequence`. A run of `cache` values is recorded
+    /// on its page before the first of them is returned.
+    pub fn next_value(
+        &mut self,
+        storage: &mut StorageEngine,
+        sequence: &SequenceSchema,
+    ) -> Result<i128, ExecutionError> {
+        let page = sequence_page(sequence)?;
+        let key = key(sequence);
+        if !self.cached.contains_key(&key) {
+            let state = storage.sequence_state(page)?;
+            self.cached.insert(
+                key.clone(),
+                Cached {
+                    state,
+                    remaining: 0,
+                },
+            );
+        }
+        let cached = self
+            .cached
+            .get_mut(&key)
+            .expect("the sequence was cached above");
+        let next = if !cached.state.is_called {
+            cached.state.last_value
+        } else {
+            match step(sequence, cached.state.last_value) {
+                Some(next) => next,
+                None if sequence.cycle && sequence.increment > 0 => sequence.min,
+                None if sequence.cycle => sequence.max,
+                None => {
+                    return Err(ExecutionError::SequenceValue(format!(
+                        "nextval: reached {} value of sequence \"{}\" ({})",
+                        if sequence.increment > 0 {
+                            "maximum"
+                        } else {
+                            "minimum"
+                        },
+                        sequence.name,
+                        if sequence.increment > 0 {
+                            sequence.max
+                        } else {
+                            sequence.min
+                        }
+                    )))
+                }
+            }
+        };
+        if cached.remaining == 0 {
+            // The values left before the limit, of which the run takes at
+            // most `cache`.
+            let limit = if sequence.increment > 0 {
+                sequence.max
+            } else {
+                sequence.min
+            };
+            let left = limit
+                .checked_sub(next)
+                .map_or(i128::MAX, |distance| distance / sequence.increment);
+            let steps = left.min(sequence.cache - 1);
+            let last_value = next + steps * sequence.increment;
+            storage.set_sequence_state(
+                page,
+                SequenceState {
+                    last_value,
+                    is_called: true,
+                },
+            )?;
+            cached.remaining = steps + 1;
+        }
+        cached.remaining -= 1;
+        cached.state = SequenceState {
+            last_value: next,
+            is_called: true,
+        };
+        Ok(next)
+    }
+
+    /// Moves `sequence` to `value`. With `is_called` the next value is the
+    /// one after it, otherwise `value` itself.
+    pub fn set_value(
+        &mut self,
+        storage: &mut StorageEngine,
+        sequence: &SequenceSchema,
+        value: i128,
+        is_called: bool,
+    ) -> Result<i128, ExecutionError> {
+        if !(sequence.min..=sequence.max).contains(&value) {
+            return Err(ExecutionError::SequenceValue(format!(
+                "setval: value {} is out of bounds for sequence \"{}\" ({}..{})",
+                value, sequence.name, sequence.min, sequence.max
+            )));
+        }
+        let state = SequenceState {
+            last_value: value,
+            is_called,
+        };
+        storage.set_sequence_state(sequence_page(sequence)?, state)?;
+        self.cached.insert(
+            key(sequence),
+            Cached {
+                state,
+                remaining: 0,
+            },
+        );
+        Ok(value)
+    }
+}
+
+/// The value after `value`, if it is within the bounds of `sequence`.
+fn step(sequence: &SequenceSchema, value: i128) -> Option<i128> {
+    value
+        .checked_add(sequence.increment)
+        .filter(|next| (sequence.min..=sequence.max).contains(next))
+}
+
+fn key(sequence: &SequenceSchema) -> (String, String) {
+    (sequence.database.clone(), sequence.name.clone())
+}
+
+fn sequence_page(sequence: &SequenceSchema) -> Result<PageId, ExecutionError> {
+    sequence
+        .page
+        .ok_or_else(|| corrupt("sequence has no page in the data file").into())
+}
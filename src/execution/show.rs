@@ -0,0 +1,57 @@
+use crate::compiler::binder::show_columns;
+use crate::compiler::parser::ShowQuery;
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::Schema;
+use crate::execution::relation::Relation;
+use crate::storage::catalog::Catalog;
+use crate::types::value::Value;
+
+/// Lists databases, the tables of the current database, or the columns of
+/// one of its tables.
+pub fn execute_show(show: &ShowQuery, catalog: &Catalog) -> Result<Relation, ExecutionError> {
+    let rows = match show {
+        ShowQuery::Databases => catalog
+            .databases()
+            .iter()
+            .map(|database| vec![Value::Text(database.clone())])
+            .collect(),
+        ShowQuery::Tables => catalog
+            .tables()
+            .iter()
+            .filter(|table| table.database == catalog.current_database())
+            .map(|table| vec![Value::Text(table.name.clone())])
+            .collect(),
+        ShowQuery::Describe(name) => {
+            let table = catalog
+                .table(name)
+                .ok_or_else(|| ExecutionError::UnknownTable(name.clone()))?;
+            table
+                .columns
+                .iter()
+                .map(|column| {
+                    let nullable = !column.not_null && !column.is_primary;
+                    let key = if column.is_primary {
+                        "PRIMARY"
+                    } else if table.indexes.iter().any(|index| {
+                        index.unique
+                            && index.predicate.is_none()
+                            && index.columns == [column.name.clone()]
+                    }) {
+                        "UNIQUE"
+                    } else {
+                        ""
+                    };
+                    vec![
+                        Value::Text(column.name.clone()),
+                        Value::Text(column.data_type.to_string()),
+                        Value::Text(if nullable { "YES" } else { "NO" }.to_string()),
+                        Value::Text(key.to_string()),
+                        Value::Text(if column.identity { "IDENTITY" } else { "" }.to_string()),
+                    ]
+                })
+                .collect()
+        }
+    };
+    let names: Vec<&str> = show_columns(show).iter().map(|(name, _)| *name).collect();
+    Ok(Relation::new(Schema::for_table("", &names), rows))
+}
@@ -0,0 +1,256 @@
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::mem::size_of;
+use std::path::PathBuf;
+use std::sync::atomic::{self, AtomicUsize};
+
+use crate::execution::aggregate::{value_size, DEFAULT_WORK_MEMORY};
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::{evaluate, Context, Schema};
+use crate::execution::query::SortKey;
+use crate::execution::relation::RowStream;
+use crate::types::value::Value;
+
+/// Runs merged at once. When there are more, they are first merged a
+/// batch at a time into fewer, longer runs.
+const MERGE_WIDTH: usize = 16;
+
+static RUN_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A row after the values of the sort keys it was evaluated to.
+type KeyedRow = (Vec<Value>, Vec<Value>);
+
+/// External merge sort operator.
+///
+/// Rows are sorted in memory until their estimated size reaches `memory`
+/// bytes. After that, every `memory` bytes of them are sorted into a run
+/// written to a spill file, and the runs are merged as the sorted rows are
+/// read. Runs hold consecutive rows of the input and ties are taken from
+/// the earlier run, so the sort is stable.
+pub struct ExternalSort {
+    keys: Vec<(SortKey, bool)>,
+    memory: usize,
+    spill_directory: PathBuf,
+}
+
+impl ExternalSort {
+    /// Sorts by `(key, ascending)` pairs.
+    pub fn new(keys: Vec<(SortKey, bool)>) -> Self {
+        ExternalSort {
+            keys,
+            memory: DEFAULT_WORK_MEMORY,
+            spill_directory: std::env::temp_dir(),
+        }
+    }
+
+    /// Spills once the rows held take about `memory` bytes. Every run
+    /// holds at least one row, so any budget makes progress.
+    pub fn with_memory(mut self, memory: usize) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    pub fn with_spill_directory<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.spill_directory = directory.into();
+        self
+    }
+
+    /// Reads all of `input` and returns its rows in order. Past `memory`
+    /// bytes the rows are read back from runs on disk as they are used.
+    pub fn execute(
+        &self,
+        input: RowStream,
+        context: &Context,
+    ) -> Result<RowStream<'static>, ExecutionError> {
+        let width = input.schema.columns.len();
+        let mut runs = Vec::new();
+        let mut held = Vec::new();
+        let mut used = 0;
+        for row in input.rows {
+            let row = row?;
+            let key = sort_key(&self.keys, &input.schema, &row, context)?;
+            used += size_of::<KeyedRow>() + values_size(&key) + values_size(&row);
+            held.push((key, row));
+            if used >= self.memory {
+                let sorted = self.sorted(std::mem::take(&mut held));
+                runs.push(self.write_run(sorted.into_iter().map(Ok))?);
+                used = 0;
+            }
+        }
+        let held = self.sorted(held);
+        if runs.is_empty() {
+            let rows = held.into_iter().map(|(_, row)| Ok(row));
+            return Ok(RowStream::new(input.schema, Box::new(rows)));
+        }
+        if !held.is_empty() {
+            runs.push(self.write_run(held.into_iter().map(Ok))?);
+        }
+        while runs.len() > MERGE_WIDTH {
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(MERGE_WIDTH));
+            let mut pending = runs.into_iter();
+            loop {
+                let batch: Vec<Run> = pending.by_ref().take(MERGE_WIDTH).collect();
+                if batch.is_empty() {
+                    break;
+                }
+                merged.push(self.write_run(self.merge(batch, width)?)?);
+            }
+            runs = merged;
+        }
+        let rows = self.merge(runs, width)?.map(|row| row.map(|(_, row)| row));
+        Ok(RowStream::new(input.schema, Box::new(rows)))
+    }
+
+    fn sorted(&self, mut rows: Vec<KeyedRow>) -> Vec<KeyedRow> {
+        rows.sort_by(|(left, _), (right, _)| compare_keys(&self.keys, left, right));
+        rows
+    }
+
+    fn merge(&self, runs: Vec<Run>, width: usize) -> Result<Merge, ExecutionError> {
+        let mut readers = Vec::with_capacity(runs.len());
+        let mut heads = Vec::with_capacity(runs.len());
+        for run in runs {
+            let mut reader = RunReader {
+                reader: BufReader::new(File::open(&run.0)?),
+                key_width: self.keys.len(),
+                width,
+                _run: run,
+            };
+            heads.push(reader.next()?);
+            readers.push(reader);
+        }
+        Ok(Merge {
+            keys: self.keys.clone(),
+            readers,
+            heads,
+        })
+    }
+
+    fn write_run<I>(&self, rows: I) -> Result<Run, ExecutionError>
+    where
+        I: IntoIterator<Item = Result<KeyedRow, ExecutionError>>,
+    {
+        let id = RUN_FILE_COUNTER.fetch_add(1, atomic::Ordering::Relaxed);
+        let run = Run(self.spill_directory.join(format!(
+            "omnissiah-sort-{}-{}.spill",
+            std::process::id(),
+            id
+        )));
+        let mut writer = BufWriter::new(File::create(&run.0)?);
+        for row in rows {
+            let (key, row) = row?;
+            for value in key.iter().chain(row.iter()) {
+                value.write_to(&mut writer)?;
+            }
+        }
+        writer.flush()?;
+        Ok(run)
+    }
+}
+
+/// The values of `keys` for one row of `schema`.
+pub fn sort_key(
+    keys: &[(SortKey, bool)],
+    schema: &Schema,
+    row: &[Value],
+    context: &Context,
+) -> Result<Vec<Value>, ExecutionError> {
+    let mut values = Vec::with_capacity(keys.len());
+    for (key, _) in keys {
+        values.push(match key {
+            SortKey::Position(position) => row[*position].clone(),
+            SortKey::Expression(expression) => evaluate(expression, schema, row, context)?,
+        });
+    }
+    Ok(values)
+}
+
+/// Orders two rows by the values [`sort_key`] gave them.
+pub fn compare_keys(keys: &[(SortKey, bool)], left: &[Value], right: &[Value]) -> Ordering {
+    for (index, (_, ascending)) in keys.iter().enumerate() {
+        let ordering = left[index].sort_cmp(&right[index]);
+        if ordering != Ordering::Equal {
+            return if *ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            };
+        }
+    }
+    Ordering::Equal
+}
+
+fn values_size(values: &[Value]) -> usize {
+    size_of::<Vec<Value>>() + values.iter().map(value_size).sum::<usize>()
+}
+
+/// A spill file of sorted rows, removed once dropped, so that an error
+/// anywhere in the sort leaves none behind.
+struct Run(PathBuf);
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+struct RunReader {
+    reader: BufReader<File>,
+    key_width: usize,
+    width: usize,
+    _run: Run,
+}
+
+impl RunReader {
+    fn next(&mut self) -> Result<Option<KeyedRow>, ExecutionError> {
+        let mut values = Vec::with_capacity(self.key_width + self.width);
+        for index in 0..self.key_width + self.width {
+            match Value::read_from(&mut self.reader)? {
+                Some(value) => values.push(value),
+                None if index == 0 => return Ok(None),
+                None => {
+                    return Err(ExecutionError::Io(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "truncated sort spill file",
+                    )))
+                }
+            }
+        }
+        let row = values.split_off(self.key_width);
+        Ok(Some((values, row)))
+    }
+}
+
+/// The rows of several runs in order, holding the next row of each.
+struct Merge {
+    keys: Vec<(SortKey, bool)>,
+    readers: Vec<RunReader>,
+    heads: Vec<Option<KeyedRow>>,
+}
+
+impl Iterator for Merge {
+    type Item = Result<KeyedRow, ExecutionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut first: Option<(usize, &Vec<Value>)> = None;
+        for (index, head) in self.heads.iter().enumerate() {
+            if let Some((key, _)) = head {
+                let earlier = match first {
+                    Some((_, first)) => compare_keys(&self.keys, key, first) == Ordering::Less,
+                    None => true,
+                };
+                if earlier {
+                    first = Some((index, key));
+                }
+            }
+        }
+        let (index, _) = first?;
+        let row = self.heads[index].take();
+        match self.readers[index].next() {
+            Ok(next) => self.heads[index] = next,
+            Err(error) => return Some(Err(error)),
+        }
+        row.map(Ok)
+    }
+}
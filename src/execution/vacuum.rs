@@ -0,0 +1,94 @@
+//! The autovacuum worker: a thread that wakes now and then and vacuums
+//! the tables that have gathered enough dead versions.
+//!
+//! A table is due once its dead versions outnumber a threshold plus a
+//! fraction of its live ones, so that a small table is not vacuumed for
+//! every few rows and a large one does not wait for millions. Each table
+//! may set its own threshold and fraction, or turn autovacuum off, with
+//! `ALTER TABLE ... SET`.
+
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::execution::executor::Executor;
+use crate::storage::catalog::VacuumSettings;
+use crate::storage::vacuum::TableStatistics;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutovacuumOptions {
+    /// How long the worker sleeps between rounds.
+    pub naptime: Duration,
+    /// The threshold of tables that set none.
+    pub threshold: u64,
+    /// The fraction of tables that set none.
+    pub scale_factor: f64,
+}
+
+impl Default for AutovacuumOptions {
+    /// A round a minute, vacuuming a table once more than 50 versions and
+    /// a fifth of its live ones are dead.
+    fn default() -> Self {
+        AutovacuumOptions {
+            naptime: Duration::from_secs(60),
+            threshold: 50,
+            scale_factor: 0.2,
+        }
+    }
+}
+
+impl AutovacuumOptions {
+    /// Whether a table with `settings` and `statistics` is due.
+    pub fn is_due(&self, settings: &VacuumSettings, statistics: TableStatistics) -> bool {
+        let threshold = settings.threshold.unwrap_or(self.threshold) as f64
+            + settings.scale_factor.unwrap_or(self.scale_factor) * statistics.live as f64;
+        settings.enabled.unwrap_or(true) && statistics.dead as f64 > threshold
+    }
+}
+
+/// A running autovacuum worker, stopped when dropped.
+pub struct Autovacuum {
+    /// Set to stop the worker, which waits on the condition variable
+    /// between rounds.
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Autovacuum {
+    /// Starts a worker vacuuming through `session`.
+    pub fn start(session: Executor, options: AutovacuumOptions) -> Self {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let signal = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            let (stopped, wake) = &*signal;
+            loop {
+                let guard = stopped.lock().unwrap_or_else(PoisonError::into_inner);
+                let (guard, _) = wake
+                    .wait_timeout_while(guard, options.naptime, |stopped| !*stopped)
+                    .unwrap_or_else(PoisonError::into_inner);
+                if *guard {
+                    return;
+                }
+                drop(guard);
+                // A round that fails leaves the table as it was, to be
+                // tried again after the next nap.
+                let _ = session.autovacuum(&options);
+            }
+        });
+        Autovacuum {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Autovacuum {
+    fn drop(&mut self) {
+        let (stopped, wake) = &*self.stop;
+        *stopped.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,607 @@
+use std::cmp::Ordering;
+
+use crate::compiler::parser::{
+    FrameBound, FrameUnits, NamedWindow, OrderByItem, SQLExpression, WindowSpecification,
+};
+use crate::execution::aggregate::{AggregateCall, GroupState};
+use crate::execution::error::ExecutionError;
+use crate::execution::expression::{evaluate, Context, Schema};
+use crate::types::function::WindowFunction;
+use crate::types::value::Value;
+
+enum Function {
+    Window(WindowFunction),
+    Aggregate(AggregateCall),
+}
+
+enum Bound {
+    UnboundedPreceding,
+    Preceding(Value),
+    CurrentRow,
+    Following(Value),
+    UnboundedFollowing,
+}
+
+struct Frame {
+    units: FrameUnits,
+    start: Bound,
+    end: Bound,
+}
+
+/// One window function call with its window resolved against the WINDOW
+/// clause.
+struct WindowCall {
+    function: Function,
+    arguments: Vec<SQLExpression>,
+    partition_by: Vec<SQLExpression>,
+    order_by: Vec<OrderByItem>,
+    frame: Frame,
+}
+
+/// Values of one input row that a window call needs.
+struct Prepared {
+    partition: Vec<Value>,
+    order: Vec<Value>,
+    arguments: Vec<Value>,
+}
+
+/// Window operator.
+///
+/// Each call sorts the input by its PARTITION BY and ORDER BY keys, walks
+/// the partitions and appends its result to every row. Rows keep their
+/// input order; the operator adds one column per call, named by the call
+/// expression, like the aggregate operator does.
+pub struct WindowOperator {
+    expressions: Vec<SQLExpression>,
+    calls: Vec<WindowCall>,
+}
+
+impl WindowOperator {
+    pub fn new(
+        expressions: Vec<SQLExpression>,
+        named: &[NamedWindow],
+    ) -> Result<Self, ExecutionError> {
+        let mut resolved: Vec<NamedWindow> = Vec::with_capacity(named.len());
+        for window in named {
+            if resolved.iter().any(|other| other.name == window.name) {
+                return Err(ExecutionError::Unsupported(format!(
+                    "window \"{}\" is defined more than once",
+                    window.name
+                )));
+            }
+            let specification = resolve_window(&window.specification, &resolved)?;
+            resolved.push(NamedWindow {
+                name: window.name.clone(),
+                specification,
+            });
+        }
+        let mut calls = Vec::with_capacity(expressions.len());
+        for expression in &expressions {
+            calls.push(WindowCall::new(expression, &resolved)?);
+        }
+        Ok(WindowOperator { expressions, calls })
+    }
+
+    /// The input columns followed by the window function calls.
+    pub fn output_schema(&self, input: &Schema) -> Schema {
+        let mut columns = input.columns.clone();
+        columns.extend(self.expressions.iter().cloned());
+        Schema::new(columns)
+    }
+
+    pub fn execute(
+        &self,
+        schema: &Schema,
+        rows: Vec<Vec<Value>>,
+        context: &Context,
+    ) -> Result<Vec<Vec<Value>>, ExecutionError> {
+        let mut results: Vec<Vec<Value>> = (0..rows.len())
+            .map(|_| Vec::with_capacity(self.calls.len()))
+            .collect();
+        for call in &self.calls {
+            for (result, value) in results
+                .iter_mut()
+                .zip(call.compute(schema, &rows, context)?)
+            {
+                result.push(value);
+            }
+        }
+        Ok(rows
+            .into_iter()
+            .zip(results)
+            .map(|(mut row, result)| {
+                row.extend(result);
+                row
+            })
+            .collect())
+    }
+}
+
+/// Fills in a window that names another one: it takes the other window's
+/// partitioning, its ordering unless it adds its own, and its frame unless
+/// it has one.
+fn resolve_window(
+    window: &WindowSpecification,
+    named: &[NamedWindow],
+) -> Result<WindowSpecification, ExecutionError> {
+    let name = match &window.name {
+        Some(name) => name,
+        None => return Ok(window.clone()),
+    };
+    let base = match named.iter().find(|other| &other.name == name) {
+        Some(base) => &base.specification,
+        None => return Err(ExecutionError::UnknownWindow(name.clone())),
+    };
+    if !window.partition_by.is_empty() {
+        return Err(ExecutionError::Unsupported(format!(
+            "cannot override PARTITION BY of window \"{}\"",
+            name
+        )));
+    }
+    if !window.order_by.is_empty() && !base.order_by.is_empty() {
+        return Err(ExecutionError::Unsupported(format!(
+            "cannot override ORDER BY of window \"{}\"",
+            name
+        )));
+    }
+    Ok(WindowSpecification {
+        name: None,
+        partition_by: base.partition_by.clone(),
+        order_by: if window.order_by.is_empty() {
+            base.order_by.clone()
+        } else {
+            window.order_by.clone()
+        },
+        frame: window.frame.clone().or_else(|| base.frame.clone()),
+    })
+}
+
+impl WindowCall {
+    fn new(expression: &SQLExpression, named: &[NamedWindow]) -> Result<Self, ExecutionError> {
+        let (function, window) = match expression {
+            SQLExpression::WindowFunction { function, window } => (function.as_ref(), window),
+            _ => {
+                return Err(ExecutionError::Unsupported(format!(
+                    "{} is not a window function call",
+                    expression
+                )))
+            }
+        };
+        let (name, distinct, arguments) = match function {
+            SQLExpression::FunctionCall {
+                name,
+                distinct,
+                arguments,
+            } => (name, *distinct, arguments),
+            _ => {
+                return Err(ExecutionError::Unsupported(format!(
+                    "{} is not a function call",
+                    function
+                )))
+            }
+        };
+        let (function, arguments) = if let Some(call) = AggregateCall::from_expression(function)? {
+            let arguments = call.argument.iter().cloned().collect();
+            (Function::Aggregate(call), arguments)
+        } else if let Some(window_function) = WindowFunction::from_name(name) {
+            let (min, max) = window_function.arity();
+            if distinct {
+                return Err(ExecutionError::Unsupported(format!(
+                    "DISTINCT is not implemented for {}",
+                    name
+                )));
+            }
+            if arguments.len() < min || arguments.len() > max {
+                return Err(ExecutionError::TypeMismatch(format!(
+                    "{} takes {} to {} arguments, got {}",
+                    name,
+                    min,
+                    max,
+                    arguments.len()
+                )));
+            }
+            (Function::Window(window_function), arguments.clone())
+        } else {
+            return Err(ExecutionError::UnknownFunction(name.clone()));
+        };
+
+        let window = resolve_window(window, named)?;
+        let frame = match &window.frame {
+            Some(frame) => Frame {
+                units: frame.units,
+                start: bound(&frame.start, frame.units, &window.order_by)?,
+                end: bound(&frame.end, frame.units, &window.order_by)?,
+            },
+            // With ORDER BY the default frame runs up to the last peer of
+            // the current row; without it every row is a peer.
+            None => Frame {
+                units: FrameUnits::Range,
+                start: Bound::UnboundedPreceding,
+                end: if window.order_by.is_empty() {
+                    Bound::UnboundedFollowing
+                } else {
+                    Bound::CurrentRow
+                },
+            },
+        };
+        Ok(WindowCall {
+            function,
+            arguments,
+            partition_by: window.partition_by,
+            order_by: window.order_by,
+            frame,
+        })
+    }
+
+    fn prepare(
+        &self,
+        schema: &Schema,
+        row: &[Value],
+        context: &Context,
+    ) -> Result<Prepared, ExecutionError> {
+        let mut partition = Vec::with_capacity(self.partition_by.len());
+        for expression in &self.partition_by {
+            partition.push(evaluate(expression, schema, row, context)?);
+        }
+        let mut order = Vec::with_capacity(self.order_by.len());
+        for item in &self.order_by {
+            order.push(evaluate(&item.expression, schema, row, context)?);
+        }
+        let mut arguments = Vec::with_capacity(self.arguments.len().max(1));
+        for argument in &self.arguments {
+            arguments.push(evaluate(argument, schema, row, context)?);
+        }
+        if matches!(&self.function, Function::Aggregate(call) if call.argument.is_none()) {
+            arguments.push(Value::Bool(true));
+        }
+        Ok(Prepared {
+            partition,
+            order,
+            arguments,
+        })
+    }
+
+    /// Returns the call's value for every row, in input order.
+    fn compute(
+        &self,
+        schema: &Schema,
+        rows: &[Vec<Value>],
+        context: &Context,
+    ) -> Result<Vec<Value>, ExecutionError> {
+        let mut prepared = Vec::with_capacity(rows.len());
+        for row in rows {
+            prepared.push(self.prepare(schema, row, context)?);
+        }
+        let mut order: Vec<usize> = (0..rows.len()).collect();
+        order.sort_by(|&left, &right| {
+            compare_values(&prepared[left].partition, &prepared[right].partition)
+                .then_with(|| self.compare_order(&prepared[left].order, &prepared[right].order))
+        });
+
+        let mut results = vec![Value::Null; rows.len()];
+        let mut begin = 0;
+        while begin < order.len() {
+            let mut end = begin + 1;
+            while end < order.len()
+                && compare_values(
+                    &prepared[order[begin]].partition,
+                    &prepared[order[end]].partition,
+                ) == Ordering::Equal
+            {
+                end += 1;
+            }
+            self.compute_partition(&order[begin..end], &prepared, &mut results)?;
+            begin = end;
+        }
+        Ok(results)
+    }
+
+    fn compute_partition(
+        &self,
+        members: &[usize],
+        prepared: &[Prepared],
+        results: &mut [Value],
+    ) -> Result<(), ExecutionError> {
+        // peers[p] is the range of positions sharing the ORDER BY values of
+        // position p, and the index of that peer group.
+        let mut peers = Vec::with_capacity(members.len());
+        let mut start = 0;
+        let mut group = 0;
+        while start < members.len() {
+            let mut end = start + 1;
+            while end < members.len()
+                && self.compare_order(
+                    &prepared[members[start]].order,
+                    &prepared[members[end]].order,
+                ) == Ordering::Equal
+            {
+                end += 1;
+            }
+            for _ in start..end {
+                peers.push((start, end, group));
+            }
+            group += 1;
+            start = end;
+        }
+
+        let mut running: Option<(GroupState, usize, usize)> = None;
+        for (position, &member) in members.iter().enumerate() {
+            let (peer_start, _, peer_group) = peers[position];
+            let arguments = &prepared[member].arguments;
+            results[member] = match &self.function {
+                Function::Window(WindowFunction::RowNumber) => Value::Integer(position as i128 + 1),
+                Function::Window(WindowFunction::Rank) => Value::Integer(peer_start as i128 + 1),
+                Function::Window(WindowFunction::DenseRank) => {
+                    Value::Integer(peer_group as i128 + 1)
+                }
+                Function::Window(function @ (WindowFunction::Lag | WindowFunction::Lead)) => {
+                    let offset = match arguments.get(1) {
+                        None => 1,
+                        Some(Value::Null) => continue,
+                        Some(value) => row_offset(value, function.name())?,
+                    };
+                    let target = if *function == WindowFunction::Lag {
+                        position.checked_sub(offset)
+                    } else {
+                        position
+                            .checked_add(offset)
+                            .filter(|target| *target < members.len())
+                    };
+                    match target {
+                        Some(target) => prepared[members[target]].arguments[0].clone(),
+                        None => arguments.get(2).cloned().unwrap_or(Value::Null),
+                    }
+                }
+                Function::Window(WindowFunction::FirstValue) => {
+                    let (start, end) = self.frame_bounds(members, prepared, &peers, position)?;
+                    if start < end {
+                        prepared[members[start]].arguments[0].clone()
+                    } else {
+                        Value::Null
+                    }
+                }
+                Function::Window(WindowFunction::LastValue) => {
+                    let (start, end) = self.frame_bounds(members, prepared, &peers, position)?;
+                    if start < end {
+                        prepared[members[end - 1]].arguments[0].clone()
+                    } else {
+                        Value::Null
+                    }
+                }
+                Function::Aggregate(call) => {
+                    let calls = std::slice::from_ref(call);
+                    let (start, end) = self.frame_bounds(members, prepared, &peers, position)?;
+                    // A frame that starts where the previous one did and
+                    // reaches at least as far extends the previous state, so
+                    // running totals take one pass.
+                    let (state, from) = match running.take() {
+                        Some((state, previous_start, previous_end))
+                            if previous_start == start && previous_end <= end =>
+                        {
+                            (state, previous_end)
+                        }
+                        _ => (GroupState::new(calls), start),
+                    };
+                    let mut state = state;
+                    for &frame_member in &members[from..end.max(from)] {
+                        state.update(calls, &prepared[frame_member].arguments)?;
+                    }
+                    let value = state.finish()?.remove(0);
+                    running = Some((state, start, end.max(from)));
+                    value
+                }
+            };
+        }
+        Ok(())
+    }
+
+    /// The frame of the row at `position` as a range of partition positions.
+    fn frame_bounds(
+        &self,
+        members: &[usize],
+        prepared: &[Prepared],
+        peers: &[(usize, usize, usize)],
+        position: usize,
+    ) -> Result<(usize, usize), ExecutionError> {
+        let length = members.len();
+        let (peer_start, peer_end, _) = peers[position];
+        let rows = self.frame.units == FrameUnits::Rows;
+        let start = match &self.frame.start {
+            Bound::UnboundedPreceding => 0,
+            Bound::CurrentRow if rows => position,
+            Bound::CurrentRow => peer_start,
+            Bound::Preceding(offset) if rows => {
+                position.saturating_sub(row_offset(offset, "ROWS")?)
+            }
+            Bound::Following(offset) if rows => position
+                .saturating_add(row_offset(offset, "ROWS")?)
+                .min(length),
+            Bound::Preceding(offset) => {
+                self.range_position(members, prepared, position, offset, false, true)?
+            }
+            Bound::Following(offset) => {
+                self.range_position(members, prepared, position, offset, true, true)?
+            }
+            Bound::UnboundedFollowing => length,
+        };
+        let end = match &self.frame.end {
+            Bound::UnboundedFollowing => length,
+            Bound::CurrentRow if rows => position + 1,
+            Bound::CurrentRow => peer_end,
+            Bound::Preceding(offset) if rows => position
+                .checked_sub(row_offset(offset, "ROWS")?)
+                .map_or(0, |last| last + 1),
+            Bound::Following(offset) if rows => position
+                .saturating_add(row_offset(offset, "ROWS")?)
+                .saturating_add(1)
+                .min(length),
+            Bound::Preceding(offset) => {
+                self.range_position(members, prepared, position, offset, false, false)?
+            }
+            Bound::Following(offset) => {
+                self.range_position(members, prepared, position, offset, true, false)?
+            }
+            Bound::UnboundedPreceding => 0,
+        };
+        if start >= end {
+            return Ok((start.min(length), start.min(length)));
+        }
+        Ok((start, end))
+    }
+
+    /// Where a `RANGE offset PRECEDING/FOLLOWING` bound falls: the first
+    /// position whose ORDER BY value reaches the bound when `is_start`, or
+    /// the position just past the last one within it otherwise. Rows with a
+    /// NULL ORDER BY value only frame their peers.
+    fn range_position(
+        &self,
+        members: &[usize],
+        prepared: &[Prepared],
+        position: usize,
+        offset: &Value,
+        following: bool,
+        is_start: bool,
+    ) -> Result<usize, ExecutionError> {
+        let ascending = self.order_by[0].ascending;
+        let current = &prepared[members[position]].order[0];
+        if current.is_null() {
+            let key = |member: &usize| &prepared[*member].order[0];
+            let first = members.iter().position(|member| key(member).is_null());
+            let count = members
+                .iter()
+                .filter(|member| key(member).is_null())
+                .count();
+            let first = first.unwrap_or(position);
+            return Ok(if is_start { first } else { first + count });
+        }
+        let target = match shift(current, offset, following == ascending)? {
+            Some(target) => target,
+            // Past the range of the type: the bound is beyond every row.
+            None => return Ok(if following { members.len() } else { 0 }),
+        };
+        let directed = |member: &usize| {
+            let ordering = prepared[*member].order[0].sort_cmp(&target);
+            if ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        };
+        Ok(if is_start {
+            members.partition_point(|member| directed(member) == Ordering::Less)
+        } else {
+            members.partition_point(|member| directed(member) != Ordering::Greater)
+        })
+    }
+
+    fn compare_order(&self, left: &[Value], right: &[Value]) -> Ordering {
+        for (index, item) in self.order_by.iter().enumerate() {
+            let ordering = left[index].sort_cmp(&right[index]);
+            if ordering != Ordering::Equal {
+                return if item.ascending {
+                    ordering
+                } else {
+                    ordering.reverse()
+                };
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+/// Evaluates the offset of a frame bound, which must be a non-negative
+/// constant: an integer under ROWS and a number under RANGE with exactly
+/// one ORDER BY key.
+fn bound(
+    bound: &FrameBound,
+    units: FrameUnits,
+    order_by: &[OrderByItem],
+) -> Result<Bound, ExecutionError> {
+    let offset = match bound {
+        FrameBound::UnboundedPreceding => return Ok(Bound::UnboundedPreceding),
+        FrameBound::CurrentRow => return Ok(Bound::CurrentRow),
+        FrameBound::UnboundedFollowing => return Ok(Bound::UnboundedFollowing),
+        FrameBound::Preceding(offset) | FrameBound::Following(offset) => offset,
+    };
+    let value = evaluate(offset, &Schema::new(Vec::new()), &[], &Context::default())?;
+    let valid = match (&value, units) {
+        (Value::Integer(offset), _) => *offset >= 0,
+        (Value::Float(offset), FrameUnits::Range) => *offset >= 0.0,
+        _ => false,
+    };
+    if !valid {
+        return Err(ExecutionError::TypeMismatch(format!(
+            "frame offset must be a non-negative {}, found {:?}",
+            if units == FrameUnits::Rows {
+                "integer"
+            } else {
+                "number"
+            },
+            value
+        )));
+    }
+    if units == FrameUnits::Range && order_by.len() != 1 {
+        return Err(ExecutionError::Unsupported(
+            "RANGE with an offset needs exactly one ORDER BY column".to_string(),
+        ));
+    }
+    Ok(match bound {
+        FrameBound::Preceding(_) => Bound::Preceding(value),
+        _ => Bound::Following(value),
+    })
+}
+
+fn row_offset(value: &Value, owner: &str) -> Result<usize, ExecutionError> {
+    match value {
+        Value::Integer(offset) if *offset >= 0 => {
+            Ok(usize::try_from(*offset).unwrap_or(usize::MAX))
+        }
+        _ => Err(ExecutionError::TypeMismatch(format!(
+            "{} offset must be a non-negative integer, found {}",
+            owner,
+            value.type_name()
+        ))),
+    }
+}
+
+/// `value + offset` when `up`, otherwise `value - offset`; `None` when an
+/// integer result does not fit.
+fn shift(value: &Value, offset: &Value, up: bool) -> Result<Option<Value>, ExecutionError> {
+    Ok(match (value, offset) {
+        (Value::Integer(value), Value::Integer(offset)) => if up {
+            value.checked_add(*offset)
+        } else {
+            value.checked_sub(*offset)
+        }
+        .map(Value::Integer),
+        (Value::Integer(value), Value::Float(offset)) => Some(Value::Float(if up {
+            *value as f64 + offset
+        } else {
+            *value as f64 - offset
+        })),
+        (Value::Float(value), Value::Integer(offset)) => Some(Value::Float(if up {
+            value + *offset as f64
+        } else {
+            value - *offset as f64
+        })),
+        (Value::Float(value), Value::Float(offset)) => Some(Value::Float(if up {
+            value + offset
+        } else {
+            value - offset
+        })),
+        _ => {
+            return Err(ExecutionError::TypeMismatch(format!(
+                "RANGE with an offset needs a numeric ORDER BY column, found {}",
+                value.type_name()
+            )))
+        }
+    })
+}
+
+fn compare_values(left: &[Value], right: &[Value]) -> Ordering {
+    left.iter()
+        .zip(right)
+        .map(|(left, right)| left.sort_cmp(right))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
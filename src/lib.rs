@@ -0,0 +1,56 @@
+//! OmnissiahDB: a SQL database with a write-ahead log, MVCC
+//! transactions and a disk-backed storage engine.
+
+pub mod compiler {
+    pub mod binder;
+    pub mod lexer;
+    pub mod parser;
+    pub mod walk;
+}
+pub mod execution {
+    pub mod access;
+    pub mod aggregate;
+    pub mod constraint;
+    pub mod cte;
+    pub mod error;
+    pub mod executor;
+    pub mod expression;
+    pub mod function;
+    pub mod join;
+    pub mod locking;
+    pub mod planner;
+    pub mod query;
+    pub mod relation;
+    pub mod select;
+    pub mod sequence;
+    pub mod show;
+    pub mod sort;
+    pub mod vacuum;
+    pub mod window;
+}
+pub mod types {
+    pub mod cast;
+    pub mod function;
+    pub mod value;
+}
+pub mod storage {
+    pub mod allocator;
+    pub mod btree;
+    pub mod buffer;
+    pub mod catalog;
+    pub mod commit_log;
+    pub mod disk;
+    pub mod engine;
+    pub mod free_space;
+    pub mod hash;
+    pub mod heap;
+    pub mod information_schema;
+    pub mod key;
+    pub mod lock;
+    pub mod mvcc;
+    pub mod page;
+    pub mod sequence;
+    pub mod tuple;
+    pub mod vacuum;
+    pub mod wal;
+}
@@ -1,27 +1,103 @@
-mod compiler{
-    pub mod lexer;
-    pub mod parser;
-}
-
-use compiler::lexer::*;
-use compiler::parser::*;
+use omnissiah_db::compiler::lexer::*;
+use omnissiah_db::compiler::parser::*;
+use omnissiah_db::execution::error::ExecutionError;
+use omnissiah_db::execution::executor::*;
+use omnissiah_db::execution::vacuum::AutovacuumOptions;
+use omnissiah_db::storage::engine::StorageOptions;
 
 fn main() {
-    let select_query = "SELECT name, value FROM table WHERE value > 5 AND name == 'Jane Doe';";
+    let fixtures_query = "CREATE TABLE readings (id INT PRIMARY, name TEXT NOTNULL, value INT NOTNULL);
+INSERT INTO readings(id, name, value) VALUES (1, 'Jane Doe', 12);
+INSERT INTO readings(id, name, value) VALUES (2, 'Jane Doe', 4);
+INSERT INTO readings(id, name, value) VALUES (3, 'John Doe', 15);
+INSERT INTO readings(id, name, value) VALUES (4, 'Alice', 9);
+INSERT INTO readings(id, name, value) VALUES (5, 'Bob', 8);
+INSERT INTO readings(id, name, value) VALUES (6, 'Bob', 3);
+INSERT INTO readings(id, name, value) VALUES (7, 'Charlie', 13);
+INSERT INTO readings(id, name, value) VALUES (8, 'Robert', 2);
+INSERT INTO readings(id, name, value) VALUES (9, 'Bob', 11);
+CREATE TABLE owners (id INT PRIMARY, name TEXT NOTNULL, age INT NOTNULL, city TEXT);
+INSERT INTO owners(id, name, age, city) VALUES (1, 'Maria', 34, 'Saratov');
+INSERT INTO owners(id, name, age, city) VALUES (2, 'Pavel', 16, 'Kazan');
+INSERT INTO owners(id, name, age, city) VALUES (3, 'Olga', 58, 'Tver');
+CREATE TABLE cats (id INT PRIMARY, name TEXT NOTNULL, owner INT NOTNULL, age INT NOTNULL);
+INSERT INTO cats(id, name, owner, age) VALUES (1, 'Bella', 1, 7);
+INSERT INTO cats(id, name, owner, age) VALUES (2, 'Murka', 1, 3);
+INSERT INTO cats(id, name, owner, age) VALUES (3, 'Vaska', 1, 11);
+INSERT INTO cats(id, name, owner, age) VALUES (4, 'Barsik', 2, 5);
+INSERT INTO cats(id, name, owner, age) VALUES (5, 'Pushok', 3, 2);
+CREATE TABLE cities (city TEXT PRIMARY, country TEXT NOTNULL);
+INSERT INTO cities(city, country) VALUES ('Saratov', 'Russia');
+INSERT INTO cities(city, country) VALUES ('Kazan', 'Russia');
+CREATE TABLE colors (color TEXT PRIMARY);
+INSERT INTO colors(color) VALUES ('white');
+INSERT INTO colors(color) VALUES ('ginger');
+CREATE TABLE sizes (size TEXT PRIMARY);
+INSERT INTO sizes(size) VALUES ('small');
+INSERT INTO sizes(size) VALUES ('large');
+CREATE TABLE bans (owner INT PRIMARY);
+INSERT INTO bans(owner) VALUES (3);
+CREATE TABLE employees (id INT PRIMARY, name TEXT NOTNULL, manager INT);
+INSERT INTO employees(id, name, manager) VALUES (1, 'Ada', NULL);
+INSERT INTO employees(id, name, manager) VALUES (2, 'Boris', 1);
+INSERT INTO employees(id, name, manager) VALUES (3, 'Vera', 1);
+INSERT INTO employees(id, name, manager) VALUES (4, 'Gleb', 2);
+CREATE TABLE live_users (name TEXT PRIMARY, city TEXT NOTNULL);
+INSERT INTO live_users(name, city) VALUES ('Ann', 'Tver');
+INSERT INTO live_users(name, city) VALUES ('Ben', 'Kazan');
+INSERT INTO live_users(name, city) VALUES ('Cid', 'Omsk');
+INSERT INTO live_users(name, city) VALUES ('Fay', 'Perm');
+INSERT INTO live_users(name, city) VALUES ('Gus', 'Kazan');
+INSERT INTO live_users(name, city) VALUES ('Hal', 'Tver');
+CREATE TABLE archived_users (name TEXT PRIMARY, city TEXT NOTNULL);
+INSERT INTO archived_users(name, city) VALUES ('Dan', 'Perm');
+INSERT INTO archived_users(name, city) VALUES ('Eva', 'Tver');
+CREATE TABLE banned (name TEXT PRIMARY, city TEXT NOTNULL);
+INSERT INTO banned(name, city) VALUES ('Cid', 'Omsk');
+CREATE TABLE flagged (name TEXT PRIMARY, city TEXT NOTNULL);
+INSERT INTO flagged(name, city) VALUES ('Cid', 'Omsk');
+INSERT INTO flagged(name, city) VALUES ('Dan', 'Perm');
+CREATE TABLE results (id INT PRIMARY, player TEXT NOTNULL, team TEXT NOTNULL, score INT NOTNULL, played INT NOTNULL);
+INSERT INTO results(id, player, team, score, played) VALUES (1, 'Ann', 'red', 30, 1);
+INSERT INTO results(id, player, team, score, played) VALUES (2, 'Ben', 'red', 45, 2);
+INSERT INTO results(id, player, team, score, played) VALUES (3, 'Cid', 'red', 45, 3);
+INSERT INTO results(id, player, team, score, played) VALUES (4, 'Dan', 'blue', 20, 1);
+INSERT INTO results(id, player, team, score, played) VALUES (5, 'Eva', 'blue', 50, 2);
+CREATE TABLE logs (id INT PRIMARY, host TEXT NOTNULL, level TEXT NOTNULL, message TEXT NOTNULL, logged_at INT NOTNULL);
+INSERT INTO logs(id, host, level, message, logged_at) VALUES (1, 'alpha', 'info', 'started', 1);
+INSERT INTO logs(id, host, level, message, logged_at) VALUES (2, 'alpha', 'warn', 'disk almost full', 2);
+INSERT INTO logs(id, host, level, message, logged_at) VALUES (3, 'alpha', 'debug', 'tick', 3);
+INSERT INTO logs(id, host, level, message, logged_at) VALUES (4, 'beta', 'error', 'lost connection', 1);
+CREATE TABLE CatsAndOwners (CatID INT PRIMARY, CatName TEXT, CatAge INT, CatColor TEXT, CatOwnerName TEXT, City TEXT);";
+
+    let select_query = "SELECT name, value FROM readings WHERE value > 5 AND name == 'Jane Doe';";
 
-    let select_query1 = "SELECT name, value FROM table WHERE (value >= 10 AND (name == 'Jane Doe' OR name == 'John Doe'));";
+    let select_query1 = "SELECT name, value FROM readings WHERE (value >= 10 AND (name == 'Jane Doe' OR name == 'John Doe'));";
 
-    let select_query2 = "SELECT name, value FROM table WHERE (((name == 'Alice' OR name == 'Bob') AND (value >= 7 OR value < 10)) OR (name == 'Charlie' AND (value <= 5 OR value > 12))) AND (value != 8 AND (name != 'Dave' OR value < 15));";
-    let select_query3 = "SELECT name, value FROM table WHERE (value < 5 AND (name == 'Bob' OR name == 'Robert'));";
+    let select_query2 = "SELECT name, value FROM readings WHERE (((name == 'Alice' OR name == 'Bob') AND (value >= 7 OR value < 10)) OR (name == 'Charlie' AND (value <= 5 OR value > 12))) AND (value != 8 AND (name != 'Dave' OR value < 15));";
+    let select_query3 =
+        "SELECT name, value FROM readings WHERE (value < 5 AND (name == 'Bob' OR name == 'Robert'));";
 
-    let select_query4 = "SELECT name, value FROM table WHERE (name LIKE 'C%' AND (value > 7 OR value < 9));";
+    let select_query4 =
+        "SELECT name, value FROM readings WHERE (name LIKE 'C%' AND (value > 7 OR value < 9));";
 
-    let select_query5 = "SELECT name, value FROM table WHERE (value BETWEEN 10 AND 15 AND (name == 'Eve' OR name == 'Eva'));";
+    let select_query5 = "SELECT name, value FROM readings WHERE (value BETWEEN 10 AND 15 AND (name == 'Eve' OR name == 'Eva'));";
 
+    let select_query6 = "SELECT name, COUNT(*), COUNT(DISTINCT value), SUM(value), AVG(value) FROM readings WHERE value > 5 GROUP BY name HAVING COUNT(*) > 1 AND MAX(value) < 100;";
 
+    let select_query7 = "SELECT c.name, o.name FROM cats AS c LEFT JOIN owners o ON c.owner == o.id AND o.age >= 18 NATURAL JOIN cities, colors CROSS JOIN sizes;";
+    let select_query8 = "SELECT name, (SELECT MAX(age) FROM cats WHERE cats.owner == o.id) AS oldest FROM owners o WHERE EXISTS (SELECT * FROM cats c WHERE c.owner == o.id) AND o.city IN (SELECT city FROM cities) AND NOT EXISTS (SELECT * FROM bans WHERE bans.owner == o.id);";
 
+    let select_query9 = "SELECT t.owner, t.cats FROM (SELECT owner, COUNT(*) AS cats FROM cats GROUP BY owner) AS t WHERE t.cats > 2;";
+    let with_query = "WITH RECURSIVE subordinates (id, name, depth) AS (SELECT id, name, 0 FROM employees WHERE id == 1 UNION ALL SELECT e.id, e.name, s.depth FROM employees e JOIN subordinates s ON e.manager == s.id), counts AS (SELECT depth, COUNT(*) AS people FROM subordinates GROUP BY depth) SELECT * FROM counts;";
+    let union_query = "SELECT name, city FROM live_users UNION ALL SELECT name, city FROM archived_users EXCEPT SELECT name, city FROM banned INTERSECT SELECT name, city FROM flagged ORDER BY 2 DESC, name LIMIT 10 OFFSET 5;";
+    let window_query = "SELECT player, score, RANK() OVER w AS place, LAG(score, 1, 0) OVER (w) AS previous, SUM(score) OVER (PARTITION BY team ORDER BY played ROWS BETWEEN 2 PRECEDING AND CURRENT ROW) AS recent FROM results WINDOW w AS (PARTITION BY team ORDER BY score DESC) ORDER BY team, place;";
+    let function_query = "SELECT UPPER(name), LENGTH(TRIM(BOTH ' ' FROM name)), SUBSTRING(name FROM 1 FOR 3), POSITION('ф' IN name), ROUND(money, 2), COALESCE(email, name), EXTRACT(YEAR FROM NOW()) FROM users;";
+    let case_query = "SELECT name, CASE WHEN age < 18 THEN 'minor' WHEN age < 65 THEN 'adult' ELSE 'senior' END AS bracket, CASE phone WHEN '555-0100' THEN 'office' ELSE 'mobile' END, CAST(money AS U_INT), age::TEXT FROM users;";
+    let distinct_query = "SELECT DISTINCT ON (host) host, level, message FROM logs WHERE level != 'debug' ORDER BY host, logged_at DESC;";
 
-    let insert_query = "INSERT INTO CatsAndOwners(CatID, CatName, CatAge, CatColor, CatOwnerName, City)
+    let insert_query =
+        "INSERT INTO CatsAndOwners(CatID, CatName, CatAge, CatColor, CatOwnerName, City)
 VALUES
 (2, 'Белла', 7, 'Белая', 'Максим', 'Саратов');";
     let create_query = "CREATE TABLE users
@@ -34,29 +110,119 @@ email TEXT,
 money DOUBLE
 );";
     let create_query1 = "CREATE DATABASE test;";
-    let queries = vec![select_query,
-                       select_query1,
-                       select_query2,
-                       select_query3,
-                       select_query4,
-                       select_query5,
-
-                       insert_query,
-
-                       create_query,
-                       create_query1];
-
-    for q in queries {
-        let mut lexer = Lexer::new(q);
-        let tokens = lexer.tokenize();
-        println!("{:?}", tokens);
-        let mut parser = Parser::new(&tokens);
-        if let Some(query) = parser.parse() {
-            println!("{:#?}", query);
-        } else {
-            println!("Failed to parse query.");
-        }
-    }
+    let show_query = "SHOW DATABASES;";
+    let show_query1 = "SHOW TABLES;";
+    let describe_query = "DESCRIBE users;";
+    let catalog_query = "SELECT column_name, data_type, is_nullable FROM information_schema.columns WHERE table_name == 'users' ORDER BY ordinal_position;";
+    let insert_query1 = "INSERT INTO users(name, age, phone, email, money) VALUES ('Jane Doe', 34, '555-0100', 'jane@example.com', 120.5);";
+    let insert_query2 = "INSERT INTO users(name, age, phone) VALUES ('John Doe', 17, '555-0101');";
+    let sequence_query =
+        "CREATE SEQUENCE invoice_numbers START WITH 1000 INCREMENT BY 10 CACHE 20;";
+    let nextval_query =
+        "SELECT name, nextval('invoice_numbers') AS invoice, currval('users_id_seq') FROM users;";
+    let index_query = "CREATE INDEX users_age_name ON users (age, name);";
+    let hash_index_query = "CREATE UNIQUE INDEX users_phone ON users USING HASH (phone);";
+    let phone_query = "SELECT name FROM users WHERE phone == '555-0101';";
+    let lookup_query = "SELECT name, age FROM users WHERE id == 2;";
+    let range_query = "SELECT name, age FROM users WHERE age >= 18 AND age < 65;";
+    let expression_index_query =
+        "CREATE UNIQUE INDEX users_email ON users (LOWER(email)) WHERE money > 0;";
+    let email_query =
+        "SELECT name FROM users WHERE LOWER(email) == 'jane@example.com' AND money > 100;";
+    let batch_query = "BEGIN ISOLATION LEVEL REPEATABLE READ;
+INSERT INTO users(name, age, phone) VALUES ('Ann Roe', 52, '555-0102');
+SAVEPOINT before_duplicate;
+INSERT INTO users(name, age, phone) VALUES ('Ann Twin', 52, '555-0102');
+ROLLBACK TO SAVEPOINT before_duplicate;
+COMMIT;";
+    let lock_query = "BEGIN;
+LOCK TABLE users IN SHARE MODE;
+SELECT id, name FROM users WHERE age >= 18 ORDER BY id LIMIT 1 FOR UPDATE SKIP LOCKED;
+COMMIT;";
+    let vacuum_query = "ALTER TABLE users SET (autovacuum_vacuum_threshold = 10, autovacuum_vacuum_scale_factor = 0.1);
+VACUUM FULL users;";
+    let users_query = "SELECT id, name, money FROM users WHERE email != 'jane@example.com' OR age >= 18 ORDER BY id;";
+    let queries = vec![
+        fixtures_query,
+        create_query,
+        create_query1,
+        show_query,
+        show_query1,
+        describe_query,
+        catalog_query,
+        insert_query1,
+        insert_query2,
+        sequence_query,
+        nextval_query,
+        index_query,
+        hash_index_query,
+        phone_query,
+        lookup_query,
+        range_query,
+        expression_index_query,
+        email_query,
+        batch_query,
+        lock_query,
+        vacuum_query,
+        users_query,
+        select_query,
+        select_query1,
+        select_query2,
+        select_query3,
+        select_query4,
+        select_query5,
+        select_query6,
+        select_query7,
+        select_query8,
+        select_query9,
+        with_query,
+        union_query,
+        window_query,
+        function_query,
+        case_query,
+        distinct_query,
+        insert_query,
+    ];
 
+    let script = queries.join("\n");
+    let mut lexer = Lexer::new(&script);
+    let tokens = lexer.tokenize();
+    let mut parser = Parser::new(&tokens);
+    let (statements, errors) = parser.parse_script();
+    for error in errors {
+        println!("{}", error.locate(lexer.positions()));
+    }
 
+    let directory = std::env::temp_dir().join("omnissiah");
+    // Start over, so that the fixtures above can be created again.
+    let _ = std::fs::remove_dir_all(&directory);
+    let mut executor = match Executor::open(directory, StorageOptions::default()) {
+        Ok(executor) => executor,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    // Small enough that the aggregates below spill all but their first
+    // groups to disk.
+    executor.set_work_memory(256);
+    let _autovacuum = executor.start_autovacuum(AutovacuumOptions::default());
+    for (query, span) in statements.iter().zip(parser.spans()) {
+        match executor.execute(query) {
+            Ok(result) => {
+                if !result.columns.is_empty() {
+                    println!("{}", result.columns.join(" | "));
+                }
+                for row in result.rows {
+                    let values: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+                    println!("{}", values.join(" | "));
+                }
+            }
+            Err(ExecutionError::Bind(error)) => {
+                let error = error.locate(&tokens[span.clone()], &lexer.positions()[span.clone()]);
+                println!("{}", error);
+            }
+            Err(error) => println!("{}", error),
+        }
+    }
 }
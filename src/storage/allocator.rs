@@ -0,0 +1,97 @@
+//! Page allocation within the data file.
+//!
+//! Page 0 is the file header. It holds the head of the free list, a chain
+//! of pages given back by tables and indexes that is used up before the
+//! file grows, the id the next transaction gets and the first page of the
+//! commit log.
+
+use std::io;
+
+use crate::storage::buffer::BufferPool;
+use crate::storage::mvcc::Xid;
+use crate::storage::page::{
+    corrupt, format_page, page_kind, read_u32, read_u64, write_u32, write_u64, PageId, PageKind,
+    HEADER_PAGE, PAGE_HEADER_SIZE,
+};
+
+const MAGIC: &[u8; 8] = b"OMNIDAT2";
+const MAGIC_POSITION: usize = PAGE_HEADER_SIZE;
+const FREE_LIST: usize = PAGE_HEADER_SIZE + 8;
+const NEXT_TRANSACTION: usize = PAGE_HEADER_SIZE + 16;
+const COMMIT_LOG: usize = PAGE_HEADER_SIZE + 24;
+/// Where a free page keeps the next page of the free list.
+const NEXT_FREE: usize = PAGE_HEADER_SIZE;
+
+/// Writes the header page of an empty file, or checks the header of an
+/// existing one. A header page still all zeros is that of a file whose
+/// creation a crash cut short before the first commit forced its log
+/// record, and is written again.
+pub fn initialize(pool: &mut BufferPool) -> io::Result<()> {
+    let unwritten = pool.page_count() == 0
+        || pool.read(HEADER_PAGE, |data| data.iter().all(|&byte| byte == 0))?;
+    if unwritten {
+        if pool.page_count() == 0 {
+            let (page, frame) = pool.extend()?;
+            debug_assert_eq!(page, HEADER_PAGE);
+            pool.unpin(frame);
+        }
+        return pool.write(HEADER_PAGE, |data| {
+            format_page(data, PageKind::Header);
+            data[MAGIC_POSITION..MAGIC_POSITION + MAGIC.len()].copy_from_slice(MAGIC);
+        });
+    }
+    pool.read(HEADER_PAGE, |data| {
+        if page_kind(data)? != PageKind::Header
+            || &data[MAGIC_POSITION..MAGIC_POSITION + MAGIC.len()] != MAGIC
+        {
+            return Err(corrupt("not an OmnissiahDB data file"));
+        }
+        Ok(())
+    })?
+}
+
+/// A page for the caller to format, taken from the free list or added to
+/// the end of the file.
+pub fn allocate_page(pool: &mut BufferPool) -> io::Result<PageId> {
+    let head = pool.read(HEADER_PAGE, |data| read_u32(data, FREE_LIST))?;
+    if head == HEADER_PAGE {
+        let (page, frame) = pool.extend()?;
+        pool.unpin(frame);
+        return Ok(page);
+    }
+    let next = pool.read(head, |data| match page_kind(data)? {
+        PageKind::Free => Ok(read_u32(data, NEXT_FREE)),
+        _ => Err(corrupt("free list points at a page in use")),
+    })??;
+    pool.write(HEADER_PAGE, |data| write_u32(data, FREE_LIST, next))?;
+    Ok(head)
+}
+
+/// Puts `page` at the head of the free list.
+pub fn free_page(pool: &mut BufferPool, page: PageId) -> io::Result<()> {
+    let head = pool.read(HEADER_PAGE, |data| read_u32(data, FREE_LIST))?;
+    pool.write(page, |data| {
+        format_page(data, PageKind::Free);
+        write_u32(data, NEXT_FREE, head);
+    })?;
+    pool.write(HEADER_PAGE, |data| write_u32(data, FREE_LIST, page))
+}
+
+/// The id the next transaction gets, as far as the file knows: no version
+/// in it has one as high.
+pub fn next_transaction(pool: &mut BufferPool) -> io::Result<Xid> {
+    pool.read(HEADER_PAGE, |data| read_u64(data, NEXT_TRANSACTION))
+}
+
+pub fn set_next_transaction(pool: &mut BufferPool, xid: Xid) -> io::Result<()> {
+    pool.write(HEADER_PAGE, |data| write_u64(data, NEXT_TRANSACTION, xid))
+}
+
+/// The first page of the commit log, or the header page if it has none.
+pub fn commit_log_root(pool: &mut BufferPool) -> io::Result<PageId> {
+    pool.read(HEADER_PAGE, |data| read_u32(data, COMMIT_LOG))
+}
+
+pub fn set_commit_log_root(pool: &mut BufferPool, page: PageId) -> io::Result<()> {
+    pool.write(HEADER_PAGE, |data| write_u32(data, COMMIT_LOG, page))
+}
@@ -0,0 +1,461 @@
+//! An on-disk B+tree mapping byte-string keys to record ids.
+//!
+//! Keys are unique within a tree; an index makes them unique by appending
+//! the record id (see [`entry_key`]). Leaves hold
+//! the entries in key order and are chained left to right for range scans.
+//! An internal node with keys `k1..kn` has children `c0..cn`, where `ci`
+//! holds the keys from `ki` up to `k(i+1)`.
+//!
+//! The root never moves, so the catalog can keep its page id: when the
+//! root splits, its halves move to two new pages and the root becomes
+//! their parent. Deletes do not merge underfull nodes.
+//!
+//! [`entry_key`]: crate::storage::key::entry_key
+
+use std::io;
+use std::ops::Bound;
+use std::vec;
+
+use crate::storage::allocator::{allocate_page, free_page};
+use crate::storage::buffer::BufferPool;
+use crate::storage::page::{
+    corrupt, format_page, page_kind, read_u16, read_u32, write_u16, write_u32, PageId, PageKind,
+    RecordId, PAGE_HEADER_SIZE, PAGE_SIZE,
+};
+
+const COUNT: usize = PAGE_HEADER_SIZE;
+/// The next leaf of a leaf, the first child of an internal node.
+const LINK: usize = PAGE_HEADER_SIZE + 4;
+const ENTRIES: usize = PAGE_HEADER_SIZE + 8;
+const RECORD_ID_SIZE: usize = 6;
+const CHILD_SIZE: usize = 4;
+
+/// The longest key a tree accepts, small enough that a node always holds
+/// several entries and either half of a split fits on a page.
+pub const MAX_KEY_SIZE: usize = (PAGE_SIZE - ENTRIES) / 4 - 2 - RECORD_ID_SIZE;
+
+/// The entries of a leaf with their keys, as a range scan reads them.
+pub type LeafEntries = Vec<(Vec<u8>, RecordId)>;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf {
+        entries: Vec<(Vec<u8>, RecordId)>,
+        next: PageId,
+    },
+    Internal {
+        keys: Vec<Vec<u8>>,
+        children: Vec<PageId>,
+    },
+}
+
+impl Node {
+    fn read(data: &[u8]) -> io::Result<Node> {
+        let count = read_u16(data, COUNT) as usize;
+        let link = read_u32(data, LINK);
+        let mut position = ENTRIES;
+        let key = |position: &mut usize| {
+            let length = read_u16(data, *position) as usize;
+            let key = data[*position + 2..*position + 2 + length].to_vec();
+            *position += 2 + length;
+            key
+        };
+        match page_kind(data)? {
+            PageKind::BTreeLeaf => {
+                let mut entries = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let key = key(&mut position);
+                    let id = RecordId {
+                        page: read_u32(data, position),
+                        slot: read_u16(data, position + 4),
+                    };
+                    position += RECORD_ID_SIZE;
+                    entries.push((key, id));
+                }
+                Ok(Node::Leaf {
+                    entries,
+                    next: link,
+                })
+            }
+            PageKind::BTreeInternal => {
+                let mut keys = Vec::with_capacity(count);
+                let mut children = vec![link];
+                for _ in 0..count {
+                    keys.push(key(&mut position));
+                    children.push(read_u32(data, position));
+                    position += CHILD_SIZE;
+                }
+                Ok(Node::Internal { keys, children })
+            }
+            _ => Err(corrupt("B+tree points at a page that is not a node")),
+        }
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        let mut position = ENTRIES;
+        let put_key = |data: &mut [u8], position: &mut usize, key: &[u8]| {
+            write_u16(data, *position, key.len() as u16);
+            data[*position + 2..*position + 2 + key.len()].copy_from_slice(key);
+            *position += 2 + key.len();
+        };
+        match self {
+            Node::Leaf { entries, next } => {
+                format_page(data, PageKind::BTreeLeaf);
+                write_u16(data, COUNT, entries.len() as u16);
+                write_u32(data, LINK, *next);
+                for (key, id) in entries {
+                    put_key(data, &mut position, key);
+                    write_u32(data, position, id.page);
+                    write_u16(data, position + 4, id.slot);
+                    position += RECORD_ID_SIZE;
+                }
+            }
+            Node::Internal { keys, children } => {
+                format_page(data, PageKind::BTreeInternal);
+                write_u16(data, COUNT, keys.len() as u16);
+                write_u32(data, LINK, children[0]);
+                for (key, child) in keys.iter().zip(&children[1..]) {
+                    put_key(data, &mut position, key);
+                    write_u32(data, position, *child);
+                    position += CHILD_SIZE;
+                }
+            }
+        }
+    }
+
+    fn size(&self) -> usize {
+        ENTRIES
+            + match self {
+                Node::Leaf { entries, .. } => entries
+                    .iter()
+                    .map(|(key, _)| 2 + key.len() + RECORD_ID_SIZE)
+                    .sum::<usize>(),
+                Node::Internal { keys, .. } => keys
+                    .iter()
+                    .map(|key| 2 + key.len() + CHILD_SIZE)
+                    .sum::<usize>(),
+            }
+    }
+
+    /// Splits an overfull node in two of about the same size, returning
+    /// the right half and the key that separates it from the left.
+    fn split(&mut self) -> (Vec<u8>, Node) {
+        let half = self.size() / 2;
+        match self {
+            Node::Leaf { entries, next } => {
+                let mut size = ENTRIES;
+                let mut middle = 0;
+                while size < half && middle < entries.len() - 1 {
+                    size += 2 + entries[middle].0.len() + RECORD_ID_SIZE;
+                    middle += 1;
+                }
+                let right = entries.split_off(middle.max(1));
+                let separator = right[0].0.clone();
+                (
+                    separator,
+                    Node::Leaf {
+                        entries: right,
+                        next: *next,
+                    },
+                )
+            }
+            Node::Internal { keys, children } => {
+                let mut size = ENTRIES;
+                let mut middle = 0;
+                while size < half && middle < keys.len() - 2 {
+                    size += 2 + keys[middle].len() + CHILD_SIZE;
+                    middle += 1;
+                }
+                let middle = middle.max(1);
+                let mut right_keys = keys.split_off(middle);
+                let separator = right_keys.remove(0);
+                let right_children = children.split_off(middle + 1);
+                (
+                    separator,
+                    Node::Internal {
+                        keys: right_keys,
+                        children: right_children,
+                    },
+                )
+            }
+        }
+    }
+}
+
+/// The child of an internal node whose range covers `key`.
+fn child_index(keys: &[Vec<u8>], key: &[u8]) -> usize {
+    keys.partition_point(|separator| separator.as_slice() <= key)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BTree {
+    root: PageId,
+}
+
+impl BTree {
+    pub fn new(root: PageId) -> Self {
+        BTree { root }
+    }
+
+    /// Creates an empty tree, a root that is a leaf.
+    pub fn create(pool: &mut BufferPool) -> io::Result<Self> {
+        let root = allocate_page(pool)?;
+        write_node(
+            pool,
+            root,
+            &Node::Leaf {
+                entries: Vec::new(),
+                next: 0,
+            },
+        )?;
+        Ok(BTree { root })
+    }
+
+    pub fn root(&self) -> PageId {
+        self.root
+    }
+
+    /// Adds an entry, returning `false` without changing the tree if the
+    /// key is already there.
+    pub fn insert(&self, pool: &mut BufferPool, key: &[u8], id: RecordId) -> io::Result<bool> {
+        if key.len() > MAX_KEY_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "index key of {} bytes is longer than the limit of {} bytes",
+                    key.len(),
+                    MAX_KEY_SIZE
+                ),
+            ));
+        }
+        let (mut path, page, mut node) = self.descend(pool, key)?;
+        if let Node::Leaf { entries, .. } = &mut node {
+            match entries.binary_search_by(|(existing, _)| existing.as_slice().cmp(key)) {
+                Ok(_) => return Ok(false),
+                Err(position) => entries.insert(position, (key.to_vec(), id)),
+            }
+        }
+        let mut page = page;
+        loop {
+            if node.size() <= PAGE_SIZE {
+                write_node(pool, page, &node)?;
+                return Ok(true);
+            }
+            let (separator, right) = node.split();
+            let right_page = allocate_page(pool)?;
+            if let Node::Leaf { next, .. } = &mut node {
+                *next = right_page;
+            }
+            let (parent_page, mut parent) = match path.pop() {
+                Some(parent) => parent,
+                None => {
+                    // The root keeps its page: its halves move out.
+                    let left_page = allocate_page(pool)?;
+                    write_node(pool, left_page, &node)?;
+                    write_node(pool, right_page, &right)?;
+                    let root = Node::Internal {
+                        keys: vec![separator],
+                        children: vec![left_page, right_page],
+                    };
+                    write_node(pool, page, &root)?;
+                    return Ok(true);
+                }
+            };
+            write_node(pool, page, &node)?;
+            write_node(pool, right_page, &right)?;
+            if let Node::Internal { keys, children } = &mut parent {
+                let position = child_index(keys, &separator);
+                keys.insert(position, separator);
+                children.insert(position + 1, right_page);
+            }
+            page = parent_page;
+            node = parent;
+        }
+    }
+
+    /// Removes the entry with `key`, returning whether there was one.
+    pub fn delete(&self, pool: &mut BufferPool, key: &[u8]) -> io::Result<bool> {
+        let (_, page, mut node) = self.descend(pool, key)?;
+        if let Node::Leaf { entries, .. } = &mut node {
+            if let Ok(position) =
+                entries.binary_search_by(|(existing, _)| existing.as_slice().cmp(key))
+            {
+                entries.remove(position);
+                write_node(pool, page, &node)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    pub fn get(&self, pool: &mut BufferPool, key: &[u8]) -> io::Result<Option<RecordId>> {
+        let (_, _, node) = self.descend(pool, key)?;
+        Ok(match node {
+            Node::Leaf { entries, .. } => entries
+                .binary_search_by(|(existing, _)| existing.as_slice().cmp(key))
+                .ok()
+                .map(|position| entries[position].1),
+            Node::Internal { .. } => None,
+        })
+    }
+
+    /// The entries with keys between `lower` and `upper`, in key order,
+    /// read a leaf at a time as the scan goes.
+    pub fn range<'a>(
+        &self,
+        pool: &'a mut BufferPool,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> BTreeRange<'a> {
+        BTreeRange {
+            tree: *self,
+            pool,
+            cursor: RangeCursor::new(lower, upper),
+            entries: Vec::new().into_iter(),
+        }
+    }
+
+    /// The entries in the range of `cursor` on the next leaf that has
+    /// any, moving the cursor past them, or `None` once there are no more.
+    pub fn next_leaf(
+        &self,
+        pool: &mut BufferPool,
+        cursor: &mut RangeCursor,
+    ) -> io::Result<Option<LeafEntries>> {
+        if cursor.done {
+            return Ok(None);
+        }
+        let start: &[u8] = match &cursor.lower {
+            Bound::Included(key) | Bound::Excluded(key) => key,
+            Bound::Unbounded => &[],
+        };
+        let (_, _, mut node) = self.descend(pool, start)?;
+        loop {
+            let (entries, next) = match node {
+                Node::Leaf { entries, next } => (entries, next),
+                Node::Internal { .. } => return Err(corrupt("B+tree leaf chain reaches a node")),
+            };
+            let mut found = Vec::new();
+            for (key, id) in entries {
+                let below = match &cursor.upper {
+                    Bound::Included(upper) => key <= *upper,
+                    Bound::Excluded(upper) => key < *upper,
+                    Bound::Unbounded => true,
+                };
+                if !below {
+                    cursor.done = true;
+                    break;
+                }
+                let above = match &cursor.lower {
+                    Bound::Included(lower) => key >= *lower,
+                    Bound::Excluded(lower) => key > *lower,
+                    Bound::Unbounded => true,
+                };
+                if above {
+                    found.push((key, id));
+                }
+            }
+            if next == 0 {
+                cursor.done = true;
+            }
+            if let Some((last, _)) = found.last() {
+                cursor.lower = Bound::Excluded(last.clone());
+                return Ok(Some(found));
+            }
+            if cursor.done {
+                return Ok(None);
+            }
+            node = read_node(pool, next)?;
+        }
+    }
+
+    /// Gives every page of the tree back to the allocator.
+    pub fn destroy(self, pool: &mut BufferPool) -> io::Result<()> {
+        let mut pages = vec![self.root];
+        while let Some(page) = pages.pop() {
+            if let Node::Internal { children, .. } = read_node(pool, page)? {
+                pages.extend(children);
+            }
+            free_page(pool, page)?;
+        }
+        Ok(())
+    }
+
+    /// Walks from the root to the leaf that covers `key`, returning the
+    /// internal nodes on the way, the leaf's page and the leaf.
+    #[allow(clippy::type_complexity)]
+    fn descend(
+        &self,
+        pool: &mut BufferPool,
+        key: &[u8],
+    ) -> io::Result<(Vec<(PageId, Node)>, PageId, Node)> {
+        let mut path = Vec::new();
+        let mut page = self.root;
+        loop {
+            let node = read_node(pool, page)?;
+            match &node {
+                Node::Leaf { .. } => return Ok((path, page, node)),
+                Node::Internal { keys, children } => {
+                    let child = children[child_index(keys, key)];
+                    path.push((page, node));
+                    page = child;
+                }
+            }
+        }
+    }
+}
+
+/// How far a range scan of a tree has got, kept apart from the tree and
+/// the pool so that the scan can let go of them between leaves. Each leaf
+/// is found again from the root by the last key read, so the scan goes on
+/// correctly over splits made in between.
+#[derive(Debug, Clone)]
+pub struct RangeCursor {
+    lower: Bound<Vec<u8>>,
+    upper: Bound<Vec<u8>>,
+    done: bool,
+}
+
+impl RangeCursor {
+    pub fn new(lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Self {
+        RangeCursor {
+            lower: lower.map(<[u8]>::to_vec),
+            upper: upper.map(<[u8]>::to_vec),
+            done: false,
+        }
+    }
+}
+
+/// A range scan of a tree, holding the entries of one leaf at a time.
+pub struct BTreeRange<'a> {
+    tree: BTree,
+    pool: &'a mut BufferPool,
+    cursor: RangeCursor,
+    entries: vec::IntoIter<(Vec<u8>, RecordId)>,
+}
+
+impl Iterator for BTreeRange<'_> {
+    type Item = io::Result<(Vec<u8>, RecordId)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(entry) = self.entries.next() {
+            return Some(Ok(entry));
+        }
+        match self.tree.next_leaf(self.pool, &mut self.cursor) {
+            Ok(Some(entries)) => {
+                self.entries = entries.into_iter();
+                self.entries.next().map(Ok)
+            }
+            Ok(None) => None,
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+fn read_node(pool: &mut BufferPool, page: PageId) -> io::Result<Node> {
+    pool.read(page, Node::read)?
+}
+
+fn write_node(pool: &mut BufferPool, page: PageId, node: &Node) -> io::Result<()> {
+    pool.write(page, |data| node.write(data))
+}
@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+
+use crate::storage::disk::DiskManager;
+use crate::storage::page::{page_lsn, set_page_lsn, PageId, PAGE_SIZE};
+use crate::storage::wal::{LogRecord, Lsn, TransactionId, Wal, NO_TRANSACTION};
+
+/// How the buffer pool picks the page to evict when every frame is taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// The page used longest ago.
+    Lru,
+    /// A hand sweeps the frames and evicts the first page not used since
+    /// its last pass; cheaper to maintain than exact LRU.
+    Clock,
+}
+
+/// Index of a frame of the buffer pool.
+pub type FrameId = usize;
+
+struct Frame {
+    page: Option<PageId>,
+    data: Box<[u8]>,
+    pins: usize,
+    dirty: bool,
+    /// When the frame was last used, for LRU.
+    used: u64,
+    /// Whether the frame was used since the clock hand last passed it.
+    referenced: bool,
+}
+
+/// A fixed number of page-sized frames caching pages of the data file.
+///
+/// A fetched page is pinned and stays in its frame until unpinned; only
+/// unpinned frames are evicted, their page written back first if it was
+/// changed. [`BufferPool::read`] and [`BufferPool::write`] pin a page for
+/// the duration of a closure.
+///
+/// Every change made through [`BufferPool::write`] is logged to the
+/// write-ahead log, and a changed page is only written back once the log
+/// is synced up to its LSN.
+pub struct BufferPool {
+    disk: DiskManager,
+    log: Wal,
+    frames: Vec<Frame>,
+    pages: HashMap<PageId, FrameId>,
+    free: Vec<FrameId>,
+    policy: EvictionPolicy,
+    clock: u64,
+    /// Frames by the time they were last used.
+    recency: BTreeMap<u64, FrameId>,
+    hand: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl BufferPool {
+    pub fn new(disk: DiskManager, log: Wal, capacity: usize, policy: EvictionPolicy) -> Self {
+        let capacity = capacity.max(1);
+        BufferPool {
+            disk,
+            log,
+            frames: (0..capacity)
+                .map(|_| Frame {
+                    page: None,
+                    data: vec![0; PAGE_SIZE].into(),
+                    pins: 0,
+                    dirty: false,
+                    used: 0,
+                    referenced: false,
+                })
+                .collect(),
+            pages: HashMap::new(),
+            free: (0..capacity).rev().collect(),
+            policy,
+            clock: 0,
+            recency: BTreeMap::new(),
+            hand: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn page_count(&self) -> PageId {
+        self.disk.page_count()
+    }
+
+    /// Pins `page`, reading it from disk if it is not cached.
+    pub fn fetch(&mut self, page: PageId) -> io::Result<FrameId> {
+        if let Some(&frame) = self.pages.get(&page) {
+            self.hits += 1;
+            self.pin(frame);
+            return Ok(frame);
+        }
+        self.misses += 1;
+        let frame = self.victim()?;
+        if let Err(error) = self.disk.read_page(page, &mut self.frames[frame].data) {
+            self.free.push(frame);
+            return Err(error);
+        }
+        self.install(frame, page);
+        Ok(frame)
+    }
+
+    /// Adds a zeroed page to the end of the file and pins it.
+    pub fn extend(&mut self) -> io::Result<(PageId, FrameId)> {
+        let frame = self.victim()?;
+        let page = match self.disk.extend() {
+            Ok(page) => page,
+            Err(error) => {
+                self.free.push(frame);
+                return Err(error);
+            }
+        };
+        self.frames[frame].data.fill(0);
+        self.install(frame, page);
+        Ok((page, frame))
+    }
+
+    pub fn data(&self, frame: FrameId) -> &[u8] {
+        &self.frames[frame].data
+    }
+
+    pub fn log(&mut self) -> &mut Wal {
+        &mut self.log
+    }
+
+    /// The page in `frame`, marked as changed.
+    fn data_mut(&mut self, frame: FrameId) -> &mut [u8] {
+        self.frames[frame].dirty = true;
+        &mut self.frames[frame].data
+    }
+
+    pub fn unpin(&mut self, frame: FrameId) {
+        let frame = &mut self.frames[frame];
+        frame.pins = frame.pins.saturating_sub(1);
+    }
+
+    pub fn read<T>(&mut self, page: PageId, f: impl FnOnce(&[u8]) -> T) -> io::Result<T> {
+        let frame = self.fetch(page)?;
+        let result = f(self.data(frame));
+        self.unpin(frame);
+        Ok(result)
+    }
+
+    /// Changes `page` for the running transaction, logging the change.
+    pub fn write<T>(&mut self, page: PageId, f: impl FnOnce(&mut [u8]) -> T) -> io::Result<T> {
+        let transaction = self.log.transaction();
+        self.write_as(transaction, page, f)
+    }
+
+    /// Like [`BufferPool::write`], but the change belongs to no
+    /// transaction: it is redone after a crash and never undone, as a
+    /// sequence must never give a value twice.
+    pub fn write_redo_only<T>(
+        &mut self,
+        page: PageId,
+        f: impl FnOnce(&mut [u8]) -> T,
+    ) -> io::Result<T> {
+        self.write_as(NO_TRANSACTION, page, f)
+    }
+
+    fn write_as<T>(
+        &mut self,
+        transaction: TransactionId,
+        page: PageId,
+        f: impl FnOnce(&mut [u8]) -> T,
+    ) -> io::Result<T> {
+        let frame = self.fetch(page)?;
+        let before = self.frames[frame].data.clone();
+        let result = f(&mut self.frames[frame].data);
+        let data = &mut self.frames[frame].data;
+        if let Some(lsn) = self.log.log_update(transaction, page, &before, data) {
+            set_page_lsn(data, lsn);
+            self.frames[frame].dirty = true;
+        }
+        self.unpin(frame);
+        Ok(result)
+    }
+
+    /// Repeats a logged change during recovery, unless the page already
+    /// has it. A change of the whole page is an image of it taken after a
+    /// checkpoint and always applies, as the page on disk may be torn.
+    pub fn redo(&mut self, page: PageId, offset: u16, bytes: &[u8], lsn: Lsn) -> io::Result<()> {
+        while self.page_count() <= page {
+            let (_, frame) = self.extend()?;
+            self.unpin(frame);
+        }
+        let frame = self.fetch(page)?;
+        let whole = bytes.len() == PAGE_SIZE;
+        if whole || page_lsn(self.data(frame)) < lsn {
+            let data = self.data_mut(frame);
+            data[offset as usize..offset as usize + bytes.len()].copy_from_slice(bytes);
+            set_page_lsn(data, lsn);
+        }
+        self.unpin(frame);
+        Ok(())
+    }
+
+    /// Puts back the bytes an update of `transaction` changed, logging a
+    /// compensation record whose undo continues at `undo_next`. Returns
+    /// the record's LSN.
+    pub fn compensate(
+        &mut self,
+        transaction: TransactionId,
+        previous: Lsn,
+        page: PageId,
+        offset: u16,
+        before: &[u8],
+        undo_next: Lsn,
+    ) -> io::Result<Lsn> {
+        let frame = self.fetch(page)?;
+        let lsn = self.log.append(
+            transaction,
+            previous,
+            &LogRecord::Compensation {
+                page,
+                offset,
+                after: before.to_vec(),
+                undo_next,
+            },
+        );
+        let data = self.data_mut(frame);
+        data[offset as usize..offset as usize + before.len()].copy_from_slice(before);
+        set_page_lsn(data, lsn);
+        self.unpin(frame);
+        Ok(lsn)
+    }
+
+    /// Writes every changed page back and syncs the file, after syncing
+    /// the log.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.log.force()?;
+        for frame in &mut self.frames {
+            if let (Some(page), true) = (frame.page, frame.dirty) {
+                self.disk.write_page(page, &frame.data)?;
+                frame.dirty = false;
+            }
+        }
+        self.disk.sync()
+    }
+
+    /// Flushes, and between transactions starts the log over, since the
+    /// data file now holds everything it describes.
+    pub fn checkpoint(&mut self) -> io::Result<()> {
+        self.flush()?;
+        if !self.log.in_transaction() {
+            self.log.restart()?;
+        }
+        Ok(())
+    }
+
+    fn pin(&mut self, frame: FrameId) {
+        self.clock += 1;
+        let entry = &mut self.frames[frame];
+        entry.pins += 1;
+        entry.referenced = true;
+        if self.policy == EvictionPolicy::Lru {
+            self.recency.remove(&entry.used);
+            self.recency.insert(self.clock, frame);
+        }
+        entry.used = self.clock;
+    }
+
+    fn install(&mut self, frame: FrameId, page: PageId) {
+        let entry = &mut self.frames[frame];
+        entry.page = Some(page);
+        entry.dirty = false;
+        entry.pins = 0;
+        self.pages.insert(page, frame);
+        self.pin(frame);
+    }
+
+    /// A frame to load a page into: an empty one if there is any, else one
+    /// whose page is evicted, after writing it back if it changed.
+    fn victim(&mut self) -> io::Result<FrameId> {
+        if let Some(frame) = self.free.pop() {
+            return Ok(frame);
+        }
+        let frame = match self.policy {
+            EvictionPolicy::Lru => self
+                .recency
+                .values()
+                .copied()
+                .find(|&frame| self.frames[frame].pins == 0),
+            EvictionPolicy::Clock => self.sweep(),
+        }
+        .ok_or_else(|| io::Error::other("every page of the buffer pool is pinned"))?;
+        let entry = &mut self.frames[frame];
+        if let Some(page) = entry.page.take() {
+            if entry.dirty {
+                self.log.force_to(page_lsn(&entry.data))?;
+                self.disk.write_page(page, &entry.data)?;
+                entry.dirty = false;
+            }
+            self.pages.remove(&page);
+        }
+        self.recency.remove(&entry.used);
+        Ok(frame)
+    }
+
+    /// Advances the clock hand to the first unpinned frame not referenced
+    /// since the last pass, clearing reference bits on the way. Two full
+    /// turns without a candidate mean every frame is pinned.
+    fn sweep(&mut self) -> Option<FrameId> {
+        for _ in 0..2 * self.frames.len() {
+            let frame = self.hand;
+            self.hand = (self.hand + 1) % self.frames.len();
+            let entry = &mut self.frames[frame];
+            if entry.pins > 0 {
+                continue;
+            }
+            if entry.referenced {
+                entry.referenced = false;
+                continue;
+            }
+            return Some(frame);
+        }
+        None
+    }
+}
+
+/// Like a buffered writer, the pool takes a checkpoint when dropped,
+/// ignoring errors; call [`BufferPool::checkpoint`] to see them.
+impl Drop for BufferPool {
+    fn drop(&mut self) {
+        let _ = self.checkpoint();
+    }
+}
@@ -0,0 +1,1059 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::compiler::binder::{BindError, BindErrorKind};
+use crate::compiler::parser::{
+    parse_data_type, parse_expression, CreateQuery, CreateSequenceQuery, DataType, IndexMethod,
+    SQLExpression,
+};
+use crate::compiler::walk::column_references;
+use crate::storage::page::PageId;
+use crate::types::cast::integer_range;
+use crate::types::value::Value;
+
+/// Tables created before any `USE` go here; it always exists.
+pub const DEFAULT_DATABASE: &str = "main";
+
+const CATALOG_FILE: &str = "catalog";
+const CATALOG_MAGIC: &[u8; 8] = b"OMNICAT1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub data_type: DataType,
+    pub is_primary: bool,
+    pub not_null: bool,
+    pub identity: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexSchema {
+    pub name: String,
+    /// The key: column names, or the text of expressions over the columns.
+    pub columns: Vec<String>,
+    /// The text of the condition of a partial index.
+    pub predicate: Option<String>,
+    pub unique: bool,
+    pub method: IndexMethod,
+    /// Root page of the index in the data file, once it has one.
+    pub root: Option<PageId>,
+}
+
+impl IndexSchema {
+    /// The parts of the key, parsed.
+    pub fn keys(&self) -> io::Result<Vec<SQLExpression>> {
+        self.columns
+            .iter()
+            .map(|text| parse_expression(text).ok_or_else(|| corrupt("unreadable index key")))
+            .collect()
+    }
+
+    /// The condition of a partial index, parsed.
+    pub fn condition(&self) -> io::Result<Option<SQLExpression>> {
+        self.predicate
+            .as_deref()
+            .map(|text| parse_expression(text).ok_or_else(|| corrupt("unreadable index predicate")))
+            .transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstraintKind {
+    PrimaryKey,
+    Unique,
+    NotNull,
+}
+
+impl ConstraintKind {
+    /// The name information_schema uses for the kind.
+    pub fn name(self) -> &'static str {
+        match self {
+            ConstraintKind::PrimaryKey => "PRIMARY KEY",
+            ConstraintKind::Unique => "UNIQUE",
+            ConstraintKind::NotNull => "NOT NULL",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "PRIMARY KEY" => Some(ConstraintKind::PrimaryKey),
+            "UNIQUE" => Some(ConstraintKind::Unique),
+            "NOT NULL" => Some(ConstraintKind::NotNull),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintSchema {
+    pub name: String,
+    pub kind: ConstraintKind,
+    pub columns: Vec<String>,
+}
+
+/// A sequence of a database. How far it has got is kept on its page in
+/// the data file; see `storage::sequence`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceSchema {
+    pub database: String,
+    pub name: String,
+    pub start: i128,
+    pub increment: i128,
+    pub min: i128,
+    pub max: i128,
+    /// How many values are allocated at a time.
+    pub cache: i128,
+    pub cycle: bool,
+    /// The table and identity column the sequence fills, if it is one's.
+    pub owner: Option<(String, String)>,
+    pub page: Option<PageId>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSchema {
+    pub database: String,
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+    pub indexes: Vec<IndexSchema>,
+    pub constraints: Vec<ConstraintSchema>,
+    /// Root page of the table's heap in the data file, once it has one.
+    pub heap: Option<PageId>,
+    pub vacuum: VacuumSettings,
+}
+
+impl TableSchema {
+    pub fn column(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+/// When autovacuum takes care of a table, as `ALTER TABLE ... SET` gives
+/// it. What is left unset follows the settings of the worker.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct VacuumSettings {
+    pub enabled: Option<bool>,
+    /// Dead versions the table may have before it is vacuumed...
+    pub threshold: Option<u64>,
+    /// ...plus this fraction of its live ones.
+    pub scale_factor: Option<f64>,
+}
+
+impl VacuumSettings {
+    /// Sets the storage option `name` to `value`, the text of a constant,
+    /// or resets it without one.
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<(), BindError> {
+        let invalid = |message: String| {
+            BindError::new(
+                BindErrorKind::InvalidValue(message),
+                Some(value.unwrap_or(name).to_string()),
+            )
+        };
+        let value = match value {
+            Some(value) => value,
+            None => {
+                match name {
+                    "autovacuum_enabled" => self.enabled = None,
+                    "autovacuum_vacuum_threshold" => self.threshold = None,
+                    "autovacuum_vacuum_scale_factor" => self.scale_factor = None,
+                    _ => return Err(invalid(format!("unrecognized parameter \"{}\"", name))),
+                }
+                return Ok(());
+            }
+        };
+        match name {
+            "autovacuum_enabled" => {
+                self.enabled = Some(match value.to_uppercase().as_str() {
+                    "TRUE" | "ON" => true,
+                    "FALSE" | "OFF" => false,
+                    _ => {
+                        return Err(invalid(format!(
+                            "invalid value for boolean option \"{}\": {}",
+                            name, value
+                        )))
+                    }
+                })
+            }
+            "autovacuum_vacuum_threshold" => {
+                let threshold = value.parse::<i64>().map_err(|_| {
+                    invalid(format!(
+                        "invalid value for integer option \"{}\": {}",
+                        name, value
+                    ))
+                })?;
+                if !(0..=i32::MAX as i64).contains(&threshold) {
+                    return Err(invalid(format!(
+                        "value {} out of bounds for option \"{}\", which must be between 0 and {}",
+                        value,
+                        name,
+                        i32::MAX
+                    )));
+                }
+                self.threshold = Some(threshold as u64)
+            }
+            "autovacuum_vacuum_scale_factor" => {
+                self.scale_factor = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|factor: &f64| (0.0..=100.0).contains(factor))
+                        .ok_or_else(|| {
+                            invalid(format!(
+                                "value {} out of bounds for option \"{}\", which must be between 0 and 100",
+                                value, name
+                            ))
+                        })?,
+                )
+            }
+            _ => return Err(invalid(format!("unrecognized parameter \"{}\"", name))),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    Bind(BindError),
+    Io(io::Error),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Bind(error) => write!(f, "{}", error),
+            CatalogError::Io(error) => write!(f, "catalog i/o error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+impl From<BindError> for CatalogError {
+    fn from(error: BindError) -> Self {
+        CatalogError::Bind(error)
+    }
+}
+
+impl From<io::Error> for CatalogError {
+    fn from(error: io::Error) -> Self {
+        CatalogError::Io(error)
+    }
+}
+
+/// Databases, their tables with columns, indexes and constraints, and
+/// their sequences.
+///
+/// An opened catalog lives in a directory and is written out after every
+/// change, as the rows of its system tables: one record per database,
+/// table, column, index, constraint and sequence, each a sequence of values in the
+/// spill format of [`Value::write_to`]. The new file replaces the old one
+/// by rename only once it is synced, so a crash leaves one or the other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catalog {
+    databases: Vec<String>,
+    current: String,
+    tables: Vec<TableSchema>,
+    sequences: Vec<SequenceSchema>,
+    directory: Option<PathBuf>,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Catalog::new()
+    }
+}
+
+impl Catalog {
+    /// A catalog kept in memory only.
+    pub fn new() -> Self {
+        Catalog {
+            databases: vec![DEFAULT_DATABASE.to_string()],
+            current: DEFAULT_DATABASE.to_string(),
+            tables: Vec::new(),
+            sequences: Vec::new(),
+            directory: None,
+        }
+    }
+
+    /// Loads the catalog saved in `directory`, creating an empty one there
+    /// if there is none yet.
+    pub fn open<P: AsRef<Path>>(directory: P) -> Result<Self, CatalogError> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory)?;
+        let path = directory.join(CATALOG_FILE);
+        let mut catalog = if path.exists() {
+            let mut reader = BufReader::new(File::open(&path)?);
+            Catalog::read_from(&mut reader)?
+        } else {
+            Catalog::new()
+        };
+        catalog.directory = Some(directory);
+        if !path.exists() {
+            catalog.save()?;
+        }
+        Ok(catalog)
+    }
+
+    pub fn from_definitions(definitions: &[CreateQuery]) -> Result<Self, CatalogError> {
+        let mut catalog = Catalog::new();
+        for definition in definitions {
+            catalog.create(definition)?;
+        }
+        Ok(catalog)
+    }
+
+    pub fn databases(&self) -> &[String] {
+        &self.databases
+    }
+
+    pub fn current_database(&self) -> &str {
+        &self.current
+    }
+
+    /// Tables of every database.
+    pub fn tables(&self) -> &[TableSchema] {
+        &self.tables
+    }
+
+    /// A table of the current database.
+    pub fn table(&self, name: &str) -> Option<&TableSchema> {
+        self.tables
+            .iter()
+            .find(|table| table.database == self.current && table.name == name)
+    }
+
+    /// An index of the current database and the table it belongs to.
+    pub fn index(&self, name: &str) -> Option<(&TableSchema, &IndexSchema)> {
+        self.tables
+            .iter()
+            .filter(|table| table.database == self.current)
+            .find_map(|table| {
+                table
+                    .indexes
+                    .iter()
+                    .find(|index| index.name == name)
+                    .map(|index| (table, index))
+            })
+    }
+
+    /// Sequences of every database.
+    pub fn sequences(&self) -> &[SequenceSchema] {
+        &self.sequences
+    }
+
+    /// A sequence of the current database.
+    pub fn sequence(&self, name: &str) -> Option<&SequenceSchema> {
+        self.sequences
+            .iter()
+            .find(|sequence| sequence.database == self.current && sequence.name == name)
+    }
+
+    /// The sequence that fills an identity column of a table of the
+    /// current database.
+    pub fn identity_sequence(&self, table: &str, column: &str) -> Option<&SequenceSchema> {
+        self.sequences.iter().find(|sequence| {
+            sequence.database == self.current
+                && sequence
+                    .owner
+                    .as_ref()
+                    .is_some_and(|(owner, identity)| owner == table && identity == column)
+        })
+    }
+
+    /// Switches the database that names without one refer to. This is
+    /// session state and is not saved.
+    pub fn use_database(&mut self, name: &str) -> Result<(), CatalogError> {
+        if !self.databases.iter().any(|database| database == name) {
+            return Err(BindError::new(
+                BindErrorKind::UnknownDatabase(name.to_string()),
+                Some(name.to_string()),
+            )
+            .into());
+        }
+        self.current = name.to_string();
+        Ok(())
+    }
+
+    /// Records a CREATE DATABASE or CREATE TABLE, the latter in the current
+    /// database with the sequences of its identity columns.
+    pub fn create(&mut self, definition: &CreateQuery) -> Result<(), CatalogError> {
+        if definition.created == "DATABASE" {
+            return self.create_database(&definition.name);
+        }
+        let table = match table_schema(definition, &self.current)? {
+            Some(table) => table,
+            None => return Ok(()),
+        };
+        if self.table(&table.name).is_some() {
+            return Err(BindError::new(
+                BindErrorKind::DuplicateTable(table.name.clone()),
+                Some(table.name),
+            )
+            .into());
+        }
+        let sequences = identity_sequences(&table);
+        if let Some(sequence) = sequences
+            .iter()
+            .find(|sequence| self.sequence(&sequence.name).is_some())
+        {
+            return Err(BindError::new(
+                BindErrorKind::DuplicateSequence(sequence.name.clone()),
+                Some(table.name),
+            )
+            .into());
+        }
+        self.tables.push(table);
+        self.sequences.extend(sequences);
+        self.save()
+    }
+
+    /// Adds a sequence to the current database.
+    pub fn create_sequence(&mut self, sequence: SequenceSchema) -> Result<(), CatalogError> {
+        if self.sequence(&sequence.name).is_some() {
+            return Err(BindError::new(
+                BindErrorKind::DuplicateSequence(sequence.name.clone()),
+                Some(sequence.name),
+            )
+            .into());
+        }
+        self.sequences.push(SequenceSchema {
+            database: self.current.clone(),
+            ..sequence
+        });
+        self.save()
+    }
+
+    /// Records the page of a sequence of `database`.
+    pub fn set_sequence_page(
+        &mut self,
+        database: &str,
+        name: &str,
+        page: PageId,
+    ) -> Result<(), CatalogError> {
+        let sequence = self
+            .sequences
+            .iter_mut()
+            .find(|sequence| sequence.database == database && sequence.name == name)
+            .ok_or_else(|| {
+                BindError::new(
+                    BindErrorKind::UnknownSequence(name.to_string()),
+                    Some(name.to_string()),
+                )
+            })?;
+        sequence.page = Some(page);
+        self.save()
+    }
+
+    /// Records where the heap of a table of `database` starts.
+    pub fn set_heap(
+        &mut self,
+        database: &str,
+        table: &str,
+        heap: PageId,
+    ) -> Result<(), CatalogError> {
+        let schema = self
+            .tables
+            .iter_mut()
+            .find(|schema| schema.database == database && schema.name == table)
+            .ok_or_else(|| {
+                BindError::new(
+                    BindErrorKind::UnknownTable(table.to_string()),
+                    Some(table.to_string()),
+                )
+            })?;
+        schema.heap = Some(heap);
+        self.save()
+    }
+
+    /// Records where an index of `database` starts.
+    pub fn set_index_root(
+        &mut self,
+        database: &str,
+        index: &str,
+        root: PageId,
+    ) -> Result<(), CatalogError> {
+        let schema = self
+            .tables
+            .iter_mut()
+            .filter(|schema| schema.database == database)
+            .flat_map(|schema| schema.indexes.iter_mut())
+            .find(|schema| schema.name == index)
+            .ok_or_else(|| {
+                BindError::new(
+                    BindErrorKind::UnknownIndex(index.to_string()),
+                    Some(index.to_string()),
+                )
+            })?;
+        schema.root = Some(root);
+        self.save()
+    }
+
+    /// Records the autovacuum settings of a table of the current database.
+    pub fn set_vacuum_settings(
+        &mut self,
+        table: &str,
+        settings: VacuumSettings,
+    ) -> Result<(), CatalogError> {
+        let current = self.current.clone();
+        let schema = self
+            .tables
+            .iter_mut()
+            .find(|schema| schema.database == current && schema.name == table)
+            .ok_or_else(|| {
+                BindError::new(
+                    BindErrorKind::UnknownTable(table.to_string()),
+                    Some(table.to_string()),
+                )
+            })?;
+        schema.vacuum = settings;
+        self.save()
+    }
+
+    pub fn create_database(&mut self, name: &str) -> Result<(), CatalogError> {
+        if self.databases.iter().any(|database| database == name) {
+            return Err(BindError::new(
+                BindErrorKind::DuplicateDatabase(name.to_string()),
+                Some(name.to_string()),
+            )
+            .into());
+        }
+        self.databases.push(name.to_string());
+        self.save()
+    }
+
+    /// Adds an index to a table of the current database. Index names are
+    /// unique within a database.
+    pub fn create_index(&mut self, table: &str, index: IndexSchema) -> Result<(), CatalogError> {
+        if self.index(&index.name).is_some() {
+            return Err(BindError::new(
+                BindErrorKind::DuplicateIndex(index.name.clone()),
+                Some(index.name),
+            )
+            .into());
+        }
+        let current = self.current.clone();
+        let schema = self
+            .tables
+            .iter_mut()
+            .find(|existing| existing.database == current && existing.name == table)
+            .ok_or_else(|| {
+                BindError::new(
+                    BindErrorKind::UnknownTable(table.to_string()),
+                    Some(table.to_string()),
+                )
+            })?;
+        let keys = index.keys()?;
+        let condition = index.condition()?;
+        let mut references = Vec::new();
+        for expression in keys.iter().chain(&condition) {
+            column_references(expression, &mut references);
+        }
+        for reference in references {
+            let column = match reference {
+                SQLExpression::QualifiedIdentifier { column, .. } => column,
+                SQLExpression::Identifier(column) => column,
+                _ => continue,
+            };
+            if schema.column(column).is_none() {
+                return Err(BindError::new(
+                    BindErrorKind::UnknownColumn(format!("{}.{}", table, column)),
+                    Some(column.clone()),
+                )
+                .into());
+            }
+        }
+        schema.indexes.push(index);
+        self.save()
+    }
+
+    /// Writes the catalog to its directory, if it has one.
+    pub fn save(&self) -> Result<(), CatalogError> {
+        let directory = match &self.directory {
+            Some(directory) => directory,
+            None => return Ok(()),
+        };
+        let temporary = directory.join(format!("{}.tmp", CATALOG_FILE));
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        self.write_to(&mut writer)?;
+        let file = writer.into_inner().map_err(|error| error.into_error())?;
+        file.sync_all()?;
+        fs::rename(&temporary, directory.join(CATALOG_FILE))?;
+        // Persists the rename itself.
+        File::open(directory)?.sync_all()?;
+        Ok(())
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(CATALOG_MAGIC)?;
+        for database in &self.databases {
+            write_record(writer, "database", &[text(database)])?;
+        }
+        for table in &self.tables {
+            write_record(
+                writer,
+                "table",
+                &[
+                    text(&table.database),
+                    text(&table.name),
+                    page_value(table.heap),
+                    table.vacuum.enabled.map_or(Value::Null, Value::Bool),
+                    table
+                        .vacuum
+                        .threshold
+                        .map_or(Value::Null, |threshold| Value::Integer(threshold.into())),
+                    table.vacuum.scale_factor.map_or(Value::Null, Value::Float),
+                ],
+            )?;
+            for column in &table.columns {
+                let length = match column.data_type {
+                    DataType::TEXT { length } => Value::Integer(length as i128),
+                    _ => Value::Null,
+                };
+                write_record(
+                    writer,
+                    "column",
+                    &[
+                        text(&column.name),
+                        text(&column.data_type.to_string()),
+                        length,
+                        Value::Bool(column.is_primary),
+                        Value::Bool(column.not_null),
+                        Value::Bool(column.identity),
+                    ],
+                )?;
+            }
+            for index in &table.indexes {
+                let mut values = vec![
+                    text(&index.name),
+                    Value::Bool(index.unique),
+                    page_value(index.root),
+                    text(&index.method.to_string()),
+                ];
+                values.extend(names(&index.columns));
+                // Written last, so that indexes recorded before partial
+                // indexes existed read as having no predicate.
+                values.push(index.predicate.as_deref().map_or(Value::Null, text));
+                write_record(writer, "index", &values)?;
+            }
+            for constraint in &table.constraints {
+                let mut values = vec![text(&constraint.name), text(constraint.kind.name())];
+                values.extend(names(&constraint.columns));
+                write_record(writer, "constraint", &values)?;
+            }
+        }
+        for sequence in &self.sequences {
+            let (table, column) = match &sequence.owner {
+                Some((table, column)) => (text(table), text(column)),
+                None => (Value::Null, Value::Null),
+            };
+            write_record(
+                writer,
+                "sequence",
+                &[
+                    text(&sequence.database),
+                    text(&sequence.name),
+                    Value::Integer(sequence.start),
+                    Value::Integer(sequence.increment),
+                    Value::Integer(sequence.min),
+                    Value::Integer(sequence.max),
+                    Value::Integer(sequence.cache),
+                    Value::Bool(sequence.cycle),
+                    page_value(sequence.page),
+                    table,
+                    column,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Reads a catalog written by `write_to`. Columns, indexes and
+    /// constraints belong to the table record before them.
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != CATALOG_MAGIC {
+            return Err(corrupt("not a catalog file"));
+        }
+        let mut catalog = Catalog {
+            databases: Vec::new(),
+            current: DEFAULT_DATABASE.to_string(),
+            tables: Vec::new(),
+            sequences: Vec::new(),
+            directory: None,
+        };
+        while let Some((kind, values)) = read_record(reader)? {
+            if kind == "database" {
+                catalog.databases.push(field_text(&values, 0)?);
+                continue;
+            }
+            if kind == "sequence" {
+                catalog.sequences.push(SequenceSchema {
+                    database: field_text(&values, 0)?,
+                    name: field_text(&values, 1)?,
+                    start: field_integer(&values, 2)?,
+                    increment: field_integer(&values, 3)?,
+                    min: field_integer(&values, 4)?,
+                    max: field_integer(&values, 5)?,
+                    cache: field_integer(&values, 6)?,
+                    cycle: field_bool(&values, 7)?,
+                    page: field_page(&values, 8),
+                    owner: match (values.get(9), values.get(10)) {
+                        (Some(Value::Text(table)), Some(Value::Text(column))) => {
+                            Some((table.clone(), column.clone()))
+                        }
+                        _ => None,
+                    },
+                });
+                continue;
+            }
+            if kind == "table" {
+                catalog.tables.push(TableSchema {
+                    database: field_text(&values, 0)?,
+                    name: field_text(&values, 1)?,
+                    columns: Vec::new(),
+                    indexes: Vec::new(),
+                    constraints: Vec::new(),
+                    heap: field_page(&values, 2),
+                    // Tables recorded before autovacuum have no settings.
+                    vacuum: VacuumSettings {
+                        enabled: match values.get(3) {
+                            Some(Value::Bool(enabled)) => Some(*enabled),
+                            _ => None,
+                        },
+                        threshold: match values.get(4) {
+                            Some(Value::Integer(threshold)) => Some(*threshold as u64),
+                            _ => None,
+                        },
+                        scale_factor: match values.get(5) {
+                            Some(Value::Float(factor)) => Some(*factor),
+                            _ => None,
+                        },
+                    },
+                });
+                continue;
+            }
+            let table = catalog
+                .tables
+                .last_mut()
+                .ok_or_else(|| corrupt("record outside of a table"))?;
+            match kind.as_str() {
+                "column" => {
+                    let mut data_type = parse_data_type(&field_text(&values, 1)?)
+                        .ok_or_else(|| corrupt("unknown column type"))?;
+                    if let (DataType::TEXT { length }, Some(Value::Integer(stored))) =
+                        (&mut data_type, values.get(2))
+                    {
+                        *length = *stored as i32;
+                    }
+                    table.columns.push(ColumnSchema {
+                        name: field_text(&values, 0)?,
+                        data_type,
+                        is_primary: field_bool(&values, 3)?,
+                        not_null: field_bool(&values, 4)?,
+                        identity: field_bool(&values, 5)?,
+                    });
+                }
+                "index" => {
+                    let columns = field_names(&values, 4)?;
+                    let predicate = match values.get(5 + columns.len()) {
+                        Some(Value::Text(predicate)) => Some(predicate.clone()),
+                        _ => None,
+                    };
+                    table.indexes.push(IndexSchema {
+                        name: field_text(&values, 0)?,
+                        unique: field_bool(&values, 1)?,
+                        root: field_page(&values, 2),
+                        method: match field_text(&values, 3)?.as_str() {
+                            "BTREE" => IndexMethod::BTree,
+                            "HASH" => IndexMethod::Hash,
+                            _ => return Err(corrupt("unknown index method")),
+                        },
+                        columns,
+                        predicate,
+                    })
+                }
+                "constraint" => table.constraints.push(ConstraintSchema {
+                    name: field_text(&values, 0)?,
+                    kind: ConstraintKind::from_name(&field_text(&values, 1)?)
+                        .ok_or_else(|| corrupt("unknown constraint kind"))?,
+                    columns: field_names(&values, 2)?,
+                }),
+                _ => return Err(corrupt("unknown record kind")),
+            }
+        }
+        if !catalog
+            .databases
+            .iter()
+            .any(|database| database == DEFAULT_DATABASE)
+        {
+            catalog.databases.insert(0, DEFAULT_DATABASE.to_string());
+        }
+        Ok(catalog)
+    }
+}
+
+/// The sequence a CREATE SEQUENCE defines in `database`, its options
+/// filled in and checked. Without MINVALUE and MAXVALUE an ascending
+/// sequence runs from 1 and a descending one from -1, to the limits of a
+/// `LONG`; it starts at the end it runs from.
+pub fn sequence_schema(
+    definition: &CreateSequenceQuery,
+    database: &str,
+) -> Result<SequenceSchema, BindError> {
+    let invalid = |message: String| {
+        BindError::new(
+            BindErrorKind::InvalidQuery(message),
+            Some(definition.name.clone()),
+        )
+    };
+    let increment = definition.increment.unwrap_or(1);
+    if increment == 0 {
+        return Err(invalid("INCREMENT must not be zero".to_string()));
+    }
+    let ascending = increment > 0;
+    let min = definition
+        .min
+        .unwrap_or(if ascending { 1 } else { i64::MIN as i128 });
+    let max = definition
+        .max
+        .unwrap_or(if ascending { i64::MAX as i128 } else { -1 });
+    if min >= max {
+        return Err(invalid(format!(
+            "MINVALUE ({}) must be less than MAXVALUE ({})",
+            min, max
+        )));
+    }
+    let start = definition
+        .start
+        .unwrap_or(if ascending { min } else { max });
+    if !(min..=max).contains(&start) {
+        return Err(invalid(format!(
+            "START value ({}) must be between MINVALUE ({}) and MAXVALUE ({})",
+            start, min, max
+        )));
+    }
+    let cache = definition.cache.unwrap_or(1);
+    if cache < 1 {
+        return Err(invalid(format!("CACHE ({}) must be at least 1", cache)));
+    }
+    Ok(SequenceSchema {
+        database: database.to_string(),
+        name: definition.name.clone(),
+        start,
+        increment,
+        min,
+        max,
+        cache,
+        cycle: definition.cycle,
+        owner: None,
+        page: None,
+    })
+}
+
+/// The `<table>_<column>_seq` sequences that fill the identity columns of
+/// `table`, counting up from 1 to the largest value of the column's type.
+fn identity_sequences(table: &TableSchema) -> Vec<SequenceSchema> {
+    table
+        .columns
+        .iter()
+        .filter(|column| column.identity)
+        .filter_map(|column| {
+            let (_, max) = integer_range(&column.data_type)?;
+            Some(SequenceSchema {
+                database: table.database.clone(),
+                name: format!("{}_{}_seq", table.name, column.name),
+                start: 1,
+                increment: 1,
+                min: 1,
+                max,
+                cache: 1,
+                cycle: false,
+                owner: Some((table.name.clone(), column.name.clone())),
+                page: None,
+            })
+        })
+        .collect()
+}
+
+/// The schema a CREATE TABLE defines in `database`, after checking that
+/// its column names are unique. Primary key columns make up the
+/// `<table>_pkey` constraint and the unique index of the same name, and
+/// every column that cannot hold NULL gets
+/// a `<table>_<column>_not_null` one.
+pub fn table_schema(
+    definition: &CreateQuery,
+    database: &str,
+) -> Result<Option<TableSchema>, BindError> {
+    let definitions = match (definition.created.as_str(), &definition.columns) {
+        ("TABLE", Some(columns)) => columns,
+        _ => return Ok(None),
+    };
+    let mut columns: Vec<ColumnSchema> = Vec::with_capacity(definitions.len());
+    for column in definitions {
+        if let SQLExpression::CreateColumn {
+            name,
+            data_type,
+            is_primary,
+            not_null,
+            identity,
+        } = column
+        {
+            if columns.iter().any(|column| column.name == *name) {
+                return Err(BindError::new(
+                    BindErrorKind::DuplicateColumn(format!("{}.{}", definition.name, name)),
+                    Some(name.clone()),
+                ));
+            }
+            if *identity && integer_range(data_type).is_none() {
+                return Err(BindError::new(
+                    BindErrorKind::TypeMismatch(format!(
+                        "identity column \"{}.{}\" must have an integer type, not {}",
+                        definition.name, name, data_type
+                    )),
+                    Some(name.clone()),
+                ));
+            }
+            columns.push(ColumnSchema {
+                name: name.clone(),
+                data_type: data_type.clone(),
+                is_primary: *is_primary,
+                not_null: *not_null,
+                identity: *identity,
+            });
+        }
+    }
+    let mut constraints = Vec::new();
+    let mut indexes = Vec::new();
+    let primary: Vec<String> = columns
+        .iter()
+        .filter(|column| column.is_primary)
+        .map(|column| column.name.clone())
+        .collect();
+    if !primary.is_empty() {
+        indexes.push(IndexSchema {
+            name: format!("{}_pkey", definition.name),
+            columns: primary.clone(),
+            predicate: None,
+            unique: true,
+            method: IndexMethod::BTree,
+            root: None,
+        });
+        constraints.push(ConstraintSchema {
+            name: format!("{}_pkey", definition.name),
+            kind: ConstraintKind::PrimaryKey,
+            columns: primary,
+        });
+    }
+    for column in columns
+        .iter()
+        .filter(|column| column.not_null || column.is_primary)
+    {
+        constraints.push(ConstraintSchema {
+            name: format!("{}_{}_not_null", definition.name, column.name),
+            kind: ConstraintKind::NotNull,
+            columns: vec![column.name.clone()],
+        });
+    }
+    Ok(Some(TableSchema {
+        database: database.to_string(),
+        name: definition.name.clone(),
+        columns,
+        indexes,
+        constraints,
+        heap: None,
+        vacuum: VacuumSettings::default(),
+    }))
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+/// A list of names stored as its length followed by the names.
+fn names(names: &[String]) -> Vec<Value> {
+    let mut values = vec![Value::Integer(names.len() as i128)];
+    values.extend(names.iter().map(|name| text(name)));
+    values
+}
+
+/// A record is its kind, the number of values and the values.
+fn write_record<W: Write>(writer: &mut W, kind: &str, values: &[Value]) -> io::Result<()> {
+    text(kind).write_to(writer)?;
+    Value::Integer(values.len() as i128).write_to(writer)?;
+    for value in values {
+        value.write_to(writer)?;
+    }
+    Ok(())
+}
+
+fn read_record<R: Read>(reader: &mut R) -> io::Result<Option<(String, Vec<Value>)>> {
+    let kind = match Value::read_from(reader)? {
+        Some(Value::Text(kind)) => kind,
+        Some(_) => return Err(corrupt("record without a kind")),
+        None => return Ok(None),
+    };
+    let count = match Value::read_from(reader)? {
+        Some(Value::Integer(count)) if count >= 0 => count as usize,
+        _ => return Err(corrupt("record without a length")),
+    };
+    let mut values = Vec::with_capacity(count);
+    for _ in 0..count {
+        values.push(Value::read_from(reader)?.ok_or_else(|| corrupt("truncated record"))?);
+    }
+    Ok(Some((kind, values)))
+}
+
+/// A page id, or NULL for none.
+fn page_value(page: Option<PageId>) -> Value {
+    match page {
+        Some(page) => Value::Integer(page as i128),
+        None => Value::Null,
+    }
+}
+
+fn field_page(values: &[Value], position: usize) -> Option<PageId> {
+    match values.get(position) {
+        Some(Value::Integer(page)) => Some(*page as PageId),
+        _ => None,
+    }
+}
+
+fn field_text(values: &[Value], position: usize) -> io::Result<String> {
+    match values.get(position) {
+        Some(Value::Text(text)) => Ok(text.clone()),
+        _ => Err(corrupt("expected a name")),
+    }
+}
+
+fn field_integer(values: &[Value], position: usize) -> io::Result<i128> {
+    match values.get(position) {
+        Some(Value::Integer(value)) => Ok(*value),
+        _ => Err(corrupt("expected an integer")),
+    }
+}
+
+fn field_bool(values: &[Value], position: usize) -> io::Result<bool> {
+    match values.get(position) {
+        Some(Value::Bool(value)) => Ok(*value),
+        _ => Err(corrupt("expected a flag")),
+    }
+}
+
+fn field_names(values: &[Value], position: usize) -> io::Result<Vec<String>> {
+    let count = match values.get(position) {
+        Some(Value::Integer(count)) => *count as usize,
+        _ => return Err(corrupt("expected a name count")),
+    };
+    (0..count)
+        .map(|offset| field_text(values, position + 1 + offset))
+        .collect()
+}
+
+fn corrupt(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("corrupt catalog: {}", message),
+    )
+}
@@ -0,0 +1,72 @@
+//! Which transactions committed, a bit per transaction id.
+//!
+//! The bits are kept on a chain of pages that the file header points at,
+//! each page holding the bits of the next [`XIDS_PER_PAGE`] ids. A
+//! transaction whose bit is clear either rolled back, was still running
+//! when the database stopped, or wrote nothing.
+
+use std::io;
+
+use crate::storage::allocator::{allocate_page, commit_log_root, set_commit_log_root};
+use crate::storage::buffer::BufferPool;
+use crate::storage::mvcc::Xid;
+use crate::storage::page::{
+    corrupt, format_page, page_kind, read_u32, write_u32, PageId, PageKind, HEADER_PAGE,
+    PAGE_HEADER_SIZE, PAGE_SIZE,
+};
+
+const NEXT_PAGE: usize = PAGE_HEADER_SIZE;
+const BITS: usize = PAGE_HEADER_SIZE + 8;
+/// Transaction ids one page has the bits of.
+pub const XIDS_PER_PAGE: u64 = ((PAGE_SIZE - BITS) * 8) as u64;
+
+/// The pages of the commit log, in the order of the ids they cover.
+#[derive(Debug, Clone, Default)]
+pub struct CommitLog {
+    pages: Vec<PageId>,
+}
+
+impl CommitLog {
+    pub fn load(pool: &mut BufferPool) -> io::Result<Self> {
+        let mut pages = Vec::new();
+        let mut page = commit_log_root(pool)?;
+        while page != HEADER_PAGE {
+            pages.push(page);
+            page = pool.read(page, |data| match page_kind(data)? {
+                PageKind::CommitLog => Ok(read_u32(data, NEXT_PAGE)),
+                _ => Err(corrupt("commit log points at another kind of page")),
+            })??;
+        }
+        Ok(CommitLog { pages })
+    }
+
+    pub fn is_committed(&self, pool: &mut BufferPool, xid: Xid) -> io::Result<bool> {
+        let (index, byte, bit) = position(xid);
+        match self.pages.get(index) {
+            Some(&page) => pool.read(page, |data| data[byte] & bit != 0),
+            None => Ok(false),
+        }
+    }
+
+    /// Records that `xid` committed, adding pages to reach its bit.
+    pub fn set_committed(&mut self, pool: &mut BufferPool, xid: Xid) -> io::Result<()> {
+        let (index, byte, bit) = position(xid);
+        while self.pages.len() <= index {
+            let page = allocate_page(pool)?;
+            pool.write(page, |data| format_page(data, PageKind::CommitLog))?;
+            match self.pages.last() {
+                Some(&last) => pool.write(last, |data| write_u32(data, NEXT_PAGE, page))?,
+                None => set_commit_log_root(pool, page)?,
+            }
+            self.pages.push(page);
+        }
+        pool.write(self.pages[index], |data| data[byte] |= bit)
+    }
+}
+
+/// The page, byte on it and bit of the byte holding the bit of `xid`.
+fn position(xid: Xid) -> (usize, usize, u8) {
+    let index = (xid / XIDS_PER_PAGE) as usize;
+    let offset = xid % XIDS_PER_PAGE;
+    (index, BITS + (offset / 8) as usize, 1 << (offset % 8))
+}
@@ -0,0 +1,125 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+#[cfg(feature = "crash-test")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::storage::page::{PageId, PAGE_SIZE};
+
+/// Page writes left before the process dies in the middle of one; zero
+/// for never. Only the crash harness sets it.
+#[cfg(feature = "crash-test")]
+static WRITES_BEFORE_TEAR: AtomicU64 = AtomicU64::new(0);
+
+/// Makes the `writes`-th page write to a data file from now write half
+/// the page and abort the process, as a power cut in the middle of the
+/// write might leave it.
+#[cfg(feature = "crash-test")]
+pub fn tear_page_write(writes: u64) {
+    WRITES_BEFORE_TEAR.store(writes, Ordering::SeqCst);
+}
+
+enum Backing {
+    File(File),
+    /// Pages of a database that is never written to disk.
+    Memory(Vec<Box<[u8]>>),
+}
+
+/// The data file as an array of fixed-size pages. Page `n` starts at byte
+/// `n * PAGE_SIZE`; the file only ever grows.
+pub struct DiskManager {
+    backing: Backing,
+    page_count: PageId,
+}
+
+impl DiskManager {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        // Pages are only ever added at the end, so a part of a page there
+        // is an extension torn by a crash. The log rebuilds that page.
+        let mut length = file.metadata()?.len();
+        if length % PAGE_SIZE as u64 != 0 {
+            length -= length % PAGE_SIZE as u64;
+            file.set_len(length)?;
+            file.sync_data()?;
+        }
+        Ok(DiskManager {
+            backing: Backing::File(file),
+            page_count: (length / PAGE_SIZE as u64) as PageId,
+        })
+    }
+
+    pub fn memory() -> Self {
+        DiskManager {
+            backing: Backing::Memory(Vec::new()),
+            page_count: 0,
+        }
+    }
+
+    pub fn page_count(&self) -> PageId {
+        self.page_count
+    }
+
+    pub fn read_page(&mut self, page: PageId, buffer: &mut [u8]) -> io::Result<()> {
+        if page >= self.page_count {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("page {} is past the end of the data file", page),
+            ));
+        }
+        match &mut self.backing {
+            Backing::File(file) => {
+                file.seek(SeekFrom::Start(page as u64 * PAGE_SIZE as u64))?;
+                file.read_exact(buffer)
+            }
+            Backing::Memory(pages) => {
+                buffer.copy_from_slice(&pages[page as usize]);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn write_page(&mut self, page: PageId, buffer: &[u8]) -> io::Result<()> {
+        match &mut self.backing {
+            Backing::File(file) => {
+                file.seek(SeekFrom::Start(page as u64 * PAGE_SIZE as u64))?;
+                #[cfg(feature = "crash-test")]
+                if WRITES_BEFORE_TEAR.load(Ordering::SeqCst) > 0
+                    && WRITES_BEFORE_TEAR.fetch_sub(1, Ordering::SeqCst) == 1
+                {
+                    file.write_all(&buffer[..PAGE_SIZE / 2])?;
+                    file.sync_data()?;
+                    std::process::abort();
+                }
+                file.write_all(buffer)?;
+            }
+            Backing::Memory(pages) => {
+                if pages.len() <= page as usize {
+                    pages.resize_with(page as usize + 1, || vec![0; PAGE_SIZE].into());
+                }
+                pages[page as usize].copy_from_slice(buffer);
+            }
+        }
+        self.page_count = self.page_count.max(page + 1);
+        Ok(())
+    }
+
+    /// Adds a zeroed page at the end of the file.
+    pub fn extend(&mut self) -> io::Result<PageId> {
+        let page = self.page_count;
+        self.write_page(page, &[0; PAGE_SIZE])?;
+        Ok(page)
+    }
+
+    pub fn sync(&mut self) -> io::Result<()> {
+        match &mut self.backing {
+            Backing::File(file) => file.sync_data(),
+            Backing::Memory(_) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,679 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::Arc;
+use std::vec;
+
+use crate::compiler::parser::{IndexMethod, IsolationLevel};
+use crate::execution::error::ExecutionError;
+use crate::storage::allocator::{initialize, next_transaction, set_next_transaction};
+use crate::storage::btree::{BTree, BTreeRange, LeafEntries, RangeCursor};
+use crate::storage::buffer::{BufferPool, EvictionPolicy};
+use crate::storage::catalog::IndexSchema;
+use crate::storage::commit_log::CommitLog;
+use crate::storage::disk::DiskManager;
+use crate::storage::hash::HashIndex;
+use crate::storage::heap::{HeapFile, PageRecords};
+use crate::storage::key::{encode_key, entry_key, prefix_range};
+use crate::storage::mvcc::{
+    creator, deleter, encode_version, set_deleter, version_row, Snapshot, Transactions,
+    VersionState, Xid, NO_XID,
+};
+use crate::storage::page::{corrupt, PageId, RecordId};
+use crate::storage::sequence::{create_sequence, read_sequence, write_sequence, SequenceState};
+use crate::storage::vacuum::{Activity, TableStatistics};
+use crate::storage::wal::{recover, rollback, LogSync, Lsn, SyncPolicy, Wal};
+use crate::types::value::Value;
+
+/// Name of the data file inside a database directory.
+pub const DATA_FILE: &str = "data";
+/// Name of the write-ahead log inside a database directory.
+pub const LOG_FILE: &str = "wal";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageOptions {
+    /// Pages the buffer pool caches.
+    pub buffer_pool_pages: usize,
+    pub eviction: EvictionPolicy,
+    /// When commits wait for the log to reach the disk.
+    pub sync: SyncPolicy,
+    /// Bytes of log after which a commit takes a checkpoint.
+    pub checkpoint_log_size: u64,
+}
+
+impl Default for StorageOptions {
+    /// 8 MiB of cache with clock eviction, every commit synced, and a
+    /// checkpoint every 16 MiB of log.
+    fn default() -> Self {
+        StorageOptions {
+            buffer_pool_pages: 1024,
+            eviction: EvictionPolicy::Clock,
+            sync: SyncPolicy::EveryCommit,
+            checkpoint_log_size: 16 << 20,
+        }
+    }
+}
+
+/// The heaps and indexes of all tables, and the sequences, in one data
+/// file, behind one buffer pool. A heap, an index or a sequence is named
+/// by its root page, which the catalog records.
+///
+/// Pages are changed in log transactions, which the write-ahead log makes
+/// atomic and durable; opening the engine recovers from the log whatever
+/// a crash left unfinished. A log transaction is one statement, or the
+/// commit of a transaction: the rows of a heap are versions, and what a
+/// transaction wrote becomes visible, or stays invisible for good, when
+/// the commit log says it committed or it ends otherwise (see
+/// [`crate::storage::mvcc`]). Versions no transaction can see any more
+/// stay until a VACUUM removes them.
+pub struct StorageEngine {
+    pool: BufferPool,
+    heaps: HashMap<PageId, HeapFile>,
+    checkpoint_log_size: u64,
+    transactions: Transactions,
+    commit_log: CommitLog,
+    activity: Activity,
+    /// A handle for each heap that has been scanned, cloned by every
+    /// scan of it that is open.
+    scans: HashMap<PageId, Arc<()>>,
+}
+
+impl StorageEngine {
+    /// Opens the data file and log in `directory`, creating them if they
+    /// do not exist, and recovers.
+    pub fn open<P: AsRef<Path>>(directory: P, options: StorageOptions) -> io::Result<Self> {
+        let directory = directory.as_ref();
+        let disk = DiskManager::open(directory.join(DATA_FILE))?;
+        let (log, entries) = Wal::open(directory.join(LOG_FILE), options.sync)?;
+        let mut pool = BufferPool::new(disk, log, options.buffer_pool_pages, options.eviction);
+        if !entries.is_empty() {
+            recover(&mut pool, entries)?;
+            pool.checkpoint()?;
+        }
+        StorageEngine::with_pool(pool, options)
+    }
+
+    /// An engine whose pages never leave memory.
+    pub fn memory(options: StorageOptions) -> Self {
+        let pool = BufferPool::new(
+            DiskManager::memory(),
+            Wal::memory(),
+            options.buffer_pool_pages,
+            options.eviction,
+        );
+        StorageEngine::with_pool(pool, options).expect("an empty in-memory data file initializes")
+    }
+
+    fn with_pool(mut pool: BufferPool, options: StorageOptions) -> io::Result<Self> {
+        initialize(&mut pool)?;
+        let transactions = Transactions::new(next_transaction(&mut pool)?);
+        let commit_log = CommitLog::load(&mut pool)?;
+        Ok(StorageEngine {
+            pool,
+            heaps: HashMap::new(),
+            checkpoint_log_size: options.checkpoint_log_size,
+            transactions,
+            commit_log,
+            activity: Activity::default(),
+            scans: HashMap::new(),
+        })
+    }
+
+    pub fn pool(&self) -> &BufferPool {
+        &self.pool
+    }
+
+    /// What sessions wait on, once they let go of the engine, for the
+    /// commits [`StorageEngine::commit`] left to group commit.
+    pub fn log_sync(&mut self) -> Arc<LogSync> {
+        self.pool.log().sync()
+    }
+
+    /// Creates an empty heap, returning its root page.
+    pub fn create_heap(&mut self) -> io::Result<PageId> {
+        let heap = HeapFile::create(&mut self.pool)?;
+        let root = heap.root();
+        self.heaps.insert(root, heap);
+        Ok(root)
+    }
+
+    pub fn insert(&mut self, heap: PageId, record: &[u8]) -> io::Result<RecordId> {
+        self.with_heap(heap, |heap, pool| heap.insert(pool, record))
+    }
+
+    pub fn get(&mut self, heap: PageId, id: RecordId) -> io::Result<Option<Vec<u8>>> {
+        self.with_heap(heap, |heap, pool| heap.get(pool, id))
+    }
+
+    pub fn delete(&mut self, heap: PageId, id: RecordId) -> io::Result<bool> {
+        self.with_heap(heap, |heap, pool| heap.delete(pool, id))
+    }
+
+    pub fn update(
+        &mut self,
+        heap: PageId,
+        id: RecordId,
+        record: &[u8],
+    ) -> io::Result<Option<RecordId>> {
+        self.with_heap(heap, |heap, pool| heap.update(pool, id, record))
+    }
+
+    /// Starts a scan of `heap`, which reads a page at a time. It counts
+    /// as open, so that no row of the heap moves, until it is dropped.
+    pub fn scan(&mut self, heap: PageId) -> io::Result<HeapCursor> {
+        let pages = self.heap_pages(heap)?;
+        Ok(HeapCursor {
+            heap,
+            pages: pages.into_iter(),
+            _open: self.open_scan(heap),
+        })
+    }
+
+    /// The records on the next page of `cursor`, with their ids, or
+    /// `None` once it has read every page the heap had when it started.
+    /// A page the heap has let go of since has none.
+    pub fn next_page(&mut self, cursor: &mut HeapCursor) -> io::Result<Option<PageRecords>> {
+        match cursor.pages.next() {
+            Some(page) => Ok(Some(self.read_page(cursor.heap, page)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The pages of `heap`, in the order a scan reads them.
+    pub fn heap_pages(&mut self, heap: PageId) -> io::Result<Vec<PageId>> {
+        self.with_heap(heap, |heap, _| Ok(heap.pages()))
+    }
+
+    /// The records on `page` of `heap`, with their ids.
+    pub fn read_page(&mut self, heap: PageId, page: PageId) -> io::Result<PageRecords> {
+        self.with_heap(heap, |heap, pool| heap.read_page(pool, page))
+    }
+
+    /// Marks `heap` as being read until the returned handle is dropped.
+    pub fn open_scan(&mut self, heap: PageId) -> OpenScan {
+        OpenScan(Arc::clone(self.scans.entry(heap).or_default()))
+    }
+
+    /// Whether a scan of `heap` is open.
+    pub fn scanning(&self, heap: PageId) -> bool {
+        self.scans
+            .get(&heap)
+            .is_some_and(|open| Arc::strong_count(open) > 1)
+    }
+
+    /// The running transactions, for what needs no page.
+    pub fn transactions(&mut self) -> &mut Transactions {
+        &mut self.transactions
+    }
+
+    /// Starts a transaction at `isolation`, returning its id.
+    pub fn begin_transaction(&mut self, isolation: IsolationLevel) -> Xid {
+        self.transactions.begin(isolation)
+    }
+
+    /// Commits `xid`. If it wrote, that it committed is recorded as the
+    /// last change of the running log transaction, or of a new one; the
+    /// log transaction then commits, before anyone sees `xid` committed.
+    /// Returns the LSN to wait for as [`StorageEngine::commit`] does, in
+    /// which case `xid` goes on running, seen by no one, until
+    /// [`StorageEngine::end_commit`] once the wait is over: a crash
+    /// before the sync rolls it back, so nothing may act on it till then.
+    pub fn commit_transaction(&mut self, xid: Xid) -> io::Result<Option<Lsn>> {
+        let ids = self.transactions.commit_ids(xid);
+        let mut pending = None;
+        if !ids.is_empty() {
+            if !self.pool.log().in_transaction() {
+                self.begin();
+            }
+            for &id in &ids {
+                self.commit_log.set_committed(&mut self.pool, id)?;
+            }
+            pending = self.commit()?;
+        }
+        if pending.is_none() {
+            self.end_commit(xid);
+        }
+        Ok(pending)
+    }
+
+    /// Ends `xid`, whose commit is durable, so that others see it
+    /// committed.
+    pub fn end_commit(&mut self, xid: Xid) {
+        let ids = self.transactions.commit_ids(xid);
+        self.activity.end(xid, &ids);
+        self.transactions.end(xid, true);
+    }
+
+    /// Ends `xid` without committing it. Nothing it wrote is ever seen.
+    pub fn abort_transaction(&mut self, xid: Xid) {
+        self.activity.end(xid, &[]);
+        self.transactions.end(xid, false);
+    }
+
+    /// Stores a new version of a row, created by `xid` or its latest
+    /// savepoint.
+    pub fn insert_version(&mut self, heap: PageId, xid: Xid, row: &[u8]) -> io::Result<RecordId> {
+        let creator = self.transactions.write_xid(xid);
+        self.record_transaction_id(creator)?;
+        let id = self.insert(heap, &encode_version(creator, row))?;
+        self.activity.inserted(xid, creator, heap);
+        Ok(id)
+    }
+
+    /// Marks the version at `id` deleted by the transaction of `snapshot`,
+    /// returning `false` if the snapshot does not see it, or under READ
+    /// COMMITTED if a transaction that committed since deleted it. Fails
+    /// if another transaction is still deleting it, or under REPEATABLE
+    /// READ and SERIALIZABLE if one that committed since did.
+    pub fn delete_version(
+        &mut self,
+        heap: PageId,
+        id: RecordId,
+        snapshot: &Snapshot,
+    ) -> Result<bool, ExecutionError> {
+        let xid = snapshot.transaction();
+        let mut record = match self.get(heap, id)? {
+            Some(record) if self.sees(snapshot, &record)? => record,
+            _ => return Ok(false),
+        };
+        match self.state(xid, &record)? {
+            VersionState::Live => {}
+            VersionState::Dead if deleter(&record) != xid => {
+                let isolation = self.transactions.isolation(xid);
+                if matches!(
+                    isolation,
+                    Some(IsolationLevel::RepeatableRead | IsolationLevel::Serializable)
+                ) {
+                    return Err(ExecutionError::SerializationFailure(
+                        "concurrent update".to_string(),
+                    ));
+                }
+                return Ok(false);
+            }
+            VersionState::Dead => return Ok(false),
+            VersionState::InProgress(_) => {
+                return Err(ExecutionError::SerializationFailure(
+                    "concurrent update".to_string(),
+                ))
+            }
+        }
+        self.transactions.write(xid, heap)?;
+        let deleter = self.transactions.write_xid(xid);
+        self.record_transaction_id(deleter)?;
+        set_deleter(&mut record, deleter);
+        self.update(heap, id, &record)?;
+        self.activity.deleted(xid, deleter, heap);
+        Ok(true)
+    }
+
+    /// Like [`StorageEngine::next_page`], the rows of the versions on the
+    /// page that `snapshot` sees.
+    pub fn next_visible(
+        &mut self,
+        cursor: &mut HeapCursor,
+        snapshot: &Snapshot,
+    ) -> io::Result<Option<PageRecords>> {
+        let records = match self.next_page(cursor)? {
+            Some(records) => records,
+            None => return Ok(None),
+        };
+        let mut rows = Vec::with_capacity(records.len());
+        for (id, record) in records {
+            if self.sees(snapshot, &record)? {
+                rows.push((id, version_row(&record).to_vec()));
+            }
+        }
+        Ok(Some(rows))
+    }
+
+    /// The row of the version at `id`, if `snapshot` sees it.
+    pub fn get_visible(
+        &mut self,
+        heap: PageId,
+        id: RecordId,
+        snapshot: &Snapshot,
+    ) -> io::Result<Option<Vec<u8>>> {
+        match self.get(heap, id)? {
+            Some(record) if self.sees(snapshot, &record)? => {
+                Ok(Some(version_row(&record).to_vec()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Like [`StorageEngine::next_page`], every version on the page with
+    /// what it is to `xid`.
+    #[allow(clippy::type_complexity)]
+    pub fn next_versions(
+        &mut self,
+        cursor: &mut HeapCursor,
+        xid: Xid,
+    ) -> io::Result<Option<Vec<(RecordId, VersionState, Vec<u8>)>>> {
+        let records = match self.next_page(cursor)? {
+            Some(records) => records,
+            None => return Ok(None),
+        };
+        let mut versions = Vec::with_capacity(records.len());
+        for (id, record) in records {
+            let state = self.state(xid, &record)?;
+            versions.push((id, state, version_row(&record).to_vec()));
+        }
+        Ok(Some(versions))
+    }
+
+    /// What the version at `id` is to `xid`, if there is one.
+    pub fn version_state(
+        &mut self,
+        heap: PageId,
+        id: RecordId,
+        xid: Xid,
+    ) -> io::Result<Option<VersionState>> {
+        match self.get(heap, id)? {
+            Some(record) => Ok(Some(self.state(xid, &record)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The versions of `heap` no transaction can see again, with their
+    /// rows. The others are counted as the table's statistics.
+    pub fn dead_versions(&mut self, heap: PageId) -> io::Result<Vec<(RecordId, Vec<u8>)>> {
+        let horizon = self.transactions.horizon();
+        let mut dead = Vec::new();
+        let mut statistics = TableStatistics::default();
+        let mut cursor = self.scan(heap)?;
+        while let Some(records) = self.next_page(&mut cursor)? {
+            for (id, record) in records {
+                let (pool, commit_log) = (&mut self.pool, &self.commit_log);
+                let mut committed = |xid| commit_log.is_committed(pool, xid);
+                let (creator, deleter) = (creator(&record), deleter(&record));
+                if self
+                    .transactions
+                    .removable(creator, deleter, horizon, &mut committed)?
+                {
+                    dead.push((id, version_row(&record).to_vec()));
+                    continue;
+                }
+                match self
+                    .transactions
+                    .version_state(NO_XID, creator, deleter, &mut committed)?
+                {
+                    VersionState::Live => statistics.live += 1,
+                    VersionState::Dead => statistics.dead += 1,
+                    // Counted when its transaction ends.
+                    VersionState::InProgress(_) => {}
+                }
+            }
+        }
+        self.activity.set_statistics(heap, statistics);
+        Ok(dead)
+    }
+
+    /// Removes the versions at `ids` from `heap`, compacting their pages.
+    pub fn remove_versions(&mut self, heap: PageId, ids: &[RecordId]) -> io::Result<()> {
+        let mut pages: BTreeMap<PageId, Vec<u16>> = BTreeMap::new();
+        for id in ids {
+            pages.entry(id.page).or_default().push(id.slot);
+        }
+        self.with_heap(heap, |heap, pool| {
+            for (&page, slots) in &pages {
+                heap.prune(pool, page, slots)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Moves the version at `id` to the first page of `heap` before its
+    /// own with room for it, returning where it went, or `None` if no
+    /// such page has room. Nothing moves while a scan of `heap` is open,
+    /// as it would miss a version moved to a page it has read.
+    pub fn relocate(&mut self, heap: PageId, id: RecordId) -> io::Result<Option<RecordId>> {
+        if self.scanning(heap) {
+            return Ok(None);
+        }
+        self.with_heap(heap, |heap, pool| heap.relocate(pool, id))
+    }
+
+    /// Frees the empty pages at the end of `heap`, returning how many
+    /// there were.
+    pub fn truncate_heap(&mut self, heap: PageId) -> io::Result<usize> {
+        self.with_heap(heap, |heap, pool| heap.truncate(pool))
+    }
+
+    /// How many live and dead versions `heap` has, as far as the engine
+    /// has kept count since it opened.
+    pub fn table_statistics(&self, heap: PageId) -> TableStatistics {
+        self.activity.statistics(heap)
+    }
+
+    fn sees(&mut self, snapshot: &Snapshot, record: &[u8]) -> io::Result<bool> {
+        let (pool, commit_log) = (&mut self.pool, &self.commit_log);
+        snapshot.sees(creator(record), deleter(record), |xid| {
+            commit_log.is_committed(pool, xid)
+        })
+    }
+
+    fn state(&mut self, xid: Xid, record: &[u8]) -> io::Result<VersionState> {
+        let (pool, commit_log) = (&mut self.pool, &self.commit_log);
+        self.transactions
+            .version_state(xid, creator(record), deleter(record), |xid| {
+                commit_log.is_committed(pool, xid)
+            })
+    }
+
+    /// Makes sure the data file gives no later transaction `xid` after a
+    /// restart, before a version with it is written.
+    fn record_transaction_id(&mut self, xid: Xid) -> io::Result<()> {
+        if next_transaction(&mut self.pool)? <= xid {
+            set_next_transaction(&mut self.pool, self.transactions.next())?;
+        }
+        Ok(())
+    }
+
+    /// Creates an empty index, returning its root page.
+    pub fn create_index(&mut self, method: IndexMethod) -> io::Result<PageId> {
+        Ok(match method {
+            IndexMethod::BTree => BTree::create(&mut self.pool)?.root(),
+            IndexMethod::Hash => HashIndex::create(&mut self.pool)?.root(),
+        })
+    }
+
+    /// Adds the entry of a version whose key in `index` is `values`. A
+    /// unique index takes it whatever other entries it has, as only the
+    /// transactions writing can tell which of their versions count.
+    pub fn index_insert(
+        &mut self,
+        index: &IndexSchema,
+        values: &[Value],
+        id: RecordId,
+    ) -> io::Result<()> {
+        let root = index_root(index)?;
+        match index.method {
+            IndexMethod::BTree => {
+                BTree::new(root).insert(&mut self.pool, &entry_key(values, id), id)?;
+            }
+            IndexMethod::Hash => {
+                HashIndex::new(root).insert(&mut self.pool, &encode_key(values), id, false)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn index_delete(
+        &mut self,
+        index: &IndexSchema,
+        values: &[Value],
+        id: RecordId,
+    ) -> io::Result<bool> {
+        let root = index_root(index)?;
+        match index.method {
+            IndexMethod::BTree => BTree::new(root).delete(&mut self.pool, &entry_key(values, id)),
+            IndexMethod::Hash => {
+                HashIndex::new(root).delete(&mut self.pool, &encode_key(values), id)
+            }
+        }
+    }
+
+    /// The records whose key in `index` equals `values`.
+    pub fn index_lookup(
+        &mut self,
+        index: &IndexSchema,
+        values: &[Value],
+    ) -> io::Result<Vec<RecordId>> {
+        let root = index_root(index)?;
+        let key = encode_key(values);
+        match index.method {
+            // Entries of equal keys differ in the record id that follows.
+            IndexMethod::BTree => {
+                let (lower, upper) = prefix_range(&key);
+                self.index_range(
+                    root,
+                    lower.as_ref().map(Vec::as_slice),
+                    upper.as_ref().map(Vec::as_slice),
+                )
+                .map(|entry| entry.map(|(_, id)| id))
+                .collect()
+            }
+            IndexMethod::Hash => HashIndex::new(root).get(&mut self.pool, &key),
+        }
+    }
+
+    /// The entries of a B+tree index with keys between `lower` and
+    /// `upper`, read a leaf at a time.
+    pub fn index_range(
+        &mut self,
+        index: PageId,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> BTreeRange<'_> {
+        BTree::new(index).range(&mut self.pool, lower, upper)
+    }
+
+    /// The entries in the range of `cursor` on the next leaf of a B+tree
+    /// index, as [`BTree::next_leaf`] reads them.
+    pub fn next_index_entries(
+        &mut self,
+        index: PageId,
+        cursor: &mut RangeCursor,
+    ) -> io::Result<Option<LeafEntries>> {
+        BTree::new(index).next_leaf(&mut self.pool, cursor)
+    }
+
+    /// Frees every page of an index.
+    pub fn drop_index(&mut self, index: &IndexSchema) -> io::Result<()> {
+        let root = index_root(index)?;
+        match index.method {
+            IndexMethod::BTree => BTree::new(root).destroy(&mut self.pool),
+            IndexMethod::Hash => HashIndex::new(root).destroy(&mut self.pool),
+        }
+    }
+
+    /// Creates the page of a new sequence, returning it.
+    pub fn create_sequence(&mut self, start: i128) -> io::Result<PageId> {
+        create_sequence(&mut self.pool, start)
+    }
+
+    pub fn sequence_state(&mut self, page: PageId) -> io::Result<SequenceState> {
+        read_sequence(&mut self.pool, page)
+    }
+
+    /// Records the state of a sequence and syncs the log, so that no
+    /// value it covers is handed out again after a crash.
+    pub fn set_sequence_state(&mut self, page: PageId, state: SequenceState) -> io::Result<()> {
+        write_sequence(&mut self.pool, page, state)?;
+        self.pool.log().force()
+    }
+
+    /// Starts a log transaction for the changes that follow.
+    pub fn begin(&mut self) {
+        self.pool.log().begin();
+    }
+
+    /// Ends the running log transaction, a statement of a transaction
+    /// that has yet to commit, without syncing it: it is durable when the
+    /// transaction's commit is.
+    pub fn end_statement(&mut self) {
+        self.pool.log().end();
+        self.activity.end_statement();
+    }
+
+    /// Commits the running log transaction, if there is one, and takes a
+    /// checkpoint once the log has grown past its limit. Under group
+    /// commit, returns the LSN to wait for on [`StorageEngine::log_sync`]
+    /// before the commit is durable.
+    pub fn commit(&mut self) -> io::Result<Option<Lsn>> {
+        let pending = self.pool.log().commit()?;
+        self.activity.end_statement();
+        if self.pool.log().size() > self.checkpoint_log_size {
+            self.pool.checkpoint()?;
+        }
+        Ok(pending)
+    }
+
+    /// Commits what the running log transaction changed so far and syncs
+    /// the log whatever the sync policy, carrying on in a new one.
+    /// The catalog is kept outside the log, so it refers to pages only
+    /// once they are durable this way.
+    pub fn commit_durable(&mut self) -> io::Result<()> {
+        let running = self.pool.log().in_transaction();
+        self.commit()?;
+        self.pool.log().force()?;
+        if running {
+            self.begin();
+        }
+        Ok(())
+    }
+
+    /// Undoes the changes of the running log transaction and ends it.
+    pub fn rollback(&mut self) -> io::Result<()> {
+        rollback(&mut self.pool)?;
+        self.activity.rollback_statement();
+        // The free-space maps and commit log held in memory may describe
+        // pages as they were before the undo.
+        self.heaps.clear();
+        self.commit_log = CommitLog::load(&mut self.pool)?;
+        Ok(())
+    }
+
+    /// Writes every changed page to the data file and syncs it, starting
+    /// the log over if no log transaction is running.
+    pub fn checkpoint(&mut self) -> io::Result<()> {
+        self.pool.checkpoint()
+    }
+
+    /// Runs `f` on the heap rooted at `root`, reading its free-space map
+    /// on first use.
+    fn with_heap<T>(
+        &mut self,
+        root: PageId,
+        f: impl FnOnce(&mut HeapFile, &mut BufferPool) -> io::Result<T>,
+    ) -> io::Result<T> {
+        if !self.heaps.contains_key(&root) {
+            let heap = HeapFile::open(&mut self.pool, root)?;
+            self.heaps.insert(root, heap);
+        }
+        let heap = self
+            .heaps
+            .get_mut(&root)
+            .expect("the heap was loaded above");
+        f(heap, &mut self.pool)
+    }
+}
+
+/// How far a scan of a heap has got. It borrows nothing, so that whoever
+/// runs the scan can let go of the engine between pages; it reads the
+/// pages the heap had when it started.
+pub struct HeapCursor {
+    heap: PageId,
+    pages: vec::IntoIter<PageId>,
+    _open: OpenScan,
+}
+
+/// Keeps a heap marked as being read, see [`StorageEngine::scanning`].
+pub struct OpenScan(#[allow(dead_code)] Arc<()>);
+
+fn index_root(index: &IndexSchema) -> io::Result<PageId> {
+    index
+        .root
+        .ok_or_else(|| corrupt("index has no pages in the data file"))
+}
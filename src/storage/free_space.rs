@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::storage::allocator::allocate_page;
+use crate::storage::buffer::BufferPool;
+use crate::storage::page::{
+    corrupt, format_page, page_kind, read_u16, read_u32, write_u16, write_u32, PageId, PageKind,
+    HEADER_PAGE, PAGE_HEADER_SIZE, PAGE_SIZE,
+};
+
+const NEXT_DIRECTORY: usize = PAGE_HEADER_SIZE;
+const ENTRY_COUNT: usize = PAGE_HEADER_SIZE + 4;
+const ENTRIES: usize = PAGE_HEADER_SIZE + 8;
+const ENTRY_SIZE: usize = 6;
+/// Heap pages one directory page describes.
+pub const ENTRIES_PER_PAGE: usize = (PAGE_SIZE - ENTRIES) / ENTRY_SIZE;
+
+/// The pages of one heap and the free bytes on each.
+///
+/// It is kept on a chain of directory pages, the first of which is the
+/// heap's root, each entry a page id and its free space. The whole map is
+/// also held in memory so that an insert finds a page without reading
+/// any; every change is written through to its directory page.
+#[derive(Debug, Clone)]
+pub struct FreeSpaceMap {
+    directories: Vec<PageId>,
+    entries: Vec<(PageId, u16)>,
+    positions: HashMap<PageId, usize>,
+}
+
+impl FreeSpaceMap {
+    pub fn create(pool: &mut BufferPool) -> io::Result<Self> {
+        let root = allocate_page(pool)?;
+        pool.write(root, |data| format_page(data, PageKind::Directory))?;
+        Ok(FreeSpaceMap {
+            directories: vec![root],
+            entries: Vec::new(),
+            positions: HashMap::new(),
+        })
+    }
+
+    pub fn load(pool: &mut BufferPool, root: PageId) -> io::Result<Self> {
+        let mut map = FreeSpaceMap {
+            directories: Vec::new(),
+            entries: Vec::new(),
+            positions: HashMap::new(),
+        };
+        let mut directory = root;
+        while directory != HEADER_PAGE {
+            let (next, entries) = pool.read(directory, |data| {
+                if page_kind(data)? != PageKind::Directory {
+                    return Err(corrupt(
+                        "heap directory chain points at another kind of page",
+                    ));
+                }
+                let count = read_u16(data, ENTRY_COUNT) as usize;
+                let entries: Vec<(PageId, u16)> = (0..count)
+                    .map(|index| {
+                        let position = ENTRIES + index * ENTRY_SIZE;
+                        (read_u32(data, position), read_u16(data, position + 4))
+                    })
+                    .collect();
+                Ok((read_u32(data, NEXT_DIRECTORY), entries))
+            })??;
+            map.directories.push(directory);
+            for (page, free) in entries {
+                map.positions.insert(page, map.entries.len());
+                map.entries.push((page, free));
+            }
+            directory = next;
+        }
+        Ok(map)
+    }
+
+    pub fn root(&self) -> PageId {
+        self.directories[0]
+    }
+
+    /// The heap's pages in the order they were added.
+    pub fn pages(&self) -> impl Iterator<Item = PageId> + '_ {
+        self.entries.iter().map(|(page, _)| *page)
+    }
+
+    /// The directory pages, root first.
+    pub fn directories(&self) -> &[PageId] {
+        &self.directories
+    }
+
+    pub fn free_space(&self, page: PageId) -> Option<usize> {
+        let position = *self.positions.get(&page)?;
+        Some(self.entries[position].1 as usize)
+    }
+
+    /// The first page with at least `needed` free bytes.
+    pub fn find(&self, needed: usize) -> Option<PageId> {
+        self.entries
+            .iter()
+            .find(|(_, free)| *free as usize >= needed)
+            .map(|(page, _)| *page)
+    }
+
+    /// The first page added before `page` with at least `needed` free
+    /// bytes.
+    pub fn find_before(&self, needed: usize, page: PageId) -> Option<PageId> {
+        let end = *self.positions.get(&page)?;
+        self.entries[..end]
+            .iter()
+            .find(|(_, free)| *free as usize >= needed)
+            .map(|(page, _)| *page)
+    }
+
+    /// Adds a page to the heap, chaining a new directory page if the last
+    /// one is full.
+    pub fn add(&mut self, pool: &mut BufferPool, page: PageId, free: usize) -> io::Result<()> {
+        let index = self.entries.len();
+        if index == self.directories.len() * ENTRIES_PER_PAGE {
+            let directory = allocate_page(pool)?;
+            pool.write(directory, |data| format_page(data, PageKind::Directory))?;
+            let last = self.directories[self.directories.len() - 1];
+            pool.write(last, |data| write_u32(data, NEXT_DIRECTORY, directory))?;
+            self.directories.push(directory);
+        }
+        self.positions.insert(page, index);
+        self.entries.push((page, free as u16));
+        let directory = self.directories[index / ENTRIES_PER_PAGE];
+        let count = (index % ENTRIES_PER_PAGE + 1) as u16;
+        pool.write(directory, |data| write_u16(data, ENTRY_COUNT, count))?;
+        self.write_entry(pool, index)
+    }
+
+    pub fn update(&mut self, pool: &mut BufferPool, page: PageId, free: usize) -> io::Result<()> {
+        let index = *self
+            .positions
+            .get(&page)
+            .ok_or_else(|| corrupt("page is not part of the heap"))?;
+        if self.entries[index].1 as usize == free {
+            return Ok(());
+        }
+        self.entries[index].1 = free as u16;
+        self.write_entry(pool, index)
+    }
+
+    /// Keeps the first `count` pages of the heap and drops the others,
+    /// returning the directory pages no longer needed. The caller frees
+    /// those and the pages dropped.
+    pub fn truncate(&mut self, pool: &mut BufferPool, count: usize) -> io::Result<Vec<PageId>> {
+        for (page, _) in self.entries.split_off(count) {
+            self.positions.remove(&page);
+        }
+        let needed = count.div_ceil(ENTRIES_PER_PAGE).max(1);
+        let dropped = self.directories.split_off(needed);
+        let last = self.directories[needed - 1];
+        let entries = (count - (needed - 1) * ENTRIES_PER_PAGE) as u16;
+        pool.write(last, |data| {
+            write_u32(data, NEXT_DIRECTORY, HEADER_PAGE);
+            write_u16(data, ENTRY_COUNT, entries);
+        })?;
+        Ok(dropped)
+    }
+
+    fn write_entry(&self, pool: &mut BufferPool, index: usize) -> io::Result<()> {
+        let (page, free) = self.entries[index];
+        let directory = self.directories[index / ENTRIES_PER_PAGE];
+        let position = ENTRIES + (index % ENTRIES_PER_PAGE) * ENTRY_SIZE;
+        pool.write(directory, |data| {
+            write_u32(data, position, page);
+            write_u16(data, position + 4, free);
+        })
+    }
+}
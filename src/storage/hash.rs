@@ -0,0 +1,441 @@
+//! An on-disk extendible hash index for equality lookups.
+//!
+//! The root is the directory: a global depth `d` and `2^d` bucket page
+//! ids, the bucket for a key being entry `hash & (2^d - 1)`. Once the
+//! entries outgrow the root, they are spread over pages of `2^10` each
+//! and the root lists those pages instead. A bucket has a local depth
+//! `l <= d` and is shared by the `2^(d - l)` entries that agree on the
+//! low `l` bits. A full bucket splits on bit `l`, doubling the directory
+//! first if `l == d`. Keys that cannot be told apart by a split, because
+//! they hash alike or the directory is at its largest, go to overflow
+//! pages chained to the bucket.
+//!
+//! Unlike the B+tree, a hash index keeps duplicate keys as separate
+//! entries, since a lookup must find every record with the key.
+
+use std::io;
+
+use crate::storage::allocator::{allocate_page, free_page};
+use crate::storage::btree::MAX_KEY_SIZE;
+use crate::storage::buffer::BufferPool;
+use crate::storage::page::{
+    corrupt, format_page, page_kind, read_u16, read_u32, write_u16, write_u32, PageId, PageKind,
+    RecordId, HEADER_PAGE, PAGE_HEADER_SIZE, PAGE_SIZE,
+};
+
+const DEPTH: usize = PAGE_HEADER_SIZE;
+const COUNT: usize = PAGE_HEADER_SIZE + 2;
+const NEXT: usize = PAGE_HEADER_SIZE + 4;
+const ENTRIES: usize = PAGE_HEADER_SIZE + 8;
+const RECORD_ID_SIZE: usize = 6;
+/// The deepest directory whose entries fit on one page.
+const PAGE_DEPTH: u16 = 10;
+/// The deepest directory, whose pages the root can still list.
+const MAX_DEPTH: u16 = 2 * PAGE_DEPTH;
+
+/// FNV-1a, which unlike the standard hasher is fixed across builds, as
+/// hashes that choose a page on disk have to be.
+fn hash(key: &[u8]) -> u64 {
+    key.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn entry_size(key: &[u8]) -> usize {
+    2 + key.len() + RECORD_ID_SIZE
+}
+
+/// One page of a bucket's chain.
+struct Bucket {
+    depth: u16,
+    entries: Vec<(Vec<u8>, RecordId)>,
+    next: PageId,
+}
+
+impl Bucket {
+    fn read(data: &[u8]) -> io::Result<Bucket> {
+        if page_kind(data)? != PageKind::HashBucket {
+            return Err(corrupt("hash index points at a page that is not a bucket"));
+        }
+        let count = read_u16(data, COUNT) as usize;
+        let mut position = ENTRIES;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let length = read_u16(data, position) as usize;
+            let key = data[position + 2..position + 2 + length].to_vec();
+            position += 2 + length;
+            let id = RecordId {
+                page: read_u32(data, position),
+                slot: read_u16(data, position + 4),
+            };
+            position += RECORD_ID_SIZE;
+            entries.push((key, id));
+        }
+        Ok(Bucket {
+            depth: read_u16(data, DEPTH),
+            entries,
+            next: read_u32(data, NEXT),
+        })
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        format_page(data, PageKind::HashBucket);
+        write_u16(data, DEPTH, self.depth);
+        write_u16(data, COUNT, self.entries.len() as u16);
+        write_u32(data, NEXT, self.next);
+        let mut position = ENTRIES;
+        for (key, id) in &self.entries {
+            write_u16(data, position, key.len() as u16);
+            data[position + 2..position + 2 + key.len()].copy_from_slice(key);
+            position += 2 + key.len();
+            write_u32(data, position, id.page);
+            write_u16(data, position + 4, id.slot);
+            position += RECORD_ID_SIZE;
+        }
+    }
+
+    fn size(&self) -> usize {
+        ENTRIES
+            + self
+                .entries
+                .iter()
+                .map(|(key, _)| entry_size(key))
+                .sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashIndex {
+    root: PageId,
+}
+
+impl HashIndex {
+    pub fn new(root: PageId) -> Self {
+        HashIndex { root }
+    }
+
+    /// Creates an index of one empty bucket.
+    pub fn create(pool: &mut BufferPool) -> io::Result<Self> {
+        let root = allocate_page(pool)?;
+        let bucket = allocate_page(pool)?;
+        write_bucket(
+            pool,
+            bucket,
+            &Bucket {
+                depth: 0,
+                entries: Vec::new(),
+                next: HEADER_PAGE,
+            },
+        )?;
+        write_directory(pool, root, 0, &[bucket])?;
+        Ok(HashIndex { root })
+    }
+
+    pub fn root(&self) -> PageId {
+        self.root
+    }
+
+    /// Adds an entry. With `unique`, an entry whose key is already there
+    /// is refused and `false` returned.
+    pub fn insert(
+        &self,
+        pool: &mut BufferPool,
+        key: &[u8],
+        id: RecordId,
+        unique: bool,
+    ) -> io::Result<bool> {
+        if key.len() > MAX_KEY_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "index key of {} bytes is longer than the limit of {} bytes",
+                    key.len(),
+                    MAX_KEY_SIZE
+                ),
+            ));
+        }
+        let hash = hash(key);
+        loop {
+            let first = self.bucket(pool, key)?;
+            let chain = read_chain(pool, first)?;
+            if unique
+                && chain
+                    .iter()
+                    .any(|(_, bucket)| bucket.entries.iter().any(|(other, _)| other == key))
+            {
+                return Ok(false);
+            }
+            if let Some((page, bucket)) = chain
+                .iter()
+                .find(|(_, bucket)| bucket.size() + entry_size(key) <= PAGE_SIZE)
+            {
+                let mut bucket = Bucket {
+                    depth: bucket.depth,
+                    entries: bucket.entries.clone(),
+                    next: bucket.next,
+                };
+                bucket.entries.push((key.to_vec(), id));
+                write_bucket(pool, *page, &bucket)?;
+                return Ok(true);
+            }
+            let local = chain[0].1.depth;
+            let separable = chain
+                .iter()
+                .flat_map(|(_, bucket)| &bucket.entries)
+                .any(|(other, _)| self::hash(other) != hash);
+            if local < MAX_DEPTH && separable {
+                self.split(pool, first, chain)?;
+                continue;
+            }
+            // Nothing a split could do: chain another page.
+            let overflow = allocate_page(pool)?;
+            write_bucket(
+                pool,
+                overflow,
+                &Bucket {
+                    depth: local,
+                    entries: vec![(key.to_vec(), id)],
+                    next: HEADER_PAGE,
+                },
+            )?;
+            let (last, _) = &chain[chain.len() - 1];
+            pool.write(*last, |data| write_u32(data, NEXT, overflow))?;
+            return Ok(true);
+        }
+    }
+
+    /// Removes the entry for `key` and `id`, returning whether there was
+    /// one.
+    pub fn delete(&self, pool: &mut BufferPool, key: &[u8], id: RecordId) -> io::Result<bool> {
+        let first = self.bucket(pool, key)?;
+        for (page, mut bucket) in read_chain(pool, first)? {
+            if let Some(position) = bucket
+                .entries
+                .iter()
+                .position(|(other, other_id)| other == key && *other_id == id)
+            {
+                bucket.entries.remove(position);
+                write_bucket(pool, page, &bucket)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// The records of every entry with `key`.
+    pub fn get(&self, pool: &mut BufferPool, key: &[u8]) -> io::Result<Vec<RecordId>> {
+        let first = self.bucket(pool, key)?;
+        Ok(read_chain(pool, first)?
+            .into_iter()
+            .flat_map(|(_, bucket)| bucket.entries)
+            .filter(|(other, _)| other == key)
+            .map(|(_, id)| id)
+            .collect())
+    }
+
+    /// Gives every page of the index back to the allocator.
+    pub fn destroy(self, pool: &mut BufferPool) -> io::Result<()> {
+        let (_, mut directory) = read_directory(pool, self.root)?;
+        directory.sort_unstable();
+        directory.dedup();
+        for first in directory {
+            for (page, _) in read_chain(pool, first)? {
+                free_page(pool, page)?;
+            }
+        }
+        for page in read_root(pool, self.root)?.1 {
+            free_page(pool, page)?;
+        }
+        free_page(pool, self.root)
+    }
+
+    /// The first page of the bucket of `key`, read from the one page of
+    /// the directory that holds its entry.
+    fn bucket(&self, pool: &mut BufferPool, key: &[u8]) -> io::Result<PageId> {
+        let (depth, pages) = read_root(pool, self.root)?;
+        let position = (hash(key) & ((1 << depth) - 1)) as usize;
+        if depth <= PAGE_DEPTH {
+            return pool.read(self.root, |data| read_u32(data, ENTRIES + position * 4));
+        }
+        let page = pages[position >> PAGE_DEPTH];
+        let offset = position & ((1 << PAGE_DEPTH) - 1);
+        pool.read(page, |data| {
+            if page_kind(data)? != PageKind::HashDirectory {
+                return Err(corrupt("hash index lists a page that is not a directory"));
+            }
+            Ok(read_u32(data, ENTRIES + offset * 4))
+        })?
+    }
+
+    /// Splits the bucket starting at `first` on its next bit, doubling the
+    /// directory if the bucket is as deep as it.
+    fn split(
+        &self,
+        pool: &mut BufferPool,
+        first: PageId,
+        chain: Vec<(PageId, Bucket)>,
+    ) -> io::Result<()> {
+        let (mut depth, mut directory) = read_directory(pool, self.root)?;
+        let local = chain[0].1.depth;
+        if local == depth {
+            directory.extend_from_within(..);
+            depth += 1;
+        }
+        let sibling = allocate_page(pool)?;
+        let mut stay = Vec::new();
+        let mut moved = Vec::new();
+        for (page, bucket) in chain {
+            if page != first {
+                free_page(pool, page)?;
+            }
+            for (key, id) in bucket.entries {
+                if hash(&key) >> local & 1 == 0 {
+                    stay.push((key, id));
+                } else {
+                    moved.push((key, id));
+                }
+            }
+        }
+        write_chain(pool, first, local + 1, stay)?;
+        write_chain(pool, sibling, local + 1, moved)?;
+        for (position, bucket) in directory.iter_mut().enumerate() {
+            if *bucket == first && position >> local & 1 == 1 {
+                *bucket = sibling;
+            }
+        }
+        write_directory(pool, self.root, depth, &directory)
+    }
+}
+
+/// The depth of the directory and, if its entries do not fit on the
+/// root, the pages that hold them.
+fn read_root(pool: &mut BufferPool, root: PageId) -> io::Result<(u16, Vec<PageId>)> {
+    pool.read(root, |data| {
+        if page_kind(data)? != PageKind::HashDirectory {
+            return Err(corrupt("hash index root is not a directory"));
+        }
+        let depth = read_u16(data, DEPTH);
+        if depth > MAX_DEPTH {
+            return Err(corrupt("hash directory is too deep"));
+        }
+        let count = match depth.checked_sub(PAGE_DEPTH) {
+            Some(extra) if extra > 0 => 1usize << extra,
+            _ => 0,
+        };
+        let pages = (0..count)
+            .map(|position| read_u32(data, ENTRIES + position * 4))
+            .collect();
+        Ok((depth, pages))
+    })?
+}
+
+fn read_directory(pool: &mut BufferPool, root: PageId) -> io::Result<(u16, Vec<PageId>)> {
+    let (depth, pages) = read_root(pool, root)?;
+    if depth <= PAGE_DEPTH {
+        let directory = pool.read(root, |data| {
+            (0..1usize << depth)
+                .map(|position| read_u32(data, ENTRIES + position * 4))
+                .collect()
+        })?;
+        return Ok((depth, directory));
+    }
+    let mut directory = Vec::with_capacity(1 << depth);
+    for page in pages {
+        let entries = pool.read(page, |data| {
+            if page_kind(data)? != PageKind::HashDirectory {
+                return Err(corrupt("hash index lists a page that is not a directory"));
+            }
+            Ok((0..1usize << PAGE_DEPTH)
+                .map(|position| read_u32(data, ENTRIES + position * 4))
+                .collect::<Vec<PageId>>())
+        })??;
+        directory.extend(entries);
+    }
+    Ok((depth, directory))
+}
+
+/// Writes the directory, on the root while it fits and otherwise on pages
+/// the root lists, keeping those it already had. The directory only
+/// grows, so no page is ever left over.
+fn write_directory(
+    pool: &mut BufferPool,
+    root: PageId,
+    depth: u16,
+    directory: &[PageId],
+) -> io::Result<()> {
+    if depth <= PAGE_DEPTH {
+        return write_entries(pool, root, depth, directory);
+    }
+    let (_, mut pages) = read_root(pool, root)?;
+    while pages.len() < directory.len() >> PAGE_DEPTH {
+        pages.push(allocate_page(pool)?);
+    }
+    for (page, part) in pages.iter().zip(directory.chunks(1 << PAGE_DEPTH)) {
+        write_entries(pool, *page, PAGE_DEPTH, part)?;
+    }
+    write_entries(pool, root, depth, &pages)
+}
+
+/// Writes a directory page of depth `depth` holding `entries`.
+fn write_entries(
+    pool: &mut BufferPool,
+    page: PageId,
+    depth: u16,
+    entries: &[PageId],
+) -> io::Result<()> {
+    pool.write(page, |data| {
+        format_page(data, PageKind::HashDirectory);
+        write_u16(data, DEPTH, depth);
+        for (position, entry) in entries.iter().enumerate() {
+            write_u32(data, ENTRIES + position * 4, *entry);
+        }
+    })
+}
+
+/// The pages of the bucket starting at `first`, in chain order.
+fn read_chain(pool: &mut BufferPool, first: PageId) -> io::Result<Vec<(PageId, Bucket)>> {
+    let mut chain = Vec::new();
+    let mut page = first;
+    while page != HEADER_PAGE {
+        let bucket = pool.read(page, Bucket::read)??;
+        let next = bucket.next;
+        chain.push((page, bucket));
+        page = next;
+    }
+    Ok(chain)
+}
+
+/// Writes `entries` as a bucket starting at `first`, chaining overflow
+/// pages for what does not fit.
+fn write_chain(
+    pool: &mut BufferPool,
+    first: PageId,
+    depth: u16,
+    entries: Vec<(Vec<u8>, RecordId)>,
+) -> io::Result<()> {
+    let mut page = first;
+    let mut bucket = Bucket {
+        depth,
+        entries: Vec::new(),
+        next: HEADER_PAGE,
+    };
+    for (key, id) in entries {
+        if bucket.size() + entry_size(&key) > PAGE_SIZE {
+            let next = allocate_page(pool)?;
+            bucket.next = next;
+            write_bucket(pool, page, &bucket)?;
+            page = next;
+            bucket = Bucket {
+                depth,
+                entries: Vec::new(),
+                next: HEADER_PAGE,
+            };
+        }
+        bucket.entries.push((key, id));
+    }
+    write_bucket(pool, page, &bucket)
+}
+
+fn write_bucket(pool: &mut BufferPool, page: PageId, bucket: &Bucket) -> io::Result<()> {
+    pool.write(page, |data| bucket.write(data))
+}
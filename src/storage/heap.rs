@@ -0,0 +1,251 @@
+use std::io;
+use std::vec;
+
+use crate::storage::allocator::{allocate_page, free_page};
+use crate::storage::buffer::BufferPool;
+use crate::storage::free_space::FreeSpaceMap;
+use crate::storage::page::{HeapPage, PageId, RecordId, MAX_RECORD_SIZE};
+
+/// The records of a page with their ids, as a scan reads them.
+pub type PageRecords = Vec<(RecordId, Vec<u8>)>;
+
+/// The unordered records of one table, spread over slotted heap pages. The
+/// free-space map rooted at [`HeapFile::root`] lists the pages.
+#[derive(Debug, Clone)]
+pub struct HeapFile {
+    map: FreeSpaceMap,
+}
+
+impl HeapFile {
+    pub fn create(pool: &mut BufferPool) -> io::Result<Self> {
+        Ok(HeapFile {
+            map: FreeSpaceMap::create(pool)?,
+        })
+    }
+
+    pub fn open(pool: &mut BufferPool, root: PageId) -> io::Result<Self> {
+        Ok(HeapFile {
+            map: FreeSpaceMap::load(pool, root)?,
+        })
+    }
+
+    pub fn root(&self) -> PageId {
+        self.map.root()
+    }
+
+    pub fn free_space_map(&self) -> &FreeSpaceMap {
+        &self.map
+    }
+
+    /// Stores `record` on the first page with room for it, adding a page
+    /// if none has.
+    pub fn insert(&mut self, pool: &mut BufferPool, record: &[u8]) -> io::Result<RecordId> {
+        if record.len() > MAX_RECORD_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "record of {} bytes is larger than a page holds ({} bytes)",
+                    record.len(),
+                    MAX_RECORD_SIZE
+                ),
+            ));
+        }
+        let page = match self.map.find(record.len()) {
+            Some(page) => page,
+            None => {
+                let page = allocate_page(pool)?;
+                let free = pool.write(page, |data| HeapPage::initialize(data).free_space())?;
+                self.map.add(pool, page, free)?;
+                page
+            }
+        };
+        self.insert_into(pool, page, record)
+    }
+
+    fn insert_into(
+        &mut self,
+        pool: &mut BufferPool,
+        page: PageId,
+        record: &[u8],
+    ) -> io::Result<RecordId> {
+        let (slot, free) = pool.write(page, |data| {
+            let mut heap = HeapPage::new(data);
+            (heap.insert(record), heap.free_space())
+        })?;
+        self.map.update(pool, page, free)?;
+        let slot = slot.ok_or_else(|| {
+            io::Error::other("free-space map promised room the page does not have")
+        })?;
+        Ok(RecordId { page, slot })
+    }
+
+    pub fn get(&self, pool: &mut BufferPool, id: RecordId) -> io::Result<Option<Vec<u8>>> {
+        if self.map.free_space(id.page).is_none() {
+            return Ok(None);
+        }
+        pool.read(id.page, |data| {
+            HeapPage::new(data)
+                .get(id.slot)
+                .map(|record| record.to_vec())
+        })
+    }
+
+    /// Removes the record, returning whether there was one.
+    pub fn delete(&mut self, pool: &mut BufferPool, id: RecordId) -> io::Result<bool> {
+        if self.map.free_space(id.page).is_none() {
+            return Ok(false);
+        }
+        let (deleted, free) = pool.write(id.page, |data| {
+            let mut heap = HeapPage::new(data);
+            (heap.delete(id.slot), heap.free_space())
+        })?;
+        self.map.update(pool, id.page, free)?;
+        Ok(deleted)
+    }
+
+    /// Replaces the record in place if its page has room, otherwise moves
+    /// it. Returns where the record now is, or `None` if there was none.
+    pub fn update(
+        &mut self,
+        pool: &mut BufferPool,
+        id: RecordId,
+        record: &[u8],
+    ) -> io::Result<Option<RecordId>> {
+        if self.map.free_space(id.page).is_none() {
+            return Ok(None);
+        }
+        let (exists, updated, free) = pool.write(id.page, |data| {
+            let mut heap = HeapPage::new(data);
+            let exists = heap.get(id.slot).is_some();
+            (
+                exists,
+                exists && heap.update(id.slot, record),
+                heap.free_space(),
+            )
+        })?;
+        if !exists {
+            return Ok(None);
+        }
+        if updated {
+            self.map.update(pool, id.page, free)?;
+            return Ok(Some(id));
+        }
+        let moved = self.insert(pool, record)?;
+        self.delete(pool, id)?;
+        Ok(Some(moved))
+    }
+
+    /// Removes the records in `slots` of `page` and compacts the page,
+    /// dropping the empty slots at its end.
+    pub fn prune(&mut self, pool: &mut BufferPool, page: PageId, slots: &[u16]) -> io::Result<()> {
+        if self.map.free_space(page).is_none() {
+            return Ok(());
+        }
+        let free = pool.write(page, |data| {
+            let mut heap = HeapPage::new(data);
+            for &slot in slots {
+                heap.delete(slot);
+            }
+            heap.truncate_slots();
+            heap.compact();
+            heap.free_space()
+        })?;
+        self.map.update(pool, page, free)
+    }
+
+    /// Moves the record at `id` to the first page before its own with
+    /// room for it. Returns where it went, or `None` if no such page has
+    /// room.
+    pub fn relocate(
+        &mut self,
+        pool: &mut BufferPool,
+        id: RecordId,
+    ) -> io::Result<Option<RecordId>> {
+        let record = match self.get(pool, id)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let page = match self.map.find_before(record.len(), id.page) {
+            Some(page) => page,
+            None => return Ok(None),
+        };
+        let moved = self.insert_into(pool, page, &record)?;
+        self.delete(pool, id)?;
+        Ok(Some(moved))
+    }
+
+    /// Frees the empty pages at the end of the heap, returning how many
+    /// there were.
+    pub fn truncate(&mut self, pool: &mut BufferPool) -> io::Result<usize> {
+        let pages: Vec<PageId> = self.map.pages().collect();
+        let mut count = pages.len();
+        while count > 0 {
+            let empty = pool.read(pages[count - 1], |data| {
+                HeapPage::new(data).records().next().is_none()
+            })?;
+            if !empty {
+                break;
+            }
+            count -= 1;
+        }
+        let directories = self.map.truncate(pool, count)?;
+        for &page in pages[count..].iter().chain(&directories) {
+            free_page(pool, page)?;
+        }
+        Ok(pages.len() - count)
+    }
+
+    /// The pages of the heap, in the order a scan reads them.
+    pub fn pages(&self) -> Vec<PageId> {
+        self.map.pages().collect()
+    }
+
+    /// The records of `page` with their ids, or none if the page is not
+    /// one of the heap's, as after it was truncated away.
+    pub fn read_page(&self, pool: &mut BufferPool, page: PageId) -> io::Result<PageRecords> {
+        if self.map.free_space(page).is_none() {
+            return Ok(Vec::new());
+        }
+        pool.read(page, |data| {
+            HeapPage::new(data)
+                .records()
+                .map(|(slot, record)| (RecordId { page, slot }, record.to_vec()))
+                .collect()
+        })
+    }
+
+    /// Every record with its id, read a page at a time as the scan goes.
+    pub fn scan<'a>(&'a self, pool: &'a mut BufferPool) -> HeapScan<'a> {
+        HeapScan {
+            heap: self,
+            pool,
+            pages: self.pages().into_iter(),
+            records: Vec::new().into_iter(),
+        }
+    }
+}
+
+/// A scan of a heap, holding the records of one page at a time.
+pub struct HeapScan<'a> {
+    heap: &'a HeapFile,
+    pool: &'a mut BufferPool,
+    pages: vec::IntoIter<PageId>,
+    records: vec::IntoIter<(RecordId, Vec<u8>)>,
+}
+
+impl Iterator for HeapScan<'_> {
+    type Item = io::Result<(RecordId, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.records.next() {
+                return Some(Ok(record));
+            }
+            let page = self.pages.next()?;
+            match self.heap.read_page(self.pool, page) {
+                Ok(records) => self.records = records.into_iter(),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::compiler::parser::DataType;
+use crate::execution::error::ExecutionError;
+use crate::execution::planner::TableProvider;
+use crate::execution::relation::Rows;
+use crate::storage::catalog::{Catalog, TableSchema};
+use crate::types::value::Value;
+
+/// Read-only views of the catalog, queried like tables as
+/// `information_schema.<view>`.
+pub const SCHEMA_NAME: &str = "information_schema";
+
+const NAME: DataType = DataType::TEXT { length: 256 };
+const FLAG: DataType = DataType::TEXT { length: 3 };
+const NUMBER: DataType = DataType::INT { unsigned: false };
+
+const TABLES: &[(&str, DataType)] = &[
+    ("table_catalog", NAME),
+    ("table_name", NAME),
+    ("table_type", NAME),
+];
+
+const COLUMNS: &[(&str, DataType)] = &[
+    ("table_catalog", NAME),
+    ("table_name", NAME),
+    ("column_name", NAME),
+    ("ordinal_position", NUMBER),
+    ("data_type", NAME),
+    ("character_maximum_length", NUMBER),
+    ("is_nullable", FLAG),
+    ("is_primary_key", FLAG),
+    ("is_identity", FLAG),
+];
+
+const TABLE_CONSTRAINTS: &[(&str, DataType)] = &[
+    ("table_catalog", NAME),
+    ("table_name", NAME),
+    ("constraint_name", NAME),
+    ("constraint_type", NAME),
+];
+
+const KEY_COLUMN_USAGE: &[(&str, DataType)] = &[
+    ("table_catalog", NAME),
+    ("table_name", NAME),
+    ("constraint_name", NAME),
+    ("column_name", NAME),
+    ("ordinal_position", NUMBER),
+];
+
+const INDEXES: &[(&str, DataType)] = &[
+    ("table_catalog", NAME),
+    ("table_name", NAME),
+    ("index_name", NAME),
+    ("column_names", NAME),
+    ("is_unique", FLAG),
+    ("index_method", NAME),
+    ("index_predicate", NAME),
+];
+
+/// The columns of the view called `name`, if `name` is one. `name` is the
+/// qualified name as written in FROM.
+pub fn view_columns(name: &str) -> Option<&'static [(&'static str, DataType)]> {
+    match view_name(name)? {
+        "tables" => Some(TABLES),
+        "columns" => Some(COLUMNS),
+        "table_constraints" => Some(TABLE_CONSTRAINTS),
+        "key_column_usage" => Some(KEY_COLUMN_USAGE),
+        "indexes" => Some(INDEXES),
+        _ => None,
+    }
+}
+
+/// The rows of the view called `name` over every database of `catalog`.
+pub fn view_rows(catalog: &Catalog, name: &str) -> Option<Vec<Vec<Value>>> {
+    let tables = catalog.tables();
+    let rows = match view_name(name)? {
+        "tables" => tables
+            .iter()
+            .map(|table| {
+                let mut row = table_key(table);
+                row.push(text("BASE TABLE"));
+                row
+            })
+            .collect(),
+        "columns" => tables
+            .iter()
+            .flat_map(|table| {
+                table
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, column)| {
+                        let length = match column.data_type {
+                            DataType::TEXT { length } => Value::Integer(length as i128),
+                            _ => Value::Null,
+                        };
+                        let mut row = table_key(table);
+                        row.extend([
+                            text(&column.name),
+                            Value::Integer(index as i128 + 1),
+                            text(&column.data_type.to_string()),
+                            length,
+                            flag(!column.not_null && !column.is_primary),
+                            flag(column.is_primary),
+                            flag(column.identity),
+                        ]);
+                        row
+                    })
+            })
+            .collect(),
+        "table_constraints" => tables
+            .iter()
+            .flat_map(|table| {
+                table.constraints.iter().map(move |constraint| {
+                    let mut row = table_key(table);
+                    row.extend([text(&constraint.name), text(constraint.kind.name())]);
+                    row
+                })
+            })
+            .collect(),
+        "key_column_usage" => tables
+            .iter()
+            .flat_map(|table| {
+                table.constraints.iter().flat_map(move |constraint| {
+                    constraint
+                        .columns
+                        .iter()
+                        .enumerate()
+                        .map(move |(index, column)| {
+                            let mut row = table_key(table);
+                            row.extend([
+                                text(&constraint.name),
+                                text(column),
+                                Value::Integer(index as i128 + 1),
+                            ]);
+                            row
+                        })
+                })
+            })
+            .collect(),
+        "indexes" => tables
+            .iter()
+            .flat_map(|table| {
+                table.indexes.iter().map(move |index| {
+                    let mut row = table_key(table);
+                    row.extend([
+                        text(&index.name),
+                        text(&index.columns.join(", ")),
+                        flag(index.unique),
+                        text(&index.method.to_string()),
+                        index.predicate.as_deref().map_or(Value::Null, text),
+                    ]);
+                    row
+                })
+            })
+            .collect(),
+        _ => return None,
+    };
+    Some(rows)
+}
+
+/// Serves the views; the catalog holds no rows of its own.
+impl TableProvider for Catalog {
+    fn scan(&self, table: &str) -> Result<(Vec<String>, Rows<'_>), ExecutionError> {
+        match (view_columns(table), view_rows(self, table)) {
+            (Some(columns), Some(rows)) => Ok((
+                columns.iter().map(|(name, _)| name.to_string()).collect(),
+                Box::new(rows.into_iter().map(Ok)),
+            )),
+            _ => Err(ExecutionError::UnknownTable(table.to_string())),
+        }
+    }
+}
+
+fn view_name(name: &str) -> Option<&str> {
+    name.strip_prefix(SCHEMA_NAME)?.strip_prefix('.')
+}
+
+fn table_key(table: &TableSchema) -> Vec<Value> {
+    vec![text(&table.database), text(&table.name)]
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+fn flag(value: bool) -> Value {
+    text(if value { "YES" } else { "NO" })
+}
@@ -0,0 +1,102 @@
+//! Index keys whose bytes sort like their values.
+//!
+//! Each value of a key is a flag byte, 0 for NULL and 1 otherwise, so
+//! NULLs sort first, followed by:
+//!
+//! - integers and datetimes: big-endian with the sign bit flipped;
+//! - floats: the IEEE bits, all inverted for negatives and with the sign
+//!   bit flipped otherwise;
+//! - booleans: one byte;
+//! - text: its bytes with 0x00 escaped as 0x00 0xFF, ended by 0x00 0x00.
+//!
+//! No encoded value is a prefix of a different one, so the encoding of a
+//! composite key's leading columns is a prefix of the full key and a range
+//! of prefixes is a range of keys.
+
+use std::ops::Bound;
+
+use crate::storage::page::RecordId;
+use crate::types::value::Value;
+
+pub fn encode_key(values: &[Value]) -> Vec<u8> {
+    let mut key = Vec::new();
+    for value in values {
+        encode_value(value, &mut key);
+    }
+    key
+}
+
+/// The key an index stores for a row: its values followed by the record
+/// id, so that every entry of the tree is distinct even where rows share
+/// a key, as the versions of one row do.
+pub fn entry_key(values: &[Value], id: RecordId) -> Vec<u8> {
+    let mut key = encode_key(values);
+    key.extend_from_slice(&id.page.to_be_bytes());
+    key.extend_from_slice(&id.slot.to_be_bytes());
+    key
+}
+
+/// The smallest byte string greater than every string starting with
+/// `prefix`, or `None` if there is none.
+pub fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut successor = prefix.to_vec();
+    while let Some(last) = successor.pop() {
+        if last < u8::MAX {
+            successor.push(last + 1);
+            return Some(successor);
+        }
+    }
+    None
+}
+
+/// The keys starting with `prefix`.
+pub fn prefix_range(prefix: &[u8]) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+    (
+        Bound::Included(prefix.to_vec()),
+        prefix_successor(prefix).map_or(Bound::Unbounded, Bound::Excluded),
+    )
+}
+
+fn encode_value(value: &Value, key: &mut Vec<u8>) {
+    if value.is_null() {
+        key.push(0);
+        return;
+    }
+    key.push(1);
+    match value {
+        Value::Integer(integer) => {
+            key.extend_from_slice(&((*integer as u128) ^ (1 << 127)).to_be_bytes())
+        }
+        Value::DateTime(micros) => {
+            key.extend_from_slice(&((*micros as u64) ^ (1 << 63)).to_be_bytes())
+        }
+        Value::Float(float) => {
+            // -0.0 equals 0.0, so both get the bits of 0.0, and every NaN
+            // gets the same bits, which order above every number.
+            let bits = if *float == 0.0 {
+                0
+            } else if float.is_nan() {
+                f64::NAN.to_bits()
+            } else {
+                float.to_bits()
+            };
+            let ordered = if bits >> 63 == 1 {
+                !bits
+            } else {
+                bits ^ (1 << 63)
+            };
+            key.extend_from_slice(&ordered.to_be_bytes());
+        }
+        Value::Bool(value) => key.push(*value as u8),
+        Value::Text(text) => {
+            for &byte in text.as_bytes() {
+                key.push(byte);
+                if byte == 0 {
+                    key.push(0xff);
+                }
+            }
+            key.extend_from_slice(&[0, 0]);
+        }
+        Value::Null => {}
+    }
+}
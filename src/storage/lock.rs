@@ -0,0 +1,314 @@
+//! Locks on tables and rows, held by transactions until they end.
+//!
+//! Reads take no locks, as snapshots already keep them apart from writes.
+//! Locks order what snapshots do not: `SELECT ... FOR UPDATE` and `FOR
+//! SHARE` lock the rows they return, `LOCK TABLE` whole tables, and INSERT
+//! takes an intention lock on its table, so that a transaction holding the
+//! table in `Share` or `Exclusive` mode keeps new rows out. VACUUM FULL
+//! holds its tables in `Exclusive` mode while it moves rows, and locking
+//! reads lock the table before reading the ids of the rows they lock, so
+//! that no row moves in between.
+//!
+//! The modes are those of multiple granularity locking (Gray et al.): a
+//! row lock comes with an intention lock on its table, which conflicts
+//! with locks of the whole table that the row lock would, and with no
+//! other intention lock.
+//!
+//! A transaction about to wait first follows the graph of which
+//! transactions wait for which. Only the edges out of the transaction are
+//! new, so a cycle, if one closes, passes through it; it then fails
+//! instead of waiting, describing the cycle, and is rolled back.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::compiler::parser::{LockMode, LockWait};
+use crate::execution::error::ExecutionError;
+use crate::storage::mvcc::Xid;
+use crate::storage::page::RecordId;
+
+/// What a lock is on. Tables are named with their database, as
+/// `database.table`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LockTarget {
+    Table(String),
+    Row { table: String, row: RecordId },
+}
+
+impl fmt::Display for LockTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockTarget::Table(table) => write!(f, "table \"{}\"", table),
+            LockTarget::Row { table, row } => {
+                write!(f, "row ({}, {}) of table \"{}\"", row.page, row.slot, table)
+            }
+        }
+    }
+}
+
+/// Whether a transaction may take a lock in mode `wanted` while another
+/// holds one in mode `held`.
+pub fn compatible(held: LockMode, wanted: LockMode) -> bool {
+    use LockMode::*;
+    match (held, wanted) {
+        (Exclusive, _) | (_, Exclusive) => false,
+        (IntentionShare, _) | (_, IntentionShare) => true,
+        (IntentionExclusive, IntentionExclusive) | (Share, Share) => true,
+        _ => false,
+    }
+}
+
+/// The weakest mode as strong as both `first` and `second`.
+fn combine(first: LockMode, second: LockMode) -> LockMode {
+    use LockMode::*;
+    match (first, second) {
+        (Exclusive, _) | (_, Exclusive) => Exclusive,
+        (ShareIntentionExclusive, _) | (_, ShareIntentionExclusive) => ShareIntentionExclusive,
+        (Share, IntentionExclusive) | (IntentionExclusive, Share) => ShareIntentionExclusive,
+        (Share, _) | (_, Share) => Share,
+        (IntentionExclusive, _) | (_, IntentionExclusive) => IntentionExclusive,
+        _ => IntentionShare,
+    }
+}
+
+#[derive(Debug, Default)]
+struct Lock {
+    granted: HashMap<Xid, LockMode>,
+    /// Transactions waiting for the lock, in the order they came, with
+    /// the mode each wants.
+    waiting: Vec<(Xid, LockMode)>,
+}
+
+#[derive(Debug, Default)]
+struct LockTable {
+    locks: HashMap<LockTarget, Lock>,
+    held: HashMap<Xid, HashSet<LockTarget>>,
+    /// What each waiting transaction waits for.
+    waits: HashMap<Xid, LockTarget>,
+}
+
+impl LockTable {
+    /// The transactions `xid` has to wait for to hold `target` in `mode`:
+    /// those holding it in a mode that conflicts, and unless `xid` holds
+    /// it already, those waiting before `xid` for one that does.
+    fn blockers(&self, target: &LockTarget, xid: Xid, mode: LockMode) -> Vec<Xid> {
+        let lock = match self.locks.get(target) {
+            Some(lock) => lock,
+            None => return Vec::new(),
+        };
+        let mut blockers: Vec<Xid> = lock
+            .granted
+            .iter()
+            .filter(|(&holder, &held)| holder != xid && !compatible(held, mode))
+            .map(|(&holder, _)| holder)
+            .collect();
+        if !lock.granted.contains_key(&xid) {
+            for &(waiter, wanted) in &lock.waiting {
+                if waiter == xid {
+                    break;
+                }
+                if !compatible(wanted, mode) && !blockers.contains(&waiter) {
+                    blockers.push(waiter);
+                }
+            }
+        }
+        blockers.sort_unstable();
+        blockers
+    }
+
+    fn grant(&mut self, xid: Xid, target: &LockTarget, mode: LockMode) {
+        self.locks
+            .entry(target.clone())
+            .or_default()
+            .granted
+            .insert(xid, mode);
+        self.held.entry(xid).or_default().insert(target.clone());
+    }
+
+    fn wait(&mut self, xid: Xid, target: &LockTarget, mode: LockMode) {
+        self.locks
+            .entry(target.clone())
+            .or_default()
+            .waiting
+            .push((xid, mode));
+        self.waits.insert(xid, target.clone());
+    }
+
+    fn stop_waiting(&mut self, xid: Xid) {
+        let target = match self.waits.remove(&xid) {
+            Some(target) => target,
+            None => return,
+        };
+        if let Some(lock) = self.locks.get_mut(&target) {
+            lock.waiting.retain(|&(waiter, _)| waiter != xid);
+            if lock.granted.is_empty() && lock.waiting.is_empty() {
+                self.locks.remove(&target);
+            }
+        }
+    }
+
+    /// What `xid` waits for, and in which mode.
+    fn waiting_for(&self, xid: Xid) -> Option<(&LockTarget, LockMode)> {
+        let target = self.waits.get(&xid)?;
+        let mode = self
+            .locks
+            .get(target)?
+            .waiting
+            .iter()
+            .find(|&&(waiter, _)| waiter == xid)
+            .map(|&(_, mode)| mode)?;
+        Some((target, mode))
+    }
+
+    /// A cycle of waits through `start`, described from `start` on.
+    fn cycle(&self, start: Xid) -> Option<String> {
+        let mut path = Vec::new();
+        if !self.find_cycle(start, start, &mut HashSet::new(), &mut path) {
+            return None;
+        }
+        let steps: Vec<String> = path
+            .iter()
+            .map(|&(waiter, blocker)| {
+                let (target, mode) = self
+                    .waiting_for(waiter)
+                    .expect("a transaction on the path waits");
+                format!(
+                    "transaction {} waits for {} lock on {}, blocked by transaction {}",
+                    waiter, mode, target, blocker
+                )
+            })
+            .collect();
+        Some(steps.join("; "))
+    }
+
+    /// Follows the waits from `from`, adding each one taken to `path`,
+    /// until one leads back to `start`.
+    fn find_cycle(
+        &self,
+        from: Xid,
+        start: Xid,
+        visited: &mut HashSet<Xid>,
+        path: &mut Vec<(Xid, Xid)>,
+    ) -> bool {
+        let (target, mode) = match self.waiting_for(from) {
+            Some(wait) => wait,
+            None => return false,
+        };
+        for blocker in self.blockers(target, from, mode) {
+            path.push((from, blocker));
+            if blocker == start
+                || (visited.insert(blocker) && self.find_cycle(blocker, start, visited, path))
+            {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+}
+
+/// The locks of every transaction of a database.
+#[derive(Debug, Default)]
+pub struct LockManager {
+    table: Mutex<LockTable>,
+    /// Notified whenever a lock is released or a transaction stops
+    /// waiting, so that those behind it look again.
+    changed: Condvar,
+}
+
+impl LockManager {
+    /// Locks `target` for `xid` in `mode`, or in the mode as strong as that
+    /// and the one `xid` holds it in. Returns whether it did, which is
+    /// only false under `SkipLocked`. Waiting gives up after `timeout`,
+    /// if there is one.
+    pub fn acquire(
+        &self,
+        xid: Xid,
+        target: &LockTarget,
+        mode: LockMode,
+        wait: LockWait,
+        timeout: Option<Duration>,
+    ) -> Result<bool, ExecutionError> {
+        let mut table = self.table();
+        let held = table
+            .locks
+            .get(target)
+            .and_then(|lock| lock.granted.get(&xid).copied());
+        let mode = match held {
+            Some(held) if combine(held, mode) == held => return Ok(true),
+            Some(held) => combine(held, mode),
+            None => mode,
+        };
+        if table.blockers(target, xid, mode).is_empty() {
+            table.grant(xid, target, mode);
+            return Ok(true);
+        }
+        match wait {
+            LockWait::SkipLocked => return Ok(false),
+            LockWait::NoWait => {
+                return Err(ExecutionError::LockNotAvailable(format!(
+                    "could not obtain {} lock on {}",
+                    mode, target
+                )))
+            }
+            LockWait::Wait => {}
+        }
+        table.wait(xid, target, mode);
+        if let Some(cycle) = table.cycle(xid) {
+            table.stop_waiting(xid);
+            self.changed.notify_all();
+            return Err(ExecutionError::Deadlock(cycle));
+        }
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            table = match deadline {
+                None => self
+                    .changed
+                    .wait(table)
+                    .unwrap_or_else(PoisonError::into_inner),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        table.stop_waiting(xid);
+                        self.changed.notify_all();
+                        return Err(ExecutionError::LockNotAvailable(format!(
+                            "canceling statement due to lock timeout: could not obtain {} lock on {}",
+                            mode, target
+                        )));
+                    }
+                    self.changed
+                        .wait_timeout(table, deadline - now)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+            };
+            if table.blockers(target, xid, mode).is_empty() {
+                table.stop_waiting(xid);
+                table.grant(xid, target, mode);
+                self.changed.notify_all();
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Releases every lock of `xid`, which has ended.
+    pub fn release_all(&self, xid: Xid) {
+        let mut table = self.table();
+        table.stop_waiting(xid);
+        for target in table.held.remove(&xid).unwrap_or_default() {
+            if let Some(lock) = table.locks.get_mut(&target) {
+                lock.granted.remove(&xid);
+                if lock.granted.is_empty() && lock.waiting.is_empty() {
+                    table.locks.remove(&target);
+                }
+            }
+        }
+        self.changed.notify_all();
+    }
+
+    fn table(&self) -> MutexGuard<'_, LockTable> {
+        self.table.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
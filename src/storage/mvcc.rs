@@ -0,0 +1,517 @@
+//! Multi-version concurrency control.
+//!
+//! A heap record is one version of a row. It starts with the id of the
+//! transaction that created it and the id of the one that deleted it,
+//! zero while none has:
+//!
+//! ```text
+//! | creator | deleter | row |
+//! ```
+//!
+//! A statement reads through a snapshot of which transactions had
+//! committed when it was taken: it sees the versions those transactions
+//! and its own created and none of them deleted. Readers so never wait
+//! for writers, nor writers for readers, and a transaction that rolls
+//! back leaves nothing to undo, as what it wrote stays invisible. Under
+//! READ COMMITTED, and READ UNCOMMITTED, which is the same here, every
+//! statement takes a new snapshot; under REPEATABLE READ and SERIALIZABLE
+//! the transaction keeps the one of its first statement.
+//!
+//! The changes made after a savepoint get an id of their own, so that
+//! rolling back to the savepoint ends them as if their own transaction
+//! had rolled back.
+//!
+//! SERIALIZABLE adds serializable snapshot isolation (Cahill, Röhm and
+//! Fekete). Each serializable transaction records the tables it read and
+//! wrote. Reading a table that a concurrent transaction wrote, or writing
+//! one that a concurrent transaction read, is a read-write dependency of
+//! the writer on the reader, which has to come first in any serial order.
+//! Every cycle of dependencies no serial order satisfies passes through
+//! a transaction with a dependency in and one out, so a read or write
+//! that would make such a transaction fails instead. Recording tables
+//! rather than rows reports some conflicts that are not, but also catches
+//! rows inserted into what another transaction read.
+//!
+//! Versions that never committed, and those whose deletion committed
+//! before every running transaction began, are seen by no one again;
+//! VACUUM removes them.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+
+use crate::compiler::parser::IsolationLevel;
+use crate::execution::error::ExecutionError;
+use crate::storage::page::{read_u64, write_u64, PageId};
+
+/// Transaction ids only grow, across restarts too.
+pub type Xid = u64;
+
+/// The deleter of a version nothing deleted.
+pub const NO_XID: Xid = 0;
+pub const FIRST_XID: Xid = 1;
+
+/// Bytes of a record before the row: its creator and deleter.
+pub const VERSION_HEADER_SIZE: usize = 16;
+
+const CREATOR: usize = 0;
+const DELETER: usize = 8;
+
+/// A new version of a row, created by `creator`.
+pub fn encode_version(creator: Xid, row: &[u8]) -> Vec<u8> {
+    let mut record = vec![0; VERSION_HEADER_SIZE];
+    write_u64(&mut record, CREATOR, creator);
+    record.extend_from_slice(row);
+    record
+}
+
+pub fn creator(record: &[u8]) -> Xid {
+    read_u64(record, CREATOR)
+}
+
+pub fn deleter(record: &[u8]) -> Xid {
+    read_u64(record, DELETER)
+}
+
+pub fn set_deleter(record: &mut [u8], xid: Xid) {
+    write_u64(record, DELETER, xid);
+}
+
+pub fn version_row(record: &[u8]) -> &[u8] {
+    &record[VERSION_HEADER_SIZE..]
+}
+
+/// Which transactions a statement sees the work of.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    transaction: Xid,
+    /// The transaction's id and those of its savepoints not rolled back.
+    own: Vec<Xid>,
+    /// Transactions from this id on had not started.
+    end: Xid,
+    /// Other transactions that were running.
+    running: HashSet<Xid>,
+}
+
+impl Snapshot {
+    /// The transaction the snapshot belongs to.
+    pub fn transaction(&self) -> Xid {
+        self.transaction
+    }
+
+    /// Whether `xid` had not finished when the snapshot was taken.
+    pub fn in_progress(&self, xid: Xid) -> bool {
+        xid >= self.end || self.running.contains(&xid)
+    }
+
+    /// Whether the snapshot sees a version of `creator` that `deleter`
+    /// deleted, if not [`NO_XID`]. `committed` says whether a finished
+    /// transaction committed.
+    pub fn sees(
+        &self,
+        creator: Xid,
+        deleter: Xid,
+        mut committed: impl FnMut(Xid) -> io::Result<bool>,
+    ) -> io::Result<bool> {
+        let mut done = |xid: Xid| -> io::Result<bool> {
+            Ok(self.own.contains(&xid) || (!self.in_progress(xid) && committed(xid)?))
+        };
+        Ok(done(creator)? && (deleter == NO_XID || !done(deleter)?))
+    }
+}
+
+/// What a version is to a transaction writing a row that may conflict
+/// with it, whatever the transaction's snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionState {
+    /// Committed, or the transaction's own, and not deleted.
+    Live,
+    /// Never committed, or deleted for good.
+    Dead,
+    /// Created or being deleted by the running transaction given.
+    InProgress(Xid),
+}
+
+/// The transaction, or a savepoint of it.
+#[derive(Debug, Default)]
+struct Level {
+    /// The id of the level's changes, given with the first of them.
+    xid: Option<Xid>,
+    /// The ids of savepoints released into the level.
+    released: Vec<Xid>,
+}
+
+#[derive(Debug)]
+struct Transaction {
+    isolation: IsolationLevel,
+    levels: Vec<Level>,
+    /// The snapshot REPEATABLE READ and SERIALIZABLE keep.
+    snapshot: Option<Snapshot>,
+    /// Whether it wrote, so that its commit has to be recorded.
+    wrote: bool,
+    /// The oldest id running when it began, or the next one if none was.
+    /// Its snapshots see every transaction before this one as finished.
+    horizon: Xid,
+}
+
+impl Transaction {
+    fn ids(&self) -> impl Iterator<Item = Xid> + '_ {
+        self.levels
+            .iter()
+            .flat_map(|level| level.xid.iter().chain(&level.released).copied())
+    }
+}
+
+/// The read-write dependencies of a serializable transaction.
+#[derive(Debug, Default)]
+struct Dependencies {
+    reads: HashSet<PageId>,
+    writes: HashSet<PageId>,
+    /// A concurrent transaction read a table before this one wrote it.
+    inbound: bool,
+    /// This transaction read a table before a concurrent one wrote it.
+    outbound: bool,
+    committed: bool,
+}
+
+/// The running transactions, the ids to give new ones, and what
+/// serializable transactions depend on.
+#[derive(Debug)]
+pub struct Transactions {
+    next: Xid,
+    /// Running ids, of transactions and their savepoints, each with the
+    /// id of its transaction.
+    running: BTreeMap<Xid, Xid>,
+    transactions: HashMap<Xid, Transaction>,
+    /// Serializable transactions that are running, or committed while a
+    /// running one may still depend on them.
+    serializable: HashMap<Xid, Dependencies>,
+}
+
+impl Transactions {
+    /// No transaction running, the next to start getting `next`.
+    pub fn new(next: Xid) -> Self {
+        Transactions {
+            next: next.max(FIRST_XID),
+            running: BTreeMap::new(),
+            transactions: HashMap::new(),
+            serializable: HashMap::new(),
+        }
+    }
+
+    /// The id the next transaction or savepoint gets.
+    pub fn next(&self) -> Xid {
+        self.next
+    }
+
+    pub fn begin(&mut self, isolation: IsolationLevel) -> Xid {
+        let horizon = self.running.keys().next().copied().unwrap_or(self.next);
+        let xid = self.allocate(None);
+        self.transactions.insert(
+            xid,
+            Transaction {
+                isolation,
+                levels: vec![Level {
+                    xid: Some(xid),
+                    released: Vec::new(),
+                }],
+                snapshot: None,
+                wrote: false,
+                horizon,
+            },
+        );
+        if isolation == IsolationLevel::Serializable {
+            self.serializable.insert(xid, Dependencies::default());
+        }
+        xid
+    }
+
+    pub fn isolation(&self, xid: Xid) -> Option<IsolationLevel> {
+        self.transactions
+            .get(&xid)
+            .map(|transaction| transaction.isolation)
+    }
+
+    /// The snapshot a statement of `xid` reads through.
+    pub fn snapshot(&mut self, xid: Xid) -> Snapshot {
+        let own: Vec<Xid> = self
+            .transactions
+            .get(&xid)
+            .map(|transaction| transaction.ids().collect())
+            .unwrap_or_default();
+        let fresh = Snapshot {
+            transaction: xid,
+            own: own.clone(),
+            end: self.next,
+            running: self
+                .running
+                .iter()
+                .filter(|(_, &owner)| owner != xid)
+                .map(|(&id, _)| id)
+                .collect(),
+        };
+        let transaction = match self.transactions.get_mut(&xid) {
+            Some(transaction) => transaction,
+            None => return fresh,
+        };
+        if matches!(
+            transaction.isolation,
+            IsolationLevel::ReadUncommitted | IsolationLevel::ReadCommitted
+        ) {
+            return fresh;
+        }
+        let kept = transaction.snapshot.get_or_insert(fresh);
+        kept.own = own;
+        kept.clone()
+    }
+
+    /// Starts a savepoint of `xid`, returning its level.
+    pub fn savepoint(&mut self, xid: Xid) -> usize {
+        match self.transactions.get_mut(&xid) {
+            Some(transaction) => {
+                transaction.levels.push(Level::default());
+                transaction.levels.len() - 1
+            }
+            None => 0,
+        }
+    }
+
+    /// Ends what `xid` did since the savepoint at `level` as if rolled
+    /// back, starting the savepoint over.
+    pub fn rollback_to(&mut self, xid: Xid, level: usize) {
+        let transaction = match self.transactions.get_mut(&xid) {
+            Some(transaction) if level > 0 => transaction,
+            _ => return,
+        };
+        for level in transaction.levels.split_off(level) {
+            for id in level.xid.iter().chain(&level.released) {
+                self.running.remove(id);
+            }
+        }
+        transaction.levels.push(Level::default());
+    }
+
+    /// Makes what `xid` did since the savepoint at `level` part of the
+    /// level before.
+    pub fn release(&mut self, xid: Xid, level: usize) {
+        let transaction = match self.transactions.get_mut(&xid) {
+            Some(transaction) if level > 0 => transaction,
+            _ => return,
+        };
+        let released: Vec<Xid> = transaction
+            .levels
+            .split_off(level)
+            .iter()
+            .flat_map(|level| level.xid.iter().chain(&level.released).copied())
+            .collect();
+        if let Some(parent) = transaction.levels.last_mut() {
+            parent.released.extend(released);
+        }
+    }
+
+    /// The id `xid` writes versions with: its own, or that of its latest
+    /// savepoint.
+    pub fn write_xid(&mut self, xid: Xid) -> Xid {
+        let allocated = match self.transactions.get(&xid) {
+            Some(transaction) => transaction.levels.last().and_then(|level| level.xid),
+            None => return xid,
+        };
+        let id = allocated.unwrap_or_else(|| self.allocate(Some(xid)));
+        if let Some(transaction) = self.transactions.get_mut(&xid) {
+            transaction.wrote = true;
+            if let Some(level) = transaction.levels.last_mut() {
+                level.xid = Some(id);
+            }
+        }
+        id
+    }
+
+    /// The transaction `xid` belongs to, if it is running.
+    pub fn owner(&self, xid: Xid) -> Option<Xid> {
+        self.running.get(&xid).copied()
+    }
+
+    /// The ids a commit of `xid` has to record as committed, none if it
+    /// wrote nothing.
+    pub fn commit_ids(&self, xid: Xid) -> Vec<Xid> {
+        match self.transactions.get(&xid) {
+            Some(transaction) if transaction.wrote => transaction.ids().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Ends `xid`, which committed or rolled back.
+    pub fn end(&mut self, xid: Xid, committed: bool) {
+        if let Some(transaction) = self.transactions.remove(&xid) {
+            for level in &transaction.levels {
+                for id in level.xid.iter().chain(&level.released) {
+                    self.running.remove(id);
+                }
+            }
+        }
+        self.running.retain(|_, owner| *owner != xid);
+        match self.serializable.get_mut(&xid) {
+            Some(dependencies) if committed => dependencies.committed = true,
+            Some(_) => {
+                self.serializable.remove(&xid);
+            }
+            None => {}
+        }
+        // A committed transaction only matters to those that could not
+        // see it.
+        let snapshots: Vec<Snapshot> = self
+            .serializable
+            .keys()
+            .filter_map(|id| self.transactions.get(id)?.snapshot.clone())
+            .collect();
+        self.serializable.retain(|id, dependencies| {
+            !dependencies.committed || snapshots.iter().any(|snapshot| snapshot.in_progress(*id))
+        });
+    }
+
+    /// What a version of `creator`, deleted by `deleter`, is to `xid`.
+    pub fn version_state(
+        &self,
+        xid: Xid,
+        creator: Xid,
+        deleter: Xid,
+        mut committed: impl FnMut(Xid) -> io::Result<bool>,
+    ) -> io::Result<VersionState> {
+        match self.owner(creator) {
+            Some(owner) if owner != xid => return Ok(VersionState::InProgress(owner)),
+            Some(_) => {}
+            None if !committed(creator)? => return Ok(VersionState::Dead),
+            None => {}
+        }
+        if deleter == NO_XID {
+            return Ok(VersionState::Live);
+        }
+        Ok(match self.owner(deleter) {
+            Some(owner) if owner != xid => VersionState::InProgress(owner),
+            Some(_) => VersionState::Dead,
+            None if committed(deleter)? => VersionState::Dead,
+            None => VersionState::Live,
+        })
+    }
+
+    /// The oldest id a running transaction may not see as finished. A
+    /// deletion committed by an earlier one is seen by every snapshot
+    /// there is or will be.
+    pub fn horizon(&self) -> Xid {
+        self.transactions
+            .values()
+            .map(|transaction| transaction.horizon)
+            .min()
+            .unwrap_or(self.next)
+    }
+
+    /// Whether no transaction, running or to come, can see a version of
+    /// `creator` that `deleter` deleted: it never committed, or its
+    /// deletion committed before `horizon`.
+    pub fn removable(
+        &self,
+        creator: Xid,
+        deleter: Xid,
+        horizon: Xid,
+        mut committed: impl FnMut(Xid) -> io::Result<bool>,
+    ) -> io::Result<bool> {
+        if self.owner(creator).is_some() {
+            return Ok(false);
+        }
+        if !committed(creator)? {
+            return Ok(true);
+        }
+        Ok(deleter != NO_XID && deleter < horizon && committed(deleter)?)
+    }
+
+    /// Records that `xid` read the heap rooted at `table`, failing if that
+    /// makes a dependency no serial order allows.
+    pub fn read(&mut self, xid: Xid, table: PageId) -> Result<(), ExecutionError> {
+        let snapshot = match self.serializable_snapshot(xid) {
+            Some(snapshot) => snapshot,
+            None => return Ok(()),
+        };
+        let writers: Vec<Xid> = self
+            .serializable
+            .iter()
+            .filter(|(&writer, dependencies)| {
+                writer != xid
+                    && dependencies.writes.contains(&table)
+                    && snapshot.in_progress(writer)
+            })
+            .map(|(&writer, _)| writer)
+            .collect();
+        for writer in writers {
+            self.depend(xid, writer)?;
+        }
+        if let Some(dependencies) = self.serializable.get_mut(&xid) {
+            dependencies.reads.insert(table);
+        }
+        Ok(())
+    }
+
+    /// Records that `xid` wrote the heap rooted at `table`, failing if that
+    /// makes a dependency no serial order allows.
+    pub fn write(&mut self, xid: Xid, table: PageId) -> Result<(), ExecutionError> {
+        let snapshot = match self.serializable_snapshot(xid) {
+            Some(snapshot) => snapshot,
+            None => return Ok(()),
+        };
+        let readers: Vec<Xid> = self
+            .serializable
+            .iter()
+            .filter(|(&reader, dependencies)| {
+                reader != xid
+                    && dependencies.reads.contains(&table)
+                    && (!dependencies.committed || snapshot.in_progress(reader))
+            })
+            .map(|(&reader, _)| reader)
+            .collect();
+        for reader in readers {
+            self.depend(reader, xid)?;
+        }
+        if let Some(dependencies) = self.serializable.get_mut(&xid) {
+            dependencies.writes.insert(table);
+        }
+        Ok(())
+    }
+
+    /// Records that `reader` read what `writer` wrote without seeing the
+    /// change, unless that would leave either with a dependency in and
+    /// one out.
+    fn depend(&mut self, reader: Xid, writer: Xid) -> Result<(), ExecutionError> {
+        let pivot = self
+            .serializable
+            .get(&reader)
+            .is_some_and(|dependencies| dependencies.inbound)
+            || self
+                .serializable
+                .get(&writer)
+                .is_some_and(|dependencies| dependencies.outbound);
+        if pivot {
+            return Err(ExecutionError::SerializationFailure(
+                "read/write dependencies among transactions".to_string(),
+            ));
+        }
+        if let Some(dependencies) = self.serializable.get_mut(&reader) {
+            dependencies.outbound = true;
+        }
+        if let Some(dependencies) = self.serializable.get_mut(&writer) {
+            dependencies.inbound = true;
+        }
+        Ok(())
+    }
+
+    fn serializable_snapshot(&self, xid: Xid) -> Option<Snapshot> {
+        if !self.serializable.contains_key(&xid) {
+            return None;
+        }
+        self.transactions.get(&xid)?.snapshot.clone()
+    }
+
+    fn allocate(&mut self, owner: Option<Xid>) -> Xid {
+        let xid = self.next;
+        self.next += 1;
+        self.running.insert(xid, owner.unwrap_or(xid));
+        xid
+    }
+}
@@ -0,0 +1,294 @@
+//! Fixed-size pages and the slotted layout of heap pages.
+//!
+//! Every page starts with the same header: the LSN of the last log record
+//! that changed it and a byte saying what kind of page it is. Heap pages
+//! follow it with a slot directory growing forward and records growing
+//! backward from the end of the page:
+//!
+//! ```text
+//! | lsn | kind | - | slots | free start | free end | slot 0 | slot 1 | ... free ... | record 1 | record 0 |
+//! ```
+//!
+//! A slot is the offset and length of its record. Deleting a record
+//! empties its slot but keeps it, so the ids of other records on the page
+//! stay valid; a later insert reuses it.
+
+use std::io;
+
+pub const PAGE_SIZE: usize = 8192;
+
+/// Index of a page in the data file.
+pub type PageId = u32;
+
+/// Page 0 is the file header, so no table or index ever points at it.
+pub const HEADER_PAGE: PageId = 0;
+
+const LSN: usize = 0;
+const KIND: usize = 8;
+/// Bytes every page starts with.
+pub const PAGE_HEADER_SIZE: usize = 16;
+
+const SLOT_COUNT: usize = PAGE_HEADER_SIZE;
+const FREE_START: usize = PAGE_HEADER_SIZE + 2;
+const FREE_END: usize = PAGE_HEADER_SIZE + 4;
+const HEAP_HEADER_SIZE: usize = PAGE_HEADER_SIZE + 8;
+pub const SLOT_SIZE: usize = 4;
+
+/// The largest record a heap page holds, alone on the page.
+pub const MAX_RECORD_SIZE: usize = PAGE_SIZE - HEAP_HEADER_SIZE - SLOT_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageKind {
+    Free = 0,
+    Header = 1,
+    Directory = 2,
+    Heap = 3,
+    BTreeLeaf = 4,
+    BTreeInternal = 5,
+    HashDirectory = 6,
+    HashBucket = 7,
+    Sequence = 8,
+    CommitLog = 9,
+}
+
+/// A record's address: its page and its slot on the page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RecordId {
+    pub page: PageId,
+    pub slot: u16,
+}
+
+pub fn page_lsn(page: &[u8]) -> u64 {
+    read_u64(page, LSN)
+}
+
+pub fn set_page_lsn(page: &mut [u8], lsn: u64) {
+    write_u64(page, LSN, lsn);
+}
+
+pub fn page_kind(page: &[u8]) -> io::Result<PageKind> {
+    match page[KIND] {
+        0 => Ok(PageKind::Free),
+        1 => Ok(PageKind::Header),
+        2 => Ok(PageKind::Directory),
+        3 => Ok(PageKind::Heap),
+        4 => Ok(PageKind::BTreeLeaf),
+        5 => Ok(PageKind::BTreeInternal),
+        6 => Ok(PageKind::HashDirectory),
+        7 => Ok(PageKind::HashBucket),
+        8 => Ok(PageKind::Sequence),
+        9 => Ok(PageKind::CommitLog),
+        kind => Err(corrupt(&format!("unknown page kind {}", kind))),
+    }
+}
+
+/// Clears the page and stamps its kind, keeping the LSN.
+pub fn format_page(page: &mut [u8], kind: PageKind) {
+    page[KIND..].fill(0);
+    page[KIND] = kind as u8;
+}
+
+/// A slotted heap page over a page buffer.
+pub struct HeapPage<T> {
+    data: T,
+}
+
+impl<T: AsRef<[u8]>> HeapPage<T> {
+    pub fn new(data: T) -> Self {
+        HeapPage { data }
+    }
+
+    pub fn slot_count(&self) -> u16 {
+        read_u16(self.data.as_ref(), SLOT_COUNT)
+    }
+
+    /// The record in `slot`, or `None` if the slot is empty or does not
+    /// exist.
+    pub fn get(&self, slot: u16) -> Option<&[u8]> {
+        let (offset, length) = self.slot(slot)?;
+        if offset == 0 {
+            return None;
+        }
+        Some(&self.data.as_ref()[offset as usize..offset as usize + length as usize])
+    }
+
+    /// The records of the page in slot order.
+    pub fn records(&self) -> impl Iterator<Item = (u16, &[u8])> {
+        (0..self.slot_count()).filter_map(move |slot| self.get(slot).map(|record| (slot, record)))
+    }
+
+    /// Bytes an insert can use once the page is compacted, counting the
+    /// slot it may need.
+    pub fn free_space(&self) -> usize {
+        let live: usize = self.records().map(|(_, record)| record.len()).sum();
+        let used = HEAP_HEADER_SIZE + self.slot_count() as usize * SLOT_SIZE + live;
+        (PAGE_SIZE - used).saturating_sub(SLOT_SIZE)
+    }
+
+    fn slot(&self, slot: u16) -> Option<(u16, u16)> {
+        if slot >= self.slot_count() {
+            return None;
+        }
+        let position = HEAP_HEADER_SIZE + slot as usize * SLOT_SIZE;
+        let data = self.data.as_ref();
+        Some((read_u16(data, position), read_u16(data, position + 2)))
+    }
+
+    fn free_start(&self) -> usize {
+        read_u16(self.data.as_ref(), FREE_START) as usize
+    }
+
+    fn free_end(&self) -> usize {
+        read_u16(self.data.as_ref(), FREE_END) as usize
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> HeapPage<T> {
+    /// Formats the buffer as an empty heap page.
+    pub fn initialize(mut data: T) -> Self {
+        let page = data.as_mut();
+        format_page(page, PageKind::Heap);
+        write_u16(page, FREE_START, HEAP_HEADER_SIZE as u16);
+        write_u16(page, FREE_END, PAGE_SIZE as u16);
+        HeapPage { data }
+    }
+
+    /// Stores `record`, compacting the page first if its free space is
+    /// fragmented. Returns `None` if the record does not fit.
+    pub fn insert(&mut self, record: &[u8]) -> Option<u16> {
+        let empty = (0..self.slot_count()).find(|&slot| self.get(slot).is_none());
+        let needed = record.len() + if empty.is_some() { 0 } else { SLOT_SIZE };
+        if self.free_end() - self.free_start() < needed {
+            if self.free_space() + SLOT_SIZE < needed {
+                return None;
+            }
+            self.compact();
+        }
+        let slot = match empty {
+            Some(slot) => slot,
+            None => {
+                let slot = self.slot_count();
+                let free_start = self.free_start() + SLOT_SIZE;
+                let data = self.data.as_mut();
+                write_u16(data, SLOT_COUNT, slot + 1);
+                write_u16(data, FREE_START, free_start as u16);
+                slot
+            }
+        };
+        let offset = self.free_end() - record.len();
+        self.place(slot, offset, record);
+        Some(slot)
+    }
+
+    /// Empties `slot`, returning whether it held a record.
+    pub fn delete(&mut self, slot: u16) -> bool {
+        if self.get(slot).is_none() {
+            return false;
+        }
+        self.set_slot(slot, 0, 0);
+        true
+    }
+
+    /// Replaces the record in `slot`, keeping its id. Fails, leaving the
+    /// page as it was, if the slot is empty or the new record does not fit.
+    pub fn update(&mut self, slot: u16, record: &[u8]) -> bool {
+        let old = match self.get(slot) {
+            Some(old) => old.len(),
+            None => return false,
+        };
+        if record.len() <= old {
+            let (offset, _) = self.slot(slot).unwrap_or_default();
+            self.place(slot, offset as usize, record);
+            return true;
+        }
+        if self.free_space() + SLOT_SIZE + old < record.len() {
+            return false;
+        }
+        self.set_slot(slot, 0, 0);
+        if self.free_end() - self.free_start() < record.len() {
+            self.compact();
+        }
+        let offset = self.free_end() - record.len();
+        self.place(slot, offset, record);
+        true
+    }
+
+    /// Moves the records to the end of the page so that the free space is
+    /// one contiguous run. Slots keep their numbers.
+    pub fn compact(&mut self) {
+        let records: Vec<(u16, Vec<u8>)> = self
+            .records()
+            .map(|(slot, record)| (slot, record.to_vec()))
+            .collect();
+        write_u16(self.data.as_mut(), FREE_END, PAGE_SIZE as u16);
+        for (slot, record) in records {
+            let offset = self.free_end() - record.len();
+            self.place(slot, offset, &record);
+        }
+    }
+
+    /// Drops the empty slots after the last record, giving their room
+    /// back to records.
+    pub fn truncate_slots(&mut self) {
+        let mut count = self.slot_count();
+        while count > 0 && self.get(count - 1).is_none() {
+            count -= 1;
+        }
+        let free_start = HEAP_HEADER_SIZE + count as usize * SLOT_SIZE;
+        let data = self.data.as_mut();
+        write_u16(data, SLOT_COUNT, count);
+        write_u16(data, FREE_START, free_start as u16);
+    }
+
+    /// Writes `record` at `offset` and points `slot` at it, moving the end
+    /// of the free space down if the record lies below it.
+    fn place(&mut self, slot: u16, offset: usize, record: &[u8]) {
+        let free_end = self.free_end().min(offset);
+        let data = self.data.as_mut();
+        data[offset..offset + record.len()].copy_from_slice(record);
+        write_u16(data, FREE_END, free_end as u16);
+        self.set_slot(slot, offset as u16, record.len() as u16);
+    }
+
+    fn set_slot(&mut self, slot: u16, offset: u16, length: u16) {
+        let position = HEAP_HEADER_SIZE + slot as usize * SLOT_SIZE;
+        let data = self.data.as_mut();
+        write_u16(data, position, offset);
+        write_u16(data, position + 2, length);
+    }
+}
+
+pub fn read_u16(data: &[u8], position: usize) -> u16 {
+    u16::from_le_bytes([data[position], data[position + 1]])
+}
+
+pub fn write_u16(data: &mut [u8], position: usize, value: u16) {
+    data[position..position + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+pub fn read_u32(data: &[u8], position: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[position..position + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+pub fn write_u32(data: &mut [u8], position: usize, value: u32) {
+    data[position..position + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+pub fn read_u64(data: &[u8], position: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[position..position + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+pub fn write_u64(data: &mut [u8], position: usize, value: u64) {
+    data[position..position + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+pub fn corrupt(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("corrupt page: {}", message),
+    )
+}
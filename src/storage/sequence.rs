@@ -0,0 +1,64 @@
+//! The page that holds the state of a sequence.
+//!
+//! A sequence is one page recording the last value handed out and whether
+//! it has been handed out yet, as `setval(..., false)` can say it has not.
+//! Values are handed out from memory in blocks of the sequence's cache
+//! size: the page is written, and the log synced, with the last value of
+//! a block before any value of the block is used. After a crash the
+//! sequence goes on past the block, so values of it may be skipped but
+//! none repeat.
+
+use std::io;
+
+use crate::storage::allocator::allocate_page;
+use crate::storage::buffer::BufferPool;
+use crate::storage::page::{corrupt, format_page, page_kind, PageId, PageKind, PAGE_HEADER_SIZE};
+
+const LAST_VALUE: usize = PAGE_HEADER_SIZE;
+const IS_CALLED: usize = PAGE_HEADER_SIZE + 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceState {
+    pub last_value: i128,
+    /// Whether `last_value` has been handed out, so that the next value
+    /// is the one after it.
+    pub is_called: bool,
+}
+
+/// Creates the page of a sequence that has not handed out `start` yet.
+pub fn create_sequence(pool: &mut BufferPool, start: i128) -> io::Result<PageId> {
+    let page = allocate_page(pool)?;
+    let state = SequenceState {
+        last_value: start,
+        is_called: false,
+    };
+    pool.write(page, |data| format_sequence(data, state))?;
+    Ok(page)
+}
+
+pub fn read_sequence(pool: &mut BufferPool, page: PageId) -> io::Result<SequenceState> {
+    pool.read(page, |data| {
+        if page_kind(data)? != PageKind::Sequence {
+            return Err(corrupt("sequence points at a page that is not a sequence"));
+        }
+        let mut last_value = [0u8; 16];
+        last_value.copy_from_slice(&data[LAST_VALUE..LAST_VALUE + 16]);
+        Ok(SequenceState {
+            last_value: i128::from_le_bytes(last_value),
+            is_called: data[IS_CALLED] != 0,
+        })
+    })?
+}
+
+/// Records the state of a sequence outside any transaction, since values
+/// handed out stay handed out when the transaction that took them rolls
+/// back.
+pub fn write_sequence(pool: &mut BufferPool, page: PageId, state: SequenceState) -> io::Result<()> {
+    pool.write_redo_only(page, |data| format_sequence(data, state))
+}
+
+fn format_sequence(data: &mut [u8], state: SequenceState) {
+    format_page(data, PageKind::Sequence);
+    data[LAST_VALUE..LAST_VALUE + 16].copy_from_slice(&state.last_value.to_le_bytes());
+    data[IS_CALLED] = state.is_called as u8;
+}
@@ -0,0 +1,257 @@
+//! The binary layout of a table row.
+//!
+//! ```text
+//! | null bitmap | fixed-width fields | variable-length fields |
+//! ```
+//!
+//! Only columns that may hold NULL get a bit in the bitmap, in column
+//! order; a row of a table whose columns are all NOT NULL has no bitmap.
+//! Fixed-width fields come next in column order, each in the width of its
+//! type, little-endian, so every one of them is at the same offset in
+//! every row. A NULL fixed-width field is zeroed but keeps its place. The
+//! `TEXT` fields follow as a 4-byte length and their UTF-8 bytes.
+//!
+//! | type | bytes | values |
+//! |------|-------|--------|
+//! | `BOOL`, `BYTE` | 1 | `BYTE` -128..=127, `U_BYTE` 0..=255 |
+//! | `SHORT` | 2 | 16-bit |
+//! | `INT`, `FLOAT` | 4 | 32-bit integer, single precision |
+//! | `LONG`, `DOUBLE`, `DATETIME` | 8 | 64-bit integer, double precision, microseconds |
+//! | `BIGINT`, `UUID` | 16 | 128-bit integer, 128-bit UUID |
+//!
+//! `LONG` is the 64-bit integer and `BIGINT` the 128-bit one. Values are
+//! 128-bit signed integers while being computed, so `U_BIGINT` holds
+//! 0..=2^127-1, the non-negative half of `BIGINT`, rather than the full
+//! unsigned range; larger values are refused like any out of range.
+
+use std::io;
+
+use crate::compiler::parser::DataType;
+use crate::execution::error::ExecutionError;
+use crate::storage::catalog::ColumnSchema;
+use crate::types::cast::{format_uuid, integer_range, parse_uuid};
+use crate::types::value::Value;
+
+/// Bytes a value of `data_type` takes, or `None` for variable-length types.
+pub fn fixed_width(data_type: &DataType) -> Option<usize> {
+    match data_type {
+        DataType::BOOL | DataType::BYTE { .. } => Some(1),
+        DataType::SHORT { .. } => Some(2),
+        DataType::INT { .. } | DataType::FLOAT { .. } => Some(4),
+        DataType::LONG { .. } | DataType::DOUBLE { .. } | DataType::DATETIME => Some(8),
+        DataType::BIGINT { .. } | DataType::UUID { .. } => Some(16),
+        DataType::TEXT { .. } => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    data_type: DataType,
+    /// The field's bit in the null bitmap, if the column may be NULL.
+    null_bit: Option<usize>,
+    /// Offset of a fixed-width field from the start of the row.
+    offset: Option<usize>,
+}
+
+/// Encodes and decodes rows of one table.
+#[derive(Debug, Clone)]
+pub struct RowLayout {
+    fields: Vec<Field>,
+    bitmap_size: usize,
+    /// Where the variable-length fields start.
+    fixed_end: usize,
+}
+
+impl RowLayout {
+    pub fn new(columns: &[ColumnSchema]) -> Self {
+        let nullable = columns
+            .iter()
+            .filter(|column| !column.not_null && !column.is_primary)
+            .count();
+        let bitmap_size = nullable.div_ceil(8);
+        let mut null_bits = 0;
+        let mut offset = bitmap_size;
+        let fields = columns
+            .iter()
+            .map(|column| {
+                let null_bit = (!column.not_null && !column.is_primary).then(|| {
+                    null_bits += 1;
+                    null_bits - 1
+                });
+                let field_offset = fixed_width(&column.data_type).map(|width| {
+                    offset += width;
+                    offset - width
+                });
+                Field {
+                    name: column.name.clone(),
+                    data_type: column.data_type.clone(),
+                    null_bit,
+                    offset: field_offset,
+                }
+            })
+            .collect();
+        RowLayout {
+            fields,
+            bitmap_size,
+            fixed_end: offset,
+        }
+    }
+
+    /// Encodes `row`, one value per column. Each value must already have
+    /// its column's representation and fit its type.
+    pub fn encode(&self, row: &[Value]) -> Result<Vec<u8>, ExecutionError> {
+        if row.len() != self.fields.len() {
+            return Err(ExecutionError::InvalidQuery(format!(
+                "row has {} values for {} columns",
+                row.len(),
+                self.fields.len()
+            )));
+        }
+        let mut record = vec![0u8; self.fixed_end];
+        for (field, value) in self.fields.iter().zip(row) {
+            if value.is_null() {
+                let bit = field.null_bit.ok_or_else(|| {
+                    ExecutionError::InvalidQuery(format!(
+                        "column \"{}\" cannot hold NULL",
+                        field.name
+                    ))
+                })?;
+                record[bit / 8] |= 1 << (bit % 8);
+                if field.offset.is_none() {
+                    record.extend_from_slice(&0u32.to_le_bytes());
+                }
+                continue;
+            }
+            match field.offset {
+                Some(offset) => encode_fixed(field, value, &mut record[offset..])?,
+                None => match value {
+                    Value::Text(text) => {
+                        record.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                        record.extend_from_slice(text.as_bytes());
+                    }
+                    _ => return Err(mismatch(field, value)),
+                },
+            }
+        }
+        Ok(record)
+    }
+
+    pub fn decode(&self, record: &[u8]) -> io::Result<Vec<Value>> {
+        if record.len() < self.fixed_end {
+            return Err(truncated());
+        }
+        let mut rest = &record[self.fixed_end..];
+        let mut row = Vec::with_capacity(self.fields.len());
+        for field in &self.fields {
+            let null = field
+                .null_bit
+                .is_some_and(|bit| record[bit / 8] & (1 << (bit % 8)) != 0);
+            let value = match field.offset {
+                Some(offset) => {
+                    let width = fixed_width(&field.data_type).unwrap_or_default();
+                    decode_fixed(&field.data_type, &record[offset..offset + width])
+                }
+                None => {
+                    if rest.len() < 4 {
+                        return Err(truncated());
+                    }
+                    let length = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+                    let bytes = rest.get(4..4 + length).ok_or_else(truncated)?;
+                    rest = &rest[4 + length..];
+                    Value::Text(
+                        String::from_utf8(bytes.to_vec())
+                            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?,
+                    )
+                }
+            };
+            row.push(if null { Value::Null } else { value });
+        }
+        Ok(row)
+    }
+
+    /// Bytes of the null bitmap.
+    pub fn bitmap_size(&self) -> usize {
+        self.bitmap_size
+    }
+}
+
+fn encode_fixed(field: &Field, value: &Value, target: &mut [u8]) -> Result<(), ExecutionError> {
+    let data_type = &field.data_type;
+    if let Some((min, max)) = integer_range(data_type) {
+        let integer = match value {
+            Value::Integer(integer) => *integer,
+            _ => return Err(mismatch(field, value)),
+        };
+        if integer < min || integer > max {
+            return Err(ExecutionError::Overflow(format!(
+                "{} is out of range for {} column \"{}\"",
+                integer, data_type, field.name
+            )));
+        }
+        let width = fixed_width(data_type).unwrap_or_default();
+        // Two's complement truncation keeps the value, which is in range.
+        target[..width].copy_from_slice(&integer.to_le_bytes()[..width]);
+        return Ok(());
+    }
+    match (data_type, value) {
+        (DataType::BOOL, Value::Bool(value)) => target[0] = *value as u8,
+        (DataType::FLOAT { .. }, Value::Float(float)) => {
+            target[..4].copy_from_slice(&(*float as f32).to_le_bytes())
+        }
+        (DataType::DOUBLE { .. }, Value::Float(float)) => {
+            target[..8].copy_from_slice(&float.to_le_bytes())
+        }
+        (DataType::DATETIME, Value::DateTime(micros)) => {
+            target[..8].copy_from_slice(&micros.to_le_bytes())
+        }
+        (DataType::UUID { .. }, Value::Text(text)) => {
+            let uuid = parse_uuid(text).ok_or_else(|| {
+                ExecutionError::InvalidLiteral(format!("'{}' is not a valid UUID", text))
+            })?;
+            target[..16].copy_from_slice(&uuid.to_le_bytes())
+        }
+        _ => return Err(mismatch(field, value)),
+    }
+    Ok(())
+}
+
+fn decode_fixed(data_type: &DataType, bytes: &[u8]) -> Value {
+    if let Some((min, _)) = integer_range(data_type) {
+        // Sign-extends signed types; unsigned ones start at zero.
+        let fill = if min < 0 && bytes[bytes.len() - 1] & 0x80 != 0 {
+            0xff
+        } else {
+            0
+        };
+        let mut wide = [fill; 16];
+        wide[..bytes.len()].copy_from_slice(bytes);
+        return Value::Integer(i128::from_le_bytes(wide));
+    }
+    let mut buffer = [0u8; 16];
+    buffer[..bytes.len()].copy_from_slice(bytes);
+    let mut eight = [0u8; 8];
+    eight.copy_from_slice(&buffer[..8]);
+    match data_type {
+        DataType::BOOL => Value::Bool(bytes[0] != 0),
+        DataType::FLOAT { .. } => {
+            Value::Float(f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64)
+        }
+        DataType::DOUBLE { .. } => Value::Float(f64::from_le_bytes(eight)),
+        DataType::DATETIME => Value::DateTime(i64::from_le_bytes(eight)),
+        _ => Value::Text(format_uuid(u128::from_le_bytes(buffer))),
+    }
+}
+
+fn mismatch(field: &Field, value: &Value) -> ExecutionError {
+    ExecutionError::TypeMismatch(format!(
+        "{} value for {} column \"{}\"",
+        value.type_name(),
+        field.data_type,
+        field.name
+    ))
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "truncated row")
+}
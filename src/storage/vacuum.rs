@@ -0,0 +1,100 @@
+//! Estimates of the live and dead versions of each table, for autovacuum
+//! to tell which tables are worth vacuuming.
+//!
+//! The versions a transaction writes are counted by the id they carry, so
+//! that when it ends those of savepoints rolled back count as dead with
+//! the rest of an aborted transaction, and the others as live. A VACUUM
+//! of a table counts its versions over. The counts are kept in memory
+//! only: after a restart, a table counts what changes until its next
+//! VACUUM.
+
+use std::collections::HashMap;
+
+use crate::storage::mvcc::Xid;
+use crate::storage::page::PageId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TableStatistics {
+    pub live: u64,
+    /// Versions no transaction sees once those running end, which VACUUM
+    /// removes then.
+    pub dead: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Changes {
+    inserted: u64,
+    deleted: u64,
+}
+
+/// The versions of each transaction by the id they carry and their heap.
+type Written = HashMap<Xid, HashMap<(Xid, PageId), Changes>>;
+
+#[derive(Debug, Default)]
+pub struct Activity {
+    /// What the running statement wrote, which counts only once its log
+    /// transaction does not roll back.
+    statement: Written,
+    transactions: Written,
+    tables: HashMap<PageId, TableStatistics>,
+}
+
+impl Activity {
+    /// Counts a version `xid` inserted into `heap` with id `id`.
+    pub fn inserted(&mut self, xid: Xid, id: Xid, heap: PageId) {
+        self.changes(xid, id, heap).inserted += 1;
+    }
+
+    /// Counts a version `xid` deleted from `heap` with id `id`.
+    pub fn deleted(&mut self, xid: Xid, id: Xid, heap: PageId) {
+        self.changes(xid, id, heap).deleted += 1;
+    }
+
+    /// Keeps what the statement wrote.
+    pub fn end_statement(&mut self) {
+        for (xid, written) in self.statement.drain() {
+            let changes = self.transactions.entry(xid).or_default();
+            for (key, statement) in written {
+                let total = changes.entry(key).or_default();
+                total.inserted += statement.inserted;
+                total.deleted += statement.deleted;
+            }
+        }
+    }
+
+    /// Forgets what the statement wrote, which was undone.
+    pub fn rollback_statement(&mut self) {
+        self.statement.clear();
+    }
+
+    /// Counts what `xid` wrote as it ended: the versions with the ids in
+    /// `committed` as committed, the others as rolled back.
+    pub fn end(&mut self, xid: Xid, committed: &[Xid]) {
+        for ((id, heap), changes) in self.transactions.remove(&xid).unwrap_or_default() {
+            let table = self.tables.entry(heap).or_default();
+            if committed.contains(&id) {
+                table.live = (table.live + changes.inserted).saturating_sub(changes.deleted);
+                table.dead += changes.deleted;
+            } else {
+                table.dead += changes.inserted;
+            }
+        }
+    }
+
+    pub fn statistics(&self, heap: PageId) -> TableStatistics {
+        self.tables.get(&heap).copied().unwrap_or_default()
+    }
+
+    /// Replaces the estimates of `heap` with what a VACUUM counted.
+    pub fn set_statistics(&mut self, heap: PageId, statistics: TableStatistics) {
+        self.tables.insert(heap, statistics);
+    }
+
+    fn changes(&mut self, xid: Xid, id: Xid, heap: PageId) -> &mut Changes {
+        self.statement
+            .entry(xid)
+            .or_default()
+            .entry((id, heap))
+            .or_default()
+    }
+}
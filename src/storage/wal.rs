@@ -0,0 +1,751 @@
+//! The write-ahead log.
+//!
+//! Every change to a page is appended to the log before the page may
+//! reach the data file, as the bytes it changed before and after. The
+//! first change to a page after a checkpoint logs the whole page, so a
+//! page torn by a crash in the middle of its write is rebuilt from the
+//! log. Each page carries the LSN, the log position, of the last record
+//! applied to it.
+//!
+//! A record is framed by its length and a CRC-32 of its body, so a
+//! record cut short by a crash ends the log instead of corrupting it:
+//!
+//! ```text
+//! | length | crc | kind | transaction | previous lsn | ... |
+//! ```
+//!
+//! An update holds the page, offset and length of the change and its
+//! bytes before and after; a compensation record, logged when an update
+//! is undone, the bytes put back and the next record of the transaction
+//! left to undo. Commit and abort records end a transaction. The records
+//! of a transaction are chained backwards through their previous LSNs.
+//!
+//! Recovery follows ARIES. Redo repeats every logged change its page
+//! lacks, in log order; undo then rolls back the transactions that
+//! neither committed nor aborted, logging compensation records as it
+//! goes so that a crash during recovery never undoes a change twice.
+//!
+//! Checkpoints are taken between transactions: every changed page is
+//! written and synced, and the log starts over in a new, empty file whose
+//! header says at which LSN it starts, so LSNs keep growing.
+//!
+//! Under group commit a commit hands its records to the operating system
+//! and returns its LSN, and the session waits on the [`LogSync`] once it
+//! has let go of the engine, so that other sessions commit meanwhile. The
+//! first to wait leads: it waits for the group to fill or the delay to
+//! pass, then syncs the log once for every commit written so far.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::storage::buffer::BufferPool;
+use crate::storage::page::{read_u16, read_u32, read_u64, PageId, PAGE_SIZE};
+
+/// Position of a record in the log, counted in bytes over every log file
+/// the database has had. Zero is no record.
+pub type Lsn = u64;
+
+pub type TransactionId = u64;
+
+/// The owner of changes made outside any transaction, which are redone
+/// after a crash and never undone.
+pub const NO_TRANSACTION: TransactionId = 0;
+
+const MAGIC: &[u8; 8] = b"OMNIWAL1";
+/// The magic, the LSN of the first record and the next transaction id.
+const HEADER_SIZE: usize = 24;
+/// The length and CRC before each record.
+const FRAME_SIZE: usize = 8;
+/// Bytes of records kept in memory before they are written to the file
+/// without waiting for a commit.
+const BUFFER_LIMIT: usize = 1 << 20;
+
+const UPDATE: u8 = 1;
+const COMPENSATION: u8 = 2;
+const COMMIT: u8 = 3;
+const ABORT: u8 = 4;
+
+/// When a commit waits for the log to reach the disk. The records of a
+/// commit are always handed to the operating system before it returns,
+/// so only a crash of the machine, not of the process, can lose them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// Every commit syncs the log.
+    EveryCommit,
+    /// Commits are synced as a group, once `commits` of them wait or the
+    /// first of them has waited `delay`.
+    Group { commits: usize, delay: Duration },
+    /// Only checkpoints and pages written back sync the log.
+    Never,
+}
+
+/// How far the log is written and synced, shared with the sessions that
+/// wait for their commits outside the engine.
+pub struct LogSync {
+    state: Mutex<SyncState>,
+    /// Signalled when a session starts waiting and when the log is synced.
+    changed: Condvar,
+    policy: SyncPolicy,
+}
+
+struct SyncState {
+    file: Option<Arc<File>>,
+    /// Everything before this LSN is handed to the operating system.
+    written: Lsn,
+    /// Everything before this LSN is synced.
+    synced: Lsn,
+    /// Whether a session is gathering a group or syncing it.
+    leading: bool,
+    waiting: usize,
+}
+
+impl LogSync {
+    fn new(file: Option<Arc<File>>, end: Lsn, policy: SyncPolicy) -> Self {
+        LogSync {
+            state: Mutex::new(SyncState {
+                file,
+                written: end,
+                synced: end,
+                leading: false,
+                waiting: 0,
+            }),
+            changed: Condvar::new(),
+            policy,
+        }
+    }
+
+    /// Blocks until the log is synced up to `lsn`, the end of a commit.
+    pub fn wait(&self, lsn: Lsn) -> io::Result<()> {
+        let (commits, delay) = match self.policy {
+            SyncPolicy::Group { commits, delay } => (commits, delay),
+            _ => (1, Duration::ZERO),
+        };
+        let mut state = self.lock();
+        state.waiting += 1;
+        self.changed.notify_all();
+        let result = loop {
+            if state.synced >= lsn {
+                break Ok(());
+            }
+            if state.leading {
+                state = self
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+                continue;
+            }
+            state.leading = true;
+            let deadline = Instant::now() + delay;
+            while state.waiting < commits && state.synced < lsn {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                state = self
+                    .changed
+                    .wait_timeout(state, deadline - now)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0;
+            }
+            let target = state.written;
+            let file = state.file.clone();
+            drop(state);
+            let synced = file.map_or(Ok(()), |file| file.sync_data());
+            state = self.lock();
+            state.leading = false;
+            if synced.is_ok() {
+                state.synced = state.synced.max(target);
+            }
+            self.changed.notify_all();
+            if let Err(error) = synced {
+                break Err(error);
+            }
+        };
+        state.waiting -= 1;
+        result
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SyncState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogRecord {
+    Update {
+        page: PageId,
+        offset: u16,
+        before: Vec<u8>,
+        after: Vec<u8>,
+    },
+    Compensation {
+        page: PageId,
+        offset: u16,
+        after: Vec<u8>,
+        undo_next: Lsn,
+    },
+    Commit,
+    Abort,
+}
+
+/// A record read back from the log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub lsn: Lsn,
+    pub transaction: TransactionId,
+    /// The record of the same transaction before this one.
+    pub previous: Lsn,
+    pub record: LogRecord,
+}
+
+/// An update of the running transaction, kept to roll it back without
+/// reading the log.
+#[derive(Debug, Clone)]
+struct Undo {
+    lsn: Lsn,
+    page: PageId,
+    offset: u16,
+    before: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Transaction {
+    id: TransactionId,
+    /// Its last record, zero while it has written nothing.
+    last: Lsn,
+    undo: Vec<Undo>,
+}
+
+/// The log of one data file, appended through a buffer.
+pub struct Wal {
+    /// `None` for a database kept in memory, whose records are only kept
+    /// as long as rollback needs them.
+    path: Option<PathBuf>,
+    file: Option<Arc<File>>,
+    /// The LSN of the first record of the file.
+    start: Lsn,
+    /// The LSN the next record gets.
+    end: Lsn,
+    /// Records not yet written to the file, which end at `end`.
+    buffer: Vec<u8>,
+    sync: Arc<LogSync>,
+    next_transaction: TransactionId,
+    current: Option<Transaction>,
+    /// Pages logged whole since the last checkpoint.
+    imaged: HashSet<PageId>,
+    policy: SyncPolicy,
+}
+
+impl Wal {
+    /// Opens the log at `path`, creating it if there is none, and returns
+    /// it with the records it holds. A record cut short by a crash, and
+    /// anything after it, is cut off the file.
+    pub fn open<P: AsRef<Path>>(path: P, policy: SyncPolicy) -> io::Result<(Self, Vec<LogEntry>)> {
+        let path = path.as_ref().to_path_buf();
+        if !path.exists() {
+            write_log_file(&path, 1, 1)?;
+        }
+        let mut bytes = Vec::new();
+        File::open(&path)?.read_to_end(&mut bytes)?;
+        if bytes.len() < HEADER_SIZE || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an OmnissiahDB log file",
+            ));
+        }
+        let start = read_u64(&bytes, 8);
+        let mut next_transaction = read_u64(&bytes, 16);
+        let mut entries = Vec::new();
+        let mut position = HEADER_SIZE;
+        while let Some((entry, length)) = read_entry(&bytes[position..], lsn_at(start, position)) {
+            next_transaction = next_transaction.max(entry.transaction + 1);
+            entries.push(entry);
+            position += length;
+        }
+        let file = OpenOptions::new().append(true).open(&path)?;
+        if position < bytes.len() {
+            file.set_len(position as u64)?;
+            file.sync_data()?;
+        }
+        let end = lsn_at(start, position);
+        let file = Arc::new(file);
+        let wal = Wal {
+            file: Some(Arc::clone(&file)),
+            sync: Arc::new(LogSync::new(Some(file), end, policy)),
+            ..Wal::new(Some(path), start, end, next_transaction, policy)
+        };
+        Ok((wal, entries))
+    }
+
+    /// A log that is never written anywhere.
+    pub fn memory() -> Self {
+        Wal::new(None, 1, 1, 1, SyncPolicy::Never)
+    }
+
+    fn new(
+        path: Option<PathBuf>,
+        start: Lsn,
+        end: Lsn,
+        next_transaction: TransactionId,
+        policy: SyncPolicy,
+    ) -> Self {
+        Wal {
+            path,
+            file: None,
+            start,
+            end,
+            buffer: Vec::new(),
+            sync: Arc::new(LogSync::new(None, end, policy)),
+            next_transaction,
+            current: None,
+            imaged: HashSet::new(),
+            policy,
+        }
+    }
+
+    /// What sessions wait on for their commits to be synced.
+    pub fn sync(&self) -> Arc<LogSync> {
+        Arc::clone(&self.sync)
+    }
+
+    /// Starts a transaction for the changes that follow.
+    pub fn begin(&mut self) -> TransactionId {
+        debug_assert!(self.current.is_none(), "a transaction is already running");
+        let id = self.next_transaction;
+        self.next_transaction += 1;
+        self.current = Some(Transaction {
+            id,
+            last: 0,
+            undo: Vec::new(),
+        });
+        id
+    }
+
+    /// The running transaction, or [`NO_TRANSACTION`].
+    pub fn transaction(&self) -> TransactionId {
+        self.current
+            .as_ref()
+            .map_or(NO_TRANSACTION, |transaction| transaction.id)
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Bytes of log since the last checkpoint.
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Logs the change of `page` from `before` to `after` for
+    /// `transaction`, returning the LSN of the record, or `None` if
+    /// nothing but the LSN differs.
+    pub fn log_update(
+        &mut self,
+        transaction: TransactionId,
+        page: PageId,
+        before: &[u8],
+        after: &[u8],
+    ) -> Option<Lsn> {
+        let (first, last) = changed_range(before, after)?;
+        let (first, last) = if self.imaged.insert(page) {
+            (0, PAGE_SIZE)
+        } else {
+            (first, last)
+        };
+        let record = LogRecord::Update {
+            page,
+            offset: first as u16,
+            before: before[first..last].to_vec(),
+            after: after[first..last].to_vec(),
+        };
+        let previous = match self.current.as_ref() {
+            Some(current) if current.id == transaction => current.last,
+            _ => 0,
+        };
+        let lsn = self.append(transaction, previous, &record);
+        if let Some(current) = self
+            .current
+            .as_mut()
+            .filter(|current| current.id == transaction)
+        {
+            current.last = lsn;
+            current.undo.push(Undo {
+                lsn,
+                page,
+                offset: first as u16,
+                before: before[first..last].to_vec(),
+            });
+        }
+        Some(lsn)
+    }
+
+    /// Ends the running transaction, making it durable as the sync policy
+    /// says. A transaction that wrote nothing leaves no record. Under group
+    /// commit, returns the LSN to wait for with [`LogSync::wait`] before
+    /// the commit is durable.
+    pub fn commit(&mut self) -> io::Result<Option<Lsn>> {
+        let transaction = match self.current.take() {
+            Some(transaction) if transaction.last != 0 => transaction,
+            _ => return Ok(None),
+        };
+        self.append(transaction.id, transaction.last, &LogRecord::Commit);
+        self.write_buffer()?;
+        match self.policy {
+            SyncPolicy::EveryCommit => self.force()?,
+            SyncPolicy::Group { .. } => return Ok(Some(self.end)),
+            SyncPolicy::Never => {}
+        }
+        Ok(None)
+    }
+
+    /// Ends the running transaction like [`Wal::commit`], but leaves
+    /// making it durable to a later commit, whose sync takes the records
+    /// before its own along.
+    pub fn end(&mut self) {
+        if let Some(transaction) = self.current.take().filter(|current| current.last != 0) {
+            self.append(transaction.id, transaction.last, &LogRecord::Commit);
+        }
+    }
+
+    /// Appends `record`, returning its LSN.
+    pub fn append(&mut self, transaction: TransactionId, previous: Lsn, record: &LogRecord) -> Lsn {
+        let lsn = self.end;
+        let body = encode(transaction, previous, record);
+        self.end += (FRAME_SIZE + body.len()) as u64;
+        if self.file.is_some() {
+            self.buffer
+                .extend_from_slice(&(body.len() as u32).to_le_bytes());
+            self.buffer.extend_from_slice(&crc32(&body).to_le_bytes());
+            self.buffer.extend_from_slice(&body);
+            if self.buffer.len() >= BUFFER_LIMIT {
+                // Nothing to do with the error here: the next write or
+                // sync meets it again.
+                let _ = self.write_buffer();
+            }
+        }
+        lsn
+    }
+
+    /// Writes and syncs the whole log if the record at `lsn` is not synced
+    /// yet, as it must be before a page that carries it is written back.
+    pub fn force_to(&mut self, lsn: Lsn) -> io::Result<()> {
+        if lsn >= self.sync.lock().synced {
+            self.force()?;
+        }
+        Ok(())
+    }
+
+    /// Writes and syncs every record, for the commits waiting as well.
+    pub fn force(&mut self) -> io::Result<()> {
+        self.write_buffer()?;
+        let mut state = self.sync.lock();
+        if state.synced < self.end {
+            if let Some(file) = &self.file {
+                file.sync_data()?;
+            }
+            state.synced = self.end;
+            self.sync.changed.notify_all();
+        }
+        Ok(())
+    }
+
+    /// Starts the log over, empty, once every page it describes is on
+    /// disk. The new file replaces the old one by rename, so a crash
+    /// leaves one or the other.
+    pub fn restart(&mut self) -> io::Result<()> {
+        debug_assert!(self.current.is_none(), "a transaction is running");
+        self.force()?;
+        if let Some(path) = &self.path {
+            let temporary = path.with_extension("tmp");
+            write_log_file(&temporary, self.end, self.next_transaction)?;
+            fs::rename(&temporary, path)?;
+            if let Some(directory) = path.parent() {
+                File::open(directory)?.sync_all()?;
+            }
+            let file = Arc::new(OpenOptions::new().append(true).open(path)?);
+            self.sync.lock().file = Some(Arc::clone(&file));
+            self.file = Some(file);
+        }
+        self.start = self.end;
+        self.imaged.clear();
+        Ok(())
+    }
+
+    /// Takes the updates of the running transaction, newest first, for
+    /// rollback, with the last record it logged.
+    fn take_undo(&mut self) -> Option<(TransactionId, Lsn, Vec<Undo>)> {
+        let current = self.current.as_mut()?;
+        let mut undo = std::mem::take(&mut current.undo);
+        undo.reverse();
+        Some((current.id, current.last, undo))
+    }
+
+    fn set_last(&mut self, lsn: Lsn) {
+        if let Some(current) = self.current.as_mut() {
+            current.last = lsn;
+        }
+    }
+
+    fn write_buffer(&mut self) -> io::Result<()> {
+        if let Some(file) = &self.file {
+            (&**file).write_all(&self.buffer)?;
+        }
+        self.buffer.clear();
+        self.sync.lock().written = self.end;
+        Ok(())
+    }
+}
+
+/// Undoes the running transaction and ends it with an abort record.
+pub fn rollback(pool: &mut BufferPool) -> io::Result<()> {
+    if let Some((id, last, undo)) = pool.log().take_undo() {
+        let last = compensate_all(pool, id, last, undo)?;
+        if last != 0 {
+            pool.log().append(id, last, &LogRecord::Abort);
+        }
+        pool.log().current = None;
+    }
+    Ok(())
+}
+
+/// Logs compensation records putting back the `undo` of transaction `id`,
+/// newest first. Returns the transaction's last record.
+fn compensate_all(
+    pool: &mut BufferPool,
+    id: TransactionId,
+    mut last: Lsn,
+    undo: Vec<Undo>,
+) -> io::Result<Lsn> {
+    let mut remaining = undo.iter().skip(1).map(|undo| undo.lsn);
+    for entry in &undo {
+        let next = remaining.next().unwrap_or(0);
+        last = pool.compensate(id, last, entry.page, entry.offset, &entry.before, next)?;
+        pool.log().set_last(last);
+    }
+    Ok(last)
+}
+
+/// Brings the data file to the state the log describes: repeats every
+/// change, then undoes the transactions that did not end.
+pub fn recover(pool: &mut BufferPool, entries: Vec<LogEntry>) -> io::Result<()> {
+    // Analysis: the transactions without a commit or abort record, with
+    // their last record.
+    let mut losers: HashMap<TransactionId, Lsn> = HashMap::new();
+    for entry in &entries {
+        if entry.transaction == NO_TRANSACTION {
+            continue;
+        }
+        match entry.record {
+            LogRecord::Commit | LogRecord::Abort => {
+                losers.remove(&entry.transaction);
+            }
+            _ => {
+                losers.insert(entry.transaction, entry.lsn);
+            }
+        }
+    }
+    // Redo.
+    for entry in &entries {
+        match &entry.record {
+            LogRecord::Update {
+                page,
+                offset,
+                after,
+                ..
+            }
+            | LogRecord::Compensation {
+                page,
+                offset,
+                after,
+                ..
+            } => pool.redo(*page, *offset, after, entry.lsn)?,
+            LogRecord::Commit | LogRecord::Abort => {}
+        }
+    }
+    // Undo, always taking the latest record left of any loser.
+    let records: HashMap<Lsn, &LogEntry> = entries.iter().map(|entry| (entry.lsn, entry)).collect();
+    let mut next: HashMap<TransactionId, (Lsn, Lsn)> = losers
+        .iter()
+        .map(|(&transaction, &last)| (transaction, (last, last)))
+        .collect();
+    while let Some((transaction, (lsn, last))) = next
+        .iter()
+        .map(|(&transaction, &position)| (transaction, position))
+        .max_by_key(|(_, (lsn, _))| *lsn)
+    {
+        let entry = records
+            .get(&lsn)
+            .ok_or_else(|| corrupt_log("a transaction's records do not chain"))?;
+        let (following, last) = match &entry.record {
+            LogRecord::Update {
+                page,
+                offset,
+                before,
+                ..
+            } => {
+                let lsn =
+                    pool.compensate(transaction, last, *page, *offset, before, entry.previous)?;
+                (entry.previous, lsn)
+            }
+            LogRecord::Compensation { undo_next, .. } => (*undo_next, last),
+            LogRecord::Commit | LogRecord::Abort => (entry.previous, last),
+        };
+        if following == 0 {
+            pool.log().append(transaction, last, &LogRecord::Abort);
+            next.remove(&transaction);
+        } else {
+            next.insert(transaction, (following, last));
+        }
+    }
+    Ok(())
+}
+
+/// The first and one past the last byte that differ, leaving out the
+/// page LSN.
+fn changed_range(before: &[u8], after: &[u8]) -> Option<(usize, usize)> {
+    let differs = |position: &usize| before[*position] != after[*position];
+    let first = (8..after.len()).find(differs)?;
+    let last = (first..after.len()).rev().find(differs)? + 1;
+    Some((first, last))
+}
+
+fn lsn_at(start: Lsn, position: usize) -> Lsn {
+    start + (position - HEADER_SIZE) as u64
+}
+
+fn write_log_file(path: &Path, start: Lsn, next_transaction: TransactionId) -> io::Result<()> {
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&start.to_le_bytes());
+    header.extend_from_slice(&next_transaction.to_le_bytes());
+    let mut file = File::create(path)?;
+    file.write_all(&header)?;
+    file.sync_all()
+}
+
+fn encode(transaction: TransactionId, previous: Lsn, record: &LogRecord) -> Vec<u8> {
+    let mut body = Vec::new();
+    let kind = match record {
+        LogRecord::Update { .. } => UPDATE,
+        LogRecord::Compensation { .. } => COMPENSATION,
+        LogRecord::Commit => COMMIT,
+        LogRecord::Abort => ABORT,
+    };
+    body.push(kind);
+    body.extend_from_slice(&transaction.to_le_bytes());
+    body.extend_from_slice(&previous.to_le_bytes());
+    match record {
+        LogRecord::Update {
+            page,
+            offset,
+            before,
+            after,
+        } => {
+            body.extend_from_slice(&page.to_le_bytes());
+            body.extend_from_slice(&offset.to_le_bytes());
+            body.extend_from_slice(&(after.len() as u16).to_le_bytes());
+            body.extend_from_slice(before);
+            body.extend_from_slice(after);
+        }
+        LogRecord::Compensation {
+            page,
+            offset,
+            after,
+            undo_next,
+        } => {
+            body.extend_from_slice(&page.to_le_bytes());
+            body.extend_from_slice(&offset.to_le_bytes());
+            body.extend_from_slice(&(after.len() as u16).to_le_bytes());
+            body.extend_from_slice(after);
+            body.extend_from_slice(&undo_next.to_le_bytes());
+        }
+        LogRecord::Commit | LogRecord::Abort => {}
+    }
+    body
+}
+
+/// The record at the start of `bytes` and the bytes it takes, or `None`
+/// where the log ends, cleanly or with a record cut short.
+fn read_entry(bytes: &[u8], lsn: Lsn) -> Option<(LogEntry, usize)> {
+    if bytes.len() < FRAME_SIZE {
+        return None;
+    }
+    let length = read_u32(bytes, 0) as usize;
+    let body = bytes.get(FRAME_SIZE..FRAME_SIZE + length)?;
+    if crc32(body) != read_u32(bytes, 4) || body.len() < 17 {
+        return None;
+    }
+    let transaction = read_u64(body, 1);
+    let previous = read_u64(body, 9);
+    let change = |body: &[u8]| -> Option<(PageId, u16, usize)> {
+        if body.len() < 25 {
+            return None;
+        }
+        Some((
+            read_u32(body, 17),
+            read_u16(body, 21),
+            read_u16(body, 23) as usize,
+        ))
+    };
+    let record = match body[0] {
+        UPDATE => {
+            let (page, offset, size) = change(body)?;
+            let data = body.get(25..25 + 2 * size)?;
+            LogRecord::Update {
+                page,
+                offset,
+                before: data[..size].to_vec(),
+                after: data[size..].to_vec(),
+            }
+        }
+        COMPENSATION => {
+            let (page, offset, size) = change(body)?;
+            let after = body.get(25..25 + size)?.to_vec();
+            body.get(25 + size..33 + size)?;
+            LogRecord::Compensation {
+                page,
+                offset,
+                after,
+                undo_next: read_u64(body, 25 + size),
+            }
+        }
+        COMMIT => LogRecord::Commit,
+        ABORT => LogRecord::Abort,
+        _ => return None,
+    };
+    let entry = LogEntry {
+        lsn,
+        transaction,
+        previous,
+        record,
+    };
+    Some((entry, FRAME_SIZE + length))
+}
+
+/// CRC-32 as zlib computes it.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn corrupt_log(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("corrupt log: {}", message),
+    )
+}
@@ -0,0 +1,167 @@
+use crate::compiler::parser::DataType;
+use crate::types::value::{parse_datetime, Value, ValueError};
+
+/// The values an integer type holds. `BIGINT` is 128 bits wide, so
+/// `U_BIGINT` is limited to the non-negative half of a 128-bit value.
+pub fn integer_range(data_type: &DataType) -> Option<(i128, i128)> {
+    Some(match data_type {
+        DataType::BYTE { unsigned: false } => (i8::MIN as i128, i8::MAX as i128),
+        DataType::BYTE { unsigned: true } => (0, u8::MAX as i128),
+        DataType::SHORT { unsigned: false } => (i16::MIN as i128, i16::MAX as i128),
+        DataType::SHORT { unsigned: true } => (0, u16::MAX as i128),
+        DataType::INT { unsigned: false } => (i32::MIN as i128, i32::MAX as i128),
+        DataType::INT { unsigned: true } => (0, u32::MAX as i128),
+        DataType::LONG { unsigned: false } => (i64::MIN as i128, i64::MAX as i128),
+        DataType::LONG { unsigned: true } => (0, u64::MAX as i128),
+        DataType::BIGINT { unsigned: false } => (i128::MIN, i128::MAX),
+        DataType::BIGINT { unsigned: true } => (0, i128::MAX),
+        _ => return None,
+    })
+}
+
+/// Converts `value` to `data_type`.
+///
+/// NULL casts to NULL. Numbers convert between each other with a range
+/// check, floats rounding to the nearest integer; text is parsed; every
+/// value converts to text, cut to the length of the TEXT type. Integers
+/// cast to DATETIME count seconds since the epoch.
+pub fn cast(value: Value, data_type: &DataType) -> Result<Value, ValueError> {
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+    if let Some((min, max)) = integer_range(data_type) {
+        let integer = match &value {
+            Value::Integer(integer) => *integer,
+            Value::Float(float) => {
+                let rounded = float.round();
+                if !rounded.is_finite() || rounded < min as f64 || rounded > max as f64 {
+                    return Err(out_of_range(&value, data_type));
+                }
+                rounded as i128
+            }
+            Value::Bool(value) => *value as i128,
+            Value::Text(text) => text
+                .trim()
+                .parse::<i128>()
+                .map_err(|_| invalid_text(text, data_type))?,
+            _ => return Err(not_castable(&value, data_type)),
+        };
+        if integer < min || integer > max {
+            return Err(out_of_range(&value, data_type));
+        }
+        return Ok(Value::Integer(integer));
+    }
+    match data_type {
+        DataType::FLOAT { unsigned } | DataType::DOUBLE { unsigned } => {
+            let float = match &value {
+                Value::Integer(integer) => *integer as f64,
+                Value::Float(float) => *float,
+                Value::Bool(value) => *value as i32 as f64,
+                Value::Text(text) => text
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| invalid_text(text, data_type))?,
+                _ => return Err(not_castable(&value, data_type)),
+            };
+            let float = if matches!(data_type, DataType::FLOAT { .. }) {
+                let narrowed = float as f32;
+                if narrowed.is_infinite() && float.is_finite() {
+                    return Err(out_of_range(&value, data_type));
+                }
+                narrowed as f64
+            } else {
+                float
+            };
+            if *unsigned && float < 0.0 {
+                return Err(out_of_range(&value, data_type));
+            }
+            Ok(Value::Float(float))
+        }
+        DataType::BOOL => match &value {
+            Value::Bool(_) => Ok(value),
+            Value::Integer(integer) => Ok(Value::Bool(*integer != 0)),
+            Value::Text(text) => match text.trim().to_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "on" | "1" => Ok(Value::Bool(true)),
+                "false" | "f" | "no" | "n" | "off" | "0" => Ok(Value::Bool(false)),
+                _ => Err(invalid_text(text, data_type)),
+            },
+            _ => Err(not_castable(&value, data_type)),
+        },
+        DataType::TEXT { length } => {
+            let text = match value {
+                Value::Text(text) => text,
+                value => value.to_string(),
+            };
+            let length = (*length).max(0) as usize;
+            Ok(Value::Text(match text.char_indices().nth(length) {
+                Some((end, _)) => text[..end].to_string(),
+                None => text,
+            }))
+        }
+        DataType::DATETIME => match &value {
+            Value::DateTime(_) => Ok(value),
+            Value::Text(text) => parse_datetime(text)
+                .map(Value::DateTime)
+                .ok_or_else(|| invalid_text(text, data_type)),
+            Value::Integer(seconds) => seconds
+                .checked_mul(1_000_000)
+                .and_then(|micros| i64::try_from(micros).ok())
+                .map(Value::DateTime)
+                .ok_or_else(|| out_of_range(&value, data_type)),
+            _ => Err(not_castable(&value, data_type)),
+        },
+        DataType::UUID { .. } => match &value {
+            Value::Text(text) => parse_uuid(text)
+                .map(|uuid| Value::Text(format_uuid(uuid)))
+                .ok_or_else(|| invalid_text(text, data_type)),
+            Value::Integer(integer) if *integer >= 0 => {
+                Ok(Value::Text(format_uuid(*integer as u128)))
+            }
+            Value::Integer(_) => Err(out_of_range(&value, data_type)),
+            _ => Err(not_castable(&value, data_type)),
+        },
+        _ => Err(not_castable(&value, data_type)),
+    }
+}
+
+/// Accepts 32 hex digits, optionally grouped by hyphens and wrapped in braces.
+pub fn parse_uuid(text: &str) -> Option<u128> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('{')
+        .and_then(|inner| inner.strip_suffix('}'))
+        .unwrap_or(text);
+    let digits: String = text.chars().filter(|ch| *ch != '-').collect();
+    if digits.len() != 32 {
+        return None;
+    }
+    u128::from_str_radix(&digits, 16).ok()
+}
+
+pub fn format_uuid(uuid: u128) -> String {
+    let digits = format!("{:032x}", uuid);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &digits[..8],
+        &digits[8..12],
+        &digits[12..16],
+        &digits[16..20],
+        &digits[20..]
+    )
+}
+
+fn out_of_range(value: &Value, data_type: &DataType) -> ValueError {
+    ValueError::Overflow(format!("{} is out of range for {}", value, data_type))
+}
+
+fn invalid_text(text: &str, data_type: &DataType) -> ValueError {
+    ValueError::InvalidLiteral(format!("'{}' is not a valid {}", text, data_type))
+}
+
+fn not_castable(value: &Value, data_type: &DataType) -> ValueError {
+    ValueError::TypeMismatch(format!(
+        "cannot cast {} to {}",
+        value.type_name(),
+        data_type
+    ))
+}
@@ -0,0 +1,244 @@
+//! The built-in functions by name, and the signatures the binder checks
+//! their calls against.
+
+/// Built-in scalar functions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalarFunction {
+    Upper,
+    Lower,
+    Length,
+    Substring,
+    Trim,
+    LeftTrim,
+    RightTrim,
+    Replace,
+    Position,
+    Abs,
+    Round,
+    Floor,
+    Ceil,
+    Power,
+    Mod,
+    Coalesce,
+    NullIf,
+    Now,
+    ToDateTime,
+    Extract,
+    DateTrunc,
+    NextVal,
+    CurrVal,
+    SetVal,
+}
+
+/// What a parameter accepts. `DateTime` parameters also take text in the
+/// format of [`parse_datetime`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterType {
+    Any,
+    Text,
+    Integer,
+    Numeric,
+    DateTime,
+    Bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReturnType {
+    Text,
+    Integer,
+    Float,
+    DateTime,
+    /// The type of the first argument.
+    FirstArgument,
+    /// The type shared by all arguments.
+    Common,
+}
+
+/// `parameters[..required]` must be given; the rest are optional. A
+/// variadic function repeats its last parameter any number of times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Signature {
+    pub parameters: &'static [ParameterType],
+    pub required: usize,
+    pub variadic: bool,
+    pub returns: ReturnType,
+}
+
+impl ParameterType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ParameterType::Any => "any type",
+            ParameterType::Text => "TEXT",
+            ParameterType::Integer => "INTEGER",
+            ParameterType::Numeric => "numeric",
+            ParameterType::DateTime => "DATETIME",
+            ParameterType::Bool => "BOOL",
+        }
+    }
+}
+
+impl Signature {
+    pub fn accepts_count(&self, count: usize) -> bool {
+        count >= self.required && (self.variadic || count <= self.parameters.len())
+    }
+
+    pub fn parameter(&self, position: usize) -> ParameterType {
+        self.parameters
+            .get(position)
+            .or(self.parameters.last())
+            .copied()
+            .unwrap_or(ParameterType::Any)
+    }
+}
+
+impl ScalarFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "UPPER" => Some(ScalarFunction::Upper),
+            "LOWER" => Some(ScalarFunction::Lower),
+            "LENGTH" | "CHAR_LENGTH" => Some(ScalarFunction::Length),
+            "SUBSTRING" => Some(ScalarFunction::Substring),
+            "TRIM" => Some(ScalarFunction::Trim),
+            "LTRIM" => Some(ScalarFunction::LeftTrim),
+            "RTRIM" => Some(ScalarFunction::RightTrim),
+            "REPLACE" => Some(ScalarFunction::Replace),
+            "POSITION" => Some(ScalarFunction::Position),
+            "ABS" => Some(ScalarFunction::Abs),
+            "ROUND" => Some(ScalarFunction::Round),
+            "FLOOR" => Some(ScalarFunction::Floor),
+            "CEIL" | "CEILING" => Some(ScalarFunction::Ceil),
+            "POWER" => Some(ScalarFunction::Power),
+            "MOD" => Some(ScalarFunction::Mod),
+            "COALESCE" => Some(ScalarFunction::Coalesce),
+            "NULLIF" => Some(ScalarFunction::NullIf),
+            "NOW" => Some(ScalarFunction::Now),
+            "TO_DATETIME" => Some(ScalarFunction::ToDateTime),
+            "EXTRACT" | "DATE_PART" => Some(ScalarFunction::Extract),
+            "DATE_TRUNC" => Some(ScalarFunction::DateTrunc),
+            "NEXTVAL" => Some(ScalarFunction::NextVal),
+            "CURRVAL" => Some(ScalarFunction::CurrVal),
+            "SETVAL" => Some(ScalarFunction::SetVal),
+            _ => None,
+        }
+    }
+
+    pub fn signature(self) -> Signature {
+        use ParameterType::*;
+        let (parameters, required, variadic, returns): (&'static [ParameterType], _, _, _) =
+            match self {
+                ScalarFunction::Upper | ScalarFunction::Lower => {
+                    (&[Text], 1, false, ReturnType::Text)
+                }
+                ScalarFunction::Length => (&[Text], 1, false, ReturnType::Integer),
+                ScalarFunction::Substring => {
+                    (&[Text, Integer, Integer], 2, false, ReturnType::Text)
+                }
+                ScalarFunction::Trim | ScalarFunction::LeftTrim | ScalarFunction::RightTrim => {
+                    (&[Text, Text], 1, false, ReturnType::Text)
+                }
+                ScalarFunction::Replace => (&[Text, Text, Text], 3, false, ReturnType::Text),
+                ScalarFunction::Position => (&[Text, Text], 2, false, ReturnType::Integer),
+                ScalarFunction::Abs | ScalarFunction::Floor | ScalarFunction::Ceil => {
+                    (&[Numeric], 1, false, ReturnType::FirstArgument)
+                }
+                ScalarFunction::Round => (&[Numeric, Integer], 1, false, ReturnType::FirstArgument),
+                ScalarFunction::Power => (&[Numeric, Numeric], 2, false, ReturnType::Float),
+                ScalarFunction::Mod => (&[Numeric, Numeric], 2, false, ReturnType::Common),
+                ScalarFunction::Coalesce => (&[Any], 1, true, ReturnType::Common),
+                ScalarFunction::NullIf => (&[Any, Any], 2, false, ReturnType::FirstArgument),
+                ScalarFunction::Now => (&[], 0, false, ReturnType::DateTime),
+                ScalarFunction::ToDateTime => (&[Text], 1, false, ReturnType::DateTime),
+                ScalarFunction::Extract => (&[Text, DateTime], 2, false, ReturnType::Integer),
+                ScalarFunction::DateTrunc => (&[Text, DateTime], 2, false, ReturnType::DateTime),
+                ScalarFunction::NextVal | ScalarFunction::CurrVal => {
+                    (&[Text], 1, false, ReturnType::Integer)
+                }
+                ScalarFunction::SetVal => (&[Text, Integer, Bool], 2, false, ReturnType::Integer),
+            };
+        Signature {
+            parameters,
+            required,
+            variadic,
+            returns,
+        }
+    }
+
+    /// `nextval`, `currval` and `setval`, which work on the sequences of
+    /// the database rather than on their arguments alone, so the provider
+    /// of tables calls them.
+    pub fn uses_sequences(self) -> bool {
+        matches!(
+            self,
+            ScalarFunction::NextVal | ScalarFunction::CurrVal | ScalarFunction::SetVal
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "COUNT" => Some(AggregateFunction::Count),
+            "SUM" => Some(AggregateFunction::Sum),
+            "AVG" => Some(AggregateFunction::Avg),
+            "MIN" => Some(AggregateFunction::Min),
+            "MAX" => Some(AggregateFunction::Max),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowFunction {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Lag,
+    Lead,
+    FirstValue,
+    LastValue,
+}
+
+impl WindowFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "ROW_NUMBER" => Some(WindowFunction::RowNumber),
+            "RANK" => Some(WindowFunction::Rank),
+            "DENSE_RANK" => Some(WindowFunction::DenseRank),
+            "LAG" => Some(WindowFunction::Lag),
+            "LEAD" => Some(WindowFunction::Lead),
+            "FIRST_VALUE" => Some(WindowFunction::FirstValue),
+            "LAST_VALUE" => Some(WindowFunction::LastValue),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WindowFunction::RowNumber => "ROW_NUMBER",
+            WindowFunction::Rank => "RANK",
+            WindowFunction::DenseRank => "DENSE_RANK",
+            WindowFunction::Lag => "LAG",
+            WindowFunction::Lead => "LEAD",
+            WindowFunction::FirstValue => "FIRST_VALUE",
+            WindowFunction::LastValue => "LAST_VALUE",
+        }
+    }
+
+    /// The smallest and largest number of arguments the function takes.
+    pub fn arity(self) -> (usize, usize) {
+        match self {
+            WindowFunction::RowNumber | WindowFunction::Rank | WindowFunction::DenseRank => (0, 0),
+            WindowFunction::Lag | WindowFunction::Lead => (1, 3),
+            WindowFunction::FirstValue | WindowFunction::LastValue => (1, 1),
+        }
+    }
+}
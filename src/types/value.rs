@@ -0,0 +1,372 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+
+/// Why a literal could not be read, two values compared or a value cast.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueError {
+    InvalidLiteral(String),
+    TypeMismatch(String),
+    Overflow(String),
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::InvalidLiteral(literal) => write!(f, "invalid literal {}", literal),
+            ValueError::TypeMismatch(message) => write!(f, "type mismatch: {}", message),
+            ValueError::Overflow(message) => write!(f, "overflow: {}", message),
+        }
+    }
+}
+
+/// A single runtime value.
+///
+/// Integers of every width share one variant; it is 128 bits wide so that
+/// `BIGINT` columns fit without loss. `DateTime` counts microseconds since
+/// 1970-01-01 00:00:00 UTC.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i128),
+    Float(f64),
+    Text(String),
+    DateTime(i64),
+}
+
+impl Value {
+    pub fn from_literal(literal: &str) -> Result<Value, ValueError> {
+        match literal {
+            "NULL" => return Ok(Value::Null),
+            "TRUE" => return Ok(Value::Bool(true)),
+            "FALSE" => return Ok(Value::Bool(false)),
+            _ => {}
+        }
+        if let Some(quote) = literal
+            .chars()
+            .next()
+            .filter(|ch| *ch == '\'' || *ch == '"')
+        {
+            let inner = &literal[1..];
+            return match inner.strip_suffix(quote) {
+                Some(text) => Ok(Value::Text(text.to_string())),
+                None => Err(ValueError::InvalidLiteral(literal.to_string())),
+            };
+        }
+        if let Ok(integer) = literal.parse::<i128>() {
+            return Ok(Value::Integer(integer));
+        }
+        if let Ok(float) = literal.parse::<f64>() {
+            return Ok(Value::Float(float));
+        }
+        Err(ValueError::InvalidLiteral(literal.to_string()))
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "NULL",
+            Value::Bool(_) => "BOOL",
+            Value::Integer(_) => "INTEGER",
+            Value::Float(_) => "FLOAT",
+            Value::Text(_) => "TEXT",
+            Value::DateTime(_) => "DATETIME",
+        }
+    }
+
+    /// SQL comparison: `None` when either side is NULL, an error when the
+    /// types cannot be compared at all.
+    pub fn compare(&self, other: &Value) -> Result<Option<Ordering>, ValueError> {
+        match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => Ok(None),
+            (Value::Bool(left), Value::Bool(right)) => Ok(Some(left.cmp(right))),
+            (Value::Integer(left), Value::Integer(right)) => Ok(Some(left.cmp(right))),
+            (Value::Integer(left), Value::Float(right)) => {
+                Ok(Some(compare_floats(*left as f64, *right)))
+            }
+            (Value::Float(left), Value::Integer(right)) => {
+                Ok(Some(compare_floats(*left, *right as f64)))
+            }
+            (Value::Float(left), Value::Float(right)) => Ok(Some(compare_floats(*left, *right))),
+            (Value::Text(left), Value::Text(right)) => Ok(Some(left.cmp(right))),
+            (Value::DateTime(left), Value::DateTime(right)) => Ok(Some(left.cmp(right))),
+            _ => Err(ValueError::TypeMismatch(format!(
+                "cannot compare {} with {}",
+                self.type_name(),
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// Total order used for sorting: NULL sorts first, then values of the
+    /// same kind by their natural order, and mismatched kinds by kind.
+    pub fn sort_cmp(&self, other: &Value) -> Ordering {
+        match self.compare(other) {
+            Ok(Some(ordering)) => ordering,
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Integer(_) | Value::Float(_) => 2,
+            Value::Text(_) => 3,
+            Value::DateTime(_) => 4,
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Value::Null => writer.write_all(&[0]),
+            Value::Bool(value) => writer.write_all(&[1, *value as u8]),
+            Value::Integer(value) => {
+                writer.write_all(&[2])?;
+                writer.write_all(&value.to_le_bytes())
+            }
+            Value::Float(value) => {
+                writer.write_all(&[3])?;
+                writer.write_all(&value.to_le_bytes())
+            }
+            Value::Text(value) => {
+                writer.write_all(&[4])?;
+                writer.write_all(&(value.len() as u32).to_le_bytes())?;
+                writer.write_all(value.as_bytes())
+            }
+            Value::DateTime(value) => {
+                writer.write_all(&[5])?;
+                writer.write_all(&value.to_le_bytes())
+            }
+        }
+    }
+
+    /// Reads a value written by [`Value::write_to`]; `Ok(None)` at a clean end of input.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Value>> {
+        let mut tag = [0u8; 1];
+        if reader.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+        let value = match tag[0] {
+            0 => Value::Null,
+            1 => {
+                let mut byte = [0u8; 1];
+                reader.read_exact(&mut byte)?;
+                Value::Bool(byte[0] != 0)
+            }
+            2 => {
+                let mut bytes = [0u8; 16];
+                reader.read_exact(&mut bytes)?;
+                Value::Integer(i128::from_le_bytes(bytes))
+            }
+            3 => {
+                let mut bytes = [0u8; 8];
+                reader.read_exact(&mut bytes)?;
+                Value::Float(f64::from_le_bytes(bytes))
+            }
+            4 => {
+                let mut length = [0u8; 4];
+                reader.read_exact(&mut length)?;
+                let mut bytes = vec![0u8; u32::from_le_bytes(length) as usize];
+                reader.read_exact(&mut bytes)?;
+                Value::Text(
+                    String::from_utf8(bytes)
+                        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?,
+                )
+            }
+            5 => {
+                let mut bytes = [0u8; 8];
+                reader.read_exact(&mut bytes)?;
+                Value::DateTime(i64::from_le_bytes(bytes))
+            }
+            tag => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown value tag {}", tag),
+                ))
+            }
+        };
+        Ok(Some(value))
+    }
+}
+
+/// Grouping equality: NULLs are equal to each other and floats compare by
+/// bit pattern, so values can be used as hash keys.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(left), Value::Bool(right)) => left == right,
+            (Value::Integer(left), Value::Integer(right)) => left == right,
+            (Value::Float(left), Value::Float(right)) => {
+                normalized_bits(*left) == normalized_bits(*right)
+            }
+            (Value::Text(left), Value::Text(right)) => left == right,
+            (Value::DateTime(left), Value::DateTime(right)) => left == right,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Value::Null => {}
+            Value::Bool(value) => value.hash(state),
+            Value::Integer(value) => value.hash(state),
+            Value::Float(value) => normalized_bits(*value).hash(state),
+            Value::Text(value) => value.hash(state),
+            Value::DateTime(value) => value.hash(state),
+        }
+    }
+}
+
+/// Floats compare as numbers, so -0.0 equals 0.0, and NaN equals itself
+/// and sorts above every number, the way index keys order them.
+fn compare_floats(left: f64, right: f64) -> Ordering {
+    match (left.is_nan(), right.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => left.partial_cmp(&right).unwrap_or(Ordering::Equal),
+    }
+}
+
+fn normalized_bits(value: f64) -> u64 {
+    if value == 0.0 {
+        0.0f64.to_bits()
+    } else if value.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        value.to_bits()
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Bool(value) => write!(f, "{}", if *value { "TRUE" } else { "FALSE" }),
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Text(value) => write!(f, "{}", value),
+            Value::DateTime(value) => {
+                let (days, micros) = (
+                    value.div_euclid(MICROS_PER_DAY),
+                    value.rem_euclid(MICROS_PER_DAY),
+                );
+                let (year, month, day) = civil_from_days(days);
+                let seconds = micros / 1_000_000;
+                write!(
+                    f,
+                    "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                    year,
+                    month,
+                    day,
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                )?;
+                if micros % 1_000_000 != 0 {
+                    write!(f, ".{:06}", micros % 1_000_000)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of [`days_from_civil`].
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Parses `YYYY-MM-DD`, optionally followed by ` HH:MM:SS` (or `T` instead
+/// of the space) and up to six fractional digits, into microseconds since
+/// the epoch.
+pub fn parse_datetime(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (date, time) = match text.find([' ', 'T']) {
+        Some(split) => (&text[..split], Some(&text[split + 1..])),
+        None => (text, None),
+    };
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut micros = 0;
+    if let Some(time) = time {
+        let (clock, fraction) = match time.split_once('.') {
+            Some((clock, fraction)) => (clock, Some(fraction)),
+            None => (time, None),
+        };
+        let mut parts = clock.splitn(3, ':');
+        let hour: i64 = parts.next()?.parse().ok()?;
+        let minute: i64 = parts.next()?.parse().ok()?;
+        let second: i64 = parts.next().unwrap_or("0").parse().ok()?;
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        micros = ((hour * 60 + minute) * 60 + second) * 1_000_000;
+        if let Some(fraction) = fraction {
+            if fraction.is_empty()
+                || fraction.len() > 6
+                || !fraction.chars().all(|ch| ch.is_ascii_digit())
+            {
+                return None;
+            }
+            micros += format!("{:0<6}", fraction).parse::<i64>().ok()?;
+        }
+    }
+    days_from_civil(year, month, day)
+        .checked_mul(MICROS_PER_DAY)?
+        .checked_add(micros)
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
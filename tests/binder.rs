@@ -0,0 +1,119 @@
+//! What the binder rejects before a statement runs, the casts it adds,
+//! and where it reports its errors.
+
+mod common;
+
+use common::{database, error, execute, query};
+use omnissiah_db::execution::executor::Executor;
+
+fn shop() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE items (id INT PRIMARY, name TEXT NOTNULL, price DOUBLE, added DATETIME);
+CREATE TABLE orders (id INT PRIMARY, item INT NOTNULL, amount SHORT NOTNULL);
+INSERT INTO items(id, name, price, added) VALUES (1, 'pen', 1.5, '2024-01-10 00:00:00');
+INSERT INTO items(id, name, price, added) VALUES (2, 'ink', 3, '2024-02-20 00:00:00');
+INSERT INTO orders(id, item, amount) VALUES (1, 1, 10);
+INSERT INTO orders(id, item, amount) VALUES (2, 2, 2);",
+    );
+    database
+}
+
+#[test]
+fn unknown_names_are_rejected_where_they_appear() {
+    let mut database = shop();
+    let mut fails = |sql: &str| error(&mut database, sql);
+    assert_eq!(
+        fails("SELECT nmae FROM items;"),
+        "1:8: unknown column \"nmae\""
+    );
+    assert_eq!(
+        fails("SELECT name\nFROM items\nWHERE items.cost > 1;"),
+        "3:13: unknown column \"items.cost\""
+    );
+    assert_eq!(
+        fails("SELECT id FROM item;"),
+        "1:16: unknown table \"item\""
+    );
+    assert_eq!(
+        fails("SELECT SQRT(price) FROM items;"),
+        "1:8: unknown function \"SQRT\""
+    );
+}
+
+#[test]
+fn unqualified_columns_of_two_tables_are_ambiguous() {
+    let mut database = shop();
+    assert_eq!(
+        error(
+            &mut database,
+            "SELECT name FROM items JOIN orders ON item == items.id WHERE id == 1;"
+        ),
+        "1:62: column reference \"id\" is ambiguous"
+    );
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT name, amount FROM items JOIN orders ON item == items.id WHERE orders.id == 1;"
+        ),
+        ["pen | 10"]
+    );
+}
+
+#[test]
+fn mismatched_types_are_rejected() {
+    let mut database = shop();
+    let mut fails = |sql: &str| error(&mut database, sql);
+    assert_eq!(
+        fails("SELECT id FROM items WHERE name == 1;"),
+        "1:36: type mismatch: cannot compare TEXT with INT"
+    );
+    assert_eq!(
+        fails("SELECT id FROM items WHERE price;"),
+        "1:28: type mismatch: argument of WHERE must be BOOL, not DOUBLE"
+    );
+    assert_eq!(
+        fails("INSERT INTO orders(id, item, amount) VALUES (3, TRUE, 1);"),
+        "1:49: type mismatch: column item is INT but the expression is BOOL"
+    );
+}
+
+#[test]
+fn compatible_types_are_cast_implicitly() {
+    let mut database = shop();
+    let mut run = |sql: &str| query(&mut database, sql);
+    // An INT compared with a DOUBLE, and text compared with a DATETIME.
+    assert_eq!(run("SELECT name FROM items WHERE id < 1.5;"), ["pen"]);
+    assert_eq!(
+        run("SELECT name FROM items WHERE added > '2024-02-01 00:00:00';"),
+        ["ink"]
+    );
+    // A SHORT column and an INT column joined and combined.
+    assert_eq!(
+        run("SELECT name FROM items JOIN orders ON amount == items.id;"),
+        ["ink"]
+    );
+    assert_eq!(
+        run("SELECT amount FROM orders UNION SELECT price FROM items ORDER BY 1;"),
+        ["1.5", "2", "3", "10"]
+    );
+    // An integer literal stored in a DOUBLE column, read back as one.
+    assert_eq!(run("SELECT price FROM items WHERE id == 2;"), ["3"]);
+    assert_eq!(
+        run("SELECT COALESCE(price, 0) FROM items ORDER BY 1;"),
+        ["1.5", "3"]
+    );
+}
+
+#[test]
+fn errors_point_into_their_own_statement() {
+    let mut database = shop();
+    assert_eq!(
+        error(
+            &mut database,
+            "SELECT id FROM items;\nSELECT id,\n       amount\n  FROM items;"
+        ),
+        "3:8: unknown column \"amount\""
+    );
+}
@@ -0,0 +1,249 @@
+//! The B+tree on its own: splits, the keys an index builds, range scans
+//! along the leaf chain, deletes and the key size limit.
+
+mod common;
+
+use std::io;
+use std::ops::Bound;
+
+use common::pool;
+use omnissiah_db::storage::btree::{BTree, RangeCursor, MAX_KEY_SIZE};
+use omnissiah_db::storage::buffer::{BufferPool, EvictionPolicy};
+use omnissiah_db::storage::key::{encode_key, entry_key, prefix_range};
+use omnissiah_db::storage::page::RecordId;
+use omnissiah_db::types::value::Value;
+
+fn id(number: u32) -> RecordId {
+    RecordId {
+        page: number / 100 + 1,
+        slot: (number % 100) as u16,
+    }
+}
+
+/// A key of 200 bytes that sorts like `number`, so a page holds a few
+/// dozen entries and a few thousand keys split the internal nodes too.
+fn padded(number: u32) -> Vec<u8> {
+    let mut key = number.to_be_bytes().to_vec();
+    key.resize(200, b'.');
+    key
+}
+
+fn numbers(entries: &[(Vec<u8>, RecordId)]) -> Vec<u32> {
+    entries
+        .iter()
+        .map(|(key, _)| u32::from_be_bytes(key[..4].try_into().unwrap()))
+        .collect()
+}
+
+/// A tree of the keys `0..count`, inserted in a scattered order.
+fn tree(pool: &mut BufferPool, count: u32) -> BTree {
+    let tree = BTree::create(pool).unwrap();
+    for step in 0..count {
+        let number = step * 7919 % count;
+        assert!(tree.insert(pool, &padded(number), id(number)).unwrap());
+    }
+    tree
+}
+
+#[test]
+fn splits_spread_the_tree_over_many_pages() {
+    let mut pool = pool(8, EvictionPolicy::Lru);
+    let tree = tree(&mut pool, 5000);
+    // 5000 entries of 200 bytes fill well over a hundred leaves, more than
+    // one internal node can point to.
+    assert!(pool.page_count() > 150, "{} pages", pool.page_count());
+    for number in (0..5000).step_by(37) {
+        assert_eq!(
+            tree.get(&mut pool, &padded(number)).unwrap(),
+            Some(id(number))
+        );
+    }
+    assert_eq!(tree.get(&mut pool, &padded(5000)).unwrap(), None);
+    assert!(!tree.insert(&mut pool, &padded(1234), id(1)).unwrap());
+    assert_eq!(tree.get(&mut pool, &padded(1234)).unwrap(), Some(id(1234)));
+    let all = tree
+        .range(&mut pool, Bound::Unbounded, Bound::Unbounded)
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(numbers(&all), (0..5000).collect::<Vec<_>>());
+    assert!(all
+        .iter()
+        .all(|(key, record)| { *record == id(u32::from_be_bytes(key[..4].try_into().unwrap())) }));
+}
+
+#[test]
+fn record_ids_make_equal_keys_distinct() {
+    let mut pool = pool(8, EvictionPolicy::Lru);
+    let tree = BTree::create(&mut pool).unwrap();
+    let mut run = |values: &[Value], number: u32| {
+        tree.insert(&mut pool, &entry_key(values, id(number)), id(number))
+            .unwrap()
+    };
+    for number in (0..300).rev() {
+        assert!(run(&[Value::Integer(7)], number));
+        assert!(run(&[Value::Integer(8)], number));
+    }
+    assert!(!run(&[Value::Integer(7)], 5));
+    for (first, second, number) in [(1, "b", 1), (1, "a", 2), (2, "a", 3), (1, "b", 4)] {
+        let key = [Value::Integer(first), Value::Text(second.to_string())];
+        assert!(run(&key, number));
+    }
+    let mut lookup = |values: &[Value]| {
+        let (lower, upper) = prefix_range(&encode_key(values));
+        let entries = tree
+            .range(&mut pool, bound(&lower), bound(&upper))
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        entries.into_iter().map(|(_, id)| id).collect::<Vec<_>>()
+    };
+    assert_eq!(
+        lookup(&[Value::Integer(7)]),
+        (0..300).map(id).collect::<Vec<_>>()
+    );
+    assert_eq!(lookup(&[Value::Integer(1)]), [id(2), id(1), id(4)]);
+    assert_eq!(
+        lookup(&[Value::Integer(1), Value::Text("b".to_string())]),
+        [id(1), id(4)]
+    );
+    assert_eq!(lookup(&[Value::Integer(2)]), [id(3)]);
+    assert!(lookup(&[Value::Integer(3)]).is_empty());
+}
+
+fn bound(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+    bound.as_ref().map(Vec::as_slice)
+}
+
+#[test]
+fn ranges_follow_the_leaf_chain_between_their_bounds() {
+    let mut pool = pool(8, EvictionPolicy::Clock);
+    let tree = tree(&mut pool, 2000);
+    let (low, high) = (padded(500), padded(1500));
+    let mut range = |lower: Bound<&[u8]>, upper: Bound<&[u8]>| {
+        numbers(
+            &tree
+                .range(&mut pool, lower, upper)
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap(),
+        )
+    };
+    use Bound::{Excluded, Included, Unbounded};
+    assert_eq!(
+        range(Included(&low), Included(&high)),
+        (500..=1500).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        range(Excluded(&low), Excluded(&high)),
+        (501..1500).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        range(Included(&low), Excluded(&high)),
+        (500..1500).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        range(Unbounded, Excluded(&low)),
+        (0..500).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        range(Excluded(&high), Unbounded),
+        (1501..2000).collect::<Vec<_>>()
+    );
+    // A bound between two keys, and a range that holds nothing.
+    assert_eq!(
+        range(Included(&low[..4]), Included(&[0, 0, 1, 245])),
+        (500..501).collect::<Vec<_>>()
+    );
+    assert!(range(Excluded(&high), Included(&high)).is_empty());
+    assert!(range(Included(&padded(3000)), Unbounded).is_empty());
+}
+
+#[test]
+fn a_range_cursor_goes_on_over_splits_made_between_leaves() {
+    let mut pool = pool(8, EvictionPolicy::Lru);
+    let tree = BTree::create(&mut pool).unwrap();
+    for number in (0..2000).step_by(2) {
+        tree.insert(&mut pool, &padded(number), id(number)).unwrap();
+    }
+    let (low, high) = (padded(100), padded(1900));
+    let mut cursor = RangeCursor::new(Bound::Included(&low), Bound::Excluded(&high));
+    let mut odd = (1..2000).step_by(2);
+    let mut seen = Vec::new();
+    let mut leaves = 0;
+    while let Some(entries) = tree.next_leaf(&mut pool, &mut cursor).unwrap() {
+        seen.extend(numbers(&entries));
+        leaves += 1;
+        // The odd keys go in while the scan is between leaves, splitting
+        // leaves behind it and ahead of it.
+        for number in odd.by_ref().take(50) {
+            tree.insert(&mut pool, &padded(number), id(number)).unwrap();
+        }
+    }
+    assert!(leaves > 10, "{} leaves", leaves);
+    assert!(seen.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(seen.iter().all(|number| (100..1900).contains(number)));
+    assert!((100..1900)
+        .step_by(2)
+        .all(|number| seen.binary_search(&number).is_ok()));
+    // Keys added ahead of the scan are read, those behind it are not.
+    assert!(seen.iter().any(|number| number % 2 == 1));
+    assert!(seen.len() < 1800);
+}
+
+#[test]
+fn deleted_keys_are_gone_from_lookups_and_ranges() {
+    let mut pool = pool(8, EvictionPolicy::Lru);
+    let tree = tree(&mut pool, 1000);
+    for number in (0..1000).filter(|number| number % 3 != 0) {
+        assert!(tree.delete(&mut pool, &padded(number)).unwrap());
+    }
+    assert!(!tree.delete(&mut pool, &padded(1)).unwrap());
+    assert!(!tree.delete(&mut pool, &padded(1000)).unwrap());
+    assert_eq!(tree.get(&mut pool, &padded(2)).unwrap(), None);
+    assert_eq!(tree.get(&mut pool, &padded(3)).unwrap(), Some(id(3)));
+    let rest = tree
+        .range(&mut pool, Bound::Unbounded, Bound::Unbounded)
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(numbers(&rest), (0..1000).step_by(3).collect::<Vec<_>>());
+    // A leaf left empty still passes a scan on to the next one.
+    assert!(tree.insert(&mut pool, &padded(2), id(2)).unwrap());
+    let start = tree
+        .range(
+            &mut pool,
+            Bound::Included(&padded(1)),
+            Bound::Included(&padded(6)),
+        )
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(numbers(&start), [2, 3, 6]);
+}
+
+#[test]
+fn keys_longer_than_the_limit_are_rejected() {
+    let mut pool = pool(8, EvictionPolicy::Lru);
+    let tree = BTree::create(&mut pool).unwrap();
+    let longest = vec![b'x'; MAX_KEY_SIZE];
+    assert!(tree.insert(&mut pool, &longest, id(1)).unwrap());
+    let error = tree
+        .insert(&mut pool, &[b'x'; MAX_KEY_SIZE + 1], id(2))
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "index key of {} bytes is longer than the limit of {} bytes",
+            MAX_KEY_SIZE + 1,
+            MAX_KEY_SIZE
+        )
+    );
+    assert_eq!(tree.get(&mut pool, &longest).unwrap(), Some(id(1)));
+    // Keys at the limit still split cleanly.
+    for number in 0..50u8 {
+        let key = vec![number; MAX_KEY_SIZE];
+        assert!(tree.insert(&mut pool, &key, id(number.into())).unwrap());
+    }
+    let all = tree
+        .range(&mut pool, Bound::Unbounded, Bound::Unbounded)
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(all.len(), 51);
+}
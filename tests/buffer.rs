@@ -0,0 +1,194 @@
+//! The buffer pool with a handful of frames: which page each eviction
+//! policy gives up, pinning and write-back, the pool size a database is
+//! opened with, heaps reusing the space the free-space map records, and
+//! heap scans reading a page at a time.
+
+mod common;
+
+use std::io;
+
+use common::{execute, pool, query, Directory};
+use omnissiah_db::execution::executor::Executor;
+use omnissiah_db::storage::allocator::allocate_page;
+use omnissiah_db::storage::buffer::{BufferPool, EvictionPolicy};
+use omnissiah_db::storage::disk::DiskManager;
+use omnissiah_db::storage::engine::StorageOptions;
+use omnissiah_db::storage::heap::HeapFile;
+use omnissiah_db::storage::page::PageId;
+use omnissiah_db::storage::wal::Wal;
+
+/// A pool of three frames over a file of four pages, having read pages
+/// 0, 1 and 2 in that order.
+fn three_frames(policy: EvictionPolicy) -> BufferPool {
+    let mut disk = DiskManager::memory();
+    for _ in 0..4 {
+        disk.extend().unwrap();
+    }
+    let mut pool = BufferPool::new(disk, Wal::memory(), 3, policy);
+    for page in 0..3 {
+        pool.read(page, |_| ()).unwrap();
+    }
+    pool
+}
+
+/// Reads `page`, returning whether it was already cached.
+fn cached(pool: &mut BufferPool, page: PageId) -> bool {
+    let hits = pool.hits;
+    pool.read(page, |_| ()).unwrap();
+    pool.hits > hits
+}
+
+#[test]
+fn lru_evicts_the_page_used_longest_ago() {
+    let mut pool = three_frames(EvictionPolicy::Lru);
+    assert!(cached(&mut pool, 0));
+    // 1 is now the least recently used, then 2.
+    assert!(!cached(&mut pool, 3));
+    assert!(cached(&mut pool, 0));
+    assert!(!cached(&mut pool, 1));
+    assert!(!cached(&mut pool, 2));
+    assert!(!cached(&mut pool, 3));
+    assert_eq!((pool.hits, pool.misses), (2, 7));
+}
+
+#[test]
+fn clock_evicts_the_first_page_its_hand_finds_unused() {
+    let mut pool = three_frames(EvictionPolicy::Clock);
+    // Every frame is marked used, so using page 0 again changes nothing:
+    // the hand clears all three marks and comes back round to page 0.
+    assert!(cached(&mut pool, 0));
+    assert!(!cached(&mut pool, 3));
+    assert!(!cached(&mut pool, 0));
+    // The hand moved on to pages 1 and 2, leaving 3 alone.
+    assert!(!cached(&mut pool, 1));
+    assert!(cached(&mut pool, 3));
+    assert_eq!((pool.hits, pool.misses), (2, 6));
+}
+
+#[test]
+fn pinned_pages_stay_and_changed_pages_are_written_back() {
+    let mut pool = three_frames(EvictionPolicy::Lru);
+    pool.write(1, |data| data[100] = 42).unwrap();
+    let pinned: Vec<_> = [0, 2, 3]
+        .into_iter()
+        .map(|page| pool.fetch(page).unwrap())
+        .collect();
+    assert_eq!(
+        pool.fetch(1).unwrap_err().to_string(),
+        "every page of the buffer pool is pinned"
+    );
+    pool.unpin(pinned[0]);
+    // Page 1 was evicted for page 3 and read back from the file.
+    assert_eq!(pool.read(1, |data| data[100]).unwrap(), 42);
+    for frame in &pinned[1..] {
+        pool.unpin(*frame);
+    }
+    // A pool has at least one frame.
+    assert_eq!(
+        BufferPool::new(DiskManager::memory(), Wal::memory(), 0, EvictionPolicy::Lru).capacity(),
+        1
+    );
+}
+
+#[test]
+fn a_database_runs_in_a_pool_of_a_few_pages() {
+    let directory = Directory::new("buffer");
+    let options = StorageOptions {
+        buffer_pool_pages: 4,
+        eviction: EvictionPolicy::Lru,
+        ..StorageOptions::default()
+    };
+    let mut database = Executor::open(&directory.0, options).unwrap();
+    execute(
+        &mut database,
+        "CREATE TABLE notes (id INT PRIMARY, body TEXT); CREATE INDEX note_bodies ON notes (body);",
+    );
+    let body = "x".repeat(200);
+    for id in 0..300 {
+        execute(
+            &mut database,
+            &format!(
+                "INSERT INTO notes(id, body) VALUES ({}, '{}{}');",
+                id, id, body
+            ),
+        );
+    }
+    assert_eq!(query(&mut database, "SELECT COUNT(*) FROM notes;"), ["300"]);
+    assert_eq!(
+        query(
+            &mut database,
+            &format!("SELECT id FROM notes WHERE body == '17{}';", body)
+        ),
+        ["17"]
+    );
+    drop(database);
+    let mut database = directory.open();
+    assert_eq!(
+        query(&mut database, "SELECT COUNT(*) FROM notes WHERE id >= 150;"),
+        ["150"]
+    );
+}
+
+#[test]
+fn freed_space_and_pages_are_used_again() {
+    let mut pool = pool(4, EvictionPolicy::Clock);
+    let mut heap = HeapFile::create(&mut pool).unwrap();
+    let record = [7; 1000];
+    let ids: Vec<_> = (0..40)
+        .map(|_| heap.insert(&mut pool, &record).unwrap())
+        .collect();
+    let pages: Vec<PageId> = heap.free_space_map().pages().collect();
+    assert!(pages.len() >= 5, "{:?}", pages);
+    // Emptying the second page gives its room back to the next inserts.
+    let second: Vec<u16> = ids
+        .iter()
+        .filter(|id| id.page == pages[1])
+        .map(|id| id.slot)
+        .collect();
+    heap.prune(&mut pool, pages[1], &second).unwrap();
+    let file = pool.page_count();
+    for _ in 0..second.len() {
+        assert_eq!(heap.insert(&mut pool, &record).unwrap().page, pages[1]);
+    }
+    assert_eq!(pool.page_count(), file);
+    // Pages freed from the end of the heap are allocated again before the
+    // file grows.
+    let last = *pages.last().unwrap();
+    let slots: Vec<u16> = ids
+        .iter()
+        .filter(|id| id.page == last)
+        .map(|id| id.slot)
+        .collect();
+    heap.prune(&mut pool, last, &slots).unwrap();
+    assert_eq!(heap.truncate(&mut pool).unwrap(), 1);
+    assert_eq!(allocate_page(&mut pool).unwrap(), last);
+    assert_eq!(pool.page_count(), file);
+}
+
+#[test]
+fn heap_scans_read_a_page_at_a_time() {
+    let mut pool = pool(4, EvictionPolicy::Lru);
+    let mut heap = HeapFile::create(&mut pool).unwrap();
+    for number in 0..80u8 {
+        heap.insert(&mut pool, &[number; 1000]).unwrap();
+    }
+    let pages = heap.pages();
+    assert!(pages.len() >= 10, "{:?}", pages);
+    // Stopped within the first page, a scan has read no other.
+    let reads = pool.hits + pool.misses;
+    let first = heap
+        .scan(&mut pool)
+        .take(3)
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(pool.hits + pool.misses, reads + 1);
+    assert!(first.iter().all(|(id, _)| id.page == pages[0]));
+    // To the end, it goes through a pool far smaller than the heap.
+    let all = heap
+        .scan(&mut pool)
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    let records: Vec<u8> = all.iter().map(|(_, record)| record[0]).collect();
+    assert_eq!(records, (0..80).collect::<Vec<_>>());
+    assert_eq!(pool.hits + pool.misses, reads + 1 + pages.len() as u64);
+}
@@ -0,0 +1,124 @@
+//! What the catalog keeps across a restart, and how SHOW, DESCRIBE and
+//! information_schema present it.
+
+mod common;
+
+use common::{database, error, execute, query, Directory};
+use omnissiah_db::execution::executor::Executor;
+
+fn pets() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE owners (id INT PRIMARY, name TEXT NOTNULL, email TEXT);
+CREATE TABLE pets (id INT PRIMARY IDENTITY, owner INT NOTNULL, name TEXT, weight U_SHORT);
+CREATE UNIQUE INDEX owner_emails ON owners (email);
+CREATE INDEX pet_owners ON pets USING HASH (owner);",
+    );
+    database
+}
+
+#[test]
+fn the_catalog_survives_a_restart() {
+    let directory = Directory::new("catalog");
+    {
+        let mut database = directory.open();
+        execute(
+            &mut database,
+            "CREATE DATABASE zoo;
+USE zoo;
+CREATE TABLE animals (id INT PRIMARY, name TEXT NOTNULL, legs U_BYTE);
+CREATE UNIQUE INDEX animal_names ON animals (LOWER(name)) WHERE legs > 0;
+CREATE SEQUENCE tags START WITH 100 INCREMENT BY 10;
+INSERT INTO animals(id, name, legs) VALUES (1, 'Cat', 4);
+SELECT nextval('tags') FROM animals;",
+        );
+    }
+    let mut database = directory.open();
+    execute(&mut database, "USE zoo;");
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("DESCRIBE animals;"),
+        [
+            "id | INT | NO | PRIMARY | ",
+            "name | TEXT | NO |  | ",
+            "legs | U_BYTE | YES |  | "
+        ]
+    );
+    assert_eq!(
+        run("SELECT index_name, column_names, is_unique, index_predicate FROM information_schema.indexes WHERE index_name == 'animal_names';"),
+        ["animal_names | LOWER(name) | YES | legs > 0"]
+    );
+    assert_eq!(run("SELECT nextval('tags') FROM animals;"), ["110"]);
+    assert_eq!(
+        run("SELECT name FROM animals WHERE LOWER(name) == 'cat';"),
+        ["Cat"]
+    );
+    assert!(error(
+        &mut database,
+        "INSERT INTO animals(id, name, legs) VALUES (2, 'CAT', 4);"
+    )
+    .contains("animal_names"));
+}
+
+#[test]
+fn show_lists_databases_and_tables() {
+    let mut database = pets();
+    execute(&mut database, "CREATE DATABASE zoo;");
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(run("SHOW DATABASES;"), ["main", "zoo"]);
+    assert_eq!(run("SHOW TABLES;"), ["owners", "pets"]);
+    execute(&mut database, "USE zoo;");
+    assert!(query(&mut database, "SHOW TABLES;").is_empty());
+}
+
+#[test]
+fn describe_shows_types_keys_and_nullability() {
+    let mut database = pets();
+    assert_eq!(
+        query(&mut database, "DESCRIBE owners;"),
+        [
+            "id | INT | NO | PRIMARY | ",
+            "name | TEXT | NO |  | ",
+            "email | TEXT | YES | UNIQUE | "
+        ]
+    );
+    assert_eq!(
+        query(&mut database, "DESCRIBE pets;"),
+        [
+            "id | INT | NO | PRIMARY | IDENTITY",
+            "owner | INT | NO |  | ",
+            "name | TEXT | YES |  | ",
+            "weight | U_SHORT | YES |  | "
+        ]
+    );
+    assert!(error(&mut database, "DESCRIBE cats;").contains("unknown table \"cats\""));
+}
+
+#[test]
+fn information_schema_describes_tables_and_columns() {
+    let mut database = pets();
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("SELECT table_catalog, table_name, table_type FROM information_schema.tables ORDER BY table_name;"),
+        ["main | owners | BASE TABLE", "main | pets | BASE TABLE"]
+    );
+    assert_eq!(
+        run("SELECT column_name, ordinal_position, data_type, character_maximum_length, is_nullable, is_primary_key, is_identity FROM information_schema.columns WHERE table_name == 'pets' ORDER BY ordinal_position;"),
+        [
+            "id | 1 | INT | NULL | NO | YES | YES",
+            "owner | 2 | INT | NULL | NO | NO | NO",
+            "name | 3 | TEXT | 256 | YES | NO | NO",
+            "weight | 4 | U_SHORT | NULL | YES | NO | NO"
+        ]
+    );
+    assert_eq!(
+        run("SELECT index_name, column_names, is_unique, index_method FROM information_schema.indexes ORDER BY index_name;"),
+        [
+            "owner_emails | email | YES | BTREE",
+            "owners_pkey | id | YES | BTREE",
+            "pet_owners | owner | NO | HASH",
+            "pets_pkey | id | YES | BTREE"
+        ]
+    );
+}
@@ -0,0 +1,110 @@
+//! Runs SQL against a database for the behavior tests.
+
+// Each test crate uses the helpers it needs.
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use omnissiah_db::compiler::lexer::Lexer;
+use omnissiah_db::compiler::parser::Parser;
+use omnissiah_db::execution::error::ExecutionError;
+use omnissiah_db::execution::executor::{Executor, ResultSet};
+use omnissiah_db::storage::allocator;
+use omnissiah_db::storage::buffer::{BufferPool, EvictionPolicy};
+use omnissiah_db::storage::catalog::Catalog;
+use omnissiah_db::storage::disk::DiskManager;
+use omnissiah_db::storage::engine::StorageOptions;
+use omnissiah_db::storage::wal::Wal;
+
+/// An empty database that lives in memory.
+pub fn database() -> Executor {
+    Executor::new(Catalog::new())
+}
+
+/// A buffer pool of `capacity` frames over an empty data file in memory,
+/// for the tests of the storage structures.
+pub fn pool(capacity: usize, policy: EvictionPolicy) -> BufferPool {
+    let mut pool = BufferPool::new(DiskManager::memory(), Wal::memory(), capacity, policy);
+    allocator::initialize(&mut pool).unwrap();
+    pool
+}
+
+/// A directory for a database on disk, empty at first and removed when
+/// the test is done with it.
+pub struct Directory(pub PathBuf);
+
+impl Directory {
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("omnissiah-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        Directory(path)
+    }
+
+    /// Opens the database in the directory, as after a restart.
+    pub fn open(&self) -> Executor {
+        Executor::open(&self.0, StorageOptions::default())
+            .unwrap_or_else(|error| panic!("{}: {}", self.0.display(), error))
+    }
+}
+
+impl Drop for Directory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Runs the statements of `sql` in order, stopping at the first that
+/// fails, and returns the result of the last. Syntax and bind errors are
+/// given the line and column they are near.
+pub fn run(executor: &mut Executor, sql: &str) -> Result<ResultSet, ExecutionError> {
+    let mut lexer = Lexer::new(sql);
+    let tokens = lexer.tokenize();
+    let mut parser = Parser::new(&tokens);
+    let (statements, errors) = parser.parse_script();
+    if let Some(error) = errors.into_iter().next() {
+        let error = error.locate(lexer.positions());
+        return Err(ExecutionError::InvalidQuery(error.to_string()));
+    }
+    let mut result = ResultSet::default();
+    for (statement, span) in statements.iter().zip(parser.spans()) {
+        result = match executor.execute(statement) {
+            Err(ExecutionError::Bind(error)) => {
+                let positions = &lexer.positions()[span.clone()];
+                return Err(ExecutionError::Bind(
+                    error.locate(&tokens[span.clone()], positions),
+                ));
+            }
+            result => result?,
+        };
+    }
+    Ok(result)
+}
+
+/// Runs `sql`, which has to succeed.
+pub fn execute(executor: &mut Executor, sql: &str) -> ResultSet {
+    run(executor, sql).unwrap_or_else(|error| panic!("{}: {}", sql, error))
+}
+
+/// The rows `sql` returns, each with its values joined by ` | `.
+pub fn query(executor: &mut Executor, sql: &str) -> Vec<String> {
+    execute(executor, sql)
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<String>>()
+                .join(" | ")
+        })
+        .collect()
+}
+
+/// The message of the error `sql` fails with.
+pub fn error(executor: &mut Executor, sql: &str) -> String {
+    match run(executor, sql) {
+        Ok(_) => panic!("{}: succeeded", sql),
+        Err(error) => error.to_string(),
+    }
+}
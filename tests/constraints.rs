@@ -0,0 +1,137 @@
+//! The rules a written row has to keep, on INSERT and on UPDATE.
+
+mod common;
+
+use common::{database, error, execute, query};
+use omnissiah_db::execution::executor::Executor;
+
+fn accounts() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE accounts (id INT PRIMARY, owner TEXT NOTNULL, level U_BYTE, phone TEXT);
+CREATE UNIQUE INDEX accounts_phone ON accounts (phone);
+INSERT INTO accounts(id, owner, level, phone) VALUES (1, 'Ann', 3, '555-0100');
+INSERT INTO accounts(id, owner, level) VALUES (2, 'Bo', 200);",
+    );
+    database
+}
+
+#[test]
+fn inserts_keep_not_null_and_primary_keys() {
+    let mut database = accounts();
+    assert_eq!(
+        error(&mut database, "INSERT INTO accounts(id) VALUES (3);"),
+        "null value in column \"owner\" of table \"accounts\" violates not-null constraint \"accounts_owner_not_null\""
+    );
+    assert_eq!(
+        error(&mut database, "INSERT INTO accounts(id, owner) VALUES (1, 'Cy');"),
+        "duplicate key (id) of table \"accounts\" violates primary key constraint \"accounts_pkey\""
+    );
+}
+
+#[test]
+fn inserts_keep_values_within_their_types() {
+    let mut database = accounts();
+    assert_eq!(
+        error(
+            &mut database,
+            "INSERT INTO accounts(id, owner, level) VALUES (3, 'Cy', -1);"
+        ),
+        "negative value in column \"level\" of table \"accounts\" violates unsigned type U_BYTE"
+    );
+    assert_eq!(
+        error(
+            &mut database,
+            "INSERT INTO accounts(id, owner, level) VALUES (3, 'Cy', 300);"
+        ),
+        "value in column \"level\" of table \"accounts\" is out of range for type U_BYTE"
+    );
+}
+
+#[test]
+fn unique_indexes_reject_duplicates_but_not_nulls() {
+    let mut database = accounts();
+    assert!(error(
+        &mut database,
+        "INSERT INTO accounts(id, owner, phone) VALUES (3, 'Cy', '555-0100');"
+    )
+    .contains("accounts_phone"));
+    execute(
+        &mut database,
+        "INSERT INTO accounts(id, owner) VALUES (3, 'Cy');",
+    );
+    assert_eq!(
+        query(&mut database, "SELECT COUNT(*) FROM accounts;"),
+        ["3"]
+    );
+}
+
+#[test]
+fn updates_are_checked_as_inserts_are() {
+    let mut database = accounts();
+    assert!(error(
+        &mut database,
+        "UPDATE accounts SET owner = NULL WHERE id == 1;"
+    )
+    .contains("violates not-null constraint"));
+    assert!(
+        error(&mut database, "UPDATE accounts SET id = 1 WHERE id == 2;")
+            .contains("violates primary key constraint")
+    );
+    assert!(error(&mut database, "UPDATE accounts SET level = 256;")
+        .contains("is out of range for type U_BYTE"));
+    assert!(error(
+        &mut database,
+        "UPDATE accounts SET phone = '555-0100' WHERE id == 2;"
+    )
+    .contains("accounts_phone"));
+    // A failed statement changes nothing.
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT id, owner, level FROM accounts ORDER BY id;"
+        ),
+        ["1 | Ann | 3", "2 | Bo | 200"]
+    );
+}
+
+#[test]
+fn updates_may_keep_or_move_a_key() {
+    let mut database = accounts();
+    let updated = execute(
+        &mut database,
+        "UPDATE accounts SET phone = '555-0100', level = 4 WHERE id == 1;",
+    );
+    assert_eq!(updated.affected, 1);
+    execute(&mut database, "UPDATE accounts SET id = 7 WHERE id == 2;");
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT id, owner, level FROM accounts ORDER BY id;"
+        ),
+        ["1 | Ann | 4", "7 | Bo | 200"]
+    );
+    assert_eq!(
+        query(&mut database, "SELECT owner FROM accounts WHERE id == 7;"),
+        ["Bo"]
+    );
+}
+
+#[test]
+fn deleted_rows_free_their_keys() {
+    let mut database = accounts();
+    let deleted = execute(&mut database, "DELETE FROM accounts WHERE id == 1;");
+    assert_eq!(deleted.affected, 1);
+    execute(
+        &mut database,
+        "INSERT INTO accounts(id, owner, phone) VALUES (1, 'Cy', '555-0100');",
+    );
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT owner FROM accounts WHERE phone == '555-0100';"
+        ),
+        ["Cy"]
+    );
+}
@@ -0,0 +1,296 @@
+//! How conditions and expressions parse and evaluate.
+
+mod common;
+
+use common::{database, error, execute, query, run};
+use omnissiah_db::execution::executor::Executor;
+
+fn points() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE points (id INT PRIMARY, x INT, y INT, label TEXT);
+INSERT INTO points(id, x, y, label) VALUES (1, 1, 1, 'a');
+INSERT INTO points(id, x, y, label) VALUES (2, 1, 2, 'b');
+INSERT INTO points(id, x, y) VALUES (3, 2, 1);
+INSERT INTO points(id, x, y) VALUES (4, 2, 2);
+INSERT INTO points(id, y, label) VALUES (5, 9, 'e');",
+    );
+    database
+}
+
+/// A table of one row, for evaluating expressions over constants.
+fn one_row() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE one (id INT PRIMARY);
+INSERT INTO one(id) VALUES (1);",
+    );
+    database
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    let mut database = points();
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("SELECT id FROM points WHERE x == 1 OR x == 2 AND y == 2 ORDER BY id;"),
+        ["1", "2", "4"]
+    );
+    assert_eq!(
+        run("SELECT id FROM points WHERE y == 2 AND x == 2 OR x == 1 ORDER BY id;"),
+        ["1", "2", "4"]
+    );
+    assert_eq!(
+        run("SELECT id FROM points WHERE (x == 1 OR x == 2) AND y == 2 ORDER BY id;"),
+        ["2", "4"]
+    );
+}
+
+#[test]
+fn not_negates_a_condition() {
+    let mut database = points();
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("SELECT id FROM points WHERE NOT x == 1 ORDER BY id;"),
+        ["3", "4"]
+    );
+    assert_eq!(
+        run("SELECT id FROM points WHERE NOT (x == 1 OR y == 1) ORDER BY id;"),
+        ["4"]
+    );
+    // NOT binds tighter than AND, and NOT of UNKNOWN stays UNKNOWN.
+    assert_eq!(
+        run("SELECT id FROM points WHERE NOT x == 2 AND y == 1 ORDER BY id;"),
+        ["1"]
+    );
+    assert_eq!(
+        run("SELECT id, NOT x == 1 FROM points ORDER BY id;"),
+        ["1 | FALSE", "2 | FALSE", "3 | TRUE", "4 | TRUE", "5 | NULL"]
+    );
+    assert!(
+        error(&mut database, "SELECT id FROM points WHERE NOT label;")
+            .contains("argument of NOT must be BOOL, not TEXT")
+    );
+}
+
+#[test]
+fn is_null_tests_for_missing_values() {
+    let mut database = points();
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("SELECT id FROM points WHERE label IS NULL ORDER BY id;"),
+        ["3", "4"]
+    );
+    assert_eq!(
+        run("SELECT id FROM points WHERE label IS NOT NULL AND x IS NOT NULL ORDER BY id;"),
+        ["1", "2"]
+    );
+    assert_eq!(
+        run("SELECT id FROM points WHERE NOT x IS NULL AND y == 1 ORDER BY id;"),
+        ["1", "3"]
+    );
+    assert_eq!(
+        run("SELECT id, x == 1 IS NULL FROM points ORDER BY id;"),
+        [
+            "1 | FALSE",
+            "2 | FALSE",
+            "3 | FALSE",
+            "4 | FALSE",
+            "5 | TRUE"
+        ]
+    );
+    assert_eq!(
+        run("SELECT COUNT(*) FROM points GROUP BY label IS NULL ORDER BY 1;"),
+        ["2", "3"]
+    );
+}
+
+#[test]
+fn function_calls_are_checked_against_signatures() {
+    let mut database = points();
+    let mut fails = |sql: &str| error(&mut database, sql);
+    assert!(fails("SELECT UPPER(label, label) FROM points;")
+        .contains("UPPER does not take 2 arguments"));
+    assert!(fails("SELECT UPPER(x) FROM points;").contains("argument 1 of UPPER must be TEXT"));
+    assert!(fails("SELECT ABS(label) FROM points;").contains("argument 1 of ABS must be numeric"));
+    assert!(fails("SELECT FROBNICATE(x) FROM points;").contains("FROBNICATE"));
+}
+
+#[test]
+fn a_space_before_the_arguments_changes_nothing() {
+    let mut database = points();
+    execute(
+        &mut database,
+        "CREATE INDEX tight ON points (UPPER(label));
+CREATE INDEX spaced ON points (UPPER (label));",
+    );
+    let tight = run(&mut database, "SELECT UPPER(label), ABS(x) FROM points;").unwrap();
+    let spaced = run(
+        &mut database,
+        "SELECT UPPER (label), ABS ( x ) FROM points;",
+    )
+    .unwrap();
+    assert_eq!(tight.columns, ["upper", "abs"]);
+    assert_eq!(spaced.columns, tight.columns);
+    assert_eq!(spaced.rows, tight.rows);
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT index_name, column_names FROM information_schema.indexes WHERE table_name == 'points' AND index_name != 'points_pkey' ORDER BY index_name;"
+        ),
+        ["spaced | UPPER(label)", "tight | UPPER(label)"]
+    );
+}
+
+#[test]
+fn keyword_call_forms() {
+    let mut database = one_row();
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("SELECT SUBSTRING('database' FROM 3 FOR 4) FROM one;"),
+        ["taba"]
+    );
+    assert_eq!(
+        run("SELECT SUBSTRING('database' FROM 5) FROM one;"),
+        ["base"]
+    );
+    assert_eq!(run("SELECT POSITION('ta' IN 'database') FROM one;"), ["3"]);
+    assert_eq!(
+        run("SELECT TRIM(LEADING 'x' FROM 'xxaxx') FROM one;"),
+        ["axx"]
+    );
+    assert_eq!(
+        run("SELECT TRIM(TRAILING 'x' FROM 'xxaxx') FROM one;"),
+        ["xxa"]
+    );
+    assert_eq!(run("SELECT TRIM(BOTH 'x' FROM 'xxaxx') FROM one;"), ["a"]);
+    assert_eq!(run("SELECT TRIM('  a  ') FROM one;"), ["a"]);
+    assert_eq!(
+        run("SELECT EXTRACT(YEAR FROM '2024-03-05 10:20:30'), EXTRACT(MONTH FROM '2024-03-05 10:20:30') FROM one;"),
+        ["2024 | 3"]
+    );
+}
+
+#[test]
+fn string_functions_count_characters() {
+    let mut database = one_row();
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("SELECT LENGTH('héllo'), CHAR_LENGTH('日本語') FROM one;"),
+        ["5 | 3"]
+    );
+    assert_eq!(
+        run("SELECT UPPER('straße'), LOWER('ÀÉÎ') FROM one;"),
+        ["STRASSE | àéî"]
+    );
+    assert_eq!(
+        run("SELECT SUBSTRING('日本語テキスト', 3, 2) FROM one;"),
+        ["語テ"]
+    );
+    assert_eq!(run("SELECT POSITION('語' IN '日本語') FROM one;"), ["3"]);
+    assert_eq!(
+        run("SELECT REPLACE('añoaño', 'ñ', 'n') FROM one;"),
+        ["anoano"]
+    );
+}
+
+#[test]
+fn arithmetic_functions_reject_undefined_results() {
+    let mut database = one_row();
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT MOD(7, 3), MOD(-7, 3), POWER(2, 10), POWER(-2, 3) FROM one;"
+        ),
+        ["1 | -1 | 1024 | -8"]
+    );
+    assert_eq!(
+        error(&mut database, "SELECT MOD(7, 0) FROM one;"),
+        "division by zero"
+    );
+    assert_eq!(
+        error(&mut database, "SELECT MOD(7.5, 0.0) FROM one;"),
+        "division by zero"
+    );
+    assert!(error(&mut database, "SELECT POWER(-8, 0.5) FROM one;")
+        .contains("of a negative base needs an integer exponent"));
+    assert_eq!(
+        error(&mut database, "SELECT POWER(10, 400) FROM one;"),
+        "overflow: POWER(10, 400)"
+    );
+    assert_eq!(
+        error(&mut database, "SELECT POWER(0, -1) FROM one;"),
+        "overflow: POWER(0, -1)"
+    );
+}
+
+#[test]
+fn simple_and_searched_case() {
+    let mut database = points();
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("SELECT id, CASE x WHEN 1 THEN 'one' WHEN 2 THEN 'two' ELSE 'none' END FROM points ORDER BY id;"),
+        ["1 | one", "2 | one", "3 | two", "4 | two", "5 | none"]
+    );
+    assert_eq!(
+        run("SELECT id, CASE WHEN x IS NULL THEN 'missing' WHEN x < y THEN 'below' WHEN x == y THEN 'on' END FROM points ORDER BY id;"),
+        ["1 | on", "2 | below", "3 | NULL", "4 | on", "5 | missing"]
+    );
+    // A simple CASE compares with ==, which is never TRUE for NULL.
+    assert_eq!(
+        run("SELECT id, CASE label WHEN NULL THEN 'null' ELSE 'other' END FROM points WHERE id == 3;"),
+        ["3 | other"]
+    );
+    assert!(error(
+        &mut database,
+        "SELECT CASE WHEN x == 1 THEN 1 ELSE 'one' END FROM points;"
+    )
+    .contains("cannot combine INT with TEXT"));
+}
+
+#[test]
+fn casts_convert_between_types() {
+    let mut database = one_row();
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("SELECT CAST('42' AS INT), '2.5'::DOUBLE, 3.6::INT, CAST(1 AS BOOL) FROM one;"),
+        ["42 | 2.5 | 4 | TRUE"]
+    );
+    assert_eq!(
+        run("SELECT 12345::TEXT, ('7'::INT)::TEXT FROM one;"),
+        ["12345 | 7"]
+    );
+}
+
+#[test]
+fn casts_check_the_range_of_the_target() {
+    let mut database = one_row();
+    let mut fails = |sql: &str| error(&mut database, sql);
+    assert!(fails("SELECT -1::U_INT FROM one;").contains("-1 is out of range for U_INT"));
+    assert!(fails("SELECT 256::U_BYTE FROM one;").contains("256 is out of range for U_BYTE"));
+    assert!(fails("SELECT 128::BYTE FROM one;").contains("128 is out of range for BYTE"));
+    assert!(fails("SELECT 40000::SHORT FROM one;").contains("40000 is out of range for SHORT"));
+    assert!(fails("SELECT 4294967296::U_INT FROM one;")
+        .contains("4294967296 is out of range for U_INT"));
+    assert!(fails("SELECT 'abc'::INT FROM one;").contains("'abc' is not a valid INT"));
+    let mut database = one_row();
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT 255::U_BYTE, -128::BYTE, 65535::U_SHORT FROM one;"
+        ),
+        ["255 | -128 | 65535"]
+    );
+}
+
+#[test]
+fn non_finite_floats_do_not_cast_to_integers() {
+    let mut database = one_row();
+    let mut fails = |sql: &str| error(&mut database, sql);
+    assert!(fails("SELECT 'NaN'::DOUBLE::INT FROM one;").contains("out of range for INT"));
+    assert!(fails("SELECT 'inf'::DOUBLE::LONG FROM one;").contains("out of range for LONG"));
+    assert!(fails("SELECT '-inf'::DOUBLE::BIGINT FROM one;").contains("out of range for BIGINT"));
+    assert!(fails("SELECT 1e300::FLOAT FROM one;").contains("out of range for FLOAT"));
+}
@@ -0,0 +1,163 @@
+//! The extendible hash index: directory growth, overflow chains for keys
+//! a split cannot separate, deletes, and lookups after a restart.
+
+mod common;
+
+use std::io;
+
+use common::{execute, pool, query, Directory};
+use omnissiah_db::storage::buffer::{BufferPool, EvictionPolicy};
+use omnissiah_db::storage::hash::HashIndex;
+use omnissiah_db::storage::page::RecordId;
+
+fn id(number: u32) -> RecordId {
+    RecordId {
+        page: number / 100 + 1,
+        slot: (number % 100) as u16,
+    }
+}
+
+/// A key of 2000 bytes, so a bucket page holds four.
+fn key(number: u32) -> Vec<u8> {
+    let mut key = number.to_be_bytes().to_vec();
+    key.resize(2000, b'.');
+    key
+}
+
+fn index(pool: &mut BufferPool, count: u32) -> io::Result<HashIndex> {
+    let index = HashIndex::create(pool)?;
+    for number in 0..count {
+        assert!(index.insert(pool, &key(number), id(number), true)?);
+    }
+    Ok(index)
+}
+
+#[test]
+fn the_directory_grows_past_one_page() {
+    let mut pool = pool(16, EvictionPolicy::Lru);
+    let index = index(&mut pool, 5000).unwrap();
+    // Distinct keys never overflow, so every page but the header, the
+    // directory and its pages is a bucket, and more than 2^10 buckets
+    // take a directory deeper than fits on the root.
+    assert!(pool.page_count() > 1100, "{} pages", pool.page_count());
+    for number in 0..5000 {
+        assert_eq!(index.get(&mut pool, &key(number)).unwrap(), [id(number)]);
+    }
+    assert!(index.get(&mut pool, &key(5000)).unwrap().is_empty());
+    assert!(!index.insert(&mut pool, &key(17), id(1), true).unwrap());
+}
+
+#[test]
+fn duplicate_keys_go_to_overflow_pages() {
+    let mut pool = pool(16, EvictionPolicy::Clock);
+    let index = index(&mut pool, 40).unwrap();
+    // Four copies fill a page; a split cannot separate the rest, so they
+    // chain pages to the bucket, which the other keys still share.
+    for number in 100..400 {
+        assert!(index.insert(&mut pool, &key(7), id(number), false).unwrap());
+    }
+    let mut copies = index.get(&mut pool, &key(7)).unwrap();
+    copies.sort();
+    assert_eq!(
+        copies,
+        [7].into_iter().chain(100..400).map(id).collect::<Vec<_>>()
+    );
+    for number in (0..40).filter(|number| *number != 7) {
+        assert_eq!(index.get(&mut pool, &key(number)).unwrap(), [id(number)]);
+    }
+    // Keys added after the chain grew still find a place.
+    for number in 40..200 {
+        assert!(index
+            .insert(&mut pool, &key(number), id(number), true)
+            .unwrap());
+    }
+    for number in 40..200 {
+        assert_eq!(index.get(&mut pool, &key(number)).unwrap(), [id(number)]);
+    }
+    assert_eq!(index.get(&mut pool, &key(7)).unwrap().len(), 301);
+    assert!(!index.insert(&mut pool, &key(7), id(1), true).unwrap());
+}
+
+#[test]
+fn delete_removes_one_entry_of_a_key() {
+    let mut pool = pool(16, EvictionPolicy::Lru);
+    let index = index(&mut pool, 500).unwrap();
+    for number in 0..20 {
+        assert!(index
+            .insert(&mut pool, &key(3), id(1000 + number), false)
+            .unwrap());
+    }
+    assert!(index.delete(&mut pool, &key(3), id(1005)).unwrap());
+    assert!(!index.delete(&mut pool, &key(3), id(1005)).unwrap());
+    assert!(index.delete(&mut pool, &key(3), id(3)).unwrap());
+    let mut rest = index.get(&mut pool, &key(3)).unwrap();
+    rest.sort();
+    assert_eq!(
+        rest,
+        (1000..1020)
+            .filter(|number| *number != 1005)
+            .map(id)
+            .collect::<Vec<_>>()
+    );
+    for number in (0..500).step_by(2) {
+        assert!(index.delete(&mut pool, &key(number), id(number)).unwrap());
+    }
+    assert!(!index.delete(&mut pool, &key(500), id(500)).unwrap());
+    assert!(index.get(&mut pool, &key(10)).unwrap().is_empty());
+    assert_eq!(index.get(&mut pool, &key(11)).unwrap(), [id(11)]);
+    // A deleted key can come back.
+    assert!(index.insert(&mut pool, &key(10), id(10), true).unwrap());
+    assert_eq!(index.get(&mut pool, &key(10)).unwrap(), [id(10)]);
+}
+
+#[test]
+fn rows_are_found_through_the_index_after_a_restart() {
+    let directory = Directory::new("hash");
+    {
+        let mut database = directory.open();
+        execute(
+            &mut database,
+            "CREATE TABLE tags (id INT PRIMARY, name TEXT NOTNULL, color INT);
+CREATE UNIQUE INDEX tag_names ON tags USING HASH (name);
+CREATE INDEX tag_colors ON tags USING HASH (color);",
+        );
+        for number in 0..600 {
+            execute(
+                &mut database,
+                &format!(
+                    "INSERT INTO tags(id, name, color) VALUES ({}, 'tag {}', {});",
+                    number,
+                    number,
+                    number % 7
+                ),
+            );
+        }
+        execute(&mut database, "DELETE FROM tags WHERE color == 6;");
+    }
+    let mut database = directory.open();
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT id, color FROM tags WHERE name == 'tag 423';"
+        ),
+        ["423 | 3"]
+    );
+    assert!(query(
+        &mut database,
+        "SELECT id FROM tags WHERE name == 'tag 426';"
+    )
+    .is_empty());
+    assert_eq!(
+        query(&mut database, "SELECT COUNT(*) FROM tags WHERE color == 2;"),
+        ["86"]
+    );
+    assert!(common::error(
+        &mut database,
+        "INSERT INTO tags(id, name, color) VALUES (1000, 'tag 5', 1);"
+    )
+    .contains("tag_names"));
+    execute(
+        &mut database,
+        "INSERT INTO tags(id, name, color) VALUES (1000, 'tag 6', 1);",
+    );
+}
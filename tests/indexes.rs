@@ -0,0 +1,159 @@
+//! Partial and expression indexes: what they keep unique, also when built
+//! over rows already there, and which WHERE clauses the planner reads
+//! them for.
+
+mod common;
+
+use common::{database, error, execute, query};
+use omnissiah_db::compiler::parser::parse_expression;
+use omnissiah_db::execution::access::{choose_access_path, AccessPath, BoundIndex};
+use omnissiah_db::execution::executor::Executor;
+use omnissiah_db::execution::planner::conjuncts;
+
+fn users() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE users (id INT PRIMARY, email TEXT NOTNULL, deleted BOOL NOTNULL, score INT);
+CREATE UNIQUE INDEX live_emails ON users (LOWER(email)) WHERE deleted == FALSE;
+CREATE INDEX high_scores ON users (score) WHERE score > 10;
+INSERT INTO users(id, email, deleted, score) VALUES (1, 'Ann@example.org', FALSE, 5);
+INSERT INTO users(id, email, deleted, score) VALUES (2, 'bo@example.org', TRUE, 20);",
+    );
+    database
+}
+
+/// The index the planner reads `users` through for `condition`, if any.
+fn chosen_index(database: &Executor, condition: &str) -> Option<String> {
+    let catalog = database.catalog();
+    let table = catalog.table("users").unwrap();
+    let indexes = table
+        .indexes
+        .iter()
+        .map(|index| BoundIndex::bind(&catalog, table, index))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let condition = parse_expression(condition).unwrap();
+    let path = choose_access_path(&indexes, "users", &conjuncts(&condition), 0).unwrap();
+    match path {
+        AccessPath::SeqScan => None,
+        AccessPath::IndexLookup { index, .. } => Some(index.name),
+        AccessPath::IndexRange { index, .. } => table
+            .indexes
+            .iter()
+            .find(|candidate| candidate.root == Some(index))
+            .map(|index| index.name.clone()),
+    }
+}
+
+#[test]
+fn a_partial_expression_index_is_unique_among_the_rows_it_keeps() {
+    let mut database = users();
+    assert_eq!(
+        error(
+            &mut database,
+            "INSERT INTO users(id, email, deleted) VALUES (3, 'ANN@example.org', FALSE);"
+        ),
+        "duplicate key (LOWER(email)) of table \"users\" violates unique constraint \"live_emails\""
+    );
+    execute(
+        &mut database,
+        "INSERT INTO users(id, email, deleted) VALUES (3, 'ANN@example.org', TRUE);
+INSERT INTO users(id, email, deleted) VALUES (4, 'ann@EXAMPLE.org', TRUE);
+INSERT INTO users(id, email, deleted) VALUES (5, 'Bo@Example.org', FALSE);",
+    );
+    assert!(error(
+        &mut database,
+        "UPDATE users SET deleted = FALSE WHERE id == 3;"
+    )
+    .contains("live_emails"));
+    // Once the live row is deleted, a deleted one may come back.
+    execute(
+        &mut database,
+        "UPDATE users SET deleted = TRUE WHERE id == 1;
+UPDATE users SET deleted = FALSE WHERE id == 4;",
+    );
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT id FROM users WHERE LOWER(email) == 'ann@example.org' AND deleted == FALSE;"
+        ),
+        ["4"]
+    );
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT id FROM users WHERE LOWER(email) == 'ann@example.org' ORDER BY id;"
+        ),
+        ["1", "3", "4"]
+    );
+}
+
+#[test]
+fn a_partial_index_is_read_only_where_the_conditions_imply_its_predicate() {
+    let database = users();
+    let live = Some("live_emails".to_string());
+    let high = Some("high_scores".to_string());
+    let used = |condition| chosen_index(&database, condition);
+    assert_eq!(
+        used("LOWER(email) == 'ann@example.org' AND deleted == FALSE"),
+        live
+    );
+    assert_eq!(
+        used("deleted == FALSE AND score < 3 AND LOWER(email) == 'ann@example.org'"),
+        live
+    );
+    assert_eq!(used("LOWER(email) == 'ann@example.org'"), None);
+    assert_eq!(
+        used("LOWER(email) == 'ann@example.org' AND deleted == TRUE"),
+        None
+    );
+    assert_eq!(
+        used("LOWER(email) == 'ann@example.org' OR deleted == FALSE"),
+        None
+    );
+    // The key must be written as the index wrote it.
+    assert_eq!(
+        used("email == 'ann@example.org' AND deleted == FALSE"),
+        None
+    );
+    assert_eq!(used("score == 20"), high);
+    assert_eq!(used("score > 50"), high);
+    assert_eq!(used("score >= 11 AND score < 30"), high);
+    assert_eq!(used("score > 5"), None);
+    assert_eq!(used("score == 10"), None);
+    assert_eq!(
+        used("id == 1 AND score == 20"),
+        Some("users_pkey".to_string())
+    );
+}
+
+#[test]
+fn a_unique_index_built_over_rows_finds_the_duplicates_that_count() {
+    let mut database = users();
+    execute(
+        &mut database,
+        "INSERT INTO users(id, email, deleted, score) VALUES (3, 'cy@example.org', FALSE, 5);",
+    );
+    assert_eq!(
+        error(
+            &mut database,
+            "CREATE UNIQUE INDEX scores ON users (score);"
+        ),
+        "duplicate key value violates unique index \"scores\""
+    );
+    // Once the second row with a score of 5 is deleted, its version no
+    // longer counts.
+    execute(
+        &mut database,
+        "DELETE FROM users WHERE id == 3;
+CREATE UNIQUE INDEX scores ON users (score);",
+    );
+    assert_eq!(
+        error(
+            &mut database,
+            "INSERT INTO users(id, email, deleted, score) VALUES (5, 'ed@example.org', FALSE, 5);"
+        ),
+        "duplicate key (score) of table \"users\" violates unique constraint \"scores\""
+    );
+}
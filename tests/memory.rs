@@ -0,0 +1,183 @@
+//! How much memory queries take over a table far larger than the buffer
+//! pool: a scan holds a page of it at a time, an aggregate or DISTINCT no
+//! more than its groups, even those of a DISTINCT call, and a sort no more
+//! than its work memory.
+//!
+//! Every allocation of this test binary is counted, so its tests measure
+//! one at a time, each holding [`MEASURING`].
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use common::{execute, query, Directory};
+use omnissiah_db::execution::executor::Executor;
+use omnissiah_db::storage::engine::StorageOptions;
+use omnissiah_db::storage::wal::SyncPolicy;
+
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = unsafe { System.alloc(layout) };
+        if !pointer.is_null() {
+            let now = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(pointer, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+static MEASURING: Mutex<()> = Mutex::new(());
+
+fn measuring() -> MutexGuard<'static, ()> {
+    MEASURING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Runs `f`, returning what it did and the most bytes it had allocated at
+/// once beyond those allocated when it started.
+fn peak_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let start = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(start, Ordering::Relaxed);
+    let result = f();
+    (result, PEAK.load(Ordering::Relaxed).saturating_sub(start))
+}
+
+const ROWS: usize = 2_000;
+/// The characters of each of the four texts of a note.
+const TEXT: usize = 250;
+/// What the rows of `notes` take, short of their headers.
+const TABLE_BYTES: usize = ROWS * 4 * TEXT;
+
+/// A table of 2 MB of notes, each with one of ten tags and a `d` of its
+/// own, reopened in a database whose buffer pool holds half a megabyte.
+fn notes(directory: &Directory) -> Executor {
+    let options = StorageOptions {
+        sync: SyncPolicy::Never,
+        ..StorageOptions::default()
+    };
+    let mut database = Executor::open(&directory.0, options).unwrap();
+    execute(
+        &mut database,
+        "CREATE TABLE notes (id INT NOTNULL, tag INT NOTNULL, a TEXT, b TEXT, c TEXT, d TEXT);
+         BEGIN;",
+    );
+    let text = "x".repeat(TEXT);
+    for id in 0..ROWS {
+        execute(
+            &mut database,
+            &format!(
+                "INSERT INTO notes(id, tag, a, b, c, d) VALUES ({}, {}, '{2}', '{2}', '{2}', '{3}{0}');",
+                id,
+                id % 10,
+                text,
+                &text[id.to_string().len()..]
+            ),
+        );
+    }
+    execute(&mut database, "COMMIT;");
+    drop(database);
+    let options = StorageOptions {
+        buffer_pool_pages: 64,
+        ..StorageOptions::default()
+    };
+    Executor::open(&directory.0, options).unwrap()
+}
+
+#[test]
+fn grouping_holds_its_groups_not_its_input() {
+    let _measuring = measuring();
+    let directory = Directory::new("memory-grouping");
+    let mut database = notes(&directory);
+    let sql = "SELECT tag, COUNT(*), MIN(id) FROM notes GROUP BY tag ORDER BY tag;";
+    let (groups, peak) = peak_during(|| query(&mut database, sql));
+    assert_eq!(groups.len(), 10);
+    assert_eq!(groups[3], "3 | 200 | 3");
+    assert!(
+        peak < TABLE_BYTES / 8,
+        "{} bytes at once to aggregate a table of {}",
+        peak,
+        TABLE_BYTES
+    );
+    // Spilling groups writes the rows of those out to disk, not to memory.
+    database.set_work_memory(64);
+    let (spilled, peak) = peak_during(|| query(&mut database, sql));
+    assert_eq!(spilled, groups);
+    assert!(
+        peak < TABLE_BYTES / 8,
+        "{} bytes at once to aggregate a table of {} with groups spilled",
+        peak,
+        TABLE_BYTES
+    );
+    let sql = "SELECT DISTINCT tag FROM notes ORDER BY tag;";
+    let (tags, peak) = peak_during(|| query(&mut database, sql));
+    assert_eq!(tags, ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"]);
+    assert!(
+        peak < TABLE_BYTES / 8,
+        "{} bytes at once for DISTINCT over a table of {}",
+        peak,
+        TABLE_BYTES
+    );
+}
+
+#[test]
+fn distinct_on_sorts_through_runs_on_disk() {
+    let _measuring = measuring();
+    let directory = Directory::new("memory-distinct-on");
+    let mut database = notes(&directory);
+    let sql = "SELECT DISTINCT ON (tag) tag, id, a FROM notes ORDER BY tag, id DESC;";
+    let (firsts, peak) = peak_during(|| query(&mut database, sql));
+    assert_eq!(firsts.len(), 10);
+    assert!(firsts[3].starts_with("3 | 1993 | x"));
+    // Sorted in memory, the whole table is held at once.
+    assert!(peak > TABLE_BYTES, "{} bytes at once", peak);
+    // Sorted in runs of 16 kB, those of the rows are merged from disk, as
+    // are the runs merged from them once there are too many to read at once.
+    database.set_work_memory(16 << 10);
+    let (spilled, peak) = peak_during(|| query(&mut database, sql));
+    assert_eq!(spilled, firsts);
+    assert!(
+        peak < TABLE_BYTES / 8,
+        "{} bytes at once for DISTINCT ON over a table of {} with rows spilled",
+        peak,
+        TABLE_BYTES
+    );
+}
+
+#[test]
+fn distinct_calls_spill_the_groups_they_grow() {
+    let _measuring = measuring();
+    let directory = Directory::new("memory-distinct-calls");
+    let mut database = notes(&directory);
+    let sql = "SELECT tag, COUNT(DISTINCT d), COUNT(*) FROM notes GROUP BY tag ORDER BY tag;";
+    let (groups, peak) = peak_during(|| query(&mut database, sql));
+    assert_eq!(groups.len(), 10);
+    assert_eq!(groups[3], "3 | 200 | 200");
+    // By default the values seen by the DISTINCT call, a quarter of the
+    // table, are all held at once.
+    assert!(peak > TABLE_BYTES / 4, "{} bytes at once", peak);
+    // Groups whose seen values outgrow the budget are spilled as they stand
+    // and go on from there, a partition at a time.
+    database.set_work_memory(16 << 10);
+    let (spilled, peak) = peak_during(|| query(&mut database, sql));
+    assert_eq!(spilled, groups);
+    assert!(
+        peak < TABLE_BYTES / 8,
+        "{} bytes at once for COUNT(DISTINCT) over a table of {} with groups spilled",
+        peak,
+        TABLE_BYTES
+    );
+}
@@ -0,0 +1,97 @@
+//! How a script splits into statements, and where its syntax errors are
+//! reported.
+
+use omnissiah_db::compiler::lexer::Lexer;
+use omnissiah_db::compiler::parser::{Parser, SQLQuery};
+
+/// The statements of `sql`, and its errors as they are shown.
+fn parse(sql: &str) -> (Vec<SQLQuery>, Vec<String>) {
+    let mut lexer = Lexer::new(sql);
+    let tokens = lexer.tokenize();
+    let (statements, errors) = Parser::new(&tokens).parse_script();
+    let errors = errors
+        .into_iter()
+        .map(|error| error.locate(lexer.positions()).to_string())
+        .collect();
+    (statements, errors)
+}
+
+#[test]
+fn the_last_semicolon_is_optional() {
+    let statements = [
+        "SELECT name FROM cats WHERE id == 1 ORDER BY name LIMIT 2",
+        "WITH c AS (SELECT id FROM cats) SELECT id FROM c",
+        "INSERT INTO cats(id, name) VALUES (1, 'Tom')",
+        "UPDATE cats SET name = 'Kit' WHERE id == 1",
+        "DELETE FROM cats WHERE id == 1",
+        "CREATE TABLE cats (id INT PRIMARY, name TEXT NOTNULL)",
+        "CREATE DATABASE zoo",
+        "CREATE UNIQUE INDEX names ON cats (LOWER(name)) WHERE id > 0",
+        "CREATE SEQUENCE ids START WITH 5 INCREMENT BY 2",
+        "SHOW TABLES",
+        "DESCRIBE cats",
+        "USE zoo",
+        "BEGIN ISOLATION LEVEL SERIALIZABLE",
+        "SAVEPOINT before",
+        "ROLLBACK TO SAVEPOINT before",
+        "RELEASE before",
+        "COMMIT",
+        "LOCK TABLE cats IN SHARE MODE",
+        "ALTER TABLE cats SET (autovacuum_vacuum_threshold = 10)",
+        "VACUUM FULL cats",
+    ];
+    for statement in statements {
+        let (bare, errors) = parse(statement);
+        assert_eq!(errors, Vec::<String>::new(), "{}", statement);
+        assert_eq!(bare.len(), 1, "{}", statement);
+        let (ended, errors) = parse(&format!("{};", statement));
+        assert!(errors.is_empty(), "{};", statement);
+        assert_eq!(bare, ended, "{}", statement);
+    }
+    let (script, errors) = parse(&statements.join(";\n"));
+    assert!(errors.is_empty());
+    assert_eq!(script.len(), statements.len());
+}
+
+#[test]
+fn empty_statements_are_skipped() {
+    let (statements, errors) = parse(";; SELECT id FROM cats;;; COMMIT;");
+    assert!(errors.is_empty());
+    assert_eq!(statements.len(), 2);
+}
+
+#[test]
+fn parsing_resumes_after_a_failed_statement() {
+    let (statements, errors) = parse(
+        "SELECT id FROM cats WHERE id IN (SELECT owner FROM owners WHERE id == 1 2);
+INSERT INTO cats(id, name) VALUES (1, 'Tom' 'Kit');
+SELECT name FROM cats;",
+    );
+    assert_eq!(
+        errors,
+        [
+            "1:73: syntax error near '2'",
+            "2:45: syntax error near ''Kit''"
+        ]
+    );
+    assert_eq!(statements.len(), 1);
+    assert!(matches!(statements[0], SQLQuery::Select(_)));
+}
+
+#[test]
+fn a_missing_semicolon_fails_only_its_statement() {
+    let (statements, errors) = parse("SELECT id FROM cats\nCOMMIT;\nDELETE FROM cats;");
+    assert_eq!(errors, ["2:1: syntax error near 'COMMIT'"]);
+    assert_eq!(statements.len(), 2);
+    assert!(matches!(statements[0], SQLQuery::Transaction(_)));
+    assert!(matches!(statements[1], SQLQuery::Delete(_)));
+}
+
+#[test]
+fn errors_at_the_end_name_the_statement() {
+    let (statements, errors) = parse("COMMIT;\n  SELECT id FROM cats WHERE (id == 1");
+    assert_eq!(statements.len(), 1);
+    assert_eq!(errors, ["statement 2: unexpected end of input"]);
+    let (_, errors) = parse("SELECT id\n  FROM cats\n  WHERE id === 1;");
+    assert_eq!(errors, ["3:12: syntax error near '==='"]);
+}
@@ -0,0 +1,481 @@
+//! What SELECT returns: aggregates, DISTINCT, joins, subqueries, CTEs, set
+//! operations and window functions.
+
+mod common;
+
+use common::{database, error, execute, query};
+use omnissiah_db::execution::executor::Executor;
+
+fn readings() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE readings (id INT PRIMARY, name TEXT NOTNULL, value INT NOTNULL);
+INSERT INTO readings(id, name, value) VALUES (1, 'Jane Doe', 12);
+INSERT INTO readings(id, name, value) VALUES (2, 'Jane Doe', 4);
+INSERT INTO readings(id, name, value) VALUES (3, 'John Doe', 15);
+INSERT INTO readings(id, name, value) VALUES (4, 'Alice', 9);
+INSERT INTO readings(id, name, value) VALUES (5, 'Bob', 8);
+INSERT INTO readings(id, name, value) VALUES (6, 'Bob', 3);
+INSERT INTO readings(id, name, value) VALUES (7, 'Charlie', 13);
+INSERT INTO readings(id, name, value) VALUES (8, 'Robert', 2);
+INSERT INTO readings(id, name, value) VALUES (9, 'Bob', 11);",
+    );
+    database
+}
+
+fn owners_and_cats() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE owners (id INT PRIMARY, name TEXT NOTNULL, city TEXT);
+INSERT INTO owners(id, name, city) VALUES (1, 'Ann', 'Oslo');
+INSERT INTO owners(id, name, city) VALUES (2, 'Bo', 'Rome');
+INSERT INTO owners(id, name) VALUES (3, 'Cy');
+CREATE TABLE cats (id INT PRIMARY, name TEXT NOTNULL, owner INT NOTNULL);
+INSERT INTO cats(id, name, owner) VALUES (1, 'Tom', 1);
+INSERT INTO cats(id, name, owner) VALUES (2, 'Kit', 1);
+INSERT INTO cats(id, name, owner) VALUES (3, 'Max', 2);
+INSERT INTO cats(id, name, owner) VALUES (4, 'Zed', 9);
+CREATE TABLE cities (city TEXT PRIMARY, country TEXT NOTNULL);
+INSERT INTO cities(city, country) VALUES ('Oslo', 'Norway');
+INSERT INTO cities(city, country) VALUES ('Rome', 'Italy');",
+    );
+    database
+}
+
+fn employees() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE employees (id INT PRIMARY, name TEXT NOTNULL, manager INT);
+INSERT INTO employees(id, name) VALUES (1, 'Ada');
+INSERT INTO employees(id, name, manager) VALUES (2, 'Bob', 1);
+INSERT INTO employees(id, name, manager) VALUES (3, 'Cal', 1);
+INSERT INTO employees(id, name, manager) VALUES (4, 'Dee', 2);
+INSERT INTO employees(id, name, manager) VALUES (5, 'Eve', 9);",
+    );
+    database
+}
+
+#[test]
+fn aggregates_summarize_groups() {
+    let mut database = readings();
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT name, COUNT(*), SUM(value), MIN(value), MAX(value) FROM readings GROUP BY name HAVING COUNT(*) > 1 ORDER BY name;"
+        ),
+        ["Bob | 3 | 22 | 3 | 11", "Jane Doe | 2 | 16 | 4 | 12"]
+    );
+}
+
+#[test]
+fn aggregates_over_no_rows_give_one_row() {
+    let mut database = readings();
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT COUNT(*), SUM(value), MAX(value) FROM readings WHERE value > 100;"
+        ),
+        ["0 | NULL | NULL"]
+    );
+}
+
+#[test]
+fn aggregates_that_spill_give_the_same_groups() {
+    let sql = "SELECT name, COUNT(*), SUM(value) FROM readings GROUP BY name ORDER BY name;";
+    let expected = [
+        "Alice | 1 | 9",
+        "Bob | 3 | 22",
+        "Charlie | 1 | 13",
+        "Jane Doe | 2 | 16",
+        "John Doe | 1 | 15",
+        "Robert | 1 | 2",
+    ];
+    let mut database = readings();
+    assert_eq!(query(&mut database, sql), expected);
+    database.set_work_memory(64);
+    assert_eq!(query(&mut database, sql), expected);
+}
+
+#[test]
+fn distinct_calls_that_spill_give_the_same_groups() {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE visits (id INT PRIMARY, page TEXT NOTNULL, visitor INT NOTNULL);",
+    );
+    for id in 0..60 {
+        let page = ["home", "about", "blog"][id % 3];
+        execute(
+            &mut database,
+            &format!(
+                "INSERT INTO visits(id, page, visitor) VALUES ({}, '{}', {});",
+                id,
+                page,
+                id % 7
+            ),
+        );
+    }
+    let sql = "SELECT page, COUNT(DISTINCT visitor), SUM(DISTINCT visitor), COUNT(*), \
+               MIN(visitor), MAX(visitor), AVG(visitor) FROM visits GROUP BY page ORDER BY page;";
+    let expected = [
+        "about | 7 | 21 | 20 | 0 | 6 | 2.9",
+        "blog | 7 | 21 | 20 | 0 | 6 | 2.85",
+        "home | 7 | 21 | 20 | 0 | 6 | 2.95",
+    ];
+    assert_eq!(query(&mut database, sql), expected);
+    // Some budgets spill groups that are new, others groups that have seen
+    // a few visitors and are merged with the rest of their rows later.
+    for memory in (0..4000).step_by(100) {
+        database.set_work_memory(memory);
+        assert_eq!(query(&mut database, sql), expected, "{} bytes", memory);
+    }
+}
+
+#[test]
+fn distinct_keeps_one_of_each_row() {
+    let sql = "SELECT DISTINCT name FROM readings ORDER BY name DESC;";
+    let expected = ["Robert", "John Doe", "Jane Doe", "Charlie", "Bob", "Alice"];
+    let mut database = readings();
+    assert_eq!(query(&mut database, sql), expected);
+    database.set_work_memory(64);
+    assert_eq!(query(&mut database, sql), expected);
+    assert!(error(
+        &mut database,
+        "SELECT DISTINCT name FROM readings ORDER BY value;"
+    )
+    .contains("ORDER BY expressions must appear in select list"));
+}
+
+#[test]
+fn distinct_on_keeps_the_first_row_of_each_group() {
+    let sql = "SELECT DISTINCT ON (name) name, value FROM readings ORDER BY name, value DESC;";
+    let expected = [
+        "Alice | 9",
+        "Bob | 11",
+        "Charlie | 13",
+        "Jane Doe | 12",
+        "John Doe | 15",
+        "Robert | 2",
+    ];
+    let mut database = readings();
+    assert_eq!(query(&mut database, sql), expected);
+    // Sorting a row at a time into runs on disk keeps the same first rows.
+    database.set_work_memory(64);
+    assert_eq!(query(&mut database, sql), expected);
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT DISTINCT ON (name) name, id FROM readings WHERE name == 'Bob';"
+        ),
+        ["Bob | 5"]
+    );
+    assert!(error(
+        &mut database,
+        "SELECT DISTINCT ON (name) name, value FROM readings ORDER BY value;"
+    )
+    .contains("DISTINCT ON expressions must match initial ORDER BY expressions"));
+}
+
+#[test]
+fn ungrouped_columns_are_rejected() {
+    let mut database = readings();
+    assert!(error(
+        &mut database,
+        "SELECT name, value FROM readings GROUP BY name;"
+    )
+    .contains("must appear in the GROUP BY clause"));
+}
+
+#[test]
+fn inner_joins_pair_matching_rows() {
+    let mut database = owners_and_cats();
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT c.name, o.name FROM cats c JOIN owners o ON c.owner == o.id ORDER BY c.name;"
+        ),
+        ["Kit | Ann", "Max | Bo", "Tom | Ann"]
+    );
+}
+
+#[test]
+fn left_joins_keep_unmatched_rows() {
+    let mut database = owners_and_cats();
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT o.name, c.name FROM owners o LEFT JOIN cats c ON c.owner == o.id ORDER BY o.name, c.name;"
+        ),
+        ["Ann | Kit", "Ann | Tom", "Bo | Max", "Cy | NULL"]
+    );
+}
+
+#[test]
+fn natural_and_cross_joins() {
+    let mut database = owners_and_cats();
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT name, country FROM owners NATURAL JOIN cities ORDER BY name;"
+        ),
+        ["Ann | Norway", "Bo | Italy"]
+    );
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT COUNT(*) FROM owners CROSS JOIN cats;"
+        ),
+        ["12"]
+    );
+}
+
+#[test]
+fn in_and_exists_subqueries_filter_rows() {
+    let mut database = owners_and_cats();
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("SELECT name FROM owners WHERE id IN (SELECT owner FROM cats) ORDER BY name;"),
+        ["Ann", "Bo"]
+    );
+    assert_eq!(
+        run("SELECT name FROM owners WHERE id NOT IN (SELECT owner FROM cats) ORDER BY name;"),
+        ["Cy"]
+    );
+    assert_eq!(
+        run("SELECT name FROM owners o WHERE EXISTS (SELECT id FROM cats c WHERE c.owner == o.id AND c.name != 'Max') ORDER BY name;"),
+        ["Ann"]
+    );
+    assert_eq!(
+        run("SELECT name FROM owners o WHERE NOT EXISTS (SELECT id FROM cats c WHERE c.owner == o.id) ORDER BY name;"),
+        ["Cy"]
+    );
+}
+
+#[test]
+fn not_in_a_subquery_holding_null_matches_nothing() {
+    let mut database = owners_and_cats();
+    // Cy has no city, so no city is certainly outside the list.
+    assert!(query(
+        &mut database,
+        "SELECT city FROM cities WHERE city NOT IN (SELECT city FROM owners);"
+    )
+    .is_empty());
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT city FROM cities WHERE city IN (SELECT city FROM owners) ORDER BY city;"
+        ),
+        ["Oslo", "Rome"]
+    );
+}
+
+#[test]
+fn scalar_subqueries_return_at_most_one_row() {
+    let mut database = owners_and_cats();
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT name, (SELECT COUNT(*) FROM cats c WHERE c.owner == o.id) FROM owners o ORDER BY name;"
+        ),
+        ["Ann | 2", "Bo | 1", "Cy | 0"]
+    );
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT name FROM owners WHERE id == (SELECT owner FROM cats WHERE name == 'Max');"
+        ),
+        ["Bo"]
+    );
+    assert_eq!(
+        error(
+            &mut database,
+            "SELECT name FROM owners WHERE id == (SELECT owner FROM cats);"
+        ),
+        "more than one row returned by a subquery used as an expression"
+    );
+}
+
+#[test]
+fn derived_tables_are_read_like_tables() {
+    let mut database = owners_and_cats();
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT o.name, counts.cats FROM owners o JOIN (SELECT owner, COUNT(*) AS cats FROM cats GROUP BY owner) counts ON counts.owner == o.id ORDER BY o.name;"
+        ),
+        ["Ann | 2", "Bo | 1"]
+    );
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT name FROM (SELECT name, owner FROM cats WHERE owner == 1) mine ORDER BY name;"
+        ),
+        ["Kit", "Tom"]
+    );
+}
+
+#[test]
+fn recursive_ctes_walk_a_hierarchy() {
+    let mut database = employees();
+    assert_eq!(
+        query(
+            &mut database,
+            "WITH RECURSIVE team (id, name) AS (SELECT id, name FROM employees WHERE id == 2 UNION SELECT e.id, e.name FROM employees e JOIN team t ON e.manager == t.id) SELECT name FROM team ORDER BY name;"
+        ),
+        ["Bob", "Dee"]
+    );
+}
+
+#[test]
+fn recursive_ctes_over_a_cycle_end_or_fail() {
+    let mut database = employees();
+    // Ada now reports to Dee, who reports to Bob, who reports to Ada.
+    execute(
+        &mut database,
+        "UPDATE employees SET manager = 4 WHERE id == 1;",
+    );
+    let walk = |union: &str| {
+        format!(
+            "WITH RECURSIVE team (id, name) AS (SELECT id, name FROM employees WHERE id == 1 {} SELECT e.id, e.name FROM employees e JOIN team t ON e.manager == t.id) SELECT name FROM team ORDER BY name;",
+            union
+        )
+    };
+    assert_eq!(
+        query(&mut database, &walk("UNION")),
+        ["Ada", "Bob", "Cal", "Dee"]
+    );
+    assert_eq!(
+        error(&mut database, &walk("UNION ALL")),
+        "recursive query \"team\" cycles: round 3 gives the rows of round 0 again"
+    );
+}
+
+#[test]
+fn recursive_ctes_take_as_many_rounds_as_the_session_allows() {
+    let mut database = employees();
+    let sql = "WITH RECURSIVE team (id) AS (SELECT id FROM employees WHERE id == 1 UNION ALL SELECT e.id FROM employees e JOIN team t ON e.manager == t.id) SELECT COUNT(*) FROM team;";
+    assert_eq!(query(&mut database, sql), ["4"]);
+    // The third round finds no one below Dee.
+    database.set_recursion_limit(2);
+    assert_eq!(
+        error(&mut database, sql),
+        "recursive query \"team\" did not finish within 2 iterations"
+    );
+    database.set_recursion_limit(3);
+    assert_eq!(query(&mut database, sql), ["4"]);
+}
+
+#[test]
+fn ctes_are_read_like_tables() {
+    let mut database = employees();
+    assert_eq!(
+        query(
+            &mut database,
+            "WITH bosses AS (SELECT manager AS id FROM employees) SELECT name FROM employees WHERE id IN (SELECT id FROM bosses) ORDER BY name;"
+        ),
+        ["Ada", "Bob"]
+    );
+}
+
+#[test]
+fn invalid_ctes_are_rejected() {
+    let mut database = employees();
+    assert!(error(
+        &mut database,
+        "WITH x AS (SELECT id FROM employees), x AS (SELECT id FROM employees) SELECT * FROM x;"
+    )
+    .contains("WITH query name \"x\" specified more than once"));
+    assert!(error(
+        &mut database,
+        "WITH RECURSIVE r (n) AS (SELECT id FROM employees UNION SELECT COUNT(*) FROM r) SELECT * FROM r;"
+    )
+    .contains("COUNT is not allowed in the recursive term of \"r\""));
+}
+
+#[test]
+fn set_operations_combine_rows() {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE a (name TEXT);
+INSERT INTO a(name) VALUES ('x');
+INSERT INTO a(name) VALUES ('y');
+INSERT INTO a(name) VALUES ('y');
+INSERT INTO a(name) VALUES ('z');
+CREATE TABLE b (name TEXT);
+INSERT INTO b(name) VALUES ('y');
+INSERT INTO b(name) VALUES ('z');
+INSERT INTO b(name) VALUES ('w');",
+    );
+    let mut run = |sql: &str| query(&mut database, sql);
+    assert_eq!(
+        run("SELECT name FROM a UNION SELECT name FROM b ORDER BY name;"),
+        ["w", "x", "y", "z"]
+    );
+    assert_eq!(
+        run("SELECT name FROM a UNION ALL SELECT name FROM b ORDER BY name;"),
+        ["w", "x", "y", "y", "y", "z", "z"]
+    );
+    assert_eq!(
+        run("SELECT name FROM a EXCEPT SELECT name FROM b ORDER BY name;"),
+        ["x"]
+    );
+    assert_eq!(
+        run("SELECT name FROM a INTERSECT SELECT name FROM b ORDER BY name;"),
+        ["y", "z"]
+    );
+    assert_eq!(
+        run("SELECT name FROM a UNION SELECT name FROM b ORDER BY name LIMIT 2 OFFSET 1;"),
+        ["x", "y"]
+    );
+}
+
+#[test]
+fn mismatched_set_operations_are_rejected() {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE a (name TEXT, size INT);
+INSERT INTO a(name, size) VALUES ('x', 1);",
+    );
+    assert!(error(
+        &mut database,
+        "SELECT name FROM a UNION SELECT name, size FROM a;"
+    )
+    .contains("each UNION query must have the same number of columns"));
+    assert!(error(
+        &mut database,
+        "SELECT name FROM a EXCEPT SELECT size FROM a;"
+    )
+    .contains("EXCEPT types TEXT and INT cannot be matched"));
+}
+
+#[test]
+fn window_functions_rank_and_look_back() {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE results (id INT PRIMARY, player TEXT NOTNULL, team TEXT NOTNULL, score INT NOTNULL, played INT NOTNULL);
+INSERT INTO results(id, player, team, score, played) VALUES (1, 'Ann', 'red', 30, 1);
+INSERT INTO results(id, player, team, score, played) VALUES (2, 'Ben', 'red', 45, 2);
+INSERT INTO results(id, player, team, score, played) VALUES (3, 'Cid', 'red', 45, 3);
+INSERT INTO results(id, player, team, score, played) VALUES (4, 'Dan', 'blue', 20, 1);
+INSERT INTO results(id, player, team, score, played) VALUES (5, 'Eva', 'blue', 50, 2);",
+    );
+    assert_eq!(
+        query(
+            &mut database,
+            "SELECT player, RANK() OVER w, DENSE_RANK() OVER w, LAG(score, 1, 0) OVER (PARTITION BY team ORDER BY played), SUM(score) OVER (PARTITION BY team ORDER BY played ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) FROM results WINDOW w AS (PARTITION BY team ORDER BY score DESC) ORDER BY player;"
+        ),
+        [
+            "Ann | 3 | 2 | 0 | 30",
+            "Ben | 1 | 1 | 30 | 75",
+            "Cid | 1 | 1 | 45 | 90",
+            "Dan | 2 | 2 | 0 | 20",
+            "Eva | 1 | 1 | 20 | 70",
+        ]
+    );
+}
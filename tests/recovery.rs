@@ -0,0 +1,348 @@
+//! A harness that kills the database at random points and checks what
+//! recovery brings back.
+//!
+//! `recovery_after_crashes` runs rounds of a worker, the `crash_worker`
+//! test of this same binary started with the `CRASH_WORKER_*` variables
+//! set, that inserts rows into a table with a primary key and a hash
+//! index, one statement at a time, printing the id of each row once its
+//! statement has returned. `CRASH_ROUNDS` and `CRASH_SEED` override the
+//! number of rounds and the seed.
+//! Every so often it first inserts the row in a transaction it rolls back
+//! and runs `VACUUM FULL`, which removes that version and moves rows. The
+//! harness kills the worker after a random delay, or has it tear a page
+//! write in half and abort, then opens the database, which recovers it,
+//! and checks that:
+//!
+//! - every row the worker printed is there, once and whole;
+//! - no row is there that the worker did not print, except the one whose
+//!   commit the kill may have beaten the printing of;
+//! - both indexes find every row, so no statement is left half done.
+//!
+//! `commits_are_seen_once_durable` cuts the power, as it were, while a
+//! commit waits for its group to be synced, by copying the database and
+//! dropping what of the log was not synced, and checks that no session
+//! saw the rows that recovery then does not bring back.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use omnissiah_db::execution::error::ExecutionError;
+use omnissiah_db::execution::executor::Executor;
+use omnissiah_db::storage::disk::tear_page_write;
+use omnissiah_db::storage::engine::{StorageOptions, LOG_FILE};
+use omnissiah_db::storage::wal::SyncPolicy;
+
+mod common;
+
+use common::{query, run, Directory};
+
+const TABLE: &str = "accounts";
+const OWNER_INDEX: &str = "accounts_owner";
+/// How many rows the worker inserts per VACUUM.
+const VACUUM_EVERY: u64 = 16;
+
+const DIRECTORY: &str = "CRASH_WORKER_DIRECTORY";
+const FIRST: &str = "CRASH_WORKER_FIRST";
+const TEAR: &str = "CRASH_WORKER_TEAR";
+
+#[test]
+fn recovery_after_crashes() {
+    let directory = env::temp_dir().join(format!("omnissiah-recovery-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let result = crash_test(&directory);
+    let _ = std::fs::remove_dir_all(&directory);
+    if let Err(error) = result {
+        panic!("{}", error);
+    }
+}
+
+/// The worker, which only runs when the harness starts it.
+#[test]
+fn crash_worker() {
+    let Ok(directory) = env::var(DIRECTORY) else {
+        return;
+    };
+    if let Err(error) = worker(Path::new(&directory)) {
+        panic!("{}", error);
+    }
+}
+
+#[test]
+fn commits_are_seen_once_durable() {
+    let directory = Directory::new("recovery-group-commit");
+    let options = StorageOptions {
+        sync: SyncPolicy::Group {
+            commits: 2,
+            delay: Duration::from_secs(1),
+        },
+        ..StorageOptions::default()
+    };
+    let mut writer = Executor::open(&directory.0, options).unwrap();
+    run(
+        &mut writer,
+        "CREATE TABLE notes (id INT PRIMARY); INSERT INTO notes(id) VALUES (1);",
+    )
+    .unwrap();
+    // The insert waited for the log to be synced, so all of it is durable.
+    let synced = fs::metadata(directory.0.join(LOG_FILE)).unwrap().len();
+    let mut reader = writer.session();
+    // Alone in its group, the commit waits out the whole delay.
+    let committing = thread::spawn(move || {
+        run(&mut writer, "INSERT INTO notes(id) VALUES (2);").unwrap();
+    });
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(query(&mut reader, "SELECT id FROM notes;"), ["1"]);
+    let cut = Directory::new("recovery-group-commit-cut");
+    fs::create_dir_all(&cut.0).unwrap();
+    for entry in fs::read_dir(&directory.0).unwrap() {
+        let path = entry.unwrap().path();
+        fs::copy(&path, cut.0.join(path.file_name().unwrap())).unwrap();
+    }
+    let log = OpenOptions::new()
+        .write(true)
+        .open(cut.0.join(LOG_FILE))
+        .unwrap();
+    assert!(log.metadata().unwrap().len() > synced);
+    log.set_len(synced).unwrap();
+    assert_eq!(query(&mut cut.open(), "SELECT id FROM notes;"), ["1"]);
+    committing.join().unwrap();
+    assert_eq!(
+        query(&mut reader, "SELECT id FROM notes ORDER BY id;"),
+        ["1", "2"]
+    );
+}
+
+/// Small enough a cache that pages are written back, and so may be torn,
+/// all the time, and checkpoints often. Commits are synced as groups, so
+/// a row is only printed once the sync its commit waited for is done.
+fn options() -> StorageOptions {
+    StorageOptions {
+        buffer_pool_pages: 16,
+        sync: SyncPolicy::Group {
+            commits: 4,
+            delay: Duration::from_millis(1),
+        },
+        checkpoint_log_size: 256 << 10,
+        ..StorageOptions::default()
+    }
+}
+
+fn crash_test(directory: &Path) -> Result<(), String> {
+    let rounds = parse_variable("CRASH_ROUNDS", 10)?;
+    let seed = parse_variable(
+        "CRASH_SEED",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |elapsed| elapsed.as_nanos() as u64),
+    )?;
+    println!("crash test in {} with seed {}", directory.display(), seed);
+    let mut random = Random(seed.max(1));
+    let program = env::current_exe().map_err(|error| error.to_string())?;
+    let mut acknowledged = BTreeSet::new();
+    for round in 1..=rounds {
+        let first = acknowledged.last().map_or(1, |last| last + 1);
+        let tear = if random.next().is_multiple_of(2) {
+            1 + random.next() % 64
+        } else {
+            0
+        };
+        let delay = Duration::from_millis(random.next() % 300);
+        let mut worker = Command::new(&program)
+            .args(["crash_worker", "--exact", "--nocapture", "--quiet"])
+            .env(DIRECTORY, directory)
+            .env(FIRST, first.to_string())
+            .env(TEAR, tear.to_string())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| error.to_string())?;
+        thread::sleep(delay);
+        // The worker may have died already, tearing its write.
+        let _ = worker.kill();
+        let _ = worker.wait();
+        let mut output = String::new();
+        if let Some(mut stdout) = worker.stdout.take() {
+            stdout
+                .read_to_string(&mut output)
+                .map_err(|error| error.to_string())?;
+        }
+        // A line cut short by the kill was never acknowledged; the lines
+        // the test harness prints are no ids.
+        let complete = &output[..output.rfind('\n').map_or(0, |end| end + 1)];
+        let printed = complete
+            .lines()
+            .filter_map(|line| line.parse::<u64>().ok())
+            .collect::<Vec<u64>>();
+        acknowledged.extend(&printed);
+        let in_flight = printed.last().map_or(first, |last| last + 1);
+        let found = verify(directory, &acknowledged, in_flight)?;
+        println!(
+            "round {}: {} after {} ms, {} rows acknowledged, {} recovered",
+            round,
+            if tear > 0 {
+                format!("tearing page write {}", tear)
+            } else {
+                "killed".to_string()
+            },
+            delay.as_millis(),
+            acknowledged.len(),
+            found
+        );
+        // The in-flight row, if it committed, is acknowledged from now on.
+        if found > acknowledged.len() {
+            acknowledged.insert(in_flight);
+        }
+    }
+    println!("all {} rounds recovered", rounds);
+    Ok(())
+}
+
+/// Opens the database the worker left and checks its rows against the
+/// ones the worker acknowledged, returning how many there are.
+fn verify(directory: &Path, acknowledged: &BTreeSet<u64>, in_flight: u64) -> Result<usize, String> {
+    let fail = |error: ExecutionError| format!("recovery failed: {}", error);
+    let mut executor = Executor::open(directory, options()).map_err(fail)?;
+    if executor.catalog().table(TABLE).is_none() {
+        return if acknowledged.is_empty() {
+            Ok(0)
+        } else {
+            Err("the table is gone".to_string())
+        };
+    }
+    let rows = run(
+        &mut executor,
+        &format!("SELECT id, owner, balance FROM {};", TABLE),
+    )
+    .map_err(fail)?
+    .rows;
+    let mut ids = BTreeSet::new();
+    for row in &rows {
+        let id = row[0]
+            .to_string()
+            .parse::<u64>()
+            .map_err(|error| error.to_string())?;
+        if !ids.insert(id) {
+            return Err(format!("row {} is there twice", id));
+        }
+        if row[1].to_string() != owner(id) || row[2].to_string() != balance(id).to_string() {
+            return Err(format!("row {} is not whole: {:?}", id, row));
+        }
+        if !acknowledged.contains(&id) && id != in_flight {
+            return Err(format!("row {} was never acknowledged", id));
+        }
+        for condition in [format!("id == {}", id), format!("owner == '{}'", owner(id))] {
+            let found = run(
+                &mut executor,
+                &format!("SELECT id FROM {} WHERE {};", TABLE, condition),
+            )
+            .map_err(fail)?;
+            if found.rows.len() != 1 {
+                return Err(format!(
+                    "an index finds row {} {} times",
+                    id,
+                    found.rows.len()
+                ));
+            }
+        }
+    }
+    if let Some(missing) = acknowledged.difference(&ids).next() {
+        return Err(format!("acknowledged row {} is lost", missing));
+    }
+    Ok(ids.len())
+}
+
+/// Inserts rows from id `first` on until it is killed.
+fn worker(directory: &Path) -> Result<(), ExecutionError> {
+    let usage = |_| ExecutionError::InvalidQuery(format!("{} and {} are numbers", FIRST, TEAR));
+    let first = parse_variable(FIRST, 1).map_err(usage)?;
+    let tear = parse_variable(TEAR, 0).map_err(usage)?;
+    let mut executor = Executor::open(directory, options())?;
+    if executor.catalog().table(TABLE).is_none() {
+        run(
+            &mut executor,
+            &format!(
+                "CREATE TABLE {} (id U_INT PRIMARY, owner TEXT NOTNULL, balance LONG NOTNULL);",
+                TABLE
+            ),
+        )?;
+    }
+    let indexed = executor
+        .catalog()
+        .table(TABLE)
+        .is_some_and(|table| table.indexes.iter().any(|index| index.name == OWNER_INDEX));
+    if !indexed {
+        run(
+            &mut executor,
+            &format!(
+                "CREATE INDEX {} ON {} USING HASH (owner);",
+                OWNER_INDEX, TABLE
+            ),
+        )?;
+    }
+    if tear > 0 {
+        tear_page_write(tear);
+    }
+    let mut stdout = io::stdout();
+    for id in first.. {
+        if id % VACUUM_EVERY == 0 {
+            run(
+                &mut executor,
+                &format!(
+                    "BEGIN; INSERT INTO {}(id, owner, balance) VALUES ({}, '{}', {}); ROLLBACK; VACUUM FULL {};",
+                    TABLE,
+                    id,
+                    owner(id),
+                    balance(id),
+                    TABLE
+                ),
+            )?;
+        }
+        run(
+            &mut executor,
+            &format!(
+                "INSERT INTO {}(id, owner, balance) VALUES ({}, '{}', {});",
+                TABLE,
+                id,
+                owner(id),
+                balance(id)
+            ),
+        )?;
+        writeln!(stdout, "{}", id)?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+fn owner(id: u64) -> String {
+    format!("owner-{}", id)
+}
+
+fn balance(id: u64) -> i64 {
+    (id as i64 * 7919) % 100_000 - 50_000
+}
+
+fn parse_variable(name: &str, default: u64) -> Result<u64, String> {
+    env::var(name).map_or(Ok(default), |value| {
+        value
+            .parse()
+            .map_err(|_| format!("{} is not a number: {}", name, value))
+    })
+}
+
+/// Xorshift, enough to pick crash points without a dependency.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
@@ -0,0 +1,166 @@
+//! Sequences: stepping, cycling and running out, `setval` and `currval`,
+//! identity columns, and the values cached across a restart.
+
+mod common;
+
+use common::{database, error, execute, query, Directory};
+use omnissiah_db::execution::executor::Executor;
+
+fn one_row() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE one (id INT); INSERT INTO one(id) VALUES (1);",
+    );
+    database
+}
+
+/// Calls `function` on the sequence once per value of `count`.
+fn values(database: &mut Executor, function: &str, count: usize) -> Vec<String> {
+    (0..count)
+        .flat_map(|_| query(database, &format!("SELECT {} FROM one;", function)))
+        .collect()
+}
+
+#[test]
+fn a_cycling_sequence_wraps_to_its_minimum() {
+    let mut database = one_row();
+    execute(
+        &mut database,
+        "CREATE SEQUENCE odd START WITH 5 INCREMENT BY 2 MINVALUE 1 MAXVALUE 9 CYCLE;
+CREATE SEQUENCE down START WITH 2 INCREMENT BY -1 MINVALUE 1 MAXVALUE 3 CYCLE;",
+    );
+    assert_eq!(
+        values(&mut database, "nextval('odd')", 6),
+        ["5", "7", "9", "1", "3", "5"]
+    );
+    assert_eq!(
+        values(&mut database, "nextval('down')", 4),
+        ["2", "1", "3", "2"]
+    );
+}
+
+#[test]
+fn a_sequence_without_cycle_runs_out() {
+    let mut database = one_row();
+    execute(
+        &mut database,
+        "CREATE SEQUENCE few MAXVALUE 2;
+CREATE SEQUENCE down INCREMENT BY -5 START WITH -1 MINVALUE -7;",
+    );
+    assert_eq!(values(&mut database, "nextval('few')", 2), ["1", "2"]);
+    assert_eq!(
+        error(&mut database, "SELECT nextval('few') FROM one;"),
+        "nextval: reached maximum value of sequence \"few\" (2)"
+    );
+    assert_eq!(values(&mut database, "nextval('down')", 2), ["-1", "-6"]);
+    assert_eq!(
+        error(&mut database, "SELECT nextval('down') FROM one;"),
+        "nextval: reached minimum value of sequence \"down\" (-7)"
+    );
+}
+
+#[test]
+fn setval_stays_within_the_bounds() {
+    let mut database = one_row();
+    execute(
+        &mut database,
+        "CREATE SEQUENCE ids MINVALUE 10 MAXVALUE 20 START WITH 10;",
+    );
+    assert_eq!(
+        error(&mut database, "SELECT setval('ids', 21) FROM one;"),
+        "setval: value 21 is out of bounds for sequence \"ids\" (10..20)"
+    );
+    assert!(error(&mut database, "SELECT setval('ids', 9) FROM one;").contains("out of bounds"));
+    assert_eq!(values(&mut database, "setval('ids', 20)", 1), ["20"]);
+    assert!(error(&mut database, "SELECT nextval('ids') FROM one;").contains("maximum value"));
+    assert_eq!(values(&mut database, "setval('ids', 15, FALSE)", 1), ["15"]);
+    assert_eq!(values(&mut database, "nextval('ids')", 2), ["15", "16"]);
+    assert_eq!(values(&mut database, "setval('ids', 12, TRUE)", 1), ["12"]);
+    assert_eq!(values(&mut database, "nextval('ids')", 1), ["13"]);
+}
+
+#[test]
+fn currval_is_the_last_nextval_of_the_session() {
+    let mut database = one_row();
+    let mut other = database.session();
+    execute(&mut database, "CREATE SEQUENCE ids;");
+    assert_eq!(
+        error(&mut database, "SELECT currval('ids') FROM one;"),
+        "currval of sequence \"ids\" is not yet defined in this session"
+    );
+    assert_eq!(values(&mut database, "nextval('ids')", 2), ["1", "2"]);
+    assert_eq!(values(&mut database, "currval('ids')", 1), ["2"]);
+    assert!(error(&mut other, "SELECT currval('ids') FROM one;").contains("not yet defined"));
+    assert_eq!(values(&mut other, "nextval('ids')", 1), ["3"]);
+    assert_eq!(values(&mut database, "currval('ids')", 1), ["2"]);
+    assert_eq!(
+        error(&mut database, "SELECT currval('nothing') FROM one;"),
+        "unknown sequence \"nothing\""
+    );
+}
+
+#[test]
+fn identity_columns_take_values_unless_given_one() {
+    let mut database = one_row();
+    execute(
+        &mut database,
+        "CREATE TABLE notes (id INT PRIMARY IDENTITY, body TEXT);
+INSERT INTO notes(body) VALUES ('first');
+INSERT INTO notes(body) VALUES ('second');
+INSERT INTO notes(id, body) VALUES (10, 'given');
+INSERT INTO notes(body) VALUES ('third');",
+    );
+    assert_eq!(
+        query(&mut database, "SELECT id, body FROM notes ORDER BY id;"),
+        ["1 | first", "2 | second", "3 | third", "10 | given"]
+    );
+    assert_eq!(values(&mut database, "currval('notes_id_seq')", 1), ["3"]);
+}
+
+#[test]
+fn u_bigint_identity_keys_are_generated_when_left_out() {
+    let mut database = one_row();
+    execute(
+        &mut database,
+        "CREATE TABLE users (id U_BIGINT PRIMARY NOTNULL IDENTITY, name TEXT NOTNULL);
+INSERT INTO users(name) VALUES ('Jane Doe');
+INSERT INTO users(name) VALUES ('John Doe');
+INSERT INTO users(name) VALUES ('Max');",
+    );
+    assert_eq!(
+        query(&mut database, "SELECT id, name FROM users ORDER BY id;"),
+        ["1 | Jane Doe", "2 | John Doe", "3 | Max"]
+    );
+    assert_eq!(
+        query(&mut database, "SELECT name FROM users WHERE id == 2;"),
+        ["John Doe"]
+    );
+    assert_eq!(values(&mut database, "currval('users_id_seq')", 1), ["3"]);
+}
+
+#[test]
+fn cached_values_are_not_given_again_after_a_restart() {
+    let directory = Directory::new("sequences");
+    {
+        let mut database = directory.open();
+        execute(
+            &mut database,
+            "CREATE TABLE one (id INT); INSERT INTO one(id) VALUES (1);
+CREATE SEQUENCE cached CACHE 10;
+CREATE SEQUENCE uncached;",
+        );
+        assert_eq!(
+            values(&mut database, "nextval('cached')", 3),
+            ["1", "2", "3"]
+        );
+        assert_eq!(
+            values(&mut database, "nextval('uncached')", 3),
+            ["1", "2", "3"]
+        );
+    }
+    let mut database = directory.open();
+    // The cached run of 1 to 10 was recorded before 1 was given out.
+    assert_eq!(values(&mut database, "nextval('cached')", 2), ["11", "12"]);
+    assert_eq!(values(&mut database, "nextval('uncached')", 1), ["4"]);
+}
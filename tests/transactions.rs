@@ -0,0 +1,348 @@
+//! Transaction blocks, what concurrent sessions see of each other's
+//! writes, and the locks they take.
+
+mod common;
+
+use std::thread;
+use std::time::Duration;
+
+use common::{database, error, execute, query, run};
+use omnissiah_db::compiler::parser::{LockMode, LockWait};
+use omnissiah_db::execution::error::ExecutionError;
+use omnissiah_db::execution::executor::Executor;
+use omnissiah_db::storage::lock::{compatible, LockManager, LockTarget};
+
+fn accounts() -> Executor {
+    let mut database = database();
+    execute(
+        &mut database,
+        "CREATE TABLE accounts (id INT PRIMARY, owner TEXT NOTNULL, balance INT NOTNULL);
+INSERT INTO accounts(id, owner, balance) VALUES (1, 'Ann', 100);
+INSERT INTO accounts(id, owner, balance) VALUES (2, 'Bo', 50);",
+    );
+    database
+}
+
+fn owners(session: &mut Executor) -> Vec<String> {
+    query(session, "SELECT owner FROM accounts ORDER BY id;")
+}
+
+#[test]
+fn rollback_undoes_a_block_and_commit_keeps_it() {
+    let mut database = accounts();
+    execute(
+        &mut database,
+        "BEGIN; INSERT INTO accounts(id, owner, balance) VALUES (3, 'Cy', 0); DELETE FROM accounts WHERE id == 1; ROLLBACK;",
+    );
+    assert_eq!(owners(&mut database), ["Ann", "Bo"]);
+    execute(
+        &mut database,
+        "BEGIN; INSERT INTO accounts(id, owner, balance) VALUES (3, 'Cy', 0); UPDATE accounts SET owner = 'Bea' WHERE id == 2; COMMIT;",
+    );
+    assert_eq!(owners(&mut database), ["Ann", "Bea", "Cy"]);
+}
+
+#[test]
+fn savepoints_undo_part_of_a_block() {
+    let mut database = accounts();
+    execute(
+        &mut database,
+        "BEGIN;
+INSERT INTO accounts(id, owner, balance) VALUES (3, 'Cy', 0);
+SAVEPOINT before_duplicate;
+INSERT INTO accounts(id, owner, balance) VALUES (4, 'Di', 0);",
+    );
+    assert!(error(
+        &mut database,
+        "INSERT INTO accounts(id, owner, balance) VALUES (4, 'Di', 0);"
+    )
+    .contains("primary key"));
+    assert!(error(&mut database, "SELECT owner FROM accounts;")
+        .contains("current transaction is aborted"));
+    execute(
+        &mut database,
+        "ROLLBACK TO SAVEPOINT before_duplicate; COMMIT;",
+    );
+    assert_eq!(owners(&mut database), ["Ann", "Bo", "Cy"]);
+}
+
+#[test]
+fn a_failed_block_is_rolled_back_at_commit() {
+    let mut database = accounts();
+    execute(
+        &mut database,
+        "BEGIN; INSERT INTO accounts(id, owner, balance) VALUES (3, 'Cy', 0);",
+    );
+    error(&mut database, "INSERT INTO accounts(id) VALUES (4);");
+    assert!(error(&mut database, "COMMIT;").contains("rolled back"));
+    assert_eq!(owners(&mut database), ["Ann", "Bo"]);
+}
+
+#[test]
+fn uncommitted_writes_are_seen_only_by_their_transaction() {
+    let mut writer = accounts();
+    let mut reader = writer.session();
+    execute(
+        &mut writer,
+        "BEGIN; INSERT INTO accounts(id, owner, balance) VALUES (3, 'Cy', 0); DELETE FROM accounts WHERE id == 1; UPDATE accounts SET owner = 'Bea' WHERE id == 2;",
+    );
+    assert_eq!(owners(&mut writer), ["Bea", "Cy"]);
+    assert_eq!(owners(&mut reader), ["Ann", "Bo"]);
+    execute(&mut writer, "COMMIT;");
+    assert_eq!(owners(&mut reader), ["Bea", "Cy"]);
+}
+
+#[test]
+fn repeatable_read_keeps_its_snapshot() {
+    let mut writer = accounts();
+    let mut committed = writer.session();
+    let mut repeatable = writer.session();
+    execute(&mut committed, "BEGIN;");
+    execute(&mut repeatable, "BEGIN ISOLATION LEVEL REPEATABLE READ;");
+    assert_eq!(owners(&mut committed), ["Ann", "Bo"]);
+    assert_eq!(owners(&mut repeatable), ["Ann", "Bo"]);
+    execute(
+        &mut writer,
+        "INSERT INTO accounts(id, owner, balance) VALUES (3, 'Cy', 0);",
+    );
+    assert_eq!(owners(&mut committed), ["Ann", "Bo", "Cy"]);
+    assert_eq!(owners(&mut repeatable), ["Ann", "Bo"]);
+}
+
+#[test]
+fn repeatable_read_fails_on_a_concurrent_update() {
+    let mut writer = accounts();
+    let mut repeatable = writer.session();
+    execute(&mut repeatable, "BEGIN ISOLATION LEVEL REPEATABLE READ;");
+    owners(&mut repeatable);
+    execute(
+        &mut writer,
+        "UPDATE accounts SET balance = 0 WHERE id == 1;",
+    );
+    assert!(error(
+        &mut repeatable,
+        "UPDATE accounts SET balance = 1 WHERE id == 1;"
+    )
+    .contains("could not serialize access"));
+    execute(&mut repeatable, "ROLLBACK;");
+    assert_eq!(
+        query(&mut writer, "SELECT balance FROM accounts WHERE id == 1;"),
+        ["0"]
+    );
+}
+
+#[test]
+fn read_committed_skips_a_row_deleted_since() {
+    let mut writer = accounts();
+    let mut committed = writer.session();
+    execute(&mut committed, "BEGIN;");
+    execute(&mut writer, "DELETE FROM accounts WHERE id == 1;");
+    let updated = execute(&mut committed, "UPDATE accounts SET balance = 0;");
+    assert_eq!(updated.affected, 1);
+    execute(&mut committed, "COMMIT;");
+    assert_eq!(
+        query(&mut writer, "SELECT owner, balance FROM accounts;"),
+        ["Bo | 0"]
+    );
+}
+
+#[test]
+fn locked_rows_are_waited_for_skipped_or_refused() {
+    let mut holder = accounts();
+    let mut other = holder.session();
+    other.set_lock_timeout(Some(Duration::from_millis(50)));
+    execute(
+        &mut holder,
+        "BEGIN; SELECT id FROM accounts WHERE id == 1 FOR UPDATE;",
+    );
+    assert!(error(
+        &mut other,
+        "SELECT id FROM accounts WHERE id == 1 FOR UPDATE NOWAIT;"
+    )
+    .starts_with("could not obtain"));
+    assert_eq!(
+        query(
+            &mut other,
+            "SELECT owner FROM accounts FOR UPDATE SKIP LOCKED;"
+        ),
+        ["Bo"]
+    );
+    assert!(error(&mut other, "DELETE FROM accounts WHERE id == 1;").contains("lock timeout"));
+    assert!(error(&mut other, "UPDATE accounts SET balance = 0;").contains("lock timeout"));
+    execute(&mut holder, "COMMIT;");
+    let deleted = execute(&mut other, "DELETE FROM accounts WHERE id == 1;");
+    assert_eq!(deleted.affected, 1);
+}
+
+#[test]
+fn table_locks_keep_out_writers() {
+    let mut holder = accounts();
+    let mut other = holder.session();
+    other.set_lock_timeout(Some(Duration::from_millis(50)));
+    execute(&mut holder, "BEGIN; LOCK TABLE accounts IN SHARE MODE;");
+    assert_eq!(owners(&mut other), ["Ann", "Bo"]);
+    assert!(error(
+        &mut other,
+        "INSERT INTO accounts(id, owner, balance) VALUES (3, 'Cy', 0);"
+    )
+    .contains("lock timeout"));
+    execute(&mut holder, "COMMIT;");
+    execute(
+        &mut other,
+        "INSERT INTO accounts(id, owner, balance) VALUES (3, 'Cy', 0);",
+    );
+    assert_eq!(owners(&mut holder), ["Ann", "Bo", "Cy"]);
+}
+
+#[test]
+fn serializable_refuses_write_skew() {
+    let mut first = accounts();
+    let mut second = first.session();
+    // Each takes 50 from an account as long as the two keep 100 between
+    // them; run one after the other, the second would see the first's
+    // withdrawal and hold back.
+    for session in [&mut first, &mut second] {
+        execute(session, "BEGIN ISOLATION LEVEL SERIALIZABLE;");
+        assert_eq!(
+            query(session, "SELECT SUM(balance) FROM accounts;"),
+            ["150"]
+        );
+    }
+    execute(
+        &mut first,
+        "UPDATE accounts SET balance = 50 WHERE id == 1;",
+    );
+    let failure = error(
+        &mut second,
+        "UPDATE accounts SET balance = 0 WHERE id == 2;",
+    );
+    assert_eq!(
+        failure,
+        "could not serialize access due to read/write dependencies among transactions"
+    );
+    execute(&mut second, "ROLLBACK;");
+    execute(&mut first, "COMMIT;");
+    assert_eq!(
+        query(&mut second, "SELECT balance FROM accounts ORDER BY id;"),
+        ["50", "50"]
+    );
+}
+
+#[test]
+fn serializable_reads_alone_are_never_refused() {
+    let mut writer = accounts();
+    let mut reader = writer.session();
+    execute(&mut reader, "BEGIN ISOLATION LEVEL SERIALIZABLE;");
+    assert_eq!(owners(&mut reader), ["Ann", "Bo"]);
+    execute(
+        &mut writer,
+        "BEGIN ISOLATION LEVEL SERIALIZABLE; UPDATE accounts SET owner = 'Bea' WHERE id == 2; COMMIT;",
+    );
+    execute(
+        &mut writer,
+        "BEGIN ISOLATION LEVEL SERIALIZABLE; INSERT INTO accounts(id, owner, balance) VALUES (3, 'Cy', 0); COMMIT;",
+    );
+    // The reader keeps its snapshot, ordered before both writers.
+    assert_eq!(owners(&mut reader), ["Ann", "Bo"]);
+    assert_eq!(
+        query(&mut reader, "SELECT SUM(balance) FROM accounts;"),
+        ["150"]
+    );
+    execute(&mut reader, "COMMIT;");
+    assert_eq!(owners(&mut reader), ["Ann", "Bea", "Cy"]);
+}
+
+#[test]
+fn a_deadlock_fails_the_transaction_that_closes_the_cycle() {
+    let mut database = accounts();
+    // Each session locks one row, then asks for the other's.
+    let sessions = [(1, 2), (2, 1)].map(|(first, second)| {
+        let mut session = database.session();
+        execute(
+            &mut session,
+            &format!(
+                "BEGIN; SELECT id FROM accounts WHERE id == {} FOR UPDATE;",
+                first
+            ),
+        );
+        (session, second)
+    });
+    let threads = sessions.map(|(mut session, second)| {
+        thread::spawn(move || {
+            let sql = format!("SELECT id FROM accounts WHERE id == {} FOR UPDATE;", second);
+            let result = run(&mut session, &sql);
+            if result.is_err() {
+                execute(&mut session, "ROLLBACK;");
+            } else {
+                execute(&mut session, "COMMIT;");
+            }
+            result.map(|result| result.rows.len())
+        })
+    });
+    // Whichever waits second sees the cycle; the other then gets the row.
+    let results = threads.map(|thread| thread.join().unwrap());
+    let cycle = match results {
+        [Err(ExecutionError::Deadlock(cycle)), Ok(1)]
+        | [Ok(1), Err(ExecutionError::Deadlock(cycle))] => cycle,
+        results => panic!("{:?}", results),
+    };
+    let steps: Vec<&str> = cycle.split("; ").collect();
+    assert_eq!(steps.len(), 2, "{}", cycle);
+    for step in steps {
+        assert!(step.starts_with("transaction "), "{}", step);
+        assert!(
+            step.contains(" waits for EXCLUSIVE lock on row (")
+                && step.contains(") of table \"main.accounts\", blocked by transaction "),
+            "{}",
+            step
+        );
+    }
+    assert_eq!(owners(&mut database), ["Ann", "Bo"]);
+}
+
+#[test]
+fn intention_locks_conflict_as_multiple_granularity_locking_says() {
+    use LockMode::*;
+    let modes = [
+        IntentionShare,
+        IntentionExclusive,
+        Share,
+        ShareIntentionExclusive,
+        Exclusive,
+    ];
+    // Rows and columns in the order of `modes`.
+    let expected = [
+        [true, true, true, true, false],
+        [true, true, false, false, false],
+        [true, false, true, false, false],
+        [true, false, false, false, false],
+        [false, false, false, false, false],
+    ];
+    for (held, row) in modes.iter().zip(expected) {
+        for (wanted, allowed) in modes.iter().zip(row) {
+            assert_eq!(
+                compatible(*held, *wanted),
+                allowed,
+                "{} held, {} wanted",
+                held,
+                wanted
+            );
+        }
+    }
+    // Taking both SHARE and ROW EXCLUSIVE holds the table in SHARE ROW
+    // EXCLUSIVE, which admits only ROW SHARE.
+    let locks = LockManager::default();
+    let table = LockTarget::Table("main.accounts".to_string());
+    let acquire = |xid, mode| locks.acquire(xid, &table, mode, LockWait::NoWait, None);
+    assert!(acquire(1, Share).unwrap());
+    assert!(acquire(1, IntentionExclusive).unwrap());
+    assert!(acquire(2, IntentionShare).unwrap());
+    assert_eq!(
+        acquire(3, IntentionExclusive).unwrap_err().to_string(),
+        "could not obtain ROW EXCLUSIVE lock on table \"main.accounts\""
+    );
+    assert!(acquire(3, Share).is_err());
+    locks.release_all(1);
+    assert!(acquire(3, IntentionExclusive).unwrap());
+    assert!(acquire(4, Share).is_err());
+}
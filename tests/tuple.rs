@@ -0,0 +1,221 @@
+//! The row layout: every type at the ends of its range, the null bitmap,
+//! and where TEXT goes among fixed-width fields.
+
+mod common;
+
+use common::{database, error, execute, query};
+use omnissiah_db::compiler::parser::parse_data_type;
+use omnissiah_db::storage::catalog::ColumnSchema;
+use omnissiah_db::storage::tuple::{fixed_width, RowLayout};
+use omnissiah_db::types::value::Value;
+
+fn column(name: &str, data_type: &str, nullable: bool) -> ColumnSchema {
+    ColumnSchema {
+        name: name.to_string(),
+        data_type: parse_data_type(data_type).unwrap(),
+        is_primary: false,
+        not_null: !nullable,
+        identity: false,
+    }
+}
+
+fn round_trip(layout: &RowLayout, row: &[Value]) -> Vec<u8> {
+    let record = layout.encode(row).unwrap();
+    assert_eq!(layout.decode(&record).unwrap(), row, "{:?}", record);
+    record
+}
+
+fn integers(values: &[i128]) -> Vec<Value> {
+    values.iter().map(|value| Value::Integer(*value)).collect()
+}
+
+#[test]
+fn every_type_keeps_the_ends_of_its_range() {
+    let uuid = |text: &str| Value::Text(text.to_string());
+    let cases = [
+        ("BOOL", vec![Value::Bool(false), Value::Bool(true)]),
+        ("BYTE", integers(&[-128, -1, 0, 127])),
+        ("U_BYTE", integers(&[0, 255])),
+        ("SHORT", integers(&[i16::MIN.into(), -1, i16::MAX.into()])),
+        ("U_SHORT", integers(&[0, u16::MAX.into()])),
+        ("INT", integers(&[i32::MIN.into(), -1, i32::MAX.into()])),
+        ("U_INT", integers(&[0, u32::MAX.into()])),
+        ("LONG", integers(&[i64::MIN.into(), -1, i64::MAX.into()])),
+        ("U_LONG", integers(&[0, u64::MAX.into()])),
+        ("BIGINT", integers(&[i128::MIN, -1, i128::MAX])),
+        ("U_BIGINT", integers(&[0, i128::MAX])),
+        (
+            "FLOAT",
+            [f32::MIN, -0.5, f32::MIN_POSITIVE, f32::MAX, f32::INFINITY]
+                .map(|float| Value::Float(float.into()))
+                .to_vec(),
+        ),
+        (
+            "DOUBLE",
+            [f64::MIN, f64::MIN_POSITIVE, f64::MAX, f64::NEG_INFINITY]
+                .map(Value::Float)
+                .to_vec(),
+        ),
+        (
+            "DATETIME",
+            [i64::MIN, 0, i64::MAX].map(Value::DateTime).to_vec(),
+        ),
+        (
+            "UUID",
+            vec![
+                uuid("00000000-0000-0000-0000-000000000000"),
+                uuid("0123abcd-4567-89ef-0123-456789abcdef"),
+                uuid("ffffffff-ffff-ffff-ffff-ffffffffffff"),
+            ],
+        ),
+    ];
+    for (name, values) in cases {
+        let columns = [column("value", name, false)];
+        let layout = RowLayout::new(&columns);
+        let width = fixed_width(&columns[0].data_type).unwrap();
+        for value in values {
+            let record = round_trip(&layout, &[value]);
+            assert_eq!(record.len(), width, "{}", name);
+        }
+    }
+    let layout = RowLayout::new(&[column("value", "TEXT", false)]);
+    for text in ["", "ünïcödé ✓ 𝄞", &"long ".repeat(1000)] {
+        let record = round_trip(&layout, &[Value::Text(text.to_string())]);
+        assert_eq!(record.len(), 4 + text.len());
+    }
+}
+
+#[test]
+fn values_past_the_range_are_refused() {
+    for (name, below, above) in [
+        ("BYTE", -129, 128),
+        ("U_BYTE", -1, 256),
+        ("SHORT", i16::MIN as i128 - 1, i16::MAX as i128 + 1),
+        ("U_INT", -1, u32::MAX as i128 + 1),
+        ("LONG", i64::MIN as i128 - 1, i64::MAX as i128 + 1),
+        ("U_LONG", -1, u64::MAX as i128 + 1),
+        // Past 2^127-1 is no 128-bit signed value, so only below is refused.
+        ("U_BIGINT", -1, -2),
+    ] {
+        let layout = RowLayout::new(&[column("value", name, false)]);
+        for value in [below, above] {
+            assert_eq!(
+                layout
+                    .encode(&[Value::Integer(value)])
+                    .unwrap_err()
+                    .to_string(),
+                format!(
+                    "overflow: {} is out of range for {} column \"value\"",
+                    value, name
+                )
+            );
+        }
+    }
+}
+
+#[test]
+fn only_nullable_columns_have_a_bit() {
+    let strict = [
+        column("id", "INT", false),
+        column("name", "TEXT", false),
+        column("flag", "BOOL", false),
+    ];
+    let layout = RowLayout::new(&strict);
+    assert_eq!(layout.bitmap_size(), 0);
+    let record = round_trip(
+        &layout,
+        &[
+            Value::Integer(7),
+            Value::Text("x".to_string()),
+            Value::Bool(true),
+        ],
+    );
+    assert_eq!(record.len(), 4 + 1 + 4 + 1);
+    assert_eq!(
+        layout
+            .encode(&[Value::Integer(7), Value::Null, Value::Bool(true)])
+            .unwrap_err()
+            .to_string(),
+        "invalid query: column \"name\" cannot hold NULL"
+    );
+
+    // Ten nullable columns among two that are not take two bytes of bits.
+    let mut columns = vec![column("id", "LONG", false)];
+    columns.extend((0..10).map(|number| column(&format!("c{}", number), "SHORT", true)));
+    columns.push(column("label", "TEXT", false));
+    let layout = RowLayout::new(&columns);
+    assert_eq!(layout.bitmap_size(), 2);
+    for nulls in [0u16, 0b11_1111_1111, 0b10_0000_0001, 0b01_0101_0100] {
+        let mut row = vec![Value::Integer(-1)];
+        row.extend((0..10).map(|bit| {
+            if nulls & (1 << bit) != 0 {
+                Value::Null
+            } else {
+                Value::Integer(bit * 100)
+            }
+        }));
+        row.push(Value::Text("end".to_string()));
+        let record = round_trip(&layout, &row);
+        assert_eq!(u16::from_le_bytes([record[0], record[1]]), nulls);
+        assert_eq!(record.len(), 2 + 8 + 10 * 2 + 4 + 3);
+    }
+}
+
+#[test]
+fn text_follows_every_fixed_width_field() {
+    let layout = RowLayout::new(&[
+        column("name", "TEXT", false),
+        column("id", "INT", false),
+        column("note", "TEXT", true),
+        column("score", "SHORT", true),
+        column("tag", "TEXT", true),
+    ]);
+    let row = [
+        Value::Text("ab".to_string()),
+        Value::Integer(1),
+        Value::Null,
+        Value::Integer(-2),
+        Value::Text("z".to_string()),
+    ];
+    let record = round_trip(&layout, &row);
+    #[rustfmt::skip]
+    let expected = [
+        0b001,                      // the bitmap: note is NULL
+        1, 0, 0, 0,                 // id
+        0xfe, 0xff,                 // score
+        2, 0, 0, 0, b'a', b'b',     // name
+        0, 0, 0, 0,                 // note, empty
+        1, 0, 0, 0, b'z',           // tag
+    ];
+    assert_eq!(record, expected);
+    assert_eq!(
+        layout.decode(&record[..6]).unwrap_err().to_string(),
+        "truncated row"
+    );
+    assert_eq!(
+        layout.decode(&record[..12]).unwrap_err().to_string(),
+        "truncated row"
+    );
+}
+
+#[test]
+fn u_bigint_columns_hold_the_non_negative_half_of_bigint() {
+    let mut database = database();
+    execute(
+        &mut database,
+        &format!(
+            "CREATE TABLE big (id U_BIGINT);
+INSERT INTO big(id) VALUES (0);
+INSERT INTO big(id) VALUES ({});",
+            i128::MAX
+        ),
+    );
+    assert_eq!(
+        query(&mut database, "SELECT id FROM big ORDER BY id;"),
+        ["0".to_string(), i128::MAX.to_string()]
+    );
+    assert_eq!(
+        error(&mut database, "INSERT INTO big(id) VALUES (-1);"),
+        "negative value in column \"id\" of table \"big\" violates unsigned type U_BIGINT"
+    );
+}